@@ -1,8 +1,15 @@
+use std::collections::BTreeSet;
 use std::fmt::{self, Display, Formatter};
 use std::path::Path;
 use std::process::Command;
 
-pub const CONTRACT_VERSION: &str = "2026-02-16";
+use serde::Deserialize;
+
+/// Newest contract Lever speaks; older entries in `SUPPORTED_CONTRACTS`
+/// stay negotiable so Assembly and Lever can be upgraded independently.
+pub const CONTRACT_VERSION: &str = "2026-10-18";
+/// The first contract, before task context hints (`--include`, `--pin`).
+pub const BASE_CONTRACT_VERSION: &str = "2026-02-16";
 pub const REQUIRED_BUILD_FLAGS: &[&str] = &[
     "--repo",
     "--task",
@@ -13,6 +20,19 @@ pub const REQUIRED_BUILD_FLAGS: &[&str] = &[
     "--exclude-runtime",
     "--summary-json",
 ];
+/// `REQUIRED_BUILD_FLAGS` plus the task context hint flags.
+pub const CONTEXT_HINT_BUILD_FLAGS: &[&str] = &[
+    "--repo",
+    "--task",
+    "--task-id",
+    "--out",
+    "--token-budget",
+    "--exclude",
+    "--exclude-runtime",
+    "--summary-json",
+    "--include",
+    "--pin",
+];
 pub const REQUIRED_PACK_FILES: &[&str] = &[
     "manifest.json",
     "index.json",
@@ -21,6 +41,133 @@ pub const REQUIRED_PACK_FILES: &[&str] = &[
     "lint.json",
 ];
 
+/// Contracts Lever supports, newest first.
+pub const SUPPORTED_CONTRACTS: &[AssemblyContract] = &[
+    AssemblyContract {
+        version: CONTRACT_VERSION,
+        assembly_versions: VersionRange {
+            min: AssemblyVersion::new(1, 0, 0),
+            max_exclusive: AssemblyVersion::new(2, 0, 0),
+        },
+        required_build_flags: CONTEXT_HINT_BUILD_FLAGS,
+    },
+    AssemblyContract {
+        version: BASE_CONTRACT_VERSION,
+        assembly_versions: VersionRange {
+            min: AssemblyVersion::new(1, 0, 0),
+            max_exclusive: AssemblyVersion::new(2, 0, 0),
+        },
+        required_build_flags: REQUIRED_BUILD_FLAGS,
+    },
+];
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct AssemblyVersion {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+}
+
+impl AssemblyVersion {
+    pub const fn new(major: u64, minor: u64, patch: u64) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    /// Finds the first `MAJOR.MINOR[.PATCH]` token in `assembly --version`
+    /// output, ignoring a leading `v` and any pre-release suffix.
+    pub fn parse(output: &str) -> Option<Self> {
+        output.split_whitespace().find_map(|token| {
+            let token = token.trim_start_matches('v');
+            let core = token.split(['-', '+']).next()?;
+            let mut parts = core.split('.');
+            let major = parts.next()?.parse().ok()?;
+            let minor = parts.next()?.parse().ok()?;
+            let patch = match parts.next() {
+                Some(part) => part.parse().ok()?,
+                None => 0,
+            };
+            if parts.next().is_some() {
+                return None;
+            }
+            Some(Self::new(major, minor, patch))
+        })
+    }
+}
+
+impl Display for AssemblyVersion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct VersionRange {
+    pub min: AssemblyVersion,
+    pub max_exclusive: AssemblyVersion,
+}
+
+impl VersionRange {
+    pub fn contains(&self, version: AssemblyVersion) -> bool {
+        self.min <= version && version < self.max_exclusive
+    }
+}
+
+impl Display for VersionRange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, ">={}, <{}", self.min, self.max_exclusive)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct AssemblyContract {
+    pub version: &'static str,
+    /// Assembly releases assumed to speak this contract when they cannot
+    /// report capabilities themselves.
+    pub assembly_versions: VersionRange,
+    pub required_build_flags: &'static [&'static str],
+}
+
+/// `assembly capabilities --json` output.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct AssemblyCapabilities {
+    pub contract_versions: Vec<String>,
+    pub build_flags: Vec<String>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum NegotiationSource {
+    Capabilities,
+    Help,
+}
+
+impl NegotiationSource {
+    pub fn label(self) -> &'static str {
+        match self {
+            NegotiationSource::Capabilities => "capabilities",
+            NegotiationSource::Help => "help",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NegotiatedContract {
+    pub contract_version: &'static str,
+    pub assembly_version: AssemblyVersion,
+    pub source: NegotiationSource,
+    /// The contract's build flags; Lever passes no others to `assembly build`.
+    pub build_flags: &'static [&'static str],
+}
+
+impl NegotiatedContract {
+    pub fn supports(&self, flag: &str) -> bool {
+        self.build_flags.contains(&flag)
+    }
+}
+
 #[derive(Debug)]
 pub enum AssemblyContractError {
     MissingDependency {
@@ -32,8 +179,18 @@ pub enum AssemblyContractError {
         output: String,
     },
     MissingBuildFlags {
+        contract: &'static str,
         missing: Vec<&'static str>,
     },
+    UnparseableVersion {
+        output: String,
+    },
+    UnsupportedVersion {
+        version: AssemblyVersion,
+    },
+    NoCommonContract {
+        offered: Vec<String>,
+    },
 }
 
 impl Display for AssemblyContractError {
@@ -48,40 +205,199 @@ impl Display for AssemblyContractError {
                 output,
             } => write!(
                 f,
-                "Assembly contract validation failed (version {}): command '{}' exited {:?}. {}",
-                CONTRACT_VERSION, command, status, output
+                "Assembly contract validation failed: command '{}' exited {:?}. {}",
+                command, status, output
             ),
-            AssemblyContractError::MissingBuildFlags { missing } => write!(
+            AssemblyContractError::MissingBuildFlags { contract, missing } => write!(
                 f,
                 "Assembly CLI contract mismatch (version {}): missing required build flags: {}. See docs/assembly-contract.md.",
-                CONTRACT_VERSION,
+                contract,
                 missing.join(", ")
             ),
+            AssemblyContractError::UnparseableVersion { output } => write!(
+                f,
+                "Assembly contract validation failed: could not parse a version from `assembly --version` output: {}",
+                output
+            ),
+            AssemblyContractError::UnsupportedVersion { version } => write!(
+                f,
+                "Unsupported Assembly version {} (supported: {}). See docs/assembly-contract.md.",
+                version,
+                supported_ranges()
+            ),
+            AssemblyContractError::NoCommonContract { offered } => write!(
+                f,
+                "Assembly offers contract versions [{}], none supported by lever [{}]. See docs/assembly-contract.md.",
+                offered.join(", "),
+                SUPPORTED_CONTRACTS
+                    .iter()
+                    .map(|contract| contract.version)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
         }
     }
 }
 
 impl std::error::Error for AssemblyContractError {}
 
-pub fn validate_assembly_contract(assembly_path: &Path) -> Result<(), AssemblyContractError> {
-    run_command(assembly_path, &["--version"])?;
-    let help_output = run_command(assembly_path, &["build", "--help"])?;
-    validate_build_help(&help_output)
+pub fn validate_assembly_contract(
+    assembly_path: &Path,
+) -> Result<NegotiatedContract, AssemblyContractError> {
+    let version_output = run_command(assembly_path, &["--version"])?;
+    let capabilities = run_command(assembly_path, &["capabilities", "--json"]).ok();
+    negotiate_contract(
+        SUPPORTED_CONTRACTS,
+        &version_output,
+        capabilities.as_deref(),
+        || run_command(assembly_path, &["build", "--help"]),
+    )
+}
+
+/// Picks the newest contract both sides support. A capabilities report is
+/// authoritative; without one (older Assembly releases may not know the
+/// subcommand or print something else), the Assembly version selects the
+/// contract and `build --help` must list its flags.
+pub fn negotiate_contract(
+    contracts: &[AssemblyContract],
+    version_output: &str,
+    capabilities: Option<&str>,
+    build_help: impl FnOnce() -> Result<String, AssemblyContractError>,
+) -> Result<NegotiatedContract, AssemblyContractError> {
+    let assembly_version = AssemblyVersion::parse(version_output).ok_or_else(|| {
+        AssemblyContractError::UnparseableVersion {
+            output: version_output.trim().to_string(),
+        }
+    })?;
+
+    let capabilities =
+        capabilities.and_then(|raw| serde_json::from_str::<AssemblyCapabilities>(raw.trim()).ok());
+    if let Some(capabilities) = capabilities {
+        let contract = contracts
+            .iter()
+            .find(|contract| {
+                capabilities
+                    .contract_versions
+                    .iter()
+                    .any(|offered| offered == contract.version)
+            })
+            .ok_or_else(|| AssemblyContractError::NoCommonContract {
+                offered: capabilities.contract_versions.clone(),
+            })?;
+        let flags: BTreeSet<&str> = capabilities
+            .build_flags
+            .iter()
+            .map(String::as_str)
+            .collect();
+        check_flags(contract, &flags)?;
+        return Ok(NegotiatedContract {
+            contract_version: contract.version,
+            assembly_version,
+            source: NegotiationSource::Capabilities,
+            build_flags: contract.required_build_flags,
+        });
+    }
+
+    let candidates: Vec<&AssemblyContract> = contracts
+        .iter()
+        .filter(|contract| contract.assembly_versions.contains(assembly_version))
+        .collect();
+    if candidates.is_empty() {
+        return Err(AssemblyContractError::UnsupportedVersion {
+            version: assembly_version,
+        });
+    }
+    let help_output = build_help()?;
+    let flags = help_flags(&help_output);
+    // The newest contract whose flags the help lists; when none fits, the
+    // error names what the oldest one is missing.
+    let mut mismatch = None;
+    for contract in candidates {
+        match check_flags(contract, &flags) {
+            Ok(()) => {
+                return Ok(NegotiatedContract {
+                    contract_version: contract.version,
+                    assembly_version,
+                    source: NegotiationSource::Help,
+                    build_flags: contract.required_build_flags,
+                })
+            }
+            Err(err) => mismatch = Some(err),
+        }
+    }
+    Err(
+        mismatch.unwrap_or(AssemblyContractError::UnsupportedVersion {
+            version: assembly_version,
+        }),
+    )
 }
 
+/// Checks `build --help` output against the oldest supported contract, whose
+/// flags every newer contract also requires.
 pub fn validate_build_help(help_output: &str) -> Result<(), AssemblyContractError> {
-    let missing: Vec<&'static str> = REQUIRED_BUILD_FLAGS
+    let oldest = &SUPPORTED_CONTRACTS[SUPPORTED_CONTRACTS.len() - 1];
+    check_flags(oldest, &help_flags(help_output))
+}
+
+/// Long flags declared by option lines in `--help` output. Only the option
+/// spec before the description counts, so a flag named in another flag's
+/// description (or a longer flag sharing its prefix) is not mistaken for it.
+pub fn help_flags(help_output: &str) -> BTreeSet<&str> {
+    let mut flags = BTreeSet::new();
+    for line in help_output.lines() {
+        let trimmed = line.trim_start();
+        if !trimmed.starts_with('-') {
+            continue;
+        }
+        for token in trimmed.split_whitespace() {
+            let token = token.trim_end_matches(',');
+            if let Some(flag) = token.strip_prefix("--") {
+                let name = flag.split('=').next().unwrap_or_default();
+                if !name.is_empty() {
+                    flags.insert(&token[..name.len() + 2]);
+                }
+            } else if !(token.starts_with('-') || token.starts_with('<') || token.starts_with('['))
+            {
+                break;
+            }
+        }
+    }
+    flags
+}
+
+fn check_flags(
+    contract: &AssemblyContract,
+    flags: &BTreeSet<&str>,
+) -> Result<(), AssemblyContractError> {
+    let missing: Vec<&'static str> = contract
+        .required_build_flags
         .iter()
         .copied()
-        .filter(|flag| !help_output.contains(flag))
+        .filter(|flag| !flags.contains(flag))
         .collect();
     if missing.is_empty() {
         Ok(())
     } else {
-        Err(AssemblyContractError::MissingBuildFlags { missing })
+        Err(AssemblyContractError::MissingBuildFlags {
+            contract: contract.version,
+            missing,
+        })
     }
 }
 
+fn supported_ranges() -> String {
+    SUPPORTED_CONTRACTS
+        .iter()
+        .map(|contract| {
+            format!(
+                "{} for contract {}",
+                contract.assembly_versions, contract.version
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
 fn run_command(command_path: &Path, args: &[&str]) -> Result<String, AssemblyContractError> {
     let output = Command::new(command_path)
         .args(args)
@@ -113,3 +429,136 @@ fn run_command(command_path: &Path, args: &[&str]) -> Result<String, AssemblyCon
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HELP: &str = "Usage: assembly build [OPTIONS]
+
+Options:
+  --repo <PATH>           Repository root
+  --task <PATH>           Task input file (supports @file)
+  --task-id <ID>          Task identifier
+  --out <DIR>             Output pack directory
+  --token-budget <TOKENS> Token budget for context
+  --exclude <GLOB>        Additive exclude glob (repeatable)
+  --exclude-runtime <GLOB> Runtime artifact exclusion glob (repeatable)
+  --summary-json <PATH>   Write machine-readable summary JSON
+";
+
+    const OLD: AssemblyContract = AssemblyContract {
+        version: "2025-06-01",
+        assembly_versions: VersionRange {
+            min: AssemblyVersion::new(0, 9, 0),
+            max_exclusive: AssemblyVersion::new(1, 0, 0),
+        },
+        required_build_flags: &["--repo", "--out"],
+    };
+
+    #[test]
+    fn parses_versions_from_version_output() {
+        assert_eq!(
+            AssemblyVersion::parse("assembly 1.2.3"),
+            Some(AssemblyVersion::new(1, 2, 3))
+        );
+        assert_eq!(
+            AssemblyVersion::parse("assembly v2.0.0-rc.1 (abc123)"),
+            Some(AssemblyVersion::new(2, 0, 0))
+        );
+        assert_eq!(
+            AssemblyVersion::parse("assembly 1.4"),
+            Some(AssemblyVersion::new(1, 4, 0))
+        );
+        assert_eq!(AssemblyVersion::parse("assembly dev build"), None);
+    }
+
+    #[test]
+    fn help_flags_ignore_prefixes_and_descriptions() {
+        let help = "Options:
+  --exclude-runtime <GLOB>  Runtime exclusion (see also --exclude)
+  -o, --out=<DIR>           Output directory
+";
+        let flags = help_flags(help);
+        assert!(flags.contains("--exclude-runtime"));
+        assert!(flags.contains("--out"));
+        assert!(!flags.contains("--exclude"));
+
+        let err = validate_build_help(help).unwrap_err();
+        assert!(err.to_string().contains("--exclude,"));
+        assert!(validate_build_help(HELP).is_ok());
+    }
+
+    #[test]
+    fn version_selects_contract_when_capabilities_are_missing() {
+        let contracts = [SUPPORTED_CONTRACTS[0], SUPPORTED_CONTRACTS[1], OLD];
+        let negotiated =
+            negotiate_contract(&contracts, "assembly 0.9.4", None, || Ok(HELP.to_string()))
+                .unwrap();
+        assert_eq!(negotiated.contract_version, "2025-06-01");
+        assert_eq!(negotiated.source, NegotiationSource::Help);
+
+        let negotiated =
+            negotiate_contract(&contracts, "assembly 1.2.3", None, || Ok(HELP.to_string()))
+                .unwrap();
+        assert_eq!(negotiated.contract_version, BASE_CONTRACT_VERSION);
+        assert!(!negotiated.supports("--include"));
+
+        let hints_help = format!(
+            "{}  --include <GLOB>        Include glob (repeatable)\n  --pin <PATH>            Pinned file (repeatable)\n",
+            HELP
+        );
+        let negotiated =
+            negotiate_contract(&contracts, "assembly 1.2.3", None, || Ok(hints_help)).unwrap();
+        assert_eq!(negotiated.contract_version, CONTRACT_VERSION);
+        assert!(negotiated.supports("--include") && negotiated.supports("--pin"));
+
+        let err = negotiate_contract(&contracts, "assembly 1.2.3", None, || {
+            Ok("  --repo <PATH>  Repository root\n".to_string())
+        })
+        .unwrap_err();
+        assert!(err.to_string().contains(BASE_CONTRACT_VERSION), "{}", err);
+
+        let err = negotiate_contract(&contracts, "assembly 3.0.0", None, || Ok(HELP.to_string()))
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            AssemblyContractError::UnsupportedVersion { .. }
+        ));
+
+        let negotiated = negotiate_contract(
+            &contracts,
+            "assembly 1.2.3",
+            Some("usage: assembly"),
+            || Ok(HELP.to_string()),
+        )
+        .unwrap();
+        assert_eq!(negotiated.source, NegotiationSource::Help);
+    }
+
+    #[test]
+    fn capabilities_pick_newest_shared_contract_for_any_version() {
+        let contracts = [SUPPORTED_CONTRACTS[0], OLD];
+        let capabilities = r#"{"contract_versions": ["2025-06-01", "2027-01-01"], "build_flags": ["--repo", "--out"]}"#;
+        let negotiated =
+            negotiate_contract(&contracts, "assembly 5.0.0", Some(capabilities), || {
+                panic!("help is not consulted when capabilities are reported")
+            })
+            .unwrap();
+        assert_eq!(negotiated.contract_version, "2025-06-01");
+        assert_eq!(negotiated.assembly_version, AssemblyVersion::new(5, 0, 0));
+        assert_eq!(negotiated.source, NegotiationSource::Capabilities);
+
+        let err = negotiate_contract(
+            &contracts,
+            "assembly 5.0.0",
+            Some(r#"{"contract_versions": ["2027-01-01"], "build_flags": []}"#),
+            || Ok(HELP.to_string()),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            AssemblyContractError::NoCommonContract { .. }
+        ));
+    }
+}
@@ -0,0 +1,336 @@
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::path::{Component, Path};
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+pub const SUMMARY_FILE: &str = "assembly-summary.json";
+pub const MANIFEST_FILE: &str = "manifest.json";
+pub const INDEX_FILE: &str = "index.json";
+
+/// `--summary-json` output.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct AssemblySummary {
+    pub task_id: String,
+    pub token_count: u64,
+    #[serde(default)]
+    pub token_budget: Option<u64>,
+}
+
+/// `pack/manifest.json`: the pack files Assembly wrote and their hashes.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct PackManifest {
+    #[serde(default)]
+    pub task_id: Option<String>,
+    pub files: Vec<ManifestFile>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ManifestFile {
+    pub path: String,
+    pub sha256: String,
+}
+
+/// `pack/index.json`: either a bare list of entries or an object holding
+/// them under `files`, `entries`, or `items`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum PackIndex {
+    List(Vec<IndexEntry>),
+    Object(IndexObject),
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct IndexObject {
+    #[serde(default, alias = "entries", alias = "items")]
+    pub files: Vec<IndexEntry>,
+}
+
+/// `priority` (or `score`) ranks the entry; higher is kept longer.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct IndexEntry {
+    #[serde(alias = "file")]
+    pub path: String,
+    #[serde(default, alias = "score")]
+    pub priority: Option<f64>,
+}
+
+impl PackIndex {
+    pub fn entries(&self) -> &[IndexEntry] {
+        match self {
+            PackIndex::List(entries) => entries,
+            PackIndex::Object(object) => &object.files,
+        }
+    }
+}
+
+/// One reason a pack failed content validation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackProblem {
+    pub file: &'static str,
+    pub reason: String,
+}
+
+impl Display for PackProblem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.file, self.reason)
+    }
+}
+
+/// Checks the summary, manifest, and index written by a build against the
+/// task and token budget Lever asked for. Returns every problem found.
+pub fn validate_pack_contents(
+    pack_dir: &Path,
+    summary_path: &Path,
+    task_id: &str,
+    token_budget: u64,
+) -> Vec<PackProblem> {
+    let mut problems = Vec::new();
+    let mut problem = |file: &'static str, reason: String| {
+        problems.push(PackProblem { file, reason });
+    };
+
+    match read_typed::<AssemblySummary>(summary_path) {
+        Ok(summary) => {
+            if summary.task_id != task_id {
+                problem(
+                    SUMMARY_FILE,
+                    format!(
+                        "task_id {} does not match task {}",
+                        summary.task_id, task_id
+                    ),
+                );
+            }
+            if let Some(reported) = summary.token_budget.filter(|value| *value != token_budget) {
+                problem(
+                    SUMMARY_FILE,
+                    format!(
+                        "token_budget {} does not match requested budget {}",
+                        reported, token_budget
+                    ),
+                );
+            }
+            if summary.token_count > token_budget {
+                problem(
+                    SUMMARY_FILE,
+                    format!(
+                        "token_count {} exceeds token budget {}",
+                        summary.token_count, token_budget
+                    ),
+                );
+            }
+        }
+        Err(reason) => problem(SUMMARY_FILE, reason),
+    }
+
+    match read_typed::<PackManifest>(&pack_dir.join(MANIFEST_FILE)) {
+        Ok(manifest) => {
+            if let Some(manifest_task) = manifest.task_id.as_deref() {
+                if manifest_task != task_id {
+                    problem(
+                        MANIFEST_FILE,
+                        format!("task_id {} does not match task {}", manifest_task, task_id),
+                    );
+                }
+            }
+            for file in &manifest.files {
+                if let Some(reason) = manifest_file_problem(pack_dir, file) {
+                    problem(MANIFEST_FILE, reason);
+                }
+            }
+            for path in pack_files(pack_dir) {
+                if !manifest.files.iter().any(|file| file.path == path) {
+                    problem(MANIFEST_FILE, format!("{} has no manifest entry", path));
+                }
+            }
+        }
+        Err(reason) => problem(MANIFEST_FILE, reason),
+    }
+
+    match read_typed::<PackIndex>(&pack_dir.join(INDEX_FILE)) {
+        Ok(index) => {
+            if let Some(position) = index
+                .entries()
+                .iter()
+                .position(|entry| entry.path.trim().is_empty())
+            {
+                problem(INDEX_FILE, format!("entry {} has an empty path", position));
+            }
+        }
+        Err(reason) => problem(INDEX_FILE, reason),
+    }
+
+    problems
+}
+
+fn manifest_file_problem(pack_dir: &Path, file: &ManifestFile) -> Option<String> {
+    let relative = Path::new(&file.path);
+    if !relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return Some(format!("{} is not a path inside the pack", file.path));
+    }
+    if file.sha256.trim().is_empty() {
+        return Some(format!("{} has no sha256", file.path));
+    }
+    let bytes = match fs::read(pack_dir.join(relative)) {
+        Ok(bytes) => bytes,
+        Err(_) => return Some(format!("{} is listed but missing", file.path)),
+    };
+    let actual = format!("{:x}", Sha256::digest(&bytes));
+    if !actual.eq_ignore_ascii_case(file.sha256.trim()) {
+        return Some(format!(
+            "sha256 mismatch for {} (manifest {}, actual {})",
+            file.path, file.sha256, actual
+        ));
+    }
+    None
+}
+
+/// Every file under the pack except the manifest itself, as `/`-separated
+/// paths relative to the pack directory.
+fn pack_files(pack_dir: &Path) -> Vec<String> {
+    let mut files = Vec::new();
+    let mut pending = vec![pack_dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                pending.push(path);
+                continue;
+            }
+            let Ok(relative) = path.strip_prefix(pack_dir) else {
+                continue;
+            };
+            let relative = relative
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            if relative != MANIFEST_FILE {
+                files.push(relative);
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+fn read_typed<T: DeserializeOwned>(path: &Path) -> Result<T, String> {
+    let raw = fs::read_to_string(path).map_err(|err| format!("unreadable ({})", err))?;
+    serde_json::from_str(&raw).map_err(|err| format!("invalid ({})", err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn write_pack(name: &str, context: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("lever-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let pack = dir.join("pack");
+        fs::create_dir_all(&pack).unwrap();
+        let index = r#"{"files": [{"path": "src/lib.rs", "score": 0.5}]}"#;
+        fs::write(pack.join("context.md"), context).unwrap();
+        fs::write(pack.join(INDEX_FILE), index).unwrap();
+        fs::write(
+            pack.join(MANIFEST_FILE),
+            format!(
+                r#"{{"task_id": "T1", "files": [{{"path": "context.md", "sha256": "{:x}"}}, {{"path": "index.json", "sha256": "{:x}"}}]}}"#,
+                Sha256::digest(b"hello\n"),
+                Sha256::digest(index.as_bytes())
+            ),
+        )
+        .unwrap();
+        fs::write(
+            dir.join(SUMMARY_FILE),
+            r#"{"task_id": "T1", "token_count": 120}"#,
+        )
+        .unwrap();
+        dir
+    }
+
+    #[test]
+    fn valid_pack_has_no_problems() {
+        let dir = write_pack("pack-valid", "hello\n");
+        let problems =
+            validate_pack_contents(&dir.join("pack"), &dir.join(SUMMARY_FILE), "T1", 200);
+        assert!(problems.is_empty(), "{:?}", problems);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn pack_problems_name_each_failed_check() {
+        let dir = write_pack("pack-invalid", "tampered\n");
+        fs::write(dir.join("pack").join(INDEX_FILE), "not json").unwrap();
+        fs::create_dir_all(dir.join("pack").join("extra")).unwrap();
+        fs::write(dir.join("pack").join("extra").join("notes.md"), "unlisted").unwrap();
+
+        let problems: Vec<String> =
+            validate_pack_contents(&dir.join("pack"), &dir.join(SUMMARY_FILE), "T2", 100)
+                .iter()
+                .map(ToString::to_string)
+                .collect();
+        assert_eq!(problems.len(), 7, "{:?}", problems);
+        assert_eq!(
+            problems[0],
+            "assembly-summary.json: task_id T1 does not match task T2"
+        );
+        assert_eq!(
+            problems[1],
+            "assembly-summary.json: token_count 120 exceeds token budget 100"
+        );
+        assert_eq!(
+            problems[2],
+            "manifest.json: task_id T1 does not match task T2"
+        );
+        assert!(problems[3].starts_with("manifest.json: sha256 mismatch for context.md"));
+        assert!(problems[4].starts_with("manifest.json: sha256 mismatch for index.json"));
+        assert_eq!(
+            problems[5],
+            "manifest.json: extra/notes.md has no manifest entry"
+        );
+        assert!(problems[6].starts_with("index.json: invalid"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn index_accepts_list_and_object_forms() {
+        let list: PackIndex =
+            serde_json::from_str(r#"[{"file": "a.rs", "priority": 2}, {"path": "b.rs"}]"#).unwrap();
+        assert_eq!(list.entries().len(), 2);
+        assert_eq!(list.entries()[0].path, "a.rs");
+        assert_eq!(list.entries()[0].priority, Some(2.0));
+
+        let object: PackIndex =
+            serde_json::from_str(r#"{"items": [{"path": "c.rs", "score": 0.1}]}"#).unwrap();
+        assert_eq!(object.entries()[0].priority, Some(0.1));
+
+        let empty: PackIndex = serde_json::from_str("{}").unwrap();
+        assert!(empty.entries().is_empty());
+        assert!(serde_json::from_str::<PackManifest>("{}").is_err());
+
+        let escape = ManifestFile {
+            path: "../secret".to_string(),
+            sha256: String::new(),
+        };
+        assert!(manifest_file_problem(Path::new("."), &escape)
+            .unwrap()
+            .contains("not a path inside the pack"));
+        let unhashed = ManifestFile {
+            path: "context.md".to_string(),
+            sha256: " ".to_string(),
+        };
+        assert_eq!(
+            manifest_file_problem(Path::new("."), &unhashed).as_deref(),
+            Some("context.md has no sha256")
+        );
+    }
+}
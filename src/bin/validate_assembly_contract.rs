@@ -22,12 +22,16 @@ struct Args {
 
 fn main() {
     let args = Args::parse();
-    if let Err(err) = lever::assembly_contract::validate_assembly_contract(&args.assembly) {
-        eprintln!("{}", err);
-        std::process::exit(1);
+    match lever::assembly_contract::validate_assembly_contract(&args.assembly) {
+        Ok(negotiated) => println!(
+            "Assembly contract validated (version {}, assembly {}, via {})",
+            negotiated.contract_version,
+            negotiated.assembly_version,
+            negotiated.source.label()
+        ),
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
     }
-    println!(
-        "Assembly contract validated (version {})",
-        lever::assembly_contract::CONTRACT_VERSION
-    );
 }
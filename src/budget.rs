@@ -0,0 +1,240 @@
+use std::{error::Error, path::Path};
+
+use serde_json::{Map, Value};
+
+use crate::rate_limit::read_usage_entries;
+
+type DynError = Box<dyn Error + Send + Sync + 'static>;
+
+/// Token and USD caps; `None` leaves that dimension unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BudgetLimit {
+    pub max_tokens: Option<u64>,
+    pub max_cost_usd: Option<f64>,
+}
+
+impl BudgetLimit {
+    pub fn is_unlimited(&self) -> bool {
+        self.max_tokens.is_none() && self.max_cost_usd.is_none()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Spend {
+    pub tokens: u64,
+    pub cost_usd: f64,
+}
+
+impl Spend {
+    pub fn add(self, other: Spend) -> Spend {
+        Spend {
+            tokens: self.tokens.saturating_add(other.tokens),
+            cost_usd: self.cost_usd + other.cost_usd,
+        }
+    }
+}
+
+/// Caps shared by every task agent run started by one lever invocation.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SessionBudget {
+    pub session_id: String,
+    pub limit: BudgetLimit,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BudgetExceeded {
+    pub scope: &'static str,
+    pub dimension: &'static str,
+    pub spent: String,
+    pub estimate: String,
+    pub limit: String,
+}
+
+impl BudgetExceeded {
+    pub fn describe(&self) -> String {
+        format!(
+            "{} {} budget exceeded: spent={} estimate={} limit={}",
+            self.scope, self.dimension, self.spent, self.estimate, self.limit
+        )
+    }
+}
+
+/// Reads the optional `budget` object of a task entry.
+pub fn task_budget(raw: &Value) -> BudgetLimit {
+    let budget = raw.get("budget").and_then(Value::as_object);
+    BudgetLimit {
+        max_tokens: budget
+            .and_then(|map| map.get("max_tokens"))
+            .and_then(Value::as_u64),
+        max_cost_usd: budget
+            .and_then(|map| map.get("max_cost_usd"))
+            .and_then(Value::as_f64),
+    }
+}
+
+/// Cumulative spend recorded in the task's `observability` object.
+pub fn task_spend(raw: &Value) -> Spend {
+    observability_spend(raw.get("observability").and_then(Value::as_object))
+}
+
+pub fn add_spend_to_observability(obs: &mut Map<String, Value>, spend: Spend) {
+    let total = observability_spend(Some(obs)).add(spend);
+    obs.insert("total_tokens".to_string(), Value::from(total.tokens));
+    obs.insert(
+        "total_cost_usd".to_string(),
+        Value::from(round_usd(total.cost_usd)),
+    );
+}
+
+fn observability_spend(obs: Option<&Map<String, Value>>) -> Spend {
+    Spend {
+        tokens: obs
+            .and_then(|map| map.get("total_tokens"))
+            .and_then(Value::as_u64)
+            .unwrap_or(0),
+        cost_usd: obs
+            .and_then(|map| map.get("total_cost_usd"))
+            .and_then(Value::as_f64)
+            .unwrap_or(0.0),
+    }
+}
+
+/// Sums the usage ledger entries attributed to one lever session.
+pub fn session_spend(usage_file: &Path, session_id: &str) -> Result<Spend, DynError> {
+    let entries = read_usage_entries(usage_file)?;
+    Ok(entries
+        .iter()
+        .filter(|entry| entry.session_id.as_deref() == Some(session_id))
+        .fold(Spend::default(), |total, entry| {
+            total.add(Spend {
+                tokens: entry.tokens.max(0) as u64,
+                cost_usd: entry.cost_usd.unwrap_or(0.0),
+            })
+        }))
+}
+
+/// Returns the first cap that `spent + estimate` would exceed.
+pub fn check_budget(
+    scope: &'static str,
+    limit: BudgetLimit,
+    spent: Spend,
+    estimate: Spend,
+) -> Option<BudgetExceeded> {
+    if let Some(max_tokens) = limit.max_tokens {
+        if spent.tokens.saturating_add(estimate.tokens) > max_tokens {
+            return Some(BudgetExceeded {
+                scope,
+                dimension: "token",
+                spent: spent.tokens.to_string(),
+                estimate: estimate.tokens.to_string(),
+                limit: max_tokens.to_string(),
+            });
+        }
+    }
+    if let Some(max_cost_usd) = limit.max_cost_usd {
+        if spent.cost_usd + estimate.cost_usd > max_cost_usd {
+            return Some(BudgetExceeded {
+                scope,
+                dimension: "cost",
+                spent: format_usd(spent.cost_usd),
+                estimate: format_usd(estimate.cost_usd),
+                limit: format_usd(max_cost_usd),
+            });
+        }
+    }
+    None
+}
+
+pub fn format_usd(value: f64) -> String {
+    format!("${:.4}", value)
+}
+
+fn round_usd(value: f64) -> f64 {
+    (value * 1_000_000.0).round() / 1_000_000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn task_budget_and_spend_read_task_entry() {
+        let raw = json!({
+            "budget": { "max_tokens": 5000, "max_cost_usd": 1.5 },
+            "observability": { "total_tokens": 1200, "total_cost_usd": 0.25 }
+        });
+        assert_eq!(
+            task_budget(&raw),
+            BudgetLimit {
+                max_tokens: Some(5000),
+                max_cost_usd: Some(1.5),
+            }
+        );
+        assert_eq!(
+            task_spend(&raw),
+            Spend {
+                tokens: 1200,
+                cost_usd: 0.25,
+            }
+        );
+        assert!(task_budget(&json!({})).is_unlimited());
+    }
+
+    #[test]
+    fn check_budget_reports_first_exceeded_dimension() {
+        let limit = BudgetLimit {
+            max_tokens: Some(1000),
+            max_cost_usd: Some(0.5),
+        };
+        let spent = Spend {
+            tokens: 600,
+            cost_usd: 0.2,
+        };
+        let fits = Spend {
+            tokens: 400,
+            cost_usd: 0.3,
+        };
+        assert_eq!(check_budget("task", limit, spent, fits), None);
+
+        let too_many_tokens = Spend {
+            tokens: 401,
+            cost_usd: 0.0,
+        };
+        let exceeded = check_budget("task", limit, spent, too_many_tokens).unwrap();
+        assert_eq!(exceeded.dimension, "token");
+        assert_eq!(
+            exceeded.describe(),
+            "task token budget exceeded: spent=600 estimate=401 limit=1000"
+        );
+
+        let too_costly = Spend {
+            tokens: 10,
+            cost_usd: 0.31,
+        };
+        let exceeded = check_budget("loop", limit, spent, too_costly).unwrap();
+        assert_eq!(exceeded.dimension, "cost");
+        assert_eq!(exceeded.limit, "$0.5000");
+    }
+
+    #[test]
+    fn add_spend_accumulates_observability_totals() {
+        let mut obs = Map::new();
+        add_spend_to_observability(
+            &mut obs,
+            Spend {
+                tokens: 100,
+                cost_usd: 0.1,
+            },
+        );
+        add_spend_to_observability(
+            &mut obs,
+            Spend {
+                tokens: 50,
+                cost_usd: 0.2,
+            },
+        );
+        assert_eq!(obs["total_tokens"], json!(150));
+        assert_eq!(obs["total_cost_usd"], json!(0.3));
+    }
+}
@@ -0,0 +1,453 @@
+use std::{collections::BTreeMap, error::Error, fs, path::Path};
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::lint_report::LintConfig;
+use crate::prompt_budget::DEFAULT_SECTION_PRIORITIES;
+use crate::prompt_profile::valid_profile_name;
+use crate::sandbox::SandboxConfig;
+use crate::verification_detectors::{detector_names, is_detector};
+
+type DynError = Box<dyn Error + Send + Sync + 'static>;
+
+pub const CONFIG_FILE: &str = "lever.json";
+pub const DEFAULT_RATE_LIMIT_WINDOW_SECONDS: u64 = 60;
+pub const DEFAULT_KILL_GRACE_SECONDS: u64 = 10;
+
+/// Optional workspace configuration read from `lever.json`.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LeverConfig {
+    pub rate_limits: RateLimitConfig,
+    pub pricing: BTreeMap<String, ModelPricing>,
+    pub prompt_budget: PromptBudgetConfig,
+    pub prompt_profiles: PromptProfilesConfig,
+    pub lint: LintConfig,
+    pub verification: VerificationConfig,
+    /// Default sandbox for tasks without a `sandbox` object of their own.
+    pub sandbox: Option<SandboxConfig>,
+    pub timeouts: TimeoutConfig,
+}
+
+/// Wall-clock limits for the Codex and Assembly processes and for each
+/// verification step. A task's `timeouts` object overrides the `*_seconds`
+/// limits; a process past its limit gets `SIGTERM`, then `SIGKILL` after
+/// `kill_grace_seconds`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TimeoutConfig {
+    pub codex_seconds: Option<u64>,
+    pub assembly_seconds: Option<u64>,
+    pub verification_seconds: Option<u64>,
+    pub kill_grace_seconds: u64,
+}
+
+/// The `timeouts` object of a task entry.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct TaskTimeouts {
+    codex_seconds: Option<u64>,
+    assembly_seconds: Option<u64>,
+    verification_seconds: Option<u64>,
+}
+
+impl Default for TimeoutConfig {
+    fn default() -> Self {
+        Self {
+            codex_seconds: None,
+            assembly_seconds: None,
+            verification_seconds: None,
+            kill_grace_seconds: DEFAULT_KILL_GRACE_SECONDS,
+        }
+    }
+}
+
+impl TimeoutConfig {
+    /// Applies the optional `timeouts` object of a task entry.
+    pub fn for_task(&self, raw: &Value) -> Result<Self, DynError> {
+        let task: TaskTimeouts = match raw.get("timeouts") {
+            Some(timeouts) => serde_json::from_value(timeouts.clone())
+                .map_err(|err| format!("Invalid task timeouts: {}", err))?,
+            None => TaskTimeouts::default(),
+        };
+        let timeouts = Self {
+            codex_seconds: task.codex_seconds.or(self.codex_seconds),
+            assembly_seconds: task.assembly_seconds.or(self.assembly_seconds),
+            verification_seconds: task.verification_seconds.or(self.verification_seconds),
+            kill_grace_seconds: self.kill_grace_seconds,
+        };
+        timeouts.validate("timeouts")?;
+        Ok(timeouts)
+    }
+
+    pub fn validate(&self, field: &str) -> Result<(), DynError> {
+        for (name, value) in [
+            ("codex_seconds", self.codex_seconds),
+            ("assembly_seconds", self.assembly_seconds),
+            ("verification_seconds", self.verification_seconds),
+        ] {
+            if value == Some(0) {
+                return Err(format!("{}.{} must be >= 1", field, name).into());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// How deterministic verification runs after Codex reports `dod_met`.
+/// `clean_checkout` verifies a fresh `git worktree` of the committed state
+/// instead of the agent's working tree. `detectors` replaces the default
+/// auto-detection order and `disabled_detectors` opts out of some. `retries`
+/// re-runs a failing step; one that then passes is recorded as flaky.
+/// `baseline` verifies the run's starting commit first (cached per commit) so
+/// failures can be compared with it.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct VerificationConfig {
+    pub clean_checkout: bool,
+    pub detectors: Option<Vec<String>>,
+    pub disabled_detectors: Vec<String>,
+    pub retries: u32,
+    pub baseline: bool,
+}
+
+/// Prompt size budget; `--prompt-token-budget` overrides `max_tokens`.
+/// `priorities` reorders the cuttable sections (lower is cut first).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PromptBudgetConfig {
+    pub max_tokens: Option<u64>,
+    pub priorities: BTreeMap<String, u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RateLimitConfig {
+    pub window_seconds: u64,
+    pub models: BTreeMap<String, ModelRateLimit>,
+}
+
+/// Default `prompts/<profile>.prompt.md` per model, used when a task has
+/// no `prompt` of its own.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PromptProfilesConfig {
+    pub models: BTreeMap<String, String>,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            window_seconds: DEFAULT_RATE_LIMIT_WINDOW_SECONDS,
+            models: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ModelRateLimit {
+    pub tpm: Option<u64>,
+    pub rpm: Option<u64>,
+    pub daily_tokens: Option<u64>,
+}
+
+/// USD price per million input/output tokens for one model.
+#[derive(Debug, Copy, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ModelPricing {
+    pub input_per_million: f64,
+    pub output_per_million: f64,
+}
+
+impl ModelPricing {
+    pub fn cost_usd(&self, input_tokens: u64, output_tokens: u64) -> f64 {
+        (input_tokens as f64 * self.input_per_million
+            + output_tokens as f64 * self.output_per_million)
+            / 1_000_000.0
+    }
+}
+
+impl LeverConfig {
+    pub fn load(workspace: &Path) -> Result<Self, DynError> {
+        let path = workspace.join(CONFIG_FILE);
+        if !path.is_file() {
+            return Ok(Self::default());
+        }
+        let raw = fs::read_to_string(&path)
+            .map_err(|err| format!("Failed to read config {}: {}", path.display(), err))?;
+        Self::parse(&raw)
+            .map_err(|err| format!("Invalid config {}: {}", path.display(), err).into())
+    }
+
+    pub fn parse(raw: &str) -> Result<Self, DynError> {
+        let config: Self = serde_json::from_str(raw)?;
+        if config.rate_limits.window_seconds == 0 {
+            return Err("rate_limits.window_seconds must be >= 1".into());
+        }
+        for (model, pricing) in &config.pricing {
+            let valid = |price: f64| price.is_finite() && price >= 0.0;
+            if !valid(pricing.input_per_million) || !valid(pricing.output_per_million) {
+                return Err(format!("pricing.{} prices must be finite and >= 0", model).into());
+            }
+        }
+        if config.prompt_budget.max_tokens == Some(0) {
+            return Err("prompt_budget.max_tokens must be >= 1".into());
+        }
+        for section in config.prompt_budget.priorities.keys() {
+            if !DEFAULT_SECTION_PRIORITIES
+                .iter()
+                .any(|(name, _)| name == section)
+            {
+                let names: Vec<&str> = DEFAULT_SECTION_PRIORITIES
+                    .iter()
+                    .map(|(name, _)| *name)
+                    .collect();
+                return Err(format!(
+                    "prompt_budget.priorities.{} is not a cuttable section (expected one of: {})",
+                    section,
+                    names.join(", ")
+                )
+                .into());
+            }
+        }
+        for (model, profile) in &config.prompt_profiles.models {
+            if !valid_profile_name(profile) {
+                return Err(format!(
+                    "prompt_profiles.models.{} must be a profile name under prompts/, got {:?}",
+                    model, profile
+                )
+                .into());
+            }
+        }
+        let verification = &config.verification;
+        for (key, names) in [
+            (
+                "detectors",
+                verification.detectors.as_deref().unwrap_or(&[]),
+            ),
+            (
+                "disabled_detectors",
+                verification.disabled_detectors.as_slice(),
+            ),
+        ] {
+            for (index, name) in names.iter().enumerate() {
+                if !is_detector(name) {
+                    return Err(format!(
+                        "verification.{} has unknown detector {:?} (expected one of: {})",
+                        key,
+                        name,
+                        detector_names().collect::<Vec<_>>().join(", ")
+                    )
+                    .into());
+                }
+                if names[..index].contains(name) {
+                    return Err(
+                        format!("verification.{} lists {:?} more than once", key, name).into(),
+                    );
+                }
+            }
+        }
+        config.timeouts.validate("timeouts")?;
+        if let Some(sandbox) = &config.sandbox {
+            sandbox.validate("sandbox")?;
+        }
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lint_report::{LintFailMode, LintSeverity};
+
+    #[test]
+    fn config_defaults_when_empty() {
+        let config = LeverConfig::parse("{}").unwrap();
+        assert_eq!(config, LeverConfig::default());
+        assert_eq!(
+            config.rate_limits.window_seconds,
+            DEFAULT_RATE_LIMIT_WINDOW_SECONDS
+        );
+    }
+
+    #[test]
+    fn config_parses_model_rate_limits() {
+        let config = LeverConfig::parse(
+            r#"{
+              "rate_limits": {
+                "window_seconds": 30,
+                "models": {
+                  "gpt-5.2-codex": { "tpm": 1000, "daily_tokens": 50000 }
+                }
+              }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(config.rate_limits.window_seconds, 30);
+        let limits = config.rate_limits.models["gpt-5.2-codex"];
+        assert_eq!(limits.tpm, Some(1000));
+        assert_eq!(limits.rpm, None);
+        assert_eq!(limits.daily_tokens, Some(50000));
+    }
+
+    #[test]
+    fn config_rejects_unknown_keys_and_zero_window() {
+        assert!(LeverConfig::parse(r#"{ "rate_limit": {} }"#).is_err());
+        assert!(LeverConfig::parse(r#"{ "rate_limits": { "window_seconds": 0 } }"#).is_err());
+    }
+
+    #[test]
+    fn config_parses_pricing_and_computes_cost() {
+        let config = LeverConfig::parse(
+            r#"{
+              "pricing": {
+                "gpt-5.2-codex": { "input_per_million": 1.25, "output_per_million": 10.0 }
+              }
+            }"#,
+        )
+        .unwrap();
+        let pricing = config.pricing["gpt-5.2-codex"];
+        let cost = pricing.cost_usd(400_000, 50_000);
+        assert!((cost - 1.0).abs() < 1e-9);
+
+        assert!(LeverConfig::parse(
+            r#"{ "pricing": { "gpt-5.2-codex": { "input_per_million": -1, "output_per_million": 1 } } }"#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn config_parses_prompt_budget() {
+        let config = LeverConfig::parse(
+            r#"{ "prompt_budget": { "max_tokens": 8000, "priorities": { "lint_summary": 5 } } }"#,
+        )
+        .unwrap();
+        assert_eq!(config.prompt_budget.max_tokens, Some(8000));
+        assert_eq!(config.prompt_budget.priorities["lint_summary"], 5);
+
+        assert!(
+            LeverConfig::parse(r#"{ "prompt_budget": { "priorities": { "task": 1 } } }"#).is_err()
+        );
+        assert!(LeverConfig::parse(r#"{ "prompt_budget": { "max_tokens": 0 } }"#).is_err());
+    }
+
+    #[test]
+    fn config_parses_model_prompt_profiles() {
+        let config = LeverConfig::parse(
+            r#"{ "prompt_profiles": { "models": { "gpt-5.2-codex": "deep-work" } } }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.prompt_profiles.models["gpt-5.2-codex"],
+            "deep-work".to_string()
+        );
+        assert!(LeverConfig::parse(
+            r#"{ "prompt_profiles": { "models": { "gpt-5.2-codex": "../outside" } } }"#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn config_parses_lint_settings() {
+        let config = LeverConfig::parse(
+            r#"{ "lint": { "min_severity": "warning", "max_findings": 20, "max_errors": 0, "fail": "verification" } }"#,
+        )
+        .unwrap();
+        assert_eq!(config.lint.min_severity, Some(LintSeverity::Warning));
+        assert_eq!(config.lint.max_findings, 20);
+        assert_eq!(config.lint.max_errors, Some(0));
+        assert_eq!(config.lint.fail, LintFailMode::Verification);
+        assert_eq!(LeverConfig::default().lint.max_findings, 5);
+
+        assert!(LeverConfig::parse(r#"{ "lint": { "min_severity": "fatal" } }"#).is_err());
+    }
+
+    #[test]
+    fn config_parses_verification_settings() {
+        let config =
+            LeverConfig::parse(r#"{ "verification": { "clean_checkout": true } }"#).unwrap();
+        assert!(config.verification.clean_checkout);
+        assert!(!LeverConfig::default().verification.clean_checkout);
+        assert!(LeverConfig::parse(r#"{ "verification": { "worktree": true } }"#).is_err());
+        assert_eq!(
+            LeverConfig::parse(r#"{ "verification": { "retries": 2 } }"#)
+                .unwrap()
+                .verification
+                .retries,
+            2
+        );
+        assert!(
+            LeverConfig::parse(r#"{ "verification": { "baseline": true } }"#)
+                .unwrap()
+                .verification
+                .baseline
+        );
+
+        let config = LeverConfig::parse(
+            r#"{ "verification": { "detectors": ["cargo", "npm"], "disabled_detectors": ["pytest"] } }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.verification.detectors,
+            Some(vec!["cargo".to_string(), "npm".to_string()])
+        );
+        assert_eq!(config.verification.disabled_detectors, vec!["pytest"]);
+        assert!(LeverConfig::parse(r#"{ "verification": { "detectors": ["bazel"] } }"#).is_err());
+        assert!(LeverConfig::parse(
+            r#"{ "verification": { "disabled_detectors": ["go", "go"] } }"#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn config_parses_sandbox_default() {
+        assert_eq!(LeverConfig::default().sandbox, None);
+        let config = LeverConfig::parse(
+            r#"{ "sandbox": { "network": true, "cpu_seconds": 600, "writable": ["../cache"] } }"#,
+        )
+        .unwrap();
+        let sandbox = config.sandbox.unwrap();
+        assert!(sandbox.enabled);
+        assert!(sandbox.network);
+        assert_eq!(sandbox.cpu_seconds, Some(600));
+        assert_eq!(sandbox.writable, vec!["../cache"]);
+        assert!(LeverConfig::parse(r#"{ "sandbox": { "cpu_seconds": 0 } }"#).is_err());
+        assert!(LeverConfig::parse(r#"{ "sandbox": { "seccomp": true } }"#).is_err());
+        assert!(LeverConfig::parse(r#"{ "sandbox": { "timeout_seconds": 60 } }"#).is_err());
+    }
+
+    #[test]
+    fn config_parses_timeouts_and_applies_task_overrides() {
+        assert_eq!(
+            LeverConfig::default().timeouts.kill_grace_seconds,
+            DEFAULT_KILL_GRACE_SECONDS
+        );
+        let config = LeverConfig::parse(
+            r#"{ "timeouts": { "codex_seconds": 1800, "assembly_seconds": 120, "kill_grace_seconds": 5 } }"#,
+        )
+        .unwrap();
+        let task =
+            serde_json::json!({ "timeouts": { "codex_seconds": 60, "verification_seconds": 300 } });
+        let timeouts = config.timeouts.for_task(&task).unwrap();
+        assert_eq!(timeouts.codex_seconds, Some(60));
+        assert_eq!(timeouts.assembly_seconds, Some(120));
+        assert_eq!(timeouts.verification_seconds, Some(300));
+        assert_eq!(timeouts.kill_grace_seconds, 5);
+        assert_eq!(
+            config.timeouts.for_task(&serde_json::json!({})).unwrap(),
+            config.timeouts
+        );
+        assert!(LeverConfig::parse(r#"{ "timeouts": { "codex_seconds": 0 } }"#).is_err());
+        assert!(LeverConfig::parse(r#"{ "timeouts": { "verification_seconds": 0 } }"#).is_err());
+        for invalid in [
+            serde_json::json!({ "timeouts": { "codex_seconds": 0 } }),
+            serde_json::json!({ "timeouts": { "assembly_seconds": -5 } }),
+            serde_json::json!({ "timeouts": { "verification_seconds": "60" } }),
+            serde_json::json!({ "timeouts": { "kill_grace_seconds": 1 } }),
+        ] {
+            assert!(config.timeouts.for_task(&invalid).is_err(), "{}", invalid);
+        }
+    }
+}
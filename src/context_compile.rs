@@ -1,5 +1,9 @@
 use std::path::PathBuf;
 
+use serde_json::{json, Map, Value};
+
+use crate::assembly_contract::NegotiatedContract;
+
 pub const DEFAULT_CONTEXT_TOKEN_BUDGET: u64 = 8_000;
 pub const DEFAULT_ASSEMBLY_PATH: &str = "assembly";
 pub const DEFAULT_CONTEXT_EXCLUDE_GLOBS: &[&str] = &[".git/**", ".ralph/**"];
@@ -11,14 +15,100 @@ pub enum ContextFailurePolicy {
     Required,
 }
 
+/// Which context compiler builds the pack. `Native` is the built-in
+/// fallback used when the Assembly executable is missing.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum ContextCompilerKind {
+    #[default]
+    Assembly,
+    Native,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ContextCompileConfig {
     pub enabled: bool,
     pub policy: ContextFailurePolicy,
+    pub compiler: ContextCompilerKind,
     pub token_budget: u64,
     pub assembly_path: PathBuf,
     pub exclude_globs: Vec<String>,
     pub exclude_runtime_globs: Vec<String>,
+    /// Globs that bring files back into context even when an exclude glob
+    /// matches them.
+    pub include_globs: Vec<String>,
+    /// Files that always go into the pack, ahead of ranked files.
+    pub pinned_files: Vec<String>,
+    /// The Assembly CLI contract negotiated at startup; it decides which
+    /// optional build flags Lever may pass.
+    pub assembly_contract: Option<NegotiatedContract>,
+}
+
+/// The optional `context` object on a task: extra include/exclude globs,
+/// pinned files, and a per-task token budget.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct TaskContextHints {
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    pub pin: Vec<String>,
+    pub token_budget: Option<u64>,
+}
+
+impl TaskContextHints {
+    /// Reads `context` from a raw task entry. Missing or malformed fields are
+    /// treated as absent; the tasks schema rejects them up front.
+    pub fn from_task(raw: &Value) -> Self {
+        let Some(context) = raw.get("context").and_then(Value::as_object) else {
+            return Self::default();
+        };
+        let strings = |key: &str| -> Vec<String> {
+            context
+                .get(key)
+                .and_then(Value::as_array)
+                .map(|items| {
+                    items
+                        .iter()
+                        .filter_map(Value::as_str)
+                        .filter(|item| !item.is_empty())
+                        .map(str::to_string)
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+        Self {
+            include: strings("include"),
+            exclude: strings("exclude"),
+            pin: strings("pin"),
+            token_budget: context
+                .get("token_budget")
+                .and_then(Value::as_u64)
+                .filter(|budget| *budget > 0),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty()
+            && self.exclude.is_empty()
+            && self.pin.is_empty()
+            && self.token_budget.is_none()
+    }
+
+    /// The hints as written to `assembly-task.json`; empty fields are omitted.
+    pub fn to_json(&self) -> Value {
+        let mut map = Map::new();
+        for (key, values) in [
+            ("include", &self.include),
+            ("exclude", &self.exclude),
+            ("pin", &self.pin),
+        ] {
+            if !values.is_empty() {
+                map.insert(key.to_string(), json!(values));
+            }
+        }
+        if let Some(budget) = self.token_budget {
+            map.insert("token_budget".to_string(), json!(budget));
+        }
+        Value::Object(map)
+    }
 }
 
 impl Default for ContextCompileConfig {
@@ -26,6 +116,7 @@ impl Default for ContextCompileConfig {
         Self {
             enabled: false,
             policy: ContextFailurePolicy::BestEffort,
+            compiler: ContextCompilerKind::Assembly,
             token_budget: DEFAULT_CONTEXT_TOKEN_BUDGET,
             assembly_path: PathBuf::from(DEFAULT_ASSEMBLY_PATH),
             exclude_globs: DEFAULT_CONTEXT_EXCLUDE_GLOBS
@@ -34,6 +125,33 @@ impl Default for ContextCompileConfig {
                 .map(str::to_string)
                 .collect(),
             exclude_runtime_globs: Vec::new(),
+            include_globs: Vec::new(),
+            pinned_files: Vec::new(),
+            assembly_contract: None,
+        }
+    }
+}
+
+impl ContextCompileConfig {
+    /// Applies a task's hints on top of the global configuration: globs and
+    /// pins are appended (without duplicates) and the task budget replaces the
+    /// global one.
+    pub fn with_task_hints(&self, hints: &TaskContextHints) -> Self {
+        let mut config = self.clone();
+        extend_unique(&mut config.include_globs, &hints.include);
+        extend_unique(&mut config.exclude_globs, &hints.exclude);
+        extend_unique(&mut config.pinned_files, &hints.pin);
+        if let Some(budget) = hints.token_budget {
+            config.token_budget = budget;
+        }
+        config
+    }
+}
+
+fn extend_unique(target: &mut Vec<String>, values: &[String]) {
+    for value in values {
+        if !target.contains(value) {
+            target.push(value.clone());
         }
     }
 }
@@ -47,6 +165,7 @@ mod tests {
         let config = ContextCompileConfig::default();
         assert!(!config.enabled);
         assert_eq!(config.policy, ContextFailurePolicy::BestEffort);
+        assert_eq!(config.compiler, ContextCompilerKind::Assembly);
         assert_eq!(config.token_budget, DEFAULT_CONTEXT_TOKEN_BUDGET);
         assert_eq!(config.assembly_path, PathBuf::from(DEFAULT_ASSEMBLY_PATH));
         assert_eq!(
@@ -58,5 +177,43 @@ mod tests {
                 .collect::<Vec<_>>()
         );
         assert!(config.exclude_runtime_globs.is_empty());
+        assert!(config.include_globs.is_empty());
+        assert!(config.pinned_files.is_empty());
+    }
+
+    #[test]
+    fn task_hints_merge_into_config() {
+        let raw = json!({
+            "task_id": "T1",
+            "context": {
+                "include": ["src/rate_limit.rs"],
+                "exclude": ["target/**", ".git/**"],
+                "pin": ["docs/cli-contract.md"],
+                "token_budget": 2000
+            }
+        });
+        let hints = TaskContextHints::from_task(&raw);
+        assert_eq!(hints.token_budget, Some(2000));
+        assert_eq!(
+            hints.to_json(),
+            raw["context"],
+            "hints round-trip into assembly-task.json"
+        );
+
+        let config = ContextCompileConfig::default().with_task_hints(&hints);
+        assert_eq!(config.token_budget, 2000);
+        assert_eq!(
+            config.exclude_globs,
+            vec![".git/**", ".ralph/**", "target/**"]
+        );
+        assert_eq!(config.include_globs, vec!["src/rate_limit.rs"]);
+        assert_eq!(config.pinned_files, vec!["docs/cli-contract.md"]);
+
+        let none = TaskContextHints::from_task(&json!({"task_id": "T2"}));
+        assert!(none.is_empty());
+        assert_eq!(
+            ContextCompileConfig::default().with_task_hints(&none),
+            ContextCompileConfig::default()
+        );
     }
 }
@@ -0,0 +1,321 @@
+use std::{
+    error::Error, ffi::OsString, fs::File, path::Path, process::Command, sync::atomic::AtomicBool,
+};
+
+use lever::assembly_contract::NegotiatedContract;
+use lever::context_compile::{ContextCompileConfig, ContextCompilerKind};
+
+use crate::native_context::NativeCompiler;
+use crate::process_group::{spawn_in_group, wait_with_deadline, Deadline, WaitOutcome};
+use crate::run_paths::RunPaths;
+
+type DynError = Box<dyn Error + Send + Sync + 'static>;
+
+pub enum CompileOutcome {
+    Success,
+    Failed { code: Option<i32>, message: String },
+    Interrupted,
+    TimedOut,
+}
+
+/// Builds the context pack for a run. Every implementation reads the task
+/// from `assembly-task.json` and writes the same pack layout plus
+/// `assembly-summary.json`, so pack validation and prompt building do not
+/// care which one ran.
+pub trait ContextCompiler {
+    /// Label used in logs and failure notes.
+    fn name(&self) -> &'static str;
+
+    /// Whether packs from this compiler go through the pack cache.
+    fn cacheable(&self) -> bool;
+
+    /// Compilers that run a child process stop it at `deadline`.
+    fn compile(
+        &self,
+        workspace: &Path,
+        task_id: &str,
+        paths: &RunPaths,
+        deadline: Deadline,
+        shutdown_flag: Option<&AtomicBool>,
+    ) -> Result<CompileOutcome, DynError>;
+}
+
+pub fn context_compiler(config: &ContextCompileConfig) -> Box<dyn ContextCompiler + '_> {
+    match config.compiler {
+        ContextCompilerKind::Assembly => Box::new(AssemblyCompiler { config }),
+        ContextCompilerKind::Native => Box::new(NativeCompiler { config }),
+    }
+}
+
+pub struct AssemblyCompiler<'a> {
+    config: &'a ContextCompileConfig,
+}
+
+impl ContextCompiler for AssemblyCompiler<'_> {
+    fn name(&self) -> &'static str {
+        "Assembly"
+    }
+
+    fn cacheable(&self) -> bool {
+        true
+    }
+
+    fn compile(
+        &self,
+        workspace: &Path,
+        task_id: &str,
+        paths: &RunPaths,
+        deadline: Deadline,
+        shutdown_flag: Option<&AtomicBool>,
+    ) -> Result<CompileOutcome, DynError> {
+        run_assembly(
+            workspace,
+            task_id,
+            paths,
+            self.config,
+            deadline,
+            shutdown_flag,
+        )
+    }
+}
+
+/// `--include` and `--pin` are only passed when the task sets them, and only
+/// to an Assembly whose negotiated contract has those flags.
+fn build_assembly_command_args(
+    workspace: &Path,
+    task_id: &str,
+    task_input: &Path,
+    pack_dir: &Path,
+    summary_json: &Path,
+    config: &ContextCompileConfig,
+    contract: Option<&NegotiatedContract>,
+) -> Result<Vec<OsString>, DynError> {
+    let mut args = vec![
+        OsString::from("build"),
+        OsString::from("--repo"),
+        workspace.as_os_str().to_os_string(),
+        OsString::from("--task"),
+    ];
+    let mut task_arg = OsString::from("@");
+    task_arg.push(task_input);
+    args.push(task_arg);
+    args.push("--task-id".into());
+    args.push(task_id.into());
+    args.push("--out".into());
+    args.push(pack_dir.as_os_str().to_os_string());
+    args.push("--token-budget".into());
+    args.push(config.token_budget.to_string().into());
+
+    for glob in &config.exclude_globs {
+        args.push("--exclude".into());
+        args.push(glob.into());
+    }
+
+    for glob in &config.exclude_runtime_globs {
+        args.push("--exclude-runtime".into());
+        args.push(glob.into());
+    }
+
+    let hints = !config.include_globs.is_empty() || !config.pinned_files.is_empty();
+    if hints
+        && !contract
+            .is_some_and(|contract| contract.supports("--include") && contract.supports("--pin"))
+    {
+        return Err(format!(
+            "Assembly does not support include/pin (contract {}); remove the task's context include/pin hints or upgrade Assembly",
+            contract.map_or("not negotiated", |contract| contract.contract_version)
+        )
+        .into());
+    }
+
+    for glob in &config.include_globs {
+        args.push("--include".into());
+        args.push(glob.into());
+    }
+
+    for path in &config.pinned_files {
+        args.push("--pin".into());
+        args.push(path.into());
+    }
+
+    args.push("--summary-json".into());
+    args.push(summary_json.as_os_str().to_os_string());
+
+    Ok(args)
+}
+
+fn run_assembly(
+    workspace: &Path,
+    task_id: &str,
+    paths: &RunPaths,
+    config: &ContextCompileConfig,
+    deadline: Deadline,
+    shutdown_flag: Option<&AtomicBool>,
+) -> Result<CompileOutcome, DynError> {
+    let args = build_assembly_command_args(
+        workspace,
+        task_id,
+        &paths.assembly_task_path,
+        &paths.pack_dir_abs,
+        &paths.assembly_summary_path,
+        config,
+        config.assembly_contract.as_ref(),
+    )?;
+
+    let stdout_file = File::create(&paths.assembly_stdout_path)?;
+    let stderr_file = File::create(&paths.assembly_stderr_path)?;
+
+    let mut child = spawn_in_group(
+        Command::new(&config.assembly_path)
+            .current_dir(workspace)
+            .args(args)
+            .stdout(stdout_file)
+            .stderr(stderr_file),
+    )?;
+
+    match wait_with_deadline(&mut child, deadline, shutdown_flag)? {
+        WaitOutcome::Exited(status) if status.success() => Ok(CompileOutcome::Success),
+        WaitOutcome::Exited(status) => Ok(CompileOutcome::Failed {
+            code: status.code(),
+            message: "Assembly exited with non-zero status".to_string(),
+        }),
+        WaitOutcome::Interrupted => Ok(CompileOutcome::Interrupted),
+        WaitOutcome::TimedOut => Ok(CompileOutcome::TimedOut),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::run_paths::run_paths;
+    use lever::assembly_contract::{
+        AssemblyVersion, NegotiationSource, BASE_CONTRACT_VERSION, CONTRACT_VERSION,
+        SUPPORTED_CONTRACTS,
+    };
+    use std::path::PathBuf;
+
+    fn negotiated(version: &str) -> NegotiatedContract {
+        let contract = SUPPORTED_CONTRACTS
+            .iter()
+            .find(|contract| contract.version == version)
+            .unwrap();
+        NegotiatedContract {
+            contract_version: contract.version,
+            assembly_version: AssemblyVersion::new(1, 2, 3),
+            source: NegotiationSource::Help,
+            build_flags: contract.required_build_flags,
+        }
+    }
+
+    fn args_to_strings(args: Vec<OsString>) -> Vec<String> {
+        args.into_iter()
+            .map(|arg| arg.to_string_lossy().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn assembly_command_args_include_expected_fields() {
+        let workspace = PathBuf::from("/repo");
+        let task_id = "T1";
+        let run_id = "run-123";
+        let paths = run_paths(&workspace, task_id, run_id);
+        let config = ContextCompileConfig {
+            enabled: true,
+            token_budget: 9001,
+            exclude_globs: vec![".git/**".to_string(), "node_modules/**".to_string()],
+            exclude_runtime_globs: vec![".ralph/**".to_string()],
+            include_globs: vec!["src/rate_limit.rs".to_string()],
+            pinned_files: vec!["docs/cli-contract.md".to_string()],
+            ..Default::default()
+        };
+
+        let args = build_assembly_command_args(
+            &workspace,
+            task_id,
+            &paths.assembly_task_path,
+            &paths.pack_dir_abs,
+            &paths.assembly_summary_path,
+            &config,
+            Some(&negotiated(CONTRACT_VERSION)),
+        )
+        .unwrap();
+        let args = args_to_strings(args);
+
+        let expected = vec![
+            "build".to_string(),
+            "--repo".to_string(),
+            "/repo".to_string(),
+            "--task".to_string(),
+            format!("@{}", paths.assembly_task_path.display()),
+            "--task-id".to_string(),
+            "T1".to_string(),
+            "--out".to_string(),
+            paths.pack_dir_abs.display().to_string(),
+            "--token-budget".to_string(),
+            "9001".to_string(),
+            "--exclude".to_string(),
+            ".git/**".to_string(),
+            "--exclude".to_string(),
+            "node_modules/**".to_string(),
+            "--exclude-runtime".to_string(),
+            ".ralph/**".to_string(),
+            "--include".to_string(),
+            "src/rate_limit.rs".to_string(),
+            "--pin".to_string(),
+            "docs/cli-contract.md".to_string(),
+            "--summary-json".to_string(),
+            paths.assembly_summary_path.display().to_string(),
+        ];
+
+        assert_eq!(args, expected);
+    }
+
+    #[test]
+    fn context_hint_flags_follow_the_negotiated_contract() {
+        let workspace = PathBuf::from("/repo");
+        let paths = run_paths(&workspace, "T1", "run-123");
+        let hinted = ContextCompileConfig {
+            enabled: true,
+            include_globs: vec!["vendor/**".to_string()],
+            pinned_files: vec!["src/lib.rs".to_string()],
+            ..Default::default()
+        };
+        let build = |config: &ContextCompileConfig, contract: Option<&NegotiatedContract>| {
+            build_assembly_command_args(
+                &workspace,
+                "T1",
+                &paths.assembly_task_path,
+                &paths.pack_dir_abs,
+                &paths.assembly_summary_path,
+                config,
+                contract,
+            )
+        };
+
+        let current = args_to_strings(build(&hinted, Some(&negotiated(CONTRACT_VERSION))).unwrap());
+        assert!(current
+            .windows(2)
+            .any(|window| window == ["--include", "vendor/**"]));
+        assert!(current
+            .windows(2)
+            .any(|window| window == ["--pin", "src/lib.rs"]));
+
+        let base = negotiated(BASE_CONTRACT_VERSION);
+        let err = build(&hinted, Some(&base)).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("Assembly does not support include/pin (contract 2026-02-16)"));
+        assert!(build(&hinted, None).is_err());
+
+        let plain = ContextCompileConfig {
+            enabled: true,
+            ..Default::default()
+        };
+        let args = args_to_strings(build(&plain, Some(&base)).unwrap());
+        assert!(!args.iter().any(|arg| arg == "--include" || arg == "--pin"));
+        assert_eq!(
+            args,
+            args_to_strings(build(&plain, Some(&negotiated(CONTRACT_VERSION))).unwrap())
+        );
+    }
+}
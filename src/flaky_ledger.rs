@@ -0,0 +1,137 @@
+use std::{error::Error, fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+type DynError = Box<dyn Error + Send + Sync + 'static>;
+
+pub const FLAKY_LEDGER_FILE: &str = ".ralph/flaky.json";
+
+/// Flakes at or above this count are reported as repeated by `lever status`.
+pub const REPEATED_FLAKE_THRESHOLD: u64 = 2;
+
+/// A verification command that failed and then passed on retry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FlakyRecord {
+    pub command: String,
+    pub flakes: u64,
+    pub first_seen_utc: String,
+    pub last_seen_utc: String,
+    pub last_task_id: String,
+    pub last_run_id: String,
+}
+
+impl FlakyRecord {
+    pub fn is_repeated(&self) -> bool {
+        self.flakes >= REPEATED_FLAKE_THRESHOLD
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct FlakyLedger {
+    #[serde(default)]
+    commands: Vec<FlakyRecord>,
+}
+
+pub fn load_flaky_records(workspace: &Path) -> Result<Vec<FlakyRecord>, DynError> {
+    Ok(read_ledger(workspace)?.commands)
+}
+
+/// Counts one flake for each command and returns their updated records.
+pub fn record_flakes(
+    workspace: &Path,
+    commands: &[String],
+    task_id: &str,
+    run_id: &str,
+    now_utc: &str,
+) -> Result<Vec<FlakyRecord>, DynError> {
+    let mut ledger = read_ledger(workspace)?;
+    for command in commands {
+        match ledger
+            .commands
+            .iter_mut()
+            .find(|record| &record.command == command)
+        {
+            Some(record) => {
+                record.flakes += 1;
+                record.last_seen_utc = now_utc.to_string();
+                record.last_task_id = task_id.to_string();
+                record.last_run_id = run_id.to_string();
+            }
+            None => ledger.commands.push(FlakyRecord {
+                command: command.clone(),
+                flakes: 1,
+                first_seen_utc: now_utc.to_string(),
+                last_seen_utc: now_utc.to_string(),
+                last_task_id: task_id.to_string(),
+                last_run_id: run_id.to_string(),
+            }),
+        }
+    }
+    write_ledger(workspace, &ledger)?;
+    Ok(ledger
+        .commands
+        .into_iter()
+        .filter(|record| commands.contains(&record.command))
+        .collect())
+}
+
+fn read_ledger(workspace: &Path) -> Result<FlakyLedger, DynError> {
+    let path = workspace.join(FLAKY_LEDGER_FILE);
+    if !path.is_file() {
+        return Ok(FlakyLedger::default());
+    }
+    let raw = fs::read_to_string(&path)
+        .map_err(|err| format!("Failed to read flaky ledger {}: {}", path.display(), err))?;
+    serde_json::from_str(&raw)
+        .map_err(|err| format!("Failed to parse flaky ledger {}: {}", path.display(), err).into())
+}
+
+fn write_ledger(workspace: &Path, ledger: &FlakyLedger) -> Result<(), DynError> {
+    let path = workspace.join(FLAKY_LEDGER_FILE);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, serde_json::to_string_pretty(ledger)?)
+        .map_err(|err| format!("Failed to write flaky ledger {}: {}", path.display(), err))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn temp_workspace(name: &str) -> PathBuf {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let path = std::env::temp_dir().join(format!("lever-flaky-{}-{}", name, nanos));
+        fs::create_dir_all(&path).unwrap();
+        path
+    }
+
+    #[test]
+    fn flakes_accumulate_per_command() {
+        let workspace = temp_workspace("accumulate");
+        assert!(load_flaky_records(&workspace).unwrap().is_empty());
+
+        let commands = vec!["cargo test".to_string()];
+        let updated =
+            record_flakes(&workspace, &commands, "T1", "run-1", "2026-01-01T00:00:00Z").unwrap();
+        assert_eq!(updated.len(), 1);
+        assert!(!updated[0].is_repeated());
+
+        let commands = vec!["cargo test".to_string(), "npm test".to_string()];
+        let updated =
+            record_flakes(&workspace, &commands, "T2", "run-2", "2026-01-02T00:00:00Z").unwrap();
+        assert_eq!(updated[0].flakes, 2);
+        assert!(updated[0].is_repeated());
+        assert_eq!(updated[0].first_seen_utc, "2026-01-01T00:00:00Z");
+        assert_eq!(updated[0].last_task_id, "T2");
+        assert_eq!(updated[1].command, "npm test");
+        assert_eq!(load_flaky_records(&workspace).unwrap().len(), 2);
+        fs::remove_dir_all(&workspace).unwrap();
+    }
+}
@@ -1,2 +1,4 @@
 pub mod assembly_contract;
+pub mod assembly_pack;
 pub mod context_compile;
+pub mod token_estimate;
@@ -0,0 +1,665 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::Path,
+};
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::prompt_budget::{LintFinding, SectionBody};
+
+pub const LINT_FILE: &str = "lint.json";
+/// Environment variable naming the run directory where verification
+/// commands may drop SARIF logs.
+pub const SARIF_DIR_ENV: &str = "LEVER_SARIF_DIR";
+/// Tool name for pack `lint.json` findings in `findings.sarif`.
+pub const CONTEXT_LINT_TOOL: &str = "lever-context-lint";
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+pub const DEFAULT_MAX_LINT_FINDINGS: usize = 5;
+const ISSUE_KEYS: &[&str] = &["issues", "findings", "diagnostics", "results", "items"];
+
+/// Severity threshold for `lint.min_severity` in `lever.json`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LintSeverity {
+    Error,
+    Warning,
+    Note,
+    Info,
+    Hint,
+}
+
+impl LintSeverity {
+    pub fn label(self) -> &'static str {
+        match self {
+            LintSeverity::Error => "error",
+            LintSeverity::Warning => "warning",
+            LintSeverity::Note => "note",
+            LintSeverity::Info => "info",
+            LintSeverity::Hint => "hint",
+        }
+    }
+}
+
+/// What `lint.max_errors` fails when the pack's lint report exceeds it.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LintFailMode {
+    /// Block the task before Codex starts.
+    #[default]
+    Run,
+    /// Let Codex run, then fail verification.
+    Verification,
+}
+
+/// `lint` in `lever.json`: which findings reach the prompt and the optional
+/// error threshold.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LintConfig {
+    pub min_severity: Option<LintSeverity>,
+    pub max_findings: usize,
+    pub max_errors: Option<usize>,
+    pub fail: LintFailMode,
+}
+
+impl Default for LintConfig {
+    fn default() -> Self {
+        Self {
+            min_severity: None,
+            max_findings: DEFAULT_MAX_LINT_FINDINGS,
+            max_errors: None,
+            fail: LintFailMode::Run,
+        }
+    }
+}
+
+impl LintConfig {
+    /// Describes the breach when `errors` is over `max_errors`.
+    pub fn threshold_failure(&self, errors: usize) -> Option<String> {
+        let max = self.max_errors?;
+        (errors > max).then(|| format!("lint errors {} exceed lint.max_errors {}", errors, max))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintIssue {
+    pub severity: String,
+    pub message: String,
+    pub path: Option<String>,
+    pub line: Option<u64>,
+    pub column: Option<u64>,
+    pub rule: Option<String>,
+}
+
+/// Findings parsed from a pack's `lint.json`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LintReport {
+    pub issues: Vec<LintIssue>,
+}
+
+impl LintReport {
+    /// Reads `lint.json`; the error says why the file is unusable.
+    pub fn read(path: &Path) -> Result<Self, String> {
+        let raw = fs::read_to_string(path).map_err(|err| format!("unreadable ({})", err))?;
+        let value: Value =
+            serde_json::from_str(&raw).map_err(|err| format!("invalid JSON ({})", err))?;
+        Self::parse(&value)
+    }
+
+    /// Accepts an array of findings or an object holding them under one of
+    /// `issues`, `findings`, `diagnostics`, `results`, or `items`. An empty
+    /// object means no findings.
+    pub fn parse(value: &Value) -> Result<Self, String> {
+        let entries = match value {
+            Value::Array(items) => items,
+            Value::Object(map) => match ISSUE_KEYS.iter().find_map(|key| map.get(*key)) {
+                Some(Value::Array(items)) => items,
+                Some(_) => return Err("findings list is not an array".to_string()),
+                None if map.is_empty() => return Ok(Self::default()),
+                None => {
+                    return Err(format!(
+                        "no findings list (expected one of: {})",
+                        ISSUE_KEYS.join(", ")
+                    ))
+                }
+            },
+            _ => return Err("expected an array or object".to_string()),
+        };
+
+        let mut issues = Vec::new();
+        for (position, entry) in entries.iter().enumerate() {
+            let Value::Object(map) = entry else {
+                return Err(format!("finding {} is not an object", position));
+            };
+            let text = |keys: &[&str]| {
+                keys.iter()
+                    .find_map(|key| map.get(*key))
+                    .and_then(Value::as_str)
+                    .map(|value| value.trim().to_string())
+                    .filter(|value| !value.is_empty())
+            };
+            let number = |keys: &[&str]| keys.iter().find_map(|key| map.get(*key)?.as_u64());
+            let severity = map
+                .get("severity")
+                .or_else(|| map.get("level"))
+                .or_else(|| map.get("kind"))
+                .and_then(Value::as_str)
+                .unwrap_or("info")
+                .trim()
+                .to_lowercase();
+            let message = text(&["message", "text", "description", "summary"]).unwrap_or_default();
+            let rule = text(&["rule", "rule_id", "code", "id"]);
+            let path = text(&["path", "file", "filename"]);
+
+            if message.is_empty() && rule.is_none() && path.is_none() {
+                continue;
+            }
+
+            issues.push(LintIssue {
+                severity,
+                message,
+                path,
+                line: number(&["line", "line_start"]),
+                column: number(&["column", "col"]),
+                rule,
+            });
+        }
+        Ok(Self { issues })
+    }
+
+    pub fn error_count(&self) -> usize {
+        self.issues
+            .iter()
+            .filter(|issue| severity_bucket(&issue.severity) == "error")
+            .count()
+    }
+
+    /// Renders the prompt's lint section. Findings below `min_severity` are
+    /// dropped; the rest are grouped by file, with files in the context pack
+    /// (`context_files`) first, then by each file's worst severity.
+    pub fn prompt_section(
+        &self,
+        config: &LintConfig,
+        context_files: &BTreeSet<String>,
+        workspace: &Path,
+    ) -> SectionBody {
+        if self.issues.is_empty() {
+            return SectionBody::Lint {
+                head: "\nLint summary:\nNo lint findings.\n".to_string(),
+                findings: Vec::new(),
+                shown: 0,
+            };
+        }
+
+        let mut head = format!("\nLint summary:\nTotals: {}\n", self.totals());
+        if let Some(min) = config.min_severity {
+            head.push_str(&format!("Showing {} and above.\n", min.label()));
+        }
+        let findings = self.findings(config, context_files, workspace);
+        SectionBody::Lint {
+            head,
+            shown: findings.len().min(config.max_findings),
+            findings,
+        }
+    }
+
+    /// Findings at or above `min_severity`, grouped by file: files in
+    /// `context_files` first, then by each file's worst severity.
+    pub fn findings(
+        &self,
+        config: &LintConfig,
+        context_files: &BTreeSet<String>,
+        workspace: &Path,
+    ) -> Vec<LintFinding> {
+        let mut groups: BTreeMap<Option<String>, Vec<&LintIssue>> = BTreeMap::new();
+        for issue in &self.issues {
+            let visible = config
+                .min_severity
+                .is_none_or(|min| severity_rank(&issue.severity) <= severity_rank(min.label()));
+            if visible {
+                let file = issue
+                    .path
+                    .as_deref()
+                    .map(|path| display_issue_path(path, workspace));
+                groups.entry(file).or_default().push(issue);
+            }
+        }
+        let mut groups: Vec<(Option<String>, Vec<&LintIssue>)> = groups.into_iter().collect();
+        for (_, issues) in &mut groups {
+            issues.sort_by(|a, b| issue_sort_key(a).cmp(&issue_sort_key(b)));
+        }
+        groups.sort_by_key(|(file, issues)| {
+            let in_context = file
+                .as_ref()
+                .is_some_and(|file| context_files.contains(file));
+            let worst = issues
+                .first()
+                .map_or(u8::MAX, |issue| severity_rank(&issue.severity));
+            (file.is_none(), !in_context, worst, file.clone())
+        });
+
+        groups
+            .into_iter()
+            .flat_map(|(file, issues)| {
+                let group = match file.as_deref() {
+                    Some(file) if context_files.contains(file) => {
+                        format!("{} (in context)", file)
+                    }
+                    Some(file) => file.to_string(),
+                    None => "(no file)".to_string(),
+                };
+                issues.into_iter().map(move |issue| LintFinding {
+                    group: group.clone(),
+                    text: format_issue(issue),
+                })
+            })
+            .collect()
+    }
+
+    pub fn totals(&self) -> String {
+        let mut counts = BTreeMap::from([
+            ("error", 0),
+            ("warning", 0),
+            ("note", 0),
+            ("info", 0),
+            ("other", 0),
+        ]);
+        for issue in &self.issues {
+            let bucket = match severity_bucket(&issue.severity) {
+                "hint" => "other",
+                bucket => bucket,
+            };
+            *counts.entry(bucket).or_default() += 1;
+        }
+        ["error", "warning", "note", "info", "other"]
+            .iter()
+            .map(|bucket| format!("{}={}", bucket, counts[bucket]))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// The findings as one SARIF run attributed to `tool`.
+    pub fn sarif_run(&self, tool: &str) -> Value {
+        let results: Vec<Value> = self
+            .issues
+            .iter()
+            .map(|issue| {
+                let mut result = json!({
+                    "level": sarif_level(&issue.severity),
+                    "message": { "text": issue.message },
+                });
+                if let Some(rule) = &issue.rule {
+                    result["ruleId"] = json!(rule);
+                }
+                if let Some(path) = &issue.path {
+                    let mut location = json!({ "artifactLocation": { "uri": path } });
+                    if let Some(line) = issue.line {
+                        location["region"] = json!({ "startLine": line });
+                        if let Some(column) = issue.column {
+                            location["region"]["startColumn"] = json!(column);
+                        }
+                    }
+                    result["locations"] = json!([{ "physicalLocation": location }]);
+                }
+                result
+            })
+            .collect();
+        json!({
+            "tool": { "driver": { "name": tool } },
+            "results": results,
+        })
+    }
+}
+
+/// A SARIF 2.1.0 log: its runs, kept verbatim for `findings.sarif`, and
+/// their results as lint issues.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SarifLog {
+    pub runs: Vec<Value>,
+    pub report: LintReport,
+}
+
+impl SarifLog {
+    pub fn read(path: &Path, workspace: &Path) -> Result<Self, String> {
+        let raw = fs::read_to_string(path).map_err(|err| format!("unreadable ({})", err))?;
+        let value: Value =
+            serde_json::from_str(&raw).map_err(|err| format!("invalid JSON ({})", err))?;
+        Self::parse(&value, workspace)
+    }
+
+    pub fn parse(value: &Value, workspace: &Path) -> Result<Self, String> {
+        let runs = value
+            .get("runs")
+            .and_then(Value::as_array)
+            .ok_or_else(|| "missing runs array".to_string())?;
+        let mut issues = Vec::new();
+        for (position, run) in runs.iter().enumerate() {
+            let results = match run.get("results") {
+                None | Some(Value::Null) => continue,
+                Some(Value::Array(results)) => results,
+                Some(_) => return Err(format!("run {} results is not an array", position)),
+            };
+            issues.extend(
+                results
+                    .iter()
+                    .filter_map(|result| sarif_issue(result, workspace)),
+            );
+        }
+        Ok(Self {
+            runs: runs.clone(),
+            report: LintReport { issues },
+        })
+    }
+
+    /// Reads every `*.sarif` / `*.sarif.json` file in `dir` (sorted by
+    /// name). Unusable files are returned as `file: reason` problems.
+    pub fn read_dir(dir: &Path, workspace: &Path) -> (Self, Vec<String>) {
+        let mut merged = Self::default();
+        let mut problems = Vec::new();
+        let mut files: Vec<_> = fs::read_dir(dir)
+            .map(|entries| {
+                entries
+                    .filter_map(Result::ok)
+                    .map(|entry| entry.path())
+                    .filter(|path| {
+                        let name = path
+                            .file_name()
+                            .and_then(|name| name.to_str())
+                            .unwrap_or("");
+                        name.ends_with(".sarif") || name.ends_with(".sarif.json")
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        files.sort();
+        for file in files {
+            match Self::read(&file, workspace) {
+                Ok(log) => {
+                    merged.runs.extend(log.runs);
+                    merged.report.issues.extend(log.report.issues);
+                }
+                Err(reason) => problems.push(format!(
+                    "{}: {}",
+                    file.file_name().unwrap_or_default().to_string_lossy(),
+                    reason
+                )),
+            }
+        }
+        (merged, problems)
+    }
+}
+
+/// Writes a SARIF 2.1.0 log holding `runs`.
+pub fn write_sarif(path: &Path, runs: Vec<Value>) -> std::io::Result<()> {
+    let log = json!({
+        "$schema": SARIF_SCHEMA,
+        "version": "2.1.0",
+        "runs": runs,
+    });
+    let serialized = serde_json::to_string_pretty(&log).map_err(std::io::Error::other)?;
+    fs::write(path, serialized)
+}
+
+fn sarif_issue(result: &Value, workspace: &Path) -> Option<LintIssue> {
+    // SARIF defaults a result's level to `warning`.
+    let severity = match result.get("level").and_then(Value::as_str) {
+        Some("none") => "info".to_string(),
+        Some(level) => level.to_lowercase(),
+        None => "warning".to_string(),
+    };
+    let message = result
+        .pointer("/message/text")
+        .or_else(|| result.pointer("/message/markdown"))
+        .and_then(Value::as_str)
+        .unwrap_or("")
+        .trim()
+        .to_string();
+    let rule = result
+        .get("ruleId")
+        .or_else(|| result.pointer("/rule/id"))
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    let location = result.pointer("/locations/0/physicalLocation");
+    let path = location
+        .and_then(|location| location.pointer("/artifactLocation/uri"))
+        .and_then(Value::as_str)
+        .map(|uri| sarif_uri_path(uri, workspace));
+    let region = location.and_then(|location| location.get("region"));
+    let number = |key: &str| region.and_then(|region| region.get(key)?.as_u64());
+    if message.is_empty() && rule.is_none() && path.is_none() {
+        return None;
+    }
+    Some(LintIssue {
+        severity,
+        message,
+        path,
+        line: number("startLine"),
+        column: number("startColumn"),
+        rule,
+    })
+}
+
+fn sarif_uri_path(uri: &str, workspace: &Path) -> String {
+    let path = uri.strip_prefix("file://").unwrap_or(uri);
+    display_issue_path(path, workspace)
+}
+
+fn sarif_level(severity: &str) -> &'static str {
+    match severity_bucket(severity) {
+        "error" => "error",
+        "warning" => "warning",
+        _ => "note",
+    }
+}
+
+fn issue_sort_key(issue: &LintIssue) -> (u8, u64, u64, &str, &str) {
+    (
+        severity_rank(&issue.severity),
+        issue.line.unwrap_or(u64::MAX),
+        issue.column.unwrap_or(u64::MAX),
+        issue.rule.as_deref().unwrap_or(""),
+        issue.message.as_str(),
+    )
+}
+
+fn format_issue(issue: &LintIssue) -> String {
+    let mut line = format!("[{}]", severity_bucket(&issue.severity));
+    if let Some(line_no) = issue.line {
+        line.push_str(&format!(" line {}", line_no));
+        if let Some(col) = issue.column {
+            line.push_str(&format!(":{}", col));
+        }
+    }
+    if let Some(rule) = issue.rule.as_deref() {
+        line.push(' ');
+        line.push_str(rule);
+    }
+    if !issue.message.is_empty() {
+        let mut message = issue.message.replace(['\n', '\r'], " ");
+        if message.len() > 160 {
+            let cut = (0..=160)
+                .rev()
+                .find(|idx| message.is_char_boundary(*idx))
+                .unwrap_or(0);
+            message.truncate(cut);
+            message.push_str("...");
+        }
+        line.push_str(" - ");
+        line.push_str(&message);
+    }
+    line
+}
+
+fn display_issue_path(path: &str, workspace: &Path) -> String {
+    let candidate = Path::new(path);
+    match candidate.strip_prefix(workspace) {
+        Ok(relative) if candidate.is_absolute() => relative.display().to_string(),
+        _ => path.to_string(),
+    }
+}
+
+fn severity_bucket(severity: &str) -> &'static str {
+    match severity {
+        "error" | "err" => "error",
+        "warning" | "warn" => "warning",
+        "note" => "note",
+        "info" | "information" => "info",
+        "hint" => "hint",
+        _ => "other",
+    }
+}
+
+fn severity_rank(severity: &str) -> u8 {
+    match severity_bucket(severity) {
+        "error" => 0,
+        "warning" => 1,
+        "note" => 2,
+        "info" => 3,
+        "hint" => 4,
+        _ => 5,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn rendered(body: SectionBody) -> Vec<String> {
+        let SectionBody::Lint { findings, .. } = body else {
+            panic!("expected a lint section");
+        };
+        findings
+            .into_iter()
+            .map(|finding| format!("{} | {}", finding.group, finding.text))
+            .collect()
+    }
+
+    #[test]
+    fn malformed_reports_are_rejected() {
+        assert!(LintReport::parse(&json!({})).unwrap().issues.is_empty());
+        assert_eq!(
+            LintReport::parse(&json!({"summary": "ok"})).unwrap_err(),
+            "no findings list (expected one of: issues, findings, diagnostics, results, items)"
+        );
+        assert_eq!(
+            LintReport::parse(&json!({"issues": "none"})).unwrap_err(),
+            "findings list is not an array"
+        );
+        assert_eq!(
+            LintReport::parse(&json!(["oops"])).unwrap_err(),
+            "finding 0 is not an object"
+        );
+    }
+
+    #[test]
+    fn findings_group_by_file_with_context_files_first() {
+        let report = LintReport::parse(&json!({"issues": [
+            {"severity": "error", "message": "Bad thing", "path": "src/main.rs", "line": 10, "rule": "R100"},
+            {"severity": "note", "message": "Optional", "path": "src/lib.rs", "line": 8},
+            {"severity": "warning", "message": "Needs attention", "path": "src/lib.rs", "line": 5, "column": 2},
+            {"severity": "info", "message": "General"}
+        ]}))
+        .unwrap();
+        assert_eq!(report.error_count(), 1);
+
+        let context = BTreeSet::from(["src/lib.rs".to_string()]);
+        let body = report.prompt_section(&LintConfig::default(), &context, Path::new("/repo"));
+        assert_eq!(
+            rendered(body),
+            vec![
+                "src/lib.rs (in context) | [warning] line 5:2 - Needs attention",
+                "src/lib.rs (in context) | [note] line 8 - Optional",
+                "src/main.rs | [error] line 10 R100 - Bad thing",
+                "(no file) | [info] - General",
+            ]
+        );
+
+        let config = LintConfig {
+            min_severity: Some(LintSeverity::Warning),
+            max_findings: 1,
+            ..LintConfig::default()
+        };
+        let body = report.prompt_section(&config, &BTreeSet::new(), Path::new("/repo"));
+        let SectionBody::Lint { head, shown, .. } = &body else {
+            panic!("expected a lint section");
+        };
+        assert!(head.ends_with("Showing warning and above.\n"));
+        assert_eq!(*shown, 1);
+        assert_eq!(
+            rendered(body),
+            vec![
+                "src/main.rs | [error] line 10 R100 - Bad thing",
+                "src/lib.rs | [warning] line 5:2 - Needs attention",
+            ]
+        );
+    }
+
+    #[test]
+    fn threshold_applies_only_when_configured() {
+        let config = LintConfig {
+            max_errors: Some(1),
+            ..LintConfig::default()
+        };
+        assert_eq!(config.threshold_failure(1), None);
+        assert_eq!(
+            config.threshold_failure(2).as_deref(),
+            Some("lint errors 2 exceed lint.max_errors 1")
+        );
+        assert_eq!(LintConfig::default().threshold_failure(100), None);
+    }
+
+    #[test]
+    fn sarif_results_become_lint_issues() {
+        let log = json!({
+            "version": "2.1.0",
+            "runs": [{
+                "tool": { "driver": { "name": "clippy" } },
+                "results": [
+                    {
+                        "level": "error",
+                        "ruleId": "clippy::unwrap_used",
+                        "message": { "text": "used unwrap" },
+                        "locations": [{ "physicalLocation": {
+                            "artifactLocation": { "uri": "file:///work/src/lib.rs" },
+                            "region": { "startLine": 7, "startColumn": 3 }
+                        }}]
+                    },
+                    { "message": { "text": "no level means warning" } },
+                    { "level": "none", "message": { "text": "informational" } }
+                ]
+            }, { "tool": { "driver": { "name": "empty" } } }]
+        });
+        let parsed = SarifLog::parse(&log, Path::new("/work")).unwrap();
+        assert_eq!(parsed.runs.len(), 2);
+        let issues = &parsed.report.issues;
+        assert_eq!(issues.len(), 3);
+        assert_eq!(issues[0].severity, "error");
+        assert_eq!(issues[0].path.as_deref(), Some("src/lib.rs"));
+        assert_eq!((issues[0].line, issues[0].column), (Some(7), Some(3)));
+        assert_eq!(issues[0].rule.as_deref(), Some("clippy::unwrap_used"));
+        assert_eq!(issues[1].severity, "warning");
+        assert_eq!(issues[2].severity, "info");
+
+        assert!(SarifLog::parse(&json!({ "version": "2.1.0" }), Path::new("/work")).is_err());
+    }
+
+    #[test]
+    fn lint_report_round_trips_through_sarif() {
+        let report = LintReport::parse(&json!([
+            { "severity": "error", "message": "broken", "path": "src/a.rs", "line": 2, "column": 5, "rule": "E1" },
+            { "severity": "info", "message": "fyi" }
+        ]))
+        .unwrap();
+        let run = report.sarif_run(CONTEXT_LINT_TOOL);
+        assert_eq!(run["tool"]["driver"]["name"], CONTEXT_LINT_TOOL);
+        assert_eq!(run["results"][1]["level"], "note");
+
+        let parsed = SarifLog::parse(&json!({ "runs": [run] }), Path::new("/work")).unwrap();
+        assert_eq!(parsed.report.issues[0], report.issues[0]);
+        assert_eq!(parsed.report.issues[1].severity, "note");
+        assert_eq!(parsed.report.issues[1].path, None);
+    }
+}
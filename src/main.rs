@@ -1,5 +1,5 @@
 use std::{
-    collections::HashSet,
+    collections::{BTreeMap, HashSet},
     error::Error,
     ffi::OsString,
     fmt::{self, Display, Formatter},
@@ -13,21 +13,50 @@ use std::{
     time::Duration,
 };
 
+use crate::budget::{BudgetLimit, SessionBudget};
+use crate::config::LeverConfig;
+use crate::flaky_ledger::{load_flaky_records, FLAKY_LEDGER_FILE, REPEATED_FLAKE_THRESHOLD};
+use crate::prompt_profile::PromptProfiles;
+use crate::stash_ledger::{
+    append_stash_record, find_pending_record, load_stash_records, update_stash_record, StashRecord,
+    STASH_LEDGER_FILE, STASH_STATUS_PENDING, STASH_STATUS_RESTORED,
+};
 use crate::task_metadata::{
     validate_task_metadata as validate_task_metadata_raw, TaskMetadataError,
 };
-use clap::{value_parser, Parser, ValueEnum};
-use lever::context_compile::{ContextCompileConfig, ContextFailurePolicy};
+use clap::{value_parser, Parser, Subcommand, ValueEnum};
+use lever::assembly_contract::AssemblyContractError;
+use lever::context_compile::{ContextCompileConfig, ContextCompilerKind, ContextFailurePolicy};
 use serde_json::Value;
 
+mod budget;
+mod config;
+mod context_compiler;
+mod flaky_ledger;
+mod lint_report;
+mod native_context;
+mod pack_cache;
+mod process_group;
+mod prompt_budget;
+mod prompt_profile;
+mod prompt_template;
 mod rate_limit;
+mod result_claims;
 mod run_paths;
+mod sandbox;
+mod stash_ledger;
 mod task_agent;
 mod task_metadata;
+mod task_result;
+mod tasks_guard;
+mod verification_baseline;
+mod verification_detectors;
 
 const DEFAULT_COMMAND_PATH: &str = "internal";
 const LEGACY_TASK_AGENT_PATH: &str = "bin/task-agent.sh";
 const TASK_FILE_SEARCH_ORDER: [&str; 2] = ["prd.json", "tasks.json"];
+/// Lever's own state never goes into a `--dirty=commit` snapshot.
+const SNAPSHOT_EXCLUDE: &str = ":(exclude).ralph";
 
 #[derive(Debug, Clone)]
 struct TaskRecord {
@@ -47,12 +76,16 @@ struct ExecutionConfig {
     workspace: PathBuf,
     assignee: Option<String>,
     reset_task: bool,
+    dirty_policy: DirtyPolicy,
     prompt_lint_summary: bool,
+    prompt_token_budget: Option<u64>,
     context_compile: ContextCompileConfig,
     context_compile_override: Option<bool>,
     context_failure_policy_override: Option<ContextFailurePolicy>,
     context_token_budget_override: Option<u64>,
     context_assembly_override: Option<PathBuf>,
+    lever_config: LeverConfig,
+    session_budget: SessionBudget,
 }
 
 struct GitWorkspaceGuard {
@@ -62,6 +95,7 @@ struct GitWorkspaceGuard {
     pre_run_head: String,
     dirty_files: Option<HashSet<String>>,
     stash_ref: Option<String>,
+    stash_commit: Option<String>,
 }
 
 #[derive(Debug)]
@@ -113,9 +147,35 @@ enum StopReason {
     Human { task_id: String, is_next: bool },
     Dependencies { task_id: String },
     Blocked { task_id: String },
+    DailyTokenCap { task_id: String },
+    BudgetExceeded { task_id: String },
+    TimedOut { task_id: String },
 }
 
 impl StopReason {
+    /// Why `--loop` stops after the task agent for `task_id` exits with
+    /// `code`, or `None` when the loop handles the code itself.
+    fn from_exit_code(code: i32, task_id: String) -> Option<Self> {
+        let reason = match code {
+            4 => StopReason::Human {
+                task_id,
+                is_next: false,
+            },
+            5 | 6 => StopReason::Dependencies { task_id },
+            task_agent::DAILY_TOKEN_CAP_EXIT_CODE => StopReason::DailyTokenCap { task_id },
+            task_agent::BUDGET_EXCEEDED_EXIT_CODE => StopReason::BudgetExceeded { task_id },
+            task_agent::TIMEOUT_EXIT_CODE => StopReason::TimedOut { task_id },
+            10
+            | 11
+            | 13
+            | task_agent::LINT_THRESHOLD_EXIT_CODE
+            | task_agent::INVALID_RESULT_EXIT_CODE
+            | task_agent::PROMPT_BUDGET_EXIT_CODE => StopReason::Blocked { task_id },
+            _ => return None,
+        };
+        Some(reason)
+    }
+
     fn exit_code(&self) -> i32 {
         1
     }
@@ -135,6 +195,24 @@ impl StopReason {
             StopReason::Blocked { task_id } => {
                 format!("Task {} blocked; manual intervention required.", task_id)
             }
+            StopReason::DailyTokenCap { task_id } => {
+                format!(
+                    "Task {} paused; daily token cap reached (see `lever usage`).",
+                    task_id
+                )
+            }
+            StopReason::BudgetExceeded { task_id } => {
+                format!(
+                    "Task {} not started; token/cost budget exceeded (see observability.last_note).",
+                    task_id
+                )
+            }
+            StopReason::TimedOut { task_id } => {
+                format!(
+                    "Task {} timed out; Codex or Assembly hit its wall-clock limit (see observability.last_note).",
+                    task_id
+                )
+            }
         }
     }
 }
@@ -150,6 +228,7 @@ impl StopReason {
 struct LeverArgs {
     #[arg(
         long,
+        global = true,
         value_name = "PATH",
         help = "Tasks JSON file leveraged by the run (auto-discovered if omitted)"
     )]
@@ -164,6 +243,7 @@ struct LeverArgs {
 
     #[arg(
         long,
+        global = true,
         value_name = "ID",
         help = "Explicit task ID leveraged by this invocation"
     )]
@@ -177,6 +257,7 @@ struct LeverArgs {
 
     #[arg(
         long,
+        global = true,
         value_name = "PATH",
         help = "Workspace directory for the run (defaults to current directory)"
     )]
@@ -214,6 +295,16 @@ struct LeverArgs {
     )]
     delay: Option<u64>,
 
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        value_name = "POLICY",
+        default_value = "stash",
+        help = "Uncommitted changes policy before a run (refuse, stash, commit, allow)"
+    )]
+    dirty: DirtyPolicy,
+
     #[arg(
         long = "context-compile",
         conflicts_with = "no_context_compile",
@@ -257,6 +348,30 @@ struct LeverArgs {
     )]
     prompt_lint_summary: bool,
 
+    #[arg(
+        long = "prompt-token-budget",
+        value_name = "TOKENS",
+        value_parser = value_parser!(u64).range(1..),
+        help = "Maximum prompt size in tokens; compiled context, then the lint summary, are cut to fit"
+    )]
+    prompt_token_budget: Option<u64>,
+
+    #[arg(
+        long = "max-tokens",
+        value_name = "TOKENS",
+        value_parser = value_parser!(u64).range(1..),
+        help = "Stop before a run would push this invocation's total Codex tokens past TOKENS"
+    )]
+    max_tokens: Option<u64>,
+
+    #[arg(
+        long = "max-cost",
+        value_name = "USD",
+        value_parser = parse_max_cost,
+        help = "Stop before a run would push this invocation's estimated Codex spend past USD (requires pricing in lever.json)"
+    )]
+    max_cost: Option<f64>,
+
     #[arg(
         long = "command-path",
         value_name = "PATH",
@@ -264,6 +379,70 @@ struct LeverArgs {
         help = "Executable invoked for each iteration (use 'internal' for Rust task agent)"
     )]
     command_path: PathBuf,
+
+    #[command(subcommand)]
+    command: Option<LeverCommand>,
+}
+
+#[derive(Subcommand, Debug)]
+enum LeverCommand {
+    #[command(about = "Preview what the git workspace guard would do without changing anything")]
+    Doctor,
+    #[command(about = "List or restore stashes created by the git workspace guard")]
+    Stash {
+        #[command(subcommand)]
+        action: StashAction,
+    },
+    #[command(about = "Show recorded token usage per model for the current window and per day")]
+    Usage {
+        #[arg(
+            long,
+            value_name = "N",
+            default_value_t = 7,
+            value_parser = value_parser!(u64).range(1..=31),
+            help = "Number of UTC days to report (including today)"
+        )]
+        days: u64,
+    },
+    #[command(about = "Show task progress and flaky verification commands from .ralph/flaky.json")]
+    Status,
+}
+
+#[derive(Subcommand, Debug)]
+enum StashAction {
+    #[command(about = "List stashes recorded in .ralph/stashes.json")]
+    List,
+    #[command(about = "Apply a recorded stash and drop it from git stash list")]
+    Restore {
+        #[arg(
+            value_name = "ID",
+            help = "Stash id (commit SHA prefix) from `lever stash list`"
+        )]
+        id: String,
+    },
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum DirtyPolicy {
+    #[value(name = "refuse")]
+    Refuse,
+    #[value(name = "stash")]
+    Stash,
+    #[value(name = "commit")]
+    Commit,
+    #[value(name = "allow")]
+    Allow,
+}
+
+impl DirtyPolicy {
+    fn label(self) -> &'static str {
+        match self {
+            DirtyPolicy::Refuse => "refuse",
+            DirtyPolicy::Stash => "stash",
+            DirtyPolicy::Commit => "commit",
+            DirtyPolicy::Allow => "allow",
+        }
+    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
@@ -291,6 +470,16 @@ type ContextCompileResolution = (
     Option<PathBuf>,
 );
 
+fn parse_max_cost(raw: &str) -> Result<f64, String> {
+    let value: f64 = raw
+        .parse()
+        .map_err(|_| format!("invalid USD amount: {}", raw))?;
+    if !value.is_finite() || value <= 0.0 {
+        return Err("must be a positive USD amount".to_string());
+    }
+    Ok(value)
+}
+
 fn validate_lever_args(args: &LeverArgs) -> Result<(), DynError> {
     let loop_mode = resolve_loop_mode(args.loop_count);
     if args.next && args.task_id.is_some() {
@@ -351,6 +540,10 @@ fn main() -> Result<(), DynError> {
     let args = LeverArgs::parse();
     validate_lever_args(&args)?;
 
+    if let Some(command) = &args.command {
+        return run_subcommand(command, &args);
+    }
+
     let LeverArgs {
         tasks,
         prompt,
@@ -361,13 +554,18 @@ fn main() -> Result<(), DynError> {
         loop_count,
         reset_task,
         delay,
+        dirty,
         context_compile,
         no_context_compile,
         context_failure_policy,
         context_token_budget,
         assembly_path,
         prompt_lint_summary,
+        prompt_token_budget,
+        max_tokens,
+        max_cost,
         command_path,
+        command: _,
     } = args;
 
     let resolved = resolve_paths(workspace, tasks, prompt, command_path)?;
@@ -377,8 +575,9 @@ fn main() -> Result<(), DynError> {
         prompt_path,
         command_path,
     } = resolved;
+    let lever_config = LeverConfig::load(&workspace)?;
     let (
-        context_compile,
+        mut context_compile,
         context_compile_override,
         context_failure_policy_override,
         context_token_budget_override,
@@ -392,8 +591,19 @@ fn main() -> Result<(), DynError> {
         &workspace,
     )?;
     if context_compile.enabled || context_assembly_override.is_some() {
-        lever::assembly_contract::validate_assembly_contract(&context_compile.assembly_path)
-            .map_err(|err| DynError::from(err.to_string()))?;
+        match lever::assembly_contract::validate_assembly_contract(&context_compile.assembly_path) {
+            Ok(contract) => context_compile.assembly_contract = Some(contract),
+            Err(AssemblyContractError::MissingDependency { command })
+                if context_compile.enabled =>
+            {
+                eprintln!(
+                    "Warning: Assembly not found at {}; using the built-in context compiler",
+                    command
+                );
+                context_compile.compiler = ContextCompilerKind::Native;
+            }
+            Err(err) => return Err(DynError::from(err.to_string())),
+        }
     }
     let tasks = load_tasks(&tasks_path)?;
     let loop_mode = resolve_loop_mode(loop_count);
@@ -444,12 +654,22 @@ fn main() -> Result<(), DynError> {
         workspace: workspace.clone(),
         assignee,
         reset_task,
+        dirty_policy: dirty,
         prompt_lint_summary,
+        prompt_token_budget,
         context_compile,
         context_compile_override,
         context_failure_policy_override,
         context_token_budget_override,
         context_assembly_override,
+        lever_config,
+        session_budget: SessionBudget {
+            session_id: format!("{}-{}", utc_timestamp()?, std::process::id()),
+            limit: BudgetLimit {
+                max_tokens,
+                max_cost_usd: max_cost,
+            },
+        },
     };
 
     if let Err(err) = run_iterations(&exec_config, loop_mode, delay_duration, &shutdown_flag) {
@@ -472,6 +692,287 @@ fn main() -> Result<(), DynError> {
     Ok(())
 }
 
+fn run_subcommand(command: &LeverCommand, args: &LeverArgs) -> Result<(), DynError> {
+    let workspace = resolve_workspace(args.workspace.clone())?;
+    match command {
+        LeverCommand::Doctor => run_doctor(&workspace, args),
+        LeverCommand::Stash { action } => match action {
+            StashAction::List => run_stash_list(&workspace),
+            StashAction::Restore { id } => run_stash_restore(&workspace, id),
+        },
+        LeverCommand::Usage { days } => run_usage(&workspace, *days),
+        LeverCommand::Status => run_status(&workspace, args),
+    }
+}
+
+fn run_status(workspace: &Path, args: &LeverArgs) -> Result<(), DynError> {
+    println!("lever status: workspace={}", workspace.display());
+    match resolve_tasks_path(args.tasks.clone(), workspace) {
+        Ok(tasks_path) => {
+            let tasks = load_tasks(&tasks_path)?;
+            let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+            for task in &tasks {
+                *counts
+                    .entry(task.status.as_deref().unwrap_or("unstarted"))
+                    .or_default() += 1;
+            }
+            let counts: Vec<String> = counts
+                .iter()
+                .map(|(status, count)| format!(" {}={}", status, count))
+                .collect();
+            println!(
+                "lever status: tasks={} total={}{}",
+                tasks_path.display(),
+                tasks.len(),
+                counts.concat()
+            );
+        }
+        Err(err) => println!("lever status: tasks file unavailable ({})", err),
+    }
+
+    let mut records = load_flaky_records(workspace)?;
+    if records.is_empty() {
+        println!(
+            "lever status: no flaky verification commands recorded in {}",
+            FLAKY_LEDGER_FILE
+        );
+        return Ok(());
+    }
+    records.sort_by_key(|record| std::cmp::Reverse(record.flakes));
+    println!(
+        "lever status: flaky commands={} repeated={} (flakes >= {})",
+        records.len(),
+        records.iter().filter(|record| record.is_repeated()).count(),
+        REPEATED_FLAKE_THRESHOLD
+    );
+    for record in &records {
+        println!(
+            "lever status: {} flakes={} last_seen={} last_task={} command={}",
+            if record.is_repeated() {
+                "repeated"
+            } else {
+                "flaky"
+            },
+            record.flakes,
+            record.last_seen_utc,
+            record.last_task_id,
+            record.command
+        );
+    }
+    Ok(())
+}
+
+fn run_usage(workspace: &Path, days: u64) -> Result<(), DynError> {
+    let config = LeverConfig::load(workspace)?;
+    let rate_limits = &config.rate_limits;
+    let window = Duration::from_secs(rate_limits.window_seconds);
+    let usage_file = workspace.join(rate_limit::USAGE_FILE);
+    let summary = rate_limit::summarize_usage(&usage_file, window, days)?;
+    println!("lever usage: store={}", usage_file.display());
+    if summary.is_empty() {
+        println!("lever usage: no usage recorded");
+        return Ok(());
+    }
+
+    let today = rate_limit::today_day_index()?;
+    for (model, usage) in &summary {
+        let limits = rate_limit::model_limits(rate_limits, model);
+        println!(
+            "lever usage: model={} window={}s requests={}/{} tokens={}/{}",
+            model,
+            rate_limits.window_seconds,
+            usage.window.requests,
+            limits.rpm,
+            usage.window.tokens,
+            limits.tpm
+        );
+        for (day, totals) in usage.days.iter().rev() {
+            let cap = match limits.daily_tokens {
+                Some(cap) if *day == today => format!("/{}", cap),
+                _ => String::new(),
+            };
+            let cost = if totals.cost_usd > 0.0 {
+                format!(" cost={}", budget::format_usd(totals.cost_usd))
+            } else {
+                String::new()
+            };
+            println!(
+                "lever usage: model={} day={} requests={} tokens={}{}{}",
+                model,
+                rate_limit::day_label(*day),
+                totals.requests,
+                totals.tokens,
+                cap,
+                cost
+            );
+        }
+    }
+    Ok(())
+}
+
+fn run_doctor(workspace: &Path, args: &LeverArgs) -> Result<(), DynError> {
+    ensure_git_available()?;
+    ensure_git_repo(workspace)?;
+
+    let branch = git_output(workspace, &["rev-parse", "--abbrev-ref", "HEAD"])?
+        .trim()
+        .to_string();
+    let head = git_output(workspace, &["rev-parse", "HEAD"])?
+        .trim()
+        .to_string();
+    println!("lever doctor: workspace={}", workspace.display());
+    println!("lever doctor: branch={} head={}", branch, short_sha(&head));
+
+    let task_id = match &args.task_id {
+        Some(task_id) => Some(task_id.clone()),
+        None => match resolve_tasks_path(args.tasks.clone(), workspace) {
+            Ok(tasks_path) => {
+                select_next_runnable(&load_tasks(&tasks_path)?).map(|task| task.task_id.clone())
+            }
+            Err(err) => {
+                println!("lever doctor: tasks file unavailable ({})", err);
+                None
+            }
+        },
+    };
+
+    let mut dirty: Vec<String> = record_dirty_files(workspace)?.into_iter().collect();
+    dirty.sort();
+    println!(
+        "lever doctor: dirty policy={} dirty_files={}",
+        args.dirty.label(),
+        dirty.len()
+    );
+    for file in &dirty {
+        println!("lever doctor:   {}", file);
+    }
+    let action = if dirty.is_empty() {
+        "workspace is clean; no local changes to protect".to_string()
+    } else {
+        match args.dirty {
+            DirtyPolicy::Refuse => format!(
+                "would refuse to run with {} uncommitted file(s)",
+                dirty.len()
+            ),
+            DirtyPolicy::Stash => format!(
+                "would stash {} file(s) with `git stash push -u`, record the stash in {}, and re-apply it after the run",
+                dirty.len(),
+                STASH_LEDGER_FILE
+            ),
+            DirtyPolicy::Commit => match commit_refusal(&branch) {
+                Some(reason) => format!("would refuse to commit: {}", reason),
+                None => format!(
+                    "would commit {} file(s) on {} before the run",
+                    dirty.len(),
+                    branch
+                ),
+            },
+            DirtyPolicy::Allow => format!(
+                "would leave {} uncommitted file(s) in place during the run",
+                dirty.len()
+            ),
+        }
+    };
+    println!("lever doctor: {}", action);
+
+    match task_id {
+        Some(task_id) => {
+            let task_branch = format!("ralph/{}", task_id);
+            let exists = git_output(
+                workspace,
+                &[
+                    "rev-parse",
+                    "--verify",
+                    "--quiet",
+                    &format!("refs/heads/{}", task_branch),
+                ],
+            )
+            .is_ok();
+            println!(
+                "lever doctor: would checkout {} then {} ({})",
+                base_branch(),
+                task_branch,
+                if exists { "existing" } else { "new" }
+            );
+        }
+        None => println!("lever doctor: no task selected; task branch checkout deferred"),
+    }
+
+    let pending: Vec<StashRecord> = load_stash_records(workspace)?
+        .into_iter()
+        .filter(StashRecord::is_pending)
+        .collect();
+    println!("lever doctor: pending stashes={}", pending.len());
+    for record in &pending {
+        println!("lever doctor:   {}", describe_stash_record(record));
+    }
+    println!("lever doctor: preview only; no changes made");
+    Ok(())
+}
+
+fn run_stash_list(workspace: &Path) -> Result<(), DynError> {
+    let records = load_stash_records(workspace)?;
+    if records.is_empty() {
+        println!("lever: no stashes recorded in {}", STASH_LEDGER_FILE);
+        return Ok(());
+    }
+    for record in &records {
+        println!("{}", describe_stash_record(record));
+    }
+    Ok(())
+}
+
+fn run_stash_restore(workspace: &Path, id: &str) -> Result<(), DynError> {
+    ensure_git_available()?;
+    ensure_git_repo(workspace)?;
+    let records = load_stash_records(workspace)?;
+    let record = find_pending_record(&records, id)?;
+    let stash_ref = find_stash_ref_by_commit(workspace, &record.commit)?.ok_or_else(|| {
+        format!(
+            "Stash {} is no longer in git stash list; recover it with `git stash apply {}`",
+            record.short_id(),
+            record.commit
+        )
+    })?;
+    git_status(workspace, &["stash", "apply", &stash_ref]).map_err(|err| {
+        format!(
+            "Stash {} could not be applied cleanly ({}); resolve manually with `git stash apply {}`",
+            record.short_id(),
+            err,
+            stash_ref
+        )
+    })?;
+    git_status(workspace, &["stash", "drop", &stash_ref])?;
+    update_stash_record(
+        workspace,
+        &record.commit,
+        STASH_STATUS_RESTORED,
+        "restored via lever stash restore",
+    )?;
+    println!(
+        "lever: restored stash {} ({} file(s))",
+        record.short_id(),
+        record.files.len()
+    );
+    Ok(())
+}
+
+fn describe_stash_record(record: &StashRecord) -> String {
+    let mut line = format!(
+        "{} status={} created={} task={} branch={} files={}",
+        record.short_id(),
+        record.status,
+        record.created_utc,
+        record.task_id.as_deref().unwrap_or("-"),
+        record.orig_branch,
+        record.files.len()
+    );
+    if !record.note.is_empty() {
+        line.push_str(&format!(" note={}", record.note));
+    }
+    line
+}
+
 fn load_tasks(path: &Path) -> Result<Vec<TaskRecord>, DynError> {
     let raw = fs::read_to_string(path).map_err(|err| {
         DynError::from(format!(
@@ -854,6 +1355,18 @@ fn run_loop_iterations(
             break;
         }
 
+        let task_id = selected_task
+            .as_ref()
+            .map(|task| task.task_id.clone())
+            .or_else(|| config.explicit_task_id.clone())
+            .unwrap_or_else(|| "unknown".to_string());
+        if let Some(reason) = status
+            .code()
+            .and_then(|code| StopReason::from_exit_code(code, task_id))
+        {
+            return Err(Box::new(StopReasonError { reason }));
+        }
+
         match status.code() {
             Some(0) => {
                 println!("lever: iteration {} completed", iteration);
@@ -862,51 +1375,6 @@ fn run_loop_iterations(
                 println!("lever: task agent reported no runnable tasks (code 3); stopping.");
                 break;
             }
-            Some(4) => {
-                let task_id = selected_task
-                    .as_ref()
-                    .map(|task| task.task_id.clone())
-                    .unwrap_or_else(|| {
-                        config
-                            .explicit_task_id
-                            .clone()
-                            .unwrap_or_else(|| "unknown".to_string())
-                    });
-                return Err(Box::new(StopReasonError {
-                    reason: StopReason::Human {
-                        task_id,
-                        is_next: false,
-                    },
-                }));
-            }
-            Some(5) | Some(6) => {
-                let task_id = selected_task
-                    .as_ref()
-                    .map(|task| task.task_id.clone())
-                    .unwrap_or_else(|| {
-                        config
-                            .explicit_task_id
-                            .clone()
-                            .unwrap_or_else(|| "unknown".to_string())
-                    });
-                return Err(Box::new(StopReasonError {
-                    reason: StopReason::Dependencies { task_id },
-                }));
-            }
-            Some(10) | Some(11) | Some(13) => {
-                let task_id = selected_task
-                    .as_ref()
-                    .map(|task| task.task_id.clone())
-                    .unwrap_or_else(|| {
-                        config
-                            .explicit_task_id
-                            .clone()
-                            .unwrap_or_else(|| "unknown".to_string())
-                    });
-                return Err(Box::new(StopReasonError {
-                    reason: StopReason::Blocked { task_id },
-                }));
-            }
             Some(130) => {
                 if shutdown_flag.load(Ordering::SeqCst) {
                     println!(
@@ -983,7 +1451,16 @@ fn run_once(
     } else {
         None
     };
-    let _git_guard = GitWorkspaceGuard::prepare(&config.workspace, task_id_for_git.as_deref())?;
+    let prompt_profiles = if internal {
+        PromptProfiles::load(&config.workspace)?
+    } else {
+        PromptProfiles::default()
+    };
+    let _git_guard = GitWorkspaceGuard::prepare(
+        &config.workspace,
+        task_id_for_git.as_deref(),
+        config.dirty_policy,
+    )?;
     let mut restored_prompt = false;
     if !internal && !config.prompt.is_file() {
         if let Some(parent) = config.prompt.parent() {
@@ -997,11 +1474,16 @@ fn run_once(
         let agent_config = task_agent::TaskAgentConfig {
             tasks_path: config.tasks_path.clone(),
             prompt_path: temp_prompt_path.clone().unwrap(),
+            prompt_origin: config.prompt.clone(),
             workspace: config.workspace.clone(),
             reset_task: config.reset_task,
             explicit_task_id: config.explicit_task_id.clone(),
             context_compile: config.context_compile.clone(),
             include_lint_summary: config.prompt_lint_summary,
+            prompt_token_budget: config.prompt_token_budget,
+            prompt_profiles,
+            lever_config: config.lever_config.clone(),
+            session_budget: config.session_budget.clone(),
         };
         let exit_code = task_agent::run_task_agent(
             &agent_config,
@@ -1099,6 +1581,11 @@ impl ExecutionConfig {
             args.push("--prompt-lint-summary".into());
         }
 
+        if let Some(budget) = self.prompt_token_budget {
+            args.push("--prompt-token-budget".into());
+            args.push(budget.to_string().into());
+        }
+
         let enabled = self
             .context_compile_override
             .unwrap_or(self.context_compile.enabled);
@@ -1184,37 +1671,120 @@ fn resolve_task_id_for_git(
 }
 
 impl GitWorkspaceGuard {
-    fn prepare(workspace: &Path, task_id: Option<&str>) -> Result<Self, DynError> {
+    fn prepare(
+        workspace: &Path,
+        task_id: Option<&str>,
+        dirty_policy: DirtyPolicy,
+    ) -> Result<Self, DynError> {
         ensure_git_available()?;
         ensure_git_repo(workspace)?;
+        ensure_lever_state_excluded(workspace)?;
 
         let orig_branch = git_output(workspace, &["rev-parse", "--abbrev-ref", "HEAD"])?
             .trim()
             .to_string();
-        let orig_head = git_output(workspace, &["rev-parse", "HEAD"])?
+        let mut orig_head = git_output(workspace, &["rev-parse", "HEAD"])?
             .trim()
             .to_string();
-        let pre_run_head = orig_head.clone();
 
         let mut dirty_files = None;
         let mut stash_ref = None;
+        let mut stash_commit = None;
 
         let status = git_output(workspace, &["status", "--porcelain"])?;
         if !status.trim().is_empty() {
-            dirty_files = Some(record_dirty_files(workspace)?);
-            let stash_msg = format!(
-                "ralph(task-agent): auto-stash {}-{}",
-                utc_timestamp()?,
-                std::process::id()
-            );
-            git_status(workspace, &["stash", "push", "-u", "-m", &stash_msg])?;
-            stash_ref = find_stash_ref(workspace, &stash_msg)?;
-            if let Some(stash) = &stash_ref {
-                eprintln!("Stashed local changes as {}.", stash);
-            } else {
-                eprintln!("Warning: auto-stash created but ref not found; check git stash list.");
+            let files = record_dirty_files(workspace)?;
+            match dirty_policy {
+                DirtyPolicy::Refuse => {
+                    return Err(format!(
+                        "Workspace {} has {} uncommitted file(s); commit or stash them, or rerun with --dirty=stash|commit|allow",
+                        workspace.display(),
+                        files.len()
+                    )
+                    .into());
+                }
+                DirtyPolicy::Stash => {
+                    let stamp = utc_timestamp()?;
+                    let stash_msg = format!(
+                        "ralph(task-agent): auto-stash {}-{}",
+                        stamp,
+                        std::process::id()
+                    );
+                    git_status(workspace, &["stash", "push", "-u", "-m", &stash_msg])?;
+                    stash_ref = find_stash_ref(workspace, &stash_msg)?;
+                    if let Some(stash) = &stash_ref {
+                        let commit = git_output(workspace, &["rev-parse", stash])?
+                            .trim()
+                            .to_string();
+                        let mut recorded: Vec<String> = files.iter().cloned().collect();
+                        recorded.sort();
+                        append_stash_record(
+                            workspace,
+                            StashRecord {
+                                commit: commit.clone(),
+                                message: stash_msg.clone(),
+                                created_utc: stamp,
+                                task_id: task_id.map(str::to_string),
+                                orig_branch: orig_branch.clone(),
+                                orig_head: orig_head.clone(),
+                                files: recorded,
+                                status: STASH_STATUS_PENDING.to_string(),
+                                note: String::new(),
+                            },
+                        )?;
+                        eprintln!(
+                            "Stashed local changes as {} (lever stash id {}).",
+                            stash,
+                            short_sha(&commit)
+                        );
+                        stash_commit = Some(commit);
+                    } else {
+                        eprintln!(
+                            "Warning: auto-stash created but ref not found; check git stash list."
+                        );
+                    }
+                    dirty_files = Some(files);
+                }
+                DirtyPolicy::Commit => {
+                    if let Some(reason) = commit_refusal(&orig_branch) {
+                        return Err(format!(
+                            "Refusing --dirty=commit: {}; switch to a working branch or use --dirty=stash",
+                            reason
+                        )
+                        .into());
+                    }
+                    let message = format!(
+                        "lever: snapshot local changes before run {}",
+                        utc_timestamp()?
+                    );
+                    git_status(workspace, &["add", "-A", "--", ".", SNAPSHOT_EXCLUDE])?;
+                    let staged = git_output(workspace, &["diff", "--cached", "--name-only"])?;
+                    let staged = staged
+                        .lines()
+                        .filter(|line| !line.trim().is_empty())
+                        .count();
+                    if staged > 0 {
+                        git_status(workspace, &["commit", "-m", &message])?;
+                        orig_head = git_output(workspace, &["rev-parse", "HEAD"])?
+                            .trim()
+                            .to_string();
+                        eprintln!(
+                            "Committed {} local change(s) as {} on {} before the run.",
+                            staged,
+                            short_sha(&orig_head),
+                            orig_branch
+                        );
+                    }
+                }
+                DirtyPolicy::Allow => {
+                    eprintln!(
+                        "Warning: leaving {} uncommitted file(s) in place (--dirty=allow).",
+                        files.len()
+                    );
+                }
             }
         }
+        let pre_run_head = orig_head.clone();
 
         if let Some(task_id) = task_id {
             let base_branch = base_branch();
@@ -1228,21 +1798,50 @@ impl GitWorkspaceGuard {
             pre_run_head,
             dirty_files,
             stash_ref,
+            stash_commit,
         })
     }
 
+    fn leave_stash(&self, commit: &str, note: &str) {
+        eprintln!(
+            "Warning: {}; run `lever stash restore {}` to recover it.",
+            note,
+            short_sha(commit)
+        );
+        if let Err(err) = update_stash_record(&self.workspace, commit, STASH_STATUS_PENDING, note) {
+            eprintln!("Warning: failed to update {}: {}", STASH_LEDGER_FILE, err);
+        }
+    }
+
     fn restore_local_changes(&self) -> Result<(), DynError> {
-        let stash_ref = match &self.stash_ref {
-            Some(stash_ref) => stash_ref,
+        let stash_commit = match &self.stash_commit {
+            Some(stash_commit) => stash_commit,
             None => return Ok(()),
         };
 
+        let stash_ref = match find_stash_ref_by_commit(&self.workspace, stash_commit)? {
+            Some(stash_ref) => stash_ref,
+            None => {
+                self.leave_stash(
+                    stash_commit,
+                    &format!(
+                        "stash {} no longer appears in git stash list",
+                        self.stash_ref.as_deref().unwrap_or(stash_commit)
+                    ),
+                );
+                return Ok(());
+            }
+        };
+
         let dirty_files = match &self.dirty_files {
             Some(dirty_files) => dirty_files,
             None => {
-                eprintln!(
-                    "Warning: missing dirty file list; leaving {} for manual apply.",
-                    stash_ref
+                self.leave_stash(
+                    stash_commit,
+                    &format!(
+                        "missing dirty file list; leaving {} for manual apply",
+                        stash_ref
+                    ),
                 );
                 return Ok(());
             }
@@ -1254,9 +1853,12 @@ impl GitWorkspaceGuard {
         ) {
             Ok(output) => output,
             Err(_) => {
-                eprintln!(
-                    "Warning: unable to compute run changes; leaving {} for manual apply.",
-                    stash_ref
+                self.leave_stash(
+                    stash_commit,
+                    &format!(
+                        "unable to compute run changes; leaving {} for manual apply",
+                        stash_ref
+                    ),
                 );
                 return Ok(());
             }
@@ -1270,35 +1872,47 @@ impl GitWorkspaceGuard {
             .collect();
 
         if dirty_files.iter().any(|file| run_files.contains(file)) {
-            eprintln!(
-                "Warning: stash {} overlaps run changes; apply manually.",
-                stash_ref
+            self.leave_stash(
+                stash_commit,
+                &format!("stash {} overlaps run changes; apply manually", stash_ref),
             );
             return Ok(());
         }
 
         if self.orig_branch == "HEAD" {
             if git_status(&self.workspace, &["checkout", "--detach", &self.orig_head]).is_err() {
-                eprintln!(
-                    "Warning: unable to restore detached HEAD; leaving {}.",
-                    stash_ref
+                self.leave_stash(
+                    stash_commit,
+                    &format!("unable to restore detached HEAD; leaving {}", stash_ref),
                 );
                 return Ok(());
             }
         } else if git_status(&self.workspace, &["checkout", &self.orig_branch]).is_err() {
-            eprintln!(
-                "Warning: unable to checkout {}; leaving {}.",
-                self.orig_branch, stash_ref
+            self.leave_stash(
+                stash_commit,
+                &format!(
+                    "unable to checkout {}; leaving {}",
+                    self.orig_branch, stash_ref
+                ),
             );
             return Ok(());
         }
 
-        if git_status(&self.workspace, &["stash", "apply", stash_ref]).is_ok() {
-            let _ = git_status(&self.workspace, &["stash", "drop", stash_ref]);
+        if git_status(&self.workspace, &["stash", "apply", &stash_ref]).is_ok() {
+            let _ = git_status(&self.workspace, &["stash", "drop", &stash_ref]);
+            update_stash_record(
+                &self.workspace,
+                stash_commit,
+                STASH_STATUS_RESTORED,
+                "restored after run",
+            )?;
         } else {
-            eprintln!(
-                "Warning: stash {} could not be applied cleanly; leaving stash for manual apply.",
-                stash_ref
+            self.leave_stash(
+                stash_commit,
+                &format!(
+                    "stash {} could not be applied cleanly; leaving stash for manual apply",
+                    stash_ref
+                ),
             );
         }
 
@@ -1396,10 +2010,75 @@ fn find_stash_ref(workspace: &Path, stash_msg: &str) -> Result<Option<String>, D
     Ok(None)
 }
 
+fn find_stash_ref_by_commit(workspace: &Path, commit: &str) -> Result<Option<String>, DynError> {
+    let output = git_output(workspace, &["stash", "list", "--format=%gd %H"])?;
+    for line in output.lines() {
+        let mut parts = line.split_whitespace();
+        if let (Some(reference), Some(hash)) = (parts.next(), parts.next()) {
+            if hash == commit {
+                return Ok(Some(reference.to_string()));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Keeps workspace-wide lever state (stash ledger, flaky ledger, usage store,
+/// context pack and verification baseline caches) out of stashes, commits, and branch switches.
+fn ensure_lever_state_excluded(workspace: &Path) -> Result<(), DynError> {
+    let exclude_path = git_output(workspace, &["rev-parse", "--git-path", "info/exclude"])?;
+    let exclude_path = resolve_relative_to_workspace(PathBuf::from(exclude_path.trim()), workspace);
+    let existing = fs::read_to_string(&exclude_path).unwrap_or_default();
+    let missing: Vec<String> = [
+        STASH_LEDGER_FILE,
+        FLAKY_LEDGER_FILE,
+        rate_limit::USAGE_FILE,
+        rate_limit::USAGE_LOCK_FILE,
+        pack_cache::PACK_CACHE_DIR,
+        verification_baseline::BASELINE_CACHE_DIR,
+    ]
+    .iter()
+    .map(|path| format!("/{}", path))
+    .filter(|pattern| !existing.lines().any(|line| line.trim() == pattern))
+    .collect();
+    if missing.is_empty() {
+        return Ok(());
+    }
+    if let Some(parent) = exclude_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut updated = existing;
+    if !updated.is_empty() && !updated.ends_with('\n') {
+        updated.push('\n');
+    }
+    for pattern in missing {
+        updated.push_str(&pattern);
+        updated.push('\n');
+    }
+    fs::write(&exclude_path, updated)?;
+    Ok(())
+}
+
+fn short_sha(commit: &str) -> &str {
+    &commit[..commit.len().min(12)]
+}
+
 fn base_branch() -> String {
     std::env::var("BASE_BRANCH").unwrap_or_else(|_| "main".to_string())
 }
 
+/// Why `--dirty=commit` must not snapshot onto `branch`, if it must not.
+fn commit_refusal(branch: &str) -> Option<String> {
+    let base_branch = base_branch();
+    if branch == "HEAD" {
+        Some("HEAD is detached".to_string())
+    } else if branch == base_branch {
+        Some(format!("{} is the base branch", branch))
+    } else {
+        None
+    }
+}
+
 fn checkout_task_branch(
     workspace: &Path,
     base_branch: &str,
@@ -1510,6 +2189,15 @@ mod tests {
             StopReason::Blocked {
                 task_id: "T3".to_string(),
             },
+            StopReason::DailyTokenCap {
+                task_id: "T4".to_string(),
+            },
+            StopReason::BudgetExceeded {
+                task_id: "T5".to_string(),
+            },
+            StopReason::TimedOut {
+                task_id: "T6".to_string(),
+            },
         ];
 
         for reason in reasons {
@@ -1518,6 +2206,27 @@ mod tests {
         }
     }
 
+    #[test]
+    fn stop_reason_from_exit_code_names_the_task() {
+        let stop = |code| StopReason::from_exit_code(code, "T1".to_string());
+        assert!(matches!(
+            stop(4),
+            Some(StopReason::Human { is_next: false, .. })
+        ));
+        assert!(matches!(stop(6), Some(StopReason::Dependencies { .. })));
+        assert!(matches!(
+            stop(task_agent::TIMEOUT_EXIT_CODE),
+            Some(StopReason::TimedOut { .. })
+        ));
+        assert_eq!(
+            stop(task_agent::LINT_THRESHOLD_EXIT_CODE).map(|reason| reason.message()),
+            Some("Task T1 blocked; manual intervention required.".to_string())
+        );
+        assert!(stop(0).is_none());
+        assert!(stop(3).is_none());
+        assert!(stop(130).is_none());
+    }
+
     #[test]
     fn task_agent_args_include_context_compile_config_enabled() {
         let context_compile = ContextCompileConfig {
@@ -1536,12 +2245,16 @@ mod tests {
             workspace: PathBuf::from("."),
             assignee: None,
             reset_task: false,
+            dirty_policy: DirtyPolicy::Stash,
             prompt_lint_summary: false,
+            prompt_token_budget: None,
             context_compile,
             context_compile_override: None,
             context_failure_policy_override: None,
             context_token_budget_override: None,
             context_assembly_override: None,
+            lever_config: LeverConfig::default(),
+            session_budget: SessionBudget::default(),
         };
 
         let args = args_to_strings(config.task_agent_args(None, false, Path::new("prompt.md")));
@@ -1572,12 +2285,16 @@ mod tests {
             workspace: PathBuf::from("."),
             assignee: None,
             reset_task: false,
+            dirty_policy: DirtyPolicy::Stash,
             prompt_lint_summary: false,
+            prompt_token_budget: None,
             context_compile,
             context_compile_override: None,
             context_failure_policy_override: None,
             context_token_budget_override: None,
             context_assembly_override: None,
+            lever_config: LeverConfig::default(),
+            session_budget: SessionBudget::default(),
         };
 
         let args = args_to_strings(config.task_agent_args(None, false, Path::new("prompt.md")));
@@ -0,0 +1,438 @@
+use std::{
+    collections::BTreeSet,
+    error::Error,
+    fs,
+    path::{Component, Path},
+    process::Command,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use lever::context_compile::ContextCompileConfig;
+use lever::token_estimate::{default_estimator, TokenEstimator};
+
+use crate::context_compiler::{CompileOutcome, ContextCompiler};
+use crate::process_group::Deadline;
+use crate::run_paths::RunPaths;
+use crate::task_metadata::definition_of_done;
+
+type DynError = Box<dyn Error + Send + Sync + 'static>;
+
+pub const NATIVE_COMPILER_NAME: &str = "lever-native";
+/// Larger files are skipped; they rarely fit a context budget whole.
+const MAX_FILE_BYTES: u64 = 256 * 1024;
+const PATH_MATCH_WEIGHT: u64 = 3;
+/// Bonus for files matching a task `context.include` glob, which are kept
+/// even without a keyword hit.
+const INCLUDE_WEIGHT: u64 = 5;
+const STOP_WORDS: &[&str] = &[
+    "the", "and", "for", "with", "that", "this", "from", "into", "when", "then", "than", "are",
+    "was", "were", "will", "should", "must", "can", "not", "all", "any", "each", "use", "using",
+    "add", "make", "run", "has", "have", "its", "but", "our", "out", "via", "per", "new",
+];
+
+/// In-process fallback for Assembly: ranks repository files by keyword
+/// overlap with the task and packs the best ones into the token budget.
+pub struct NativeCompiler<'a> {
+    pub config: &'a ContextCompileConfig,
+}
+
+impl ContextCompiler for NativeCompiler<'_> {
+    fn name(&self) -> &'static str {
+        "Native context compiler"
+    }
+
+    fn cacheable(&self) -> bool {
+        false
+    }
+
+    fn compile(
+        &self,
+        workspace: &Path,
+        task_id: &str,
+        paths: &RunPaths,
+        _deadline: Deadline,
+        shutdown_flag: Option<&AtomicBool>,
+    ) -> Result<CompileOutcome, DynError> {
+        let task: Value = serde_json::from_str(&fs::read_to_string(&paths.assembly_task_path)?)?;
+        let keywords = task_keywords(&task);
+        let estimator = default_estimator();
+
+        let mut ranked = Vec::new();
+        for path in candidate_files(workspace, self.config)? {
+            if shutdown_flag.is_some_and(|flag| flag.load(Ordering::SeqCst)) {
+                return Ok(CompileOutcome::Interrupted);
+            }
+            if self.config.pinned_files.contains(&path) {
+                continue;
+            }
+            let included = self
+                .config
+                .include_globs
+                .iter()
+                .any(|glob| glob_match(glob, &path));
+            if let Some(file) = score_file(workspace, &path, &keywords, included) {
+                ranked.push(file);
+            }
+        }
+        ranked.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.path.cmp(&b.path)));
+
+        // Pinned files lead the pack and outrank every ranked file.
+        let pinned_score = ranked.first().map_or(1, |file| file.score + 1);
+        let mut files = Vec::new();
+        for pin in &self.config.pinned_files {
+            match read_text(workspace, pin) {
+                Some(content) => files.push(ScoredFile {
+                    path: pin.clone(),
+                    score: pinned_score,
+                    content,
+                }),
+                None => {
+                    return Ok(CompileOutcome::Failed {
+                        code: None,
+                        message: format!("Pinned context file {} is missing or not text.", pin),
+                    })
+                }
+            }
+        }
+        files.extend(ranked);
+
+        let pack = build_pack(&files, self.config.token_budget, estimator);
+        write_pack(&paths.pack_dir_abs, task_id, &pack)?;
+        fs::write(
+            &paths.assembly_summary_path,
+            serde_json::to_string_pretty(&json!({
+                "task_id": task_id,
+                "token_count": pack.tokens,
+                "token_budget": self.config.token_budget,
+                "compiler": NATIVE_COMPILER_NAME,
+                "files": pack.files.len(),
+            }))?,
+        )?;
+        Ok(CompileOutcome::Success)
+    }
+}
+
+struct ScoredFile {
+    path: String,
+    score: u64,
+    content: String,
+}
+
+struct NativePack {
+    context: String,
+    tokens: u64,
+    files: Vec<(String, u64, u64)>,
+}
+
+fn task_keywords(task: &Value) -> BTreeSet<String> {
+    let mut text = String::new();
+    for pointer in ["/title", "/recommended/approach"] {
+        if let Some(value) = task.pointer(pointer).and_then(Value::as_str) {
+            text.push_str(value);
+            text.push('\n');
+        }
+    }
+    for item in definition_of_done(task) {
+        text.push_str(&item.text);
+        text.push('\n');
+    }
+    words(&text)
+        .filter(|word| word.len() >= 3 && !STOP_WORDS.contains(&word.as_str()))
+        .collect()
+}
+
+fn words(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|ch: char| !ch.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_ascii_lowercase)
+}
+
+/// Tracked and untracked files that `.gitignore` does not exclude, minus the
+/// configured exclude globs unless an include glob brings them back.
+fn candidate_files(
+    workspace: &Path,
+    config: &ContextCompileConfig,
+) -> Result<Vec<String>, DynError> {
+    let output = Command::new("git")
+        .current_dir(workspace)
+        .args([
+            "ls-files",
+            "-z",
+            "--cached",
+            "--others",
+            "--exclude-standard",
+        ])
+        .output()?;
+    if !output.status.success() {
+        return Err(format!(
+            "git ls-files failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into());
+    }
+    let listing = String::from_utf8_lossy(&output.stdout);
+    let excludes: Vec<&str> = config
+        .exclude_globs
+        .iter()
+        .chain(&config.exclude_runtime_globs)
+        .map(String::as_str)
+        .collect();
+    let files: BTreeSet<String> = listing
+        .split('\0')
+        .filter(|path| !path.is_empty())
+        .filter(|path| {
+            config
+                .include_globs
+                .iter()
+                .any(|glob| glob_match(glob, path))
+                || !excludes.iter().any(|glob| glob_match(glob, path))
+        })
+        .map(str::to_string)
+        .collect();
+    Ok(files.into_iter().collect())
+}
+
+/// Path-weighted keyword overlap; `None` for unreadable, binary, oversized,
+/// or unrelated files.
+fn score_file(
+    workspace: &Path,
+    path: &str,
+    keywords: &BTreeSet<String>,
+    included: bool,
+) -> Option<ScoredFile> {
+    let content = read_text(workspace, path)?;
+    let path_words: BTreeSet<String> = words(path).collect();
+    let content_words: BTreeSet<String> = words(&content).collect();
+    let score = keywords
+        .iter()
+        .map(|keyword| {
+            let in_path = if path_words.contains(keyword) {
+                PATH_MATCH_WEIGHT
+            } else {
+                0
+            };
+            in_path + u64::from(content_words.contains(keyword))
+        })
+        .sum::<u64>()
+        + if included { INCLUDE_WEIGHT } else { 0 };
+    (score > 0).then(|| ScoredFile {
+        path: path.to_string(),
+        score,
+        content,
+    })
+}
+
+fn read_text(workspace: &Path, path: &str) -> Option<String> {
+    let relative = Path::new(path);
+    if !relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return None;
+    }
+    let full = workspace.join(relative);
+    if fs::metadata(&full).ok()?.len() > MAX_FILE_BYTES {
+        return None;
+    }
+    let content = String::from_utf8(fs::read(&full).ok()?).ok()?;
+    (!content.contains('\0')).then_some(content)
+}
+
+fn build_pack(
+    ranked: &[ScoredFile],
+    token_budget: u64,
+    estimator: &dyn TokenEstimator,
+) -> NativePack {
+    let mut context = format!(
+        "# Context pack ({})\n\nFiles ranked by keyword overlap with the task.\n\n",
+        NATIVE_COMPILER_NAME
+    );
+    let mut tokens = estimator.count(&context);
+    let mut files = Vec::new();
+    for file in ranked {
+        let fence = if file.content.contains("```") {
+            "~~~"
+        } else {
+            "```"
+        };
+        let block = format!(
+            "## {}\n{}\n{}{}{}\n\n",
+            file.path,
+            fence,
+            file.content,
+            if file.content.ends_with('\n') {
+                ""
+            } else {
+                "\n"
+            },
+            fence
+        );
+        let block_tokens = estimator.count(&block);
+        if tokens + block_tokens > token_budget {
+            continue;
+        }
+        tokens += block_tokens;
+        context.push_str(&block);
+        files.push((file.path.clone(), file.score, block_tokens));
+    }
+    NativePack {
+        context,
+        tokens,
+        files,
+    }
+}
+
+fn write_pack(pack_dir: &Path, task_id: &str, pack: &NativePack) -> Result<(), DynError> {
+    fs::create_dir_all(pack_dir)?;
+    let index = json!({
+        "compiler": NATIVE_COMPILER_NAME,
+        "files": pack
+            .files
+            .iter()
+            .map(|(path, score, tokens)| json!({"path": path, "score": score, "tokens": tokens}))
+            .collect::<Vec<_>>(),
+    });
+    let outputs = [
+        ("context.md", pack.context.clone()),
+        ("index.json", serde_json::to_string_pretty(&index)?),
+        (
+            "policy.md",
+            format!(
+                "# Policy\n\nNo repository policy was extracted; this pack was built by {} because Assembly is unavailable.\n",
+                NATIVE_COMPILER_NAME
+            ),
+        ),
+        ("lint.json", serde_json::to_string_pretty(&json!({"issues": []}))?),
+    ];
+    let mut manifest_files = Vec::new();
+    for (name, content) in &outputs {
+        fs::write(pack_dir.join(name), content)?;
+        manifest_files.push(json!({
+            "path": name,
+            "sha256": format!("{:x}", Sha256::digest(content.as_bytes())),
+        }));
+    }
+    let manifest = json!({
+        "task_id": task_id,
+        "compiler": NATIVE_COMPILER_NAME,
+        "files": manifest_files,
+    });
+    fs::write(
+        pack_dir.join("manifest.json"),
+        serde_json::to_string_pretty(&manifest)?,
+    )?;
+    Ok(())
+}
+
+/// Matches `path` against an exclude glob: `**` spans directories, `*` and
+/// `?` stay within one path segment. A pattern without `/` also matches any
+/// file name, as in `.gitignore`.
+pub fn glob_match(pattern: &str, path: &str) -> bool {
+    if !pattern.contains('/') {
+        if let Some(name) = path.rsplit('/').next() {
+            if glob_match_bytes(pattern.as_bytes(), name.as_bytes()) {
+                return true;
+            }
+        }
+    }
+    glob_match_bytes(pattern.as_bytes(), path.as_bytes())
+}
+
+fn glob_match_bytes(pattern: &[u8], path: &[u8]) -> bool {
+    match pattern {
+        [] => path.is_empty(),
+        [b'*', b'*', b'/', rest @ ..] => {
+            glob_match_bytes(rest, path)
+                || path
+                    .iter()
+                    .enumerate()
+                    .any(|(idx, ch)| *ch == b'/' && glob_match_bytes(rest, &path[idx + 1..]))
+        }
+        [b'*', b'*', rest @ ..] => (0..=path.len()).any(|idx| glob_match_bytes(rest, &path[idx..])),
+        [b'*', rest @ ..] => {
+            let segment = path.iter().position(|ch| *ch == b'/').unwrap_or(path.len());
+            (0..=segment).any(|idx| glob_match_bytes(rest, &path[idx..]))
+        }
+        [b'?', rest @ ..] => {
+            matches!(path.first(), Some(ch) if *ch != b'/') && glob_match_bytes(rest, &path[1..])
+        }
+        [ch, rest @ ..] => path.first() == Some(ch) && glob_match_bytes(rest, &path[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lever::token_estimate::ByteRatioEstimator;
+
+    #[test]
+    fn globs_match_directories_and_names() {
+        assert!(glob_match(".git/**", ".git/HEAD"));
+        assert!(glob_match(".ralph/**", ".ralph/runs/T1/run/prompt.md"));
+        assert!(!glob_match(".ralph/**", "src/ralph.rs"));
+        assert!(glob_match("**/*.lock", "deps/Cargo.lock"));
+        assert!(glob_match("*.lock", "deps/Cargo.lock"));
+        assert!(glob_match("src/*.rs", "src/main.rs"));
+        assert!(!glob_match("src/*.rs", "src/bin/tool.rs"));
+        assert!(glob_match("docs/?.md", "docs/a.md"));
+    }
+
+    #[test]
+    fn keywords_come_from_title_dod_and_approach() {
+        let task = json!({
+            "title": "Add retry backoff",
+            "definition_of_done": ["Scheduler retries with jitter"],
+            "recommended": {"approach": "Use the existing clock module"},
+            "status": "unstarted"
+        });
+        let keywords: Vec<String> = task_keywords(&task).into_iter().collect();
+        assert_eq!(
+            keywords,
+            vec![
+                "backoff",
+                "clock",
+                "existing",
+                "jitter",
+                "module",
+                "retries",
+                "retry",
+                "scheduler"
+            ]
+        );
+    }
+
+    #[test]
+    fn pack_keeps_best_ranked_files_within_budget() {
+        let ranked = vec![
+            ScoredFile {
+                path: "src/retry.rs".to_string(),
+                score: 7,
+                content: "fn retry() {}\n".to_string(),
+            },
+            ScoredFile {
+                path: "docs/huge.md".to_string(),
+                score: 5,
+                content: "retry ".repeat(400),
+            },
+            ScoredFile {
+                path: "src/clock.rs".to_string(),
+                score: 1,
+                content: "fn now() {}\n".to_string(),
+            },
+        ];
+        let pack = build_pack(&ranked, 120, &ByteRatioEstimator);
+        let kept: Vec<&str> = pack
+            .files
+            .iter()
+            .map(|(path, _, _)| path.as_str())
+            .collect();
+        assert_eq!(kept, vec!["src/retry.rs", "src/clock.rs"]);
+        assert!(pack.tokens <= 120);
+        assert!(pack
+            .context
+            .contains("## src/retry.rs\n```\nfn retry() {}\n```\n"));
+    }
+}
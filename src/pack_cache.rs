@@ -0,0 +1,329 @@
+use std::{
+    error::Error,
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use lever::assembly_contract::REQUIRED_PACK_FILES;
+use lever::context_compile::ContextCompileConfig;
+use serde_json::{json, Value};
+
+type DynError = Box<dyn Error + Send + Sync + 'static>;
+
+pub const PACK_CACHE_DIR: &str = ".ralph/cache/packs";
+const CACHED_PACK_DIR: &str = "pack";
+const CACHED_SUMMARY_FILE: &str = "assembly-summary.json";
+
+/// Content address of an Assembly build: a hash over everything that can
+/// change the pack. The committed and working trees are hashed without
+/// `.ralph` and the tasks file, which Lever commits after every run, and the
+/// task input without its `status`, so a retry of the same task on its branch
+/// reuses the pack.
+pub fn pack_cache_key(
+    workspace: &Path,
+    tasks_path: &Path,
+    assembly_task_path: &Path,
+    config: &ContextCompileConfig,
+) -> Result<String, DynError> {
+    let pathspecs = tree_pathspecs(
+        tasks_path.strip_prefix(workspace).ok(),
+        &config.exclude_globs,
+    );
+    let tree = head_tree_hash(workspace, &pathspecs)?;
+    let dirty_tree = dirty_tree_hash(workspace, &pathspecs)?;
+    let mut task_input: Value = serde_json::from_slice(&fs::read(assembly_task_path)?)?;
+    if let Some(fields) = task_input.as_object_mut() {
+        fields.remove("status");
+    }
+    let task_input = git_hash(workspace, serde_json::to_string(&task_input)?.as_bytes())?;
+    let assembly_version = assembly_version(workspace, &config.assembly_path)?;
+    let components = json!({
+        "tree": tree,
+        "dirty_tree": dirty_tree,
+        "assembly_task": task_input,
+        "token_budget": config.token_budget,
+        "exclude_globs": config.exclude_globs,
+        "exclude_runtime_globs": config.exclude_runtime_globs,
+        "assembly_version": assembly_version,
+        "assembly_contract": config
+            .assembly_contract
+            .as_ref()
+            .map(|contract| contract.contract_version),
+    });
+    git_hash(workspace, serde_json::to_string(&components)?.as_bytes())
+}
+
+/// Copies a cached pack (and its summary) into the run; copies keep the
+/// agent's edits to its pack out of the cache. Returns `false` when the cache
+/// has no complete entry for `key`.
+pub fn restore_cached_pack(
+    workspace: &Path,
+    key: &str,
+    pack_dir: &Path,
+    summary_path: &Path,
+) -> Result<bool, DynError> {
+    let entry = cache_entry(workspace, key);
+    let cached_pack = entry.join(CACHED_PACK_DIR);
+    if !REQUIRED_PACK_FILES
+        .iter()
+        .all(|file| cached_pack.join(file).is_file())
+    {
+        return Ok(false);
+    }
+    copy_tree(&cached_pack, pack_dir)?;
+    let cached_summary = entry.join(CACHED_SUMMARY_FILE);
+    if cached_summary.is_file() {
+        copy_file(&cached_summary, summary_path)?;
+    }
+    Ok(true)
+}
+
+/// Stores a freshly built pack under `key`. Entries are staged and renamed
+/// into place so concurrent runs never see a partial pack.
+pub fn store_pack(
+    workspace: &Path,
+    key: &str,
+    pack_dir: &Path,
+    summary_path: &Path,
+) -> Result<(), DynError> {
+    let entry = cache_entry(workspace, key);
+    if entry.exists() {
+        return Ok(());
+    }
+    let staging =
+        workspace
+            .join(PACK_CACHE_DIR)
+            .join(format!(".{}.tmp-{}", key, std::process::id()));
+    if staging.exists() {
+        fs::remove_dir_all(&staging)?;
+    }
+    copy_tree(pack_dir, &staging.join(CACHED_PACK_DIR))?;
+    if summary_path.is_file() {
+        copy_file(summary_path, &staging.join(CACHED_SUMMARY_FILE))?;
+    }
+    if fs::rename(&staging, &entry).is_err() {
+        fs::remove_dir_all(&staging)?;
+        if !entry.exists() {
+            return Err(format!("Failed to store context pack cache entry {}", key).into());
+        }
+    }
+    Ok(())
+}
+
+fn cache_entry(workspace: &Path, key: &str) -> PathBuf {
+    workspace.join(PACK_CACHE_DIR).join(key)
+}
+
+fn copy_tree(src: &Path, dst: &Path) -> Result<(), DynError> {
+    fs::create_dir_all(dst)?;
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let target = dst.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_tree(&entry.path(), &target)?;
+        } else {
+            copy_file(&entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Replaces `dst` rather than writing through it, in case it is a hard link
+/// into the cache left by an older Lever.
+fn copy_file(src: &Path, dst: &Path) -> Result<(), DynError> {
+    if dst.exists() {
+        fs::remove_file(dst)?;
+    }
+    fs::copy(src, dst)?;
+    Ok(())
+}
+
+/// Pathspecs covering the files Assembly reads. Lever's own `.ralph` state
+/// and the tasks file change with every run, so they never count.
+fn tree_pathspecs(tasks_file: Option<&Path>, exclude_globs: &[String]) -> Vec<String> {
+    let mut pathspecs = vec![
+        "--".to_string(),
+        ".".to_string(),
+        ":(exclude).ralph".to_string(),
+    ];
+    pathspecs.extend(tasks_file.map(|path| format!(":(exclude,literal){}", path.display())));
+    pathspecs.extend(
+        exclude_globs
+            .iter()
+            .map(|glob| format!(":(exclude,glob){}", glob)),
+    );
+    pathspecs
+}
+
+/// Hash of the blobs `HEAD` holds under `pathspecs`, listed as a diff from
+/// the empty tree because `git ls-tree` has no exclude pathspecs.
+fn head_tree_hash(workspace: &Path, pathspecs: &[String]) -> Result<String, DynError> {
+    let empty_tree = git_stdout(
+        workspace,
+        &["hash-object", "-t", "tree", "--stdin"],
+        Some(&[]),
+    )?;
+    let mut args = vec!["diff-tree", "-r", "--no-renames", empty_tree.trim(), "HEAD"];
+    args.extend(pathspecs.iter().map(String::as_str));
+    let listing = git_stdout(workspace, &args, None)?;
+    git_hash(workspace, listing.as_bytes())
+}
+
+fn dirty_tree_hash(workspace: &Path, pathspecs: &[String]) -> Result<String, DynError> {
+    let mut diff_args = vec!["diff", "HEAD", "--binary", "--no-ext-diff", "--no-color"];
+    diff_args.extend(pathspecs.iter().map(String::as_str));
+    let diff = git_stdout(workspace, &diff_args, None)?;
+
+    let mut untracked_args = vec!["ls-files", "--others", "--exclude-standard"];
+    untracked_args.extend(pathspecs.iter().map(String::as_str));
+    let untracked = git_stdout(workspace, &untracked_args, None)?;
+    let untracked: Vec<&str> = untracked.lines().filter(|line| !line.is_empty()).collect();
+
+    if diff.is_empty() && untracked.is_empty() {
+        return Ok("clean".to_string());
+    }
+
+    let mut state = diff.into_bytes();
+    if !untracked.is_empty() {
+        let paths = format!("{}\n", untracked.join("\n"));
+        let hashes = git_stdout(
+            workspace,
+            &["hash-object", "--stdin-paths"],
+            Some(paths.as_bytes()),
+        )?;
+        for (path, hash) in untracked.iter().zip(hashes.lines()) {
+            state.extend_from_slice(format!("untracked {} {}\n", path, hash).as_bytes());
+        }
+    }
+    git_hash(workspace, &state)
+}
+
+fn git_hash(workspace: &Path, bytes: &[u8]) -> Result<String, DynError> {
+    Ok(
+        git_stdout(workspace, &["hash-object", "--stdin"], Some(bytes))?
+            .trim()
+            .to_string(),
+    )
+}
+
+fn assembly_version(workspace: &Path, assembly_path: &Path) -> Result<String, DynError> {
+    let output = Command::new(assembly_path)
+        .current_dir(workspace)
+        .arg("--version")
+        .output()?;
+    if !output.status.success() {
+        return Err(format!("{} --version failed", assembly_path.display()).into());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn git_stdout(workspace: &Path, args: &[&str], stdin: Option<&[u8]>) -> Result<String, DynError> {
+    let mut child = Command::new("git")
+        .current_dir(workspace)
+        .args(args)
+        .stdin(if stdin.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    if let (Some(bytes), Some(mut handle)) = (stdin, child.stdin.take()) {
+        handle.write_all(bytes)?;
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(format!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_workspace(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("lever-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn stored_pack_restores_into_new_run() {
+        let workspace = temp_workspace("pack-cache-roundtrip");
+        let built = workspace.join("run-1/pack");
+        fs::create_dir_all(built.join("extra")).unwrap();
+        for file in REQUIRED_PACK_FILES {
+            fs::write(built.join(file), format!("{}\n", file)).unwrap();
+        }
+        fs::write(built.join("extra/notes.md"), "notes\n").unwrap();
+        let summary = workspace.join("run-1/assembly-summary.json");
+        fs::write(&summary, "{}\n").unwrap();
+
+        store_pack(&workspace, "abc123", &built, &summary).unwrap();
+
+        let restored = workspace.join("run-2/pack");
+        let restored_summary = workspace.join("run-2/assembly-summary.json");
+        assert!(restore_cached_pack(&workspace, "abc123", &restored, &restored_summary).unwrap());
+        assert_eq!(
+            fs::read_to_string(restored.join("context.md")).unwrap(),
+            "context.md\n"
+        );
+        assert_eq!(
+            fs::read_to_string(restored.join("extra/notes.md")).unwrap(),
+            "notes\n"
+        );
+        assert_eq!(fs::read_to_string(&restored_summary).unwrap(), "{}\n");
+
+        // Editing the run's pack leaves the cached copy alone.
+        fs::write(restored.join("context.md"), "edited\n").unwrap();
+        let again = workspace.join("run-3/pack");
+        assert!(restore_cached_pack(&workspace, "abc123", &again, &restored_summary).unwrap());
+        assert_eq!(
+            fs::read_to_string(again.join("context.md")).unwrap(),
+            "context.md\n"
+        );
+
+        fs::remove_dir_all(&workspace).unwrap();
+    }
+
+    #[test]
+    fn incomplete_cache_entry_is_a_miss() {
+        let workspace = temp_workspace("pack-cache-miss");
+        let entry = cache_entry(&workspace, "partial").join(CACHED_PACK_DIR);
+        fs::create_dir_all(&entry).unwrap();
+        fs::write(entry.join("context.md"), "only context\n").unwrap();
+
+        let pack = workspace.join("run/pack");
+        let summary = workspace.join("run/assembly-summary.json");
+        assert!(!restore_cached_pack(&workspace, "partial", &pack, &summary).unwrap());
+        assert!(!restore_cached_pack(&workspace, "missing", &pack, &summary).unwrap());
+        assert!(!pack.exists());
+
+        fs::remove_dir_all(&workspace).unwrap();
+    }
+
+    #[test]
+    fn tree_pathspecs_skip_lever_state_and_excludes() {
+        let pathspecs = tree_pathspecs(Some(Path::new("prd.json")), &["target/**".to_string()]);
+        assert_eq!(
+            pathspecs,
+            vec![
+                "--",
+                ".",
+                ":(exclude).ralph",
+                ":(exclude,literal)prd.json",
+                ":(exclude,glob)target/**"
+            ]
+        );
+    }
+}
@@ -0,0 +1,161 @@
+use std::{
+    error::Error,
+    process::{Child, Command, ExitStatus},
+    sync::atomic::{AtomicBool, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+
+type DynError = Box<dyn Error + Send + Sync + 'static>;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Wall-clock limit for a child process and how long it gets between
+/// `SIGTERM` and `SIGKILL` once the limit is reached.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Deadline {
+    pub timeout: Option<Duration>,
+    pub grace: Duration,
+}
+
+impl Deadline {
+    pub fn new(timeout_seconds: Option<u64>, grace_seconds: u64) -> Self {
+        Self {
+            timeout: timeout_seconds.map(Duration::from_secs),
+            grace: Duration::from_secs(grace_seconds),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum WaitOutcome {
+    Exited(ExitStatus),
+    /// The deadline passed and the process group was terminated.
+    TimedOut,
+    /// The shutdown flag was set and the process group was terminated.
+    Interrupted,
+}
+
+/// Spawns `command` as the leader of a new process group, so the whole
+/// tree it starts can be signalled together.
+pub fn spawn_in_group(command: &mut Command) -> Result<Child, DynError> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+    Ok(command.spawn()?)
+}
+
+/// Polls `child` until it exits, the deadline passes, or `shutdown_flag` is
+/// set. The last two terminate its process group.
+pub fn wait_with_deadline(
+    child: &mut Child,
+    deadline: Deadline,
+    shutdown_flag: Option<&AtomicBool>,
+) -> Result<WaitOutcome, DynError> {
+    let started = Instant::now();
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(WaitOutcome::Exited(status));
+        }
+        if shutdown_flag.is_some_and(|flag| flag.load(Ordering::SeqCst)) {
+            terminate_group(child, deadline.grace)?;
+            return Ok(WaitOutcome::Interrupted);
+        }
+        if deadline
+            .timeout
+            .is_some_and(|timeout| started.elapsed() >= timeout)
+        {
+            terminate_group(child, deadline.grace)?;
+            return Ok(WaitOutcome::TimedOut);
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Sends `SIGTERM` to the child's process group, then `SIGKILL` if the child
+/// is still running after `grace`.
+pub fn terminate_group(child: &mut Child, grace: Duration) -> Result<(), DynError> {
+    signal_group(child, Signal::Term);
+    let started = Instant::now();
+    while started.elapsed() < grace {
+        if child.try_wait()?.is_some() {
+            // Descendants that ignored SIGTERM go down with the group.
+            signal_group(child, Signal::Kill);
+            return Ok(());
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+    signal_group(child, Signal::Kill);
+    let _ = child.kill();
+    child.wait()?;
+    Ok(())
+}
+
+enum Signal {
+    Term,
+    Kill,
+}
+
+#[cfg(unix)]
+fn signal_group(child: &mut Child, signal: Signal) {
+    let signal = match signal {
+        Signal::Term => libc::SIGTERM,
+        Signal::Kill => libc::SIGKILL,
+    };
+    // The group id equals the leader's pid (see `spawn_in_group`).
+    unsafe {
+        libc::kill(-(child.id() as libc::pid_t), signal);
+    }
+}
+
+#[cfg(not(unix))]
+fn signal_group(child: &mut Child, _signal: Signal) {
+    let _ = child.kill();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deadline_terminates_the_process_group() {
+        let mut command = Command::new("bash");
+        command.arg("-c").arg("sleep 30 & sleep 30");
+        let mut child = spawn_in_group(&mut command).unwrap();
+        let started = Instant::now();
+        let outcome = wait_with_deadline(
+            &mut child,
+            Deadline {
+                timeout: Some(Duration::from_millis(200)),
+                grace: Duration::from_secs(5),
+            },
+            None,
+        )
+        .unwrap();
+        assert!(matches!(outcome, WaitOutcome::TimedOut));
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn sigkill_follows_an_ignored_sigterm() {
+        let mut command = Command::new("bash");
+        command
+            .arg("-c")
+            .arg("trap '' TERM; while true; do sleep 0.1; done");
+        let mut child = spawn_in_group(&mut command).unwrap();
+        thread::sleep(Duration::from_millis(200));
+        let started = Instant::now();
+        terminate_group(&mut child, Duration::from_millis(300)).unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(300));
+        assert!(child.try_wait().unwrap().is_some());
+    }
+
+    #[test]
+    fn exited_processes_report_their_status() {
+        let mut child = spawn_in_group(Command::new("true").arg("--")).unwrap();
+        let outcome = wait_with_deadline(&mut child, Deadline::new(Some(5), 1), None).unwrap();
+        assert!(matches!(outcome, WaitOutcome::Exited(status) if status.success()));
+    }
+}
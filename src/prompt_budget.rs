@@ -0,0 +1,642 @@
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use lever::assembly_pack::PackIndex;
+use lever::token_estimate::{truncate_to_tokens, TokenEstimator};
+
+/// Sections that may be cut, with their default priority. Lower priorities
+/// are cut first; `base_prompt` and `task` are required and never cut.
+pub const DEFAULT_SECTION_PRIORITIES: [(&str, u32); 5] = [
+    ("task_json", 10),
+    ("compiled_context", 20),
+    ("previous_attempt", 25),
+    ("lint_summary", 30),
+    ("verification_findings", 35),
+];
+
+pub const CONTEXT_TRUNCATED_MARKER: &str =
+    "[compiled context truncated to fit the prompt token budget]\n";
+
+const TASK_JSON_DUPLICATE_FIELDS: [&str; 3] =
+    ["title", "definition_of_done", "recommended.approach"];
+
+/// One `context.md` block; `path` is set when its heading names an
+/// `index.json` entry, which makes the block droppable.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContextBlock {
+    pub path: Option<String>,
+    pub importance: f64,
+    pub text: String,
+}
+
+/// One lint finding line; consecutive findings with the same `group` (the
+/// file heading) are rendered under one heading.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintFinding {
+    pub group: String,
+    pub text: String,
+}
+
+pub enum SectionBody {
+    Text(String),
+    TaskJson {
+        full: String,
+        elided: Option<String>,
+    },
+    Lint {
+        head: String,
+        findings: Vec<LintFinding>,
+        shown: usize,
+    },
+    Context {
+        header: String,
+        blocks: Vec<ContextBlock>,
+    },
+}
+
+pub struct PromptSection {
+    pub name: &'static str,
+    pub priority: Option<u32>,
+    pub body: SectionBody,
+    pub text: String,
+    pub original_tokens: u64,
+    pub status: &'static str,
+}
+
+impl PromptSection {
+    pub fn new(
+        name: &'static str,
+        priority: Option<u32>,
+        body: SectionBody,
+        estimator: &dyn TokenEstimator,
+    ) -> Self {
+        let text = match &body {
+            SectionBody::Text(text) => text.clone(),
+            SectionBody::TaskJson { full, .. } => full.clone(),
+            SectionBody::Lint {
+                head,
+                findings,
+                shown,
+            } => render_lint(head, findings, *shown),
+            SectionBody::Context { header, blocks } => render_context(header, blocks, "", ""),
+        };
+        Self {
+            name,
+            priority,
+            original_tokens: estimator.count(&text),
+            body,
+            text,
+            status: "included",
+        }
+    }
+
+    fn omit(&mut self) {
+        self.text.clear();
+        self.status = "omitted";
+    }
+
+    fn strategies(&self) -> &'static [&'static str] {
+        match self.body {
+            SectionBody::Text(_) if self.priority.is_some() => &["omit"],
+            SectionBody::Text(_) => &[],
+            SectionBody::TaskJson { .. } => &["elide_duplicates"],
+            SectionBody::Lint { .. } => &["trim_findings", "omit"],
+            SectionBody::Context { .. } => &["drop_files", "truncate", "omit"],
+        }
+    }
+}
+
+/// A reduction applied to one section, recorded in `prompt-budget.json`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PromptCut {
+    pub section: &'static str,
+    pub action: &'static str,
+    pub tokens_saved: u64,
+    pub detail: Value,
+}
+
+impl PromptCut {
+    fn to_json(&self) -> Value {
+        let mut value = json!({
+            "section": self.section,
+            "action": self.action,
+            "tokens_saved": self.tokens_saved,
+        });
+        if let (Some(map), Value::Object(detail)) = (value.as_object_mut(), &self.detail) {
+            map.extend(detail.clone());
+        }
+        value
+    }
+}
+
+/// Resolves the priority of each cuttable section, applying overrides
+/// from `lever.json`.
+pub fn section_priorities(overrides: &BTreeMap<String, u32>) -> BTreeMap<&'static str, u32> {
+    DEFAULT_SECTION_PRIORITIES
+        .iter()
+        .map(|(name, priority)| (*name, overrides.get(*name).copied().unwrap_or(*priority)))
+        .collect()
+}
+
+/// Cuts sections, lowest priority first, until the prompt fits `budget`.
+/// Returns the cuts made and whether the prompt fits.
+pub fn enforce_prompt_budget(
+    sections: &mut [PromptSection],
+    budget: u64,
+    estimator: &dyn TokenEstimator,
+) -> (Vec<PromptCut>, bool) {
+    let mut cuts = Vec::new();
+    let mut order: Vec<usize> = (0..sections.len())
+        .filter(|idx| sections[*idx].priority.is_some())
+        .collect();
+    order.sort_by_key(|idx| sections[*idx].priority);
+
+    for index in order {
+        if prompt_tokens(sections, estimator) <= budget {
+            break;
+        }
+        let others: u64 = sections
+            .iter()
+            .enumerate()
+            .filter(|(idx, _)| *idx != index)
+            .map(|(_, section)| estimator.count(&section.text))
+            .sum();
+        let available = budget.saturating_sub(others);
+        cut_section(&mut sections[index], available, estimator, &mut cuts);
+    }
+    (cuts, prompt_tokens(sections, estimator) <= budget)
+}
+
+pub fn prompt_tokens(sections: &[PromptSection], estimator: &dyn TokenEstimator) -> u64 {
+    sections
+        .iter()
+        .map(|section| estimator.count(&section.text))
+        .sum()
+}
+
+fn cut_section(
+    section: &mut PromptSection,
+    available: u64,
+    estimator: &dyn TokenEstimator,
+    cuts: &mut Vec<PromptCut>,
+) {
+    let name = section.name;
+    let mut record = |section: &PromptSection, before: u64, action, detail| {
+        cuts.push(PromptCut {
+            section: name,
+            action,
+            tokens_saved: before.saturating_sub(estimator.count(&section.text)),
+            detail,
+        });
+    };
+    let fits = |section: &PromptSection| estimator.count(&section.text) <= available;
+
+    match &section.body {
+        SectionBody::Text(_) => {
+            if section.priority.is_some() {
+                let before = estimator.count(&section.text);
+                section.omit();
+                record(section, before, "omit", json!({}));
+            }
+        }
+        SectionBody::TaskJson { elided, .. } => {
+            let before = estimator.count(&section.text);
+            if let Some(elided) = elided
+                .clone()
+                .filter(|elided| estimator.count(elided) < before)
+            {
+                section.text = elided;
+                section.status = "elided";
+                record(
+                    section,
+                    before,
+                    "elide_duplicates",
+                    json!({ "fields": TASK_JSON_DUPLICATE_FIELDS }),
+                );
+            }
+        }
+        SectionBody::Lint {
+            head,
+            findings,
+            shown,
+        } => {
+            let (head, findings, initial) = (head.clone(), findings.clone(), *shown);
+            let before = estimator.count(&section.text);
+            let mut kept = initial;
+            while kept > 0 && !fits(section) {
+                kept -= 1;
+                section.text = render_lint(&head, &findings, kept);
+                section.status = "trimmed";
+            }
+            if kept < initial {
+                record(
+                    section,
+                    before,
+                    "trim_findings",
+                    json!({ "kept": kept, "total": findings.len() }),
+                );
+            }
+            if !fits(section) {
+                let before = estimator.count(&section.text);
+                section.omit();
+                record(section, before, "omit", json!({}));
+            }
+        }
+        SectionBody::Context { header, blocks } => {
+            let (header, mut blocks) = (header.clone(), blocks.clone());
+            let mut droppable: Vec<usize> = (0..blocks.len())
+                .filter(|idx| blocks[*idx].path.is_some())
+                .collect();
+            droppable.sort_by(|a, b| blocks[*a].importance.total_cmp(&blocks[*b].importance));
+
+            let mut dropped: Vec<String> = Vec::new();
+            let mut drop_note = String::new();
+            for idx in droppable {
+                if fits(section) {
+                    break;
+                }
+                let before = estimator.count(&section.text);
+                let path = blocks[idx].path.clone().unwrap_or_default();
+                blocks[idx].text.clear();
+                dropped.push(path.clone());
+                drop_note = dropped_files_note(&dropped);
+                section.text = render_context(&header, &blocks, &drop_note, "");
+                section.status = "trimmed";
+                record(
+                    section,
+                    before,
+                    "drop_file",
+                    json!({ "path": path, "importance": blocks[idx].importance }),
+                );
+            }
+            if fits(section) {
+                return;
+            }
+
+            let before = estimator.count(&section.text);
+            let body: String = blocks.iter().map(|block| block.text.as_str()).collect();
+            let overhead = estimator.count(&header)
+                + estimator.count(&drop_note)
+                + estimator.count(CONTEXT_TRUNCATED_MARKER);
+            let kept = truncate_to_tokens(estimator, &body, available.saturating_sub(overhead));
+            if available > overhead && !kept.trim().is_empty() {
+                let kept_lines = kept.lines().count();
+                section.text = format!(
+                    "{}{}{}{}",
+                    header, kept, drop_note, CONTEXT_TRUNCATED_MARKER
+                );
+                section.status = "truncated";
+                record(
+                    section,
+                    before,
+                    "truncate",
+                    json!({ "kept_lines": kept_lines, "total_lines": body.lines().count() }),
+                );
+            } else {
+                section.omit();
+                record(section, before, "omit", json!({}));
+            }
+        }
+    }
+}
+
+fn render_lint(head: &str, findings: &[LintFinding], shown: usize) -> String {
+    let mut text = head.to_string();
+    if findings.is_empty() {
+        return text;
+    }
+    text.push_str(&format!(
+        "Findings (showing {} of {}):\n",
+        shown,
+        findings.len()
+    ));
+    let mut group = None;
+    for finding in findings.iter().take(shown) {
+        if group != Some(&finding.group) {
+            text.push_str(&format!("{}:\n", finding.group));
+            group = Some(&finding.group);
+        }
+        text.push_str(&format!("- {}\n", finding.text));
+    }
+    text
+}
+
+fn render_context(header: &str, blocks: &[ContextBlock], drop_note: &str, marker: &str) -> String {
+    let mut text = header.to_string();
+    for block in blocks {
+        text.push_str(&block.text);
+    }
+    text.push_str(drop_note);
+    text.push_str(marker);
+    text
+}
+
+fn dropped_files_note(dropped: &[String]) -> String {
+    format!(
+        "[compiled context: dropped {} low-priority file(s) to fit the prompt token budget: {}]\n",
+        dropped.len(),
+        dropped.join(", ")
+    )
+}
+
+/// Splits `context.md` into blocks at headings that name an `index.json`
+/// entry. Files with a `priority` or `score` keep that importance; otherwise
+/// earlier index entries are more important. Unmatched text is never dropped.
+pub fn context_blocks(body: &str, index: Option<&Value>) -> Vec<ContextBlock> {
+    let entries = index.map(index_entries).unwrap_or_default();
+    let mut blocks = vec![ContextBlock {
+        path: None,
+        importance: f64::INFINITY,
+        text: String::new(),
+    }];
+    let mut in_fence = false;
+    for line in body.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+        }
+        let heading = !in_fence && line.starts_with('#');
+        let matched = heading
+            .then(|| {
+                entries
+                    .iter()
+                    .filter(|(path, _)| line.contains(path.as_str()))
+                    .max_by_key(|(path, _)| path.len())
+            })
+            .flatten();
+        if let Some((path, importance)) = matched {
+            blocks.push(ContextBlock {
+                path: Some(path.clone()),
+                importance: *importance,
+                text: String::new(),
+            });
+        }
+        if let Some(block) = blocks.last_mut() {
+            block.text.push_str(line);
+        }
+    }
+    blocks.retain(|block| !block.text.is_empty());
+    blocks
+}
+
+fn index_entries(index: &Value) -> Vec<(String, f64)> {
+    let Ok(index) = PackIndex::deserialize(index) else {
+        return Vec::new();
+    };
+    index
+        .entries()
+        .iter()
+        .enumerate()
+        .filter_map(|(position, entry)| {
+            let path = entry.path.trim();
+            (!path.is_empty()).then(|| {
+                (
+                    path.to_string(),
+                    entry.priority.unwrap_or(-(position as f64)),
+                )
+            })
+        })
+        .collect()
+}
+
+/// Removes the fields already rendered above the task JSON. Returns `None`
+/// when the JSON is invalid or carries none of them.
+pub fn elide_task_json(raw: &str) -> Option<String> {
+    let mut value: Value = serde_json::from_str(raw.trim()).ok()?;
+    let map = value.as_object_mut()?;
+    let mut removed = map.remove("title").is_some();
+    removed |= map.remove("definition_of_done").is_some();
+    if let Some(Value::Object(recommended)) = map.get_mut("recommended") {
+        removed |= recommended.remove("approach").is_some();
+        if recommended.is_empty() {
+            map.remove("recommended");
+        }
+    }
+    if !removed {
+        return None;
+    }
+    Some(format!(
+        "\nTask JSON (authoritative; fields shown above removed):\n{}\n",
+        serde_json::to_string(&value).ok()?
+    ))
+}
+
+/// The `prompt-budget.json` payload: per-section priorities and outcome,
+/// plus every cut in the order it was made.
+pub fn budget_report_json(
+    estimator: &dyn TokenEstimator,
+    budget: u64,
+    fits: bool,
+    sections: &[PromptSection],
+    cuts: &[PromptCut],
+) -> Value {
+    let section_values: Vec<Value> = sections
+        .iter()
+        .map(|section| {
+            json!({
+                "name": section.name,
+                "priority": section.priority,
+                "required": section.priority.is_none(),
+                "strategies": section.strategies(),
+                "original_tokens": section.original_tokens,
+                "tokens": estimator.count(&section.text),
+                "status": section.status,
+            })
+        })
+        .collect();
+    json!({
+        "estimator": estimator.name(),
+        "budget": budget,
+        "fits": fits,
+        "original_tokens": sections
+            .iter()
+            .map(|section| section.original_tokens)
+            .sum::<u64>(),
+        "total_tokens": prompt_tokens(sections, estimator),
+        "sections": section_values,
+        "cuts": cuts.iter().map(PromptCut::to_json).collect::<Vec<_>>(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lever::token_estimate::default_estimator;
+
+    const RAW_TASK: &str = r#"{"task_id":"T1","title":"Add the prompt budget","definition_of_done":["Sections are cut by priority","A report records every cut"],"recommended":{"approach":"Split context.md by index.json headings and drop the least important files first."}}"#;
+
+    fn sections(context: &str, index: &Value) -> Vec<PromptSection> {
+        let estimator = default_estimator();
+        let priorities = section_priorities(&BTreeMap::new());
+        vec![
+            PromptSection::new(
+                "base_prompt",
+                None,
+                SectionBody::Text("Base prompt\n\n".to_string()),
+                estimator,
+            ),
+            PromptSection::new(
+                "task",
+                None,
+                SectionBody::Text("Task title: T1\n".to_string()),
+                estimator,
+            ),
+            PromptSection::new(
+                "task_json",
+                Some(priorities["task_json"]),
+                SectionBody::TaskJson {
+                    full: format!("\nTask JSON (authoritative):\n{}\n", RAW_TASK),
+                    elided: elide_task_json(RAW_TASK),
+                },
+                estimator,
+            ),
+            PromptSection::new(
+                "lint_summary",
+                Some(priorities["lint_summary"]),
+                SectionBody::Lint {
+                    head: "\nLint summary:\nTotals: error=3\n".to_string(),
+                    findings: (0..3)
+                        .map(|idx| LintFinding {
+                            group: "src/lib.rs".to_string(),
+                            text: format!("[error] line {} - finding", idx),
+                        })
+                        .collect(),
+                    shown: 3,
+                },
+                estimator,
+            ),
+            PromptSection::new(
+                "compiled_context",
+                Some(priorities["compiled_context"]),
+                SectionBody::Context {
+                    header: "\nCompiled context:\n".to_string(),
+                    blocks: context_blocks(context, Some(index)),
+                },
+                estimator,
+            ),
+        ]
+    }
+
+    fn context_fixture() -> (String, Value) {
+        let mut context = String::from("# Context pack\n");
+        for name in ["src/a.rs", "src/b.rs", "src/c.rs"] {
+            context.push_str(&format!("## {}\n```rust\n", name));
+            for line in 0..40 {
+                context.push_str(&format!("// {} line {}\n", name, line));
+            }
+            context.push_str("#[test]\n```\n");
+        }
+        let index = json!({ "files": [
+            { "path": "src/a.rs", "score": 0.9 },
+            { "path": "src/b.rs", "score": 0.2 },
+            { "path": "src/c.rs", "score": 0.5 }
+        ] });
+        (context, index)
+    }
+
+    #[test]
+    fn context_blocks_follow_index_headings() {
+        let (context, index) = context_fixture();
+        let blocks = context_blocks(&context, Some(&index));
+        let paths: Vec<Option<&str>> = blocks.iter().map(|block| block.path.as_deref()).collect();
+        assert_eq!(
+            paths,
+            vec![None, Some("src/a.rs"), Some("src/b.rs"), Some("src/c.rs")]
+        );
+        assert!(blocks[1].text.ends_with("#[test]\n```\n"));
+        let joined: String = blocks.iter().map(|block| block.text.as_str()).collect();
+        assert_eq!(joined, context);
+
+        let blocks = context_blocks(&context, None);
+        assert_eq!(blocks.len(), 1);
+    }
+
+    #[test]
+    fn elide_task_json_removes_rendered_fields() {
+        let elided =
+            elide_task_json(r#"{"task_id":"T1","title":"x","recommended":{"approach":"y"}}"#)
+                .unwrap();
+        assert!(elided.ends_with("{\"task_id\":\"T1\"}\n"));
+        let elided =
+            elide_task_json(r#"{"title":"x","recommended":{"approach":"y","model":"m"}}"#).unwrap();
+        assert!(elided.contains("{\"recommended\":{\"model\":\"m\"}}"));
+        assert_eq!(elide_task_json(r#"{"task_id":"T1"}"#), None);
+        assert_eq!(elide_task_json("not json"), None);
+    }
+
+    #[test]
+    fn budget_cuts_sections_in_priority_order() {
+        let estimator = default_estimator();
+        let (context, index) = context_fixture();
+
+        let mut full = sections(&context, &index);
+        let total = prompt_tokens(&full, estimator);
+        let (cuts, fits) = enforce_prompt_budget(&mut full, total, estimator);
+        assert!(fits && cuts.is_empty());
+
+        // Eliding the task JSON and dropping the least important file is enough.
+        let mut parts = sections(&context, &index);
+        let block_b = context_blocks(&context, Some(&index))[2].text.clone();
+        let budget = total - estimator.count(&block_b) + 30;
+        let (cuts, fits) = enforce_prompt_budget(&mut parts, budget, estimator);
+        assert!(fits);
+        let actions: Vec<(&str, &str)> = cuts.iter().map(|cut| (cut.section, cut.action)).collect();
+        assert_eq!(
+            actions,
+            vec![
+                ("task_json", "elide_duplicates"),
+                ("compiled_context", "drop_file")
+            ]
+        );
+        assert_eq!(cuts[1].detail["path"], "src/b.rs");
+        assert_eq!(parts[2].status, "elided");
+        assert_eq!(parts[3].status, "included");
+        assert_eq!(parts[4].status, "trimmed");
+        assert!(parts[4].text.contains("dropped 1 low-priority file(s)"));
+        assert!(!parts[4].text.contains("src/b.rs line 0"));
+
+        // With only the required sections left, context and lint go away.
+        let mut parts = sections(&context, &index);
+        let required = estimator.count("Base prompt\n\n")
+            + estimator.count("Task title: T1\n")
+            + estimator.count(&elide_task_json(RAW_TASK).unwrap());
+        let (cuts, fits) = enforce_prompt_budget(&mut parts, required, estimator);
+        assert!(fits);
+        assert_eq!(parts[3].status, "omitted");
+        assert_eq!(parts[4].status, "omitted");
+        assert!(cuts
+            .iter()
+            .any(|cut| cut.section == "lint_summary" && cut.action == "trim_findings"));
+
+        let mut parts = sections(&context, &index);
+        let (_, fits) = enforce_prompt_budget(&mut parts, 2, estimator);
+        assert!(!fits);
+    }
+
+    #[test]
+    fn budget_truncates_context_without_index() {
+        let estimator = default_estimator();
+        let (context, _) = context_fixture();
+        let mut parts = sections(&context, &Value::Null);
+        let fixed: u64 = parts[..4]
+            .iter()
+            .map(|section| estimator.count(&section.text))
+            .sum();
+        let (cuts, fits) = enforce_prompt_budget(&mut parts, fixed + 60, estimator);
+        assert!(fits);
+        assert_eq!(parts[4].status, "truncated");
+        assert!(parts[4].text.ends_with(CONTEXT_TRUNCATED_MARKER));
+        assert!(parts[4].text.contains("# Context pack\n"));
+        assert_eq!(cuts.last().unwrap().action, "truncate");
+    }
+
+    #[test]
+    fn section_priorities_apply_overrides() {
+        let overrides = BTreeMap::from([("lint_summary".to_string(), 5)]);
+        let priorities = section_priorities(&overrides);
+        assert_eq!(priorities["lint_summary"], 5);
+        assert_eq!(priorities["compiled_context"], 20);
+    }
+}
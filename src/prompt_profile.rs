@@ -0,0 +1,171 @@
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde_json::{json, Value};
+
+type DynError = Box<dyn Error + Send + Sync + 'static>;
+
+pub const PROMPTS_DIR: &str = "prompts";
+pub const PROMPT_PROFILE_SUFFIX: &str = ".prompt.md";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PromptProfile {
+    pub name: String,
+    pub path: PathBuf,
+    pub content: String,
+}
+
+/// `prompts/<name>.prompt.md` files, read before the git guard switches
+/// branches or stashes untracked files.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PromptProfiles {
+    profiles: BTreeMap<String, PromptProfile>,
+}
+
+impl PromptProfiles {
+    pub fn load(workspace: &Path) -> Result<Self, DynError> {
+        let dir = workspace.join(PROMPTS_DIR);
+        let mut profiles = BTreeMap::new();
+        if !dir.is_dir() {
+            return Ok(Self { profiles });
+        }
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            let Some(name) = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_suffix(PROMPT_PROFILE_SUFFIX))
+                .filter(|name| valid_profile_name(name))
+            else {
+                continue;
+            };
+            if !path.is_file() {
+                continue;
+            }
+            let content = fs::read_to_string(&path).map_err(|err| {
+                format!("Failed to read prompt profile {}: {}", path.display(), err)
+            })?;
+            profiles.insert(
+                name.to_string(),
+                PromptProfile {
+                    name: name.to_string(),
+                    path: Path::new(PROMPTS_DIR).join(format!("{}{}", name, PROMPT_PROFILE_SUFFIX)),
+                    content,
+                },
+            );
+        }
+        Ok(Self { profiles })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&PromptProfile> {
+        self.profiles.get(name)
+    }
+}
+
+pub fn valid_profile_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|ch| ch.is_ascii_alphanumeric())
+        && chars.all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '.' | '_' | '-'))
+}
+
+/// Which profile a run uses and why. `name` is `None` for the `--prompt` file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProfileSelection {
+    pub name: Option<String>,
+    pub source: &'static str,
+}
+
+impl ProfileSelection {
+    pub fn label(&self) -> &str {
+        self.name.as_deref().unwrap_or("default")
+    }
+
+    pub fn to_json(&self, path: &str) -> Value {
+        json!({
+            "profile": self.label(),
+            "source": self.source,
+            "path": path,
+        })
+    }
+}
+
+/// Task `prompt` wins over the model default from `lever.json`, which wins
+/// over the `--prompt` file.
+pub fn select_profile(
+    task_profile: Option<&str>,
+    model: &str,
+    model_profiles: &BTreeMap<String, String>,
+) -> ProfileSelection {
+    if let Some(name) = task_profile {
+        return ProfileSelection {
+            name: Some(name.to_string()),
+            source: "task",
+        };
+    }
+    if let Some(name) = model_profiles.get(model) {
+        return ProfileSelection {
+            name: Some(name.clone()),
+            source: "model",
+        };
+    }
+    ProfileSelection {
+        name: None,
+        source: "default",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn select_profile_prefers_task_then_model() {
+        let models = BTreeMap::from([("gpt-5.2-codex".to_string(), "deep".to_string())]);
+
+        let selection = select_profile(Some("refactor"), "gpt-5.2-codex", &models);
+        assert_eq!(selection.name.as_deref(), Some("refactor"));
+        assert_eq!(selection.source, "task");
+
+        let selection = select_profile(None, "gpt-5.2-codex", &models);
+        assert_eq!(selection.label(), "deep");
+        assert_eq!(selection.source, "model");
+
+        let selection = select_profile(None, "gpt-5.1-codex-mini", &models);
+        assert_eq!(selection.label(), "default");
+        assert_eq!(selection.source, "default");
+    }
+
+    #[test]
+    fn profiles_load_from_prompts_dir() {
+        let workspace =
+            std::env::temp_dir().join(format!("lever-prompt-profiles-{}", std::process::id()));
+        let prompts = workspace.join(PROMPTS_DIR);
+        fs::create_dir_all(&prompts).unwrap();
+        fs::write(prompts.join("refactor.prompt.md"), "Refactor carefully").unwrap();
+        fs::write(prompts.join("notes.md"), "not a profile").unwrap();
+
+        let profiles = PromptProfiles::load(&workspace).unwrap();
+        let refactor = profiles.get("refactor").unwrap();
+        assert_eq!(refactor.content, "Refactor carefully");
+        assert_eq!(refactor.path, PathBuf::from("prompts/refactor.prompt.md"));
+        assert!(profiles.get("notes").is_none());
+        assert_eq!(
+            PromptProfiles::load(&workspace.join("missing")).unwrap(),
+            PromptProfiles::default()
+        );
+
+        fs::remove_dir_all(&workspace).unwrap();
+    }
+
+    #[test]
+    fn profile_names_reject_paths() {
+        assert!(valid_profile_name("docs-v2.short_form"));
+        assert!(!valid_profile_name("../secret"));
+        assert!(!valid_profile_name("nested/name"));
+        assert!(!valid_profile_name(""));
+    }
+}
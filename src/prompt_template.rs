@@ -0,0 +1,201 @@
+use std::error::Error;
+
+use minijinja::{syntax::SyntaxConfig, value::Serde, AutoEscape, Environment, UndefinedBehavior};
+use serde_json::{Map, Value};
+
+type DynError = Box<dyn Error + Send + Sync + 'static>;
+
+/// Variables rendered from prompt sections, in prompt order. Each one is a
+/// section in `prompt-tokens.json` and may be cut by the prompt budget.
+pub const SECTION_VARIABLES: [&str; 7] = [
+    "base_prompt",
+    "task",
+    "task_json",
+    "previous_attempt",
+    "verification_findings",
+    "lint_summary",
+    "compiled_context",
+];
+
+/// Plain task and repository values available to templates.
+pub const VALUE_VARIABLES: [&str; 5] =
+    ["task_id", "title", "definition_of_done", "approach", "repo"];
+
+/// Reproduces the fixed layout used for prompt files without placeholders.
+pub const DEFAULT_PROMPT_TEMPLATE: &str =
+    "{{ base_prompt }}{{ task }}{{ task_json }}{{ verification_findings }}{{ lint_summary }}{{ compiled_context }}";
+
+pub struct PromptTemplate {
+    source: String,
+    base_prompt: Option<String>,
+}
+
+impl PromptTemplate {
+    /// A prompt file that references a known variable (`{{ title }}`, ...) is
+    /// the template; any other prompt is wrapped by the default template.
+    pub fn from_prompt(raw: String) -> Self {
+        if references_template_variable(&raw) {
+            Self {
+                source: raw,
+                base_prompt: None,
+            }
+        } else {
+            Self {
+                source: DEFAULT_PROMPT_TEMPLATE.to_string(),
+                base_prompt: Some(raw),
+            }
+        }
+    }
+
+    pub fn is_default(&self) -> bool {
+        self.base_prompt.is_some()
+    }
+
+    /// The prompt file text used as the `base_prompt` section, if any.
+    pub fn base_prompt(&self) -> Option<&str> {
+        self.base_prompt.as_deref()
+    }
+
+    /// Section variables the template references, in prompt order.
+    pub fn referenced_sections(&self) -> Result<Vec<&'static str>, DynError> {
+        let env = environment();
+        let template = env.template_from_str(&self.source)?;
+        let referenced = template.undeclared_variables(false);
+        Ok(SECTION_VARIABLES
+            .iter()
+            .copied()
+            .filter(|name| referenced.contains(*name))
+            .collect())
+    }
+
+    /// Renders with `values` plus one string per section; sections missing
+    /// from `sections` render as empty strings.
+    pub fn render(
+        &self,
+        values: &Map<String, Value>,
+        sections: &[(&str, &str)],
+    ) -> Result<String, DynError> {
+        let mut context = values.clone();
+        for name in SECTION_VARIABLES {
+            let text = sections
+                .iter()
+                .find(|(section, _)| *section == name)
+                .map(|(_, text)| *text)
+                .unwrap_or("");
+            context.insert(name.to_string(), Value::from(text));
+        }
+        if !self.is_default() {
+            context.remove("base_prompt");
+        }
+        let env = environment();
+        let template = env.template_from_str(&self.source)?;
+        Ok(template.render(Serde(&Value::Object(context)))?)
+    }
+}
+
+fn environment<'source>() -> Environment<'source> {
+    let mut env = Environment::new();
+    let syntax = SyntaxConfig::builder()
+        .keep_trailing_newline(true)
+        .trim_blocks(true)
+        .lstrip_blocks(true)
+        .build()
+        .expect("default delimiters are valid");
+    env.set_syntax(syntax);
+    env.set_undefined_behavior(UndefinedBehavior::Strict);
+    env.set_auto_escape_callback(|_| AutoEscape::None);
+    env
+}
+
+fn references_template_variable(raw: &str) -> bool {
+    raw.match_indices("{{").any(|(idx, _)| {
+        let rest = raw[idx + 2..].trim_start_matches(['-', ' ', '\t']);
+        let name: String = rest
+            .chars()
+            .take_while(|ch| ch.is_ascii_alphanumeric() || *ch == '_')
+            .collect();
+        SECTION_VARIABLES.contains(&name.as_str()) || VALUE_VARIABLES.contains(&name.as_str())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn values() -> Map<String, Value> {
+        json!({
+            "task_id": "T1",
+            "title": "Ship it",
+            "definition_of_done": ["Tests pass", "Docs updated"],
+            "approach": "Small steps",
+            "repo": { "name": "lever", "branch": "main", "commit": "abc123" }
+        })
+        .as_object()
+        .cloned()
+        .unwrap()
+    }
+
+    #[test]
+    fn plain_prompt_uses_default_layout() {
+        let template = PromptTemplate::from_prompt("Base {{ not a variable }}".to_string());
+        assert!(template.is_default());
+        assert_eq!(
+            template.referenced_sections().unwrap(),
+            vec![
+                "base_prompt",
+                "task",
+                "task_json",
+                "verification_findings",
+                "lint_summary",
+                "compiled_context"
+            ]
+        );
+        let rendered = template
+            .render(
+                &values(),
+                &[
+                    ("base_prompt", "Base\n\n"),
+                    ("task", "Task title: Ship it\n"),
+                    ("compiled_context", "\nCompiled context:\nbody\n"),
+                ],
+            )
+            .unwrap();
+        assert_eq!(
+            rendered,
+            "Base\n\nTask title: Ship it\n\nCompiled context:\nbody\n"
+        );
+    }
+
+    #[test]
+    fn custom_template_renders_values_and_sections() {
+        let source = "# {{ title }} ({{ task_id }}) on {{ repo.branch }}\n\
+                      {% for item in definition_of_done %}\n\
+                      - {{ item }}\n\
+                      {% endfor %}\n\
+                      {{ previous_attempt }}{{ lint_summary }}end\n";
+        let template = PromptTemplate::from_prompt(source.to_string());
+        assert!(!template.is_default());
+        assert_eq!(
+            template.referenced_sections().unwrap(),
+            vec!["previous_attempt", "lint_summary"]
+        );
+        let rendered = template
+            .render(&values(), &[("lint_summary", "Lint: ok\n")])
+            .unwrap();
+        assert_eq!(
+            rendered,
+            "# Ship it (T1) on main\n- Tests pass\n- Docs updated\nLint: ok\nend\n"
+        );
+    }
+
+    #[test]
+    fn custom_template_rejects_unknown_variables() {
+        let template = PromptTemplate::from_prompt("{{ title }} {{ titel }}".to_string());
+        assert!(template.render(&values(), &[]).is_err());
+        let template = PromptTemplate::from_prompt("{{ title }} {{ base_prompt }}".to_string());
+        assert!(template.render(&values(), &[]).is_err());
+        let template = PromptTemplate::from_prompt("{{ title }} {% if %}".to_string());
+        assert!(template.referenced_sections().is_err());
+    }
+}
@@ -1,19 +1,99 @@
 use std::{
+    collections::BTreeMap,
     error::Error,
-    fs,
-    path::Path,
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
     time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
-use serde_json::{json, Map, Value};
+use serde_json::{Map, Value};
+
+use crate::config::RateLimitConfig;
 
 type DynError = Box<dyn Error + Send + Sync + 'static>;
 
-#[derive(Debug, Clone)]
-struct RateLimitEntry {
-    ts: f64,
-    model: String,
-    tokens: i64,
+pub const USAGE_FILE: &str = ".ralph/usage.jsonl";
+pub const USAGE_LOCK_FILE: &str = ".ralph/usage.lock";
+const SECONDS_PER_DAY: f64 = 86_400.0;
+const USAGE_RETENTION_DAYS: f64 = 31.0;
+
+/// One recorded Codex request. The optional fields attribute spend to a
+/// task run and a lever session for budget accounting.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UsageEntry {
+    pub ts: f64,
+    pub model: String,
+    pub tokens: i64,
+    pub input_tokens: Option<u64>,
+    pub output_tokens: Option<u64>,
+    pub cost_usd: Option<f64>,
+    pub task_id: Option<String>,
+    pub run_id: Option<String>,
+    pub session_id: Option<String>,
+    /// Daily-cap headroom held for `run_id` until its usage is recorded.
+    pub reserved: bool,
+}
+
+impl UsageEntry {
+    pub fn new(model: &str, tokens: u64) -> Self {
+        Self {
+            model: model.to_string(),
+            tokens: tokens as i64,
+            ..Self::default()
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ModelLimits {
+    pub tpm: u64,
+    pub rpm: u64,
+    pub daily_tokens: Option<u64>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DailyCapExceeded {
+    pub used: u64,
+    pub cap: u64,
+    pub resets_in_seconds: u64,
+}
+
+#[derive(Debug)]
+pub enum DailyCapCheck {
+    /// The model has no daily cap.
+    Unlimited,
+    Reserved(UsageReservation),
+    Exceeded(DailyCapExceeded),
+}
+
+/// A run's estimated tokens, counted against the daily cap from the check
+/// until the run records its usage. Dropping it releases whatever was not
+/// replaced by a recorded entry.
+#[derive(Debug)]
+pub struct UsageReservation {
+    usage_file: PathBuf,
+    run_id: String,
+}
+
+impl Drop for UsageReservation {
+    fn drop(&mut self) {
+        let _ = release_reservation(&self.usage_file, &self.run_id);
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UsageTotals {
+    pub requests: u64,
+    pub tokens: u64,
+    pub cost_usd: f64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ModelUsage {
+    pub window: UsageTotals,
+    /// Totals keyed by UTC day index (days since the UNIX epoch).
+    pub days: BTreeMap<i64, UsageTotals>,
 }
 
 fn now_epoch_seconds() -> Result<f64, DynError> {
@@ -31,47 +111,130 @@ pub fn rate_limit_settings(model: &str) -> (u64, u64) {
     }
 }
 
-pub fn estimate_prompt_tokens(path: &Path) -> u64 {
-    let size = fs::metadata(path).map(|meta| meta.len()).unwrap_or(0);
-    if size == 0 {
-        return 1000;
+/// Built-in TPM/RPM defaults overridden by any per-model values in config.
+pub fn model_limits(config: &RateLimitConfig, model: &str) -> ModelLimits {
+    let (tpm, rpm) = rate_limit_settings(model);
+    let overrides = config.models.get(model).copied().unwrap_or_default();
+    ModelLimits {
+        tpm: overrides.tpm.unwrap_or(tpm),
+        rpm: overrides.rpm.unwrap_or(rpm),
+        daily_tokens: overrides.daily_tokens,
     }
-    let estimate = ((size as f64) / 4.0).ceil() as u64;
-    std::cmp::max(1000, estimate)
 }
 
 pub fn rate_limit_sleep_seconds(
-    rate_file: &Path,
+    usage_file: &Path,
     model: &str,
     window: Duration,
-    tpm_limit: u64,
-    rpm_limit: u64,
+    limits: ModelLimits,
     estimated_tokens: u64,
 ) -> Result<u64, DynError> {
     let now = now_epoch_seconds()?;
+    let entries = read_usage_entries(usage_file)?;
     Ok(rate_limit_sleep_seconds_at(
-        rate_file,
+        &entries,
         model,
         window,
-        tpm_limit,
-        rpm_limit,
+        limits.tpm,
+        limits.rpm,
         estimated_tokens,
         now,
     ))
 }
 
-pub fn record_rate_usage(
-    rate_file: &Path,
+/// Checks the daily cap and, when the run fits, reserves its estimated
+/// tokens under the same exclusive lock, so concurrent runs cannot both
+/// claim the remaining headroom.
+pub fn reserve_daily_cap(
+    usage_file: &Path,
     model: &str,
+    limits: ModelLimits,
+    estimated_tokens: u64,
+    run_id: &str,
+) -> Result<DailyCapCheck, DynError> {
+    let Some(cap) = limits.daily_tokens else {
+        return Ok(DailyCapCheck::Unlimited);
+    };
+    let now = now_epoch_seconds()?;
+    let lock = lock_usage_file(usage_file)?;
+    let result = read_usage_entries_unlocked(usage_file).and_then(|entries| {
+        if let Some(exceeded) = daily_cap_exceeded_at(&entries, model, cap, estimated_tokens, now) {
+            return Ok(DailyCapCheck::Exceeded(exceeded));
+        }
+        let reservation = UsageEntry {
+            ts: now,
+            run_id: Some(run_id.to_string()),
+            reserved: true,
+            ..UsageEntry::new(model, estimated_tokens)
+        };
+        append_or_compact(usage_file, &reservation, now)?;
+        Ok(DailyCapCheck::Reserved(UsageReservation {
+            usage_file: usage_file.to_path_buf(),
+            run_id: run_id.to_string(),
+        }))
+    });
+    let _ = lock.unlock();
+    result
+}
+
+pub fn record_rate_usage(usage_file: &Path, entry: UsageEntry) -> Result<(), DynError> {
+    let now = now_epoch_seconds()?;
+    record_rate_usage_at(usage_file, entry, now)
+}
+
+pub fn summarize_usage(
+    usage_file: &Path,
     window: Duration,
-    tokens: u64,
-) -> Result<(), DynError> {
+    days: u64,
+) -> Result<BTreeMap<String, ModelUsage>, DynError> {
     let now = now_epoch_seconds()?;
-    record_rate_usage_at(rate_file, model, window, tokens, now)
+    let entries = read_usage_entries(usage_file)?;
+    Ok(summarize_usage_at(&entries, window, days, now))
+}
+
+pub fn today_day_index() -> Result<i64, DynError> {
+    Ok(day_index(now_epoch_seconds()?))
+}
+
+/// Formats a UTC day index as `YYYY-MM-DD` (proleptic Gregorian calendar).
+pub fn day_label(day_index: i64) -> String {
+    let z = day_index + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Reads every recorded request while holding a shared lock so concurrent
+/// writers never expose a half-rewritten file. Daily-cap reservations are
+/// left out.
+pub fn read_usage_entries(usage_file: &Path) -> Result<Vec<UsageEntry>, DynError> {
+    if !usage_file.exists() {
+        return Ok(Vec::new());
+    }
+    let lock = open_lock_file(usage_file)?;
+    lock.lock_shared().map_err(|err| {
+        format!(
+            "Failed to lock {}: {}",
+            lock_path(usage_file).display(),
+            err
+        )
+    })?;
+    let entries = read_usage_entries_unlocked(usage_file);
+    let _ = lock.unlock();
+    Ok(entries?
+        .into_iter()
+        .filter(|entry| !entry.reserved)
+        .collect())
 }
 
 fn rate_limit_sleep_seconds_at(
-    rate_file: &Path,
+    entries: &[UsageEntry],
     model: &str,
     window: Duration,
     tpm_limit: u64,
@@ -80,9 +243,8 @@ fn rate_limit_sleep_seconds_at(
     now: f64,
 ) -> u64 {
     let window_secs = window.as_secs_f64();
-    let mut requests = read_rate_limit_requests(rate_file);
-    let mut recent: Vec<RateLimitEntry> = requests
-        .drain(..)
+    let mut recent: Vec<&UsageEntry> = entries
+        .iter()
         .filter(|entry| entry.model == model && is_recent(entry, now, window_secs))
         .collect();
     recent.sort_by(|a, b| a.ts.partial_cmp(&b.ts).unwrap_or(std::cmp::Ordering::Equal));
@@ -119,110 +281,272 @@ fn rate_limit_sleep_seconds_at(
     (sleep_for + 0.999).floor() as u64
 }
 
-fn record_rate_usage_at(
-    rate_file: &Path,
+fn daily_cap_exceeded_at(
+    entries: &[UsageEntry],
     model: &str,
+    cap: u64,
+    estimated_tokens: u64,
+    now: f64,
+) -> Option<DailyCapExceeded> {
+    let today = day_index(now);
+    let used: i64 = entries
+        .iter()
+        .filter(|entry| {
+            entry.model == model && entry.ts.is_finite() && day_index(entry.ts) == today
+        })
+        .map(|entry| entry.tokens.max(0))
+        .sum();
+    let used = used as u64;
+    if used.saturating_add(estimated_tokens) <= cap {
+        return None;
+    }
+    let next_day = (today + 1) as f64 * SECONDS_PER_DAY;
+    Some(DailyCapExceeded {
+        used,
+        cap,
+        resets_in_seconds: (next_day - now).max(0.0).ceil() as u64,
+    })
+}
+
+fn summarize_usage_at(
+    entries: &[UsageEntry],
     window: Duration,
-    tokens: u64,
+    days: u64,
     now: f64,
-) -> Result<(), DynError> {
+) -> BTreeMap<String, ModelUsage> {
     let window_secs = window.as_secs_f64();
-    let (mut payload, mut requests) = read_rate_limit_payload(rate_file);
-    requests.retain(|entry| is_recent(entry, now, window_secs));
-    requests.push(RateLimitEntry {
-        ts: now,
-        model: model.to_string(),
-        tokens: tokens as i64,
-    });
-
-    let mut entries = Vec::with_capacity(requests.len());
-    for entry in requests {
-        entries.push(rate_limit_entry_value(&entry));
+    let today = day_index(now);
+    let first_day = today - days.saturating_sub(1) as i64;
+    let mut summary: BTreeMap<String, ModelUsage> = BTreeMap::new();
+    for entry in entries {
+        if !entry.ts.is_finite() {
+            continue;
+        }
+        let day = day_index(entry.ts);
+        let in_window = is_recent(entry, now, window_secs);
+        if !in_window && (day < first_day || day > today) {
+            continue;
+        }
+        let usage = summary.entry(entry.model.clone()).or_default();
+        let tokens = entry.tokens.max(0) as u64;
+        let cost = entry.cost_usd.unwrap_or(0.0);
+        if in_window {
+            usage.window.requests += 1;
+            usage.window.tokens += tokens;
+            usage.window.cost_usd += cost;
+        }
+        if day >= first_day && day <= today {
+            let totals = usage.days.entry(day).or_default();
+            totals.requests += 1;
+            totals.tokens += tokens;
+            totals.cost_usd += cost;
+        }
     }
+    summary
+}
 
-    if let Value::Object(map) = &mut payload {
-        map.insert("requests".to_string(), Value::Array(entries));
-    } else {
-        payload = json!({ "requests": entries });
-    }
+fn record_rate_usage_at(
+    usage_file: &Path,
+    mut entry: UsageEntry,
+    now: f64,
+) -> Result<(), DynError> {
+    entry.ts = now;
+    let lock = lock_usage_file(usage_file)?;
+    let result = append_or_compact(usage_file, &entry, now);
+    let _ = lock.unlock();
+    result
+}
 
-    if let Some(parent) = rate_file.parent() {
-        if !parent.as_os_str().is_empty() {
-            fs::create_dir_all(parent).map_err(|err| {
-                format!(
-                    "Failed to create rate limit directory {}: {}",
-                    parent.display(),
-                    err
-                )
-            })?;
+/// Drops the reservation held for `run_id`, if it is still there.
+fn release_reservation(usage_file: &Path, run_id: &str) -> Result<(), DynError> {
+    let lock = lock_usage_file(usage_file)?;
+    let result = read_usage_entries_unlocked(usage_file).and_then(|existing| {
+        if !existing
+            .iter()
+            .any(|existing| is_reservation_for(existing, run_id))
+        {
+            return Ok(());
         }
-    }
-
-    let serialized = serde_json::to_string(&payload)?;
-    fs::write(rate_file, serialized).map_err(|err| {
-        format!(
-            "Failed to write rate limit file {}: {}",
-            rate_file.display(),
-            err
-        )
-    })?;
-    Ok(())
+        let kept: Vec<&UsageEntry> = existing
+            .iter()
+            .filter(|existing| !is_reservation_for(existing, run_id))
+            .collect();
+        rewrite_usage_file(usage_file, &kept)
+    });
+    let _ = lock.unlock();
+    result
 }
 
-fn read_rate_limit_payload(rate_file: &Path) -> (Value, Vec<RateLimitEntry>) {
-    if !rate_file.exists() {
-        return (json!({ "requests": [] }), Vec::new());
-    }
+fn is_reservation_for(entry: &UsageEntry, run_id: &str) -> bool {
+    entry.reserved && entry.run_id.as_deref() == Some(run_id)
+}
 
-    let raw = match fs::read_to_string(rate_file) {
-        Ok(contents) => contents,
-        Err(_) => return (json!({ "requests": [] }), Vec::new()),
+/// Appends one JSON line per request; once entries age past the retention
+/// window, or a recorded run replaces its reservation, the file is rewritten
+/// (still under the exclusive lock).
+fn append_or_compact(usage_file: &Path, entry: &UsageEntry, now: f64) -> Result<(), DynError> {
+    let existing = read_usage_entries_unlocked(usage_file)?;
+    let retention = USAGE_RETENTION_DAYS * SECONDS_PER_DAY;
+    let replaced = |existing: &UsageEntry| {
+        !entry.reserved
+            && entry
+                .run_id
+                .as_deref()
+                .is_some_and(|run_id| is_reservation_for(existing, run_id))
     };
+    let rewrite = existing.iter().any(|existing| {
+        !existing.ts.is_finite() || now - existing.ts >= retention || replaced(existing)
+    });
 
-    let payload: Value = match serde_json::from_str(&raw) {
-        Ok(value) => value,
-        Err(_) => return (json!({ "requests": [] }), Vec::new()),
-    };
+    if rewrite {
+        let mut kept: Vec<&UsageEntry> = existing
+            .iter()
+            .filter(|existing| {
+                existing.ts.is_finite() && now - existing.ts < retention && !replaced(existing)
+            })
+            .collect();
+        kept.push(entry);
+        return rewrite_usage_file(usage_file, &kept);
+    }
 
-    let requests = extract_requests(&payload);
-    (payload, requests)
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(usage_file)
+        .map_err(|err| write_err(usage_file, err))?;
+    file.write_all(usage_entry_line(entry)?.as_bytes())
+        .map_err(|err| write_err(usage_file, err))?;
+    Ok(())
+}
+
+fn rewrite_usage_file(usage_file: &Path, entries: &[&UsageEntry]) -> Result<(), DynError> {
+    let mut contents = String::new();
+    for entry in entries {
+        contents.push_str(&usage_entry_line(entry)?);
+    }
+    let tmp_path = usage_file.with_extension("jsonl.tmp");
+    fs::write(&tmp_path, contents).map_err(|err| write_err(usage_file, err))?;
+    fs::rename(&tmp_path, usage_file).map_err(|err| write_err(usage_file, err))?;
+    Ok(())
 }
 
-fn read_rate_limit_requests(rate_file: &Path) -> Vec<RateLimitEntry> {
-    let (_, requests) = read_rate_limit_payload(rate_file);
-    requests
+fn write_err(usage_file: &Path, err: std::io::Error) -> String {
+    format!(
+        "Failed to write usage file {}: {}",
+        usage_file.display(),
+        err
+    )
 }
 
-fn extract_requests(payload: &Value) -> Vec<RateLimitEntry> {
-    let requests_value = match payload.get("requests") {
-        Some(Value::Array(items)) => items,
-        _ => return Vec::new(),
+fn read_usage_entries_unlocked(usage_file: &Path) -> Result<Vec<UsageEntry>, DynError> {
+    let raw = match fs::read_to_string(usage_file) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => {
+            return Err(format!(
+                "Failed to read usage file {}: {}",
+                usage_file.display(),
+                err
+            )
+            .into())
+        }
     };
+    Ok(raw
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .filter_map(|value| parse_usage_entry(&value))
+        .collect())
+}
 
-    let mut requests = Vec::with_capacity(requests_value.len());
-    for item in requests_value {
-        let object = match item.as_object() {
-            Some(map) => map,
-            None => continue,
-        };
-        let ts = value_to_f64(object.get("ts"));
-        let tokens = value_to_i64(object.get("tokens"));
-        let model = object
-            .get("model")
-            .and_then(Value::as_str)
-            .unwrap_or("")
-            .to_string();
-        requests.push(RateLimitEntry { ts, model, tokens });
-    }
-    requests
+fn parse_usage_entry(value: &Value) -> Option<UsageEntry> {
+    let object = value.as_object()?;
+    let ts = value_to_f64(object.get("ts"));
+    let tokens = value_to_i64(object.get("tokens"));
+    let model = object
+        .get("model")
+        .and_then(Value::as_str)
+        .unwrap_or("")
+        .to_string();
+    let text = |key: &str| object.get(key).and_then(Value::as_str).map(str::to_string);
+    Some(UsageEntry {
+        ts,
+        model,
+        tokens,
+        input_tokens: object.get("input_tokens").and_then(Value::as_u64),
+        output_tokens: object.get("output_tokens").and_then(Value::as_u64),
+        cost_usd: object.get("cost_usd").and_then(Value::as_f64),
+        task_id: text("task_id"),
+        run_id: text("run_id"),
+        session_id: text("session_id"),
+        reserved: object.get("reserved").and_then(Value::as_bool) == Some(true),
+    })
 }
 
-fn rate_limit_entry_value(entry: &RateLimitEntry) -> Value {
+fn usage_entry_line(entry: &UsageEntry) -> Result<String, DynError> {
     let mut map = Map::new();
     map.insert("ts".to_string(), Value::from(entry.ts));
     map.insert("model".to_string(), Value::from(entry.model.clone()));
     map.insert("tokens".to_string(), Value::from(entry.tokens));
-    Value::Object(map)
+    if let Some(input_tokens) = entry.input_tokens {
+        map.insert("input_tokens".to_string(), Value::from(input_tokens));
+    }
+    if let Some(output_tokens) = entry.output_tokens {
+        map.insert("output_tokens".to_string(), Value::from(output_tokens));
+    }
+    if let Some(cost_usd) = entry.cost_usd {
+        map.insert("cost_usd".to_string(), Value::from(cost_usd));
+    }
+    for (key, value) in [
+        ("task_id", &entry.task_id),
+        ("run_id", &entry.run_id),
+        ("session_id", &entry.session_id),
+    ] {
+        if let Some(value) = value {
+            map.insert(key.to_string(), Value::from(value.clone()));
+        }
+    }
+    if entry.reserved {
+        map.insert("reserved".to_string(), Value::from(true));
+    }
+    Ok(format!("{}\n", serde_json::to_string(&Value::Object(map))?))
+}
+
+fn lock_path(usage_file: &Path) -> PathBuf {
+    usage_file.with_extension("lock")
+}
+
+/// Creates the usage directory if needed and takes the exclusive lock.
+fn lock_usage_file(usage_file: &Path) -> Result<File, DynError> {
+    if let Some(parent) = usage_file.parent() {
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent).map_err(|err| {
+                format!(
+                    "Failed to create usage directory {}: {}",
+                    parent.display(),
+                    err
+                )
+            })?;
+        }
+    }
+    let lock = open_lock_file(usage_file)?;
+    lock.lock().map_err(|err| {
+        format!(
+            "Failed to lock {}: {}",
+            lock_path(usage_file).display(),
+            err
+        )
+    })?;
+    Ok(lock)
+}
+
+fn open_lock_file(usage_file: &Path) -> Result<File, DynError> {
+    let path = lock_path(usage_file);
+    OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&path)
+        .map_err(|err| format!("Failed to open lock file {}: {}", path.display(), err).into())
 }
 
 fn value_to_f64(value: Option<&Value>) -> f64 {
@@ -244,18 +568,24 @@ fn value_to_i64(value: Option<&Value>) -> i64 {
     }
 }
 
-fn is_recent(entry: &RateLimitEntry, now: f64, window_secs: f64) -> bool {
+fn is_recent(entry: &UsageEntry, now: f64, window_secs: f64) -> bool {
     if !entry.ts.is_finite() {
         return false;
     }
     now - entry.ts < window_secs
 }
 
+fn day_index(ts: f64) -> i64 {
+    (ts / SECONDS_PER_DAY).floor() as i64
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::ModelRateLimit;
     use std::fs;
-    use std::path::PathBuf;
+    use std::sync::Arc;
+    use std::thread;
 
     fn temp_path(name: &str) -> PathBuf {
         let mut path = std::env::temp_dir();
@@ -267,112 +597,255 @@ mod tests {
         path
     }
 
+    fn entry(ts: f64, model: &str, tokens: i64) -> UsageEntry {
+        UsageEntry {
+            ts,
+            model: model.to_string(),
+            tokens,
+            ..UsageEntry::default()
+        }
+    }
+
     #[test]
     fn sleep_respects_rpm_limit() {
-        let rate_file = temp_path("rpm");
         let window = Duration::from_secs(60);
-        let now = 1000.0;
-
-        let payload = json!({
-            "requests": [
-                { "ts": 950.0, "model": "gpt-5.2-codex", "tokens": 10 },
-                { "ts": 980.0, "model": "gpt-5.2-codex", "tokens": 20 },
-                { "ts": 990.0, "model": "gpt-5.2-codex", "tokens": 30 }
-            ]
-        });
-
-        fs::create_dir_all(rate_file.parent().unwrap()).unwrap();
-        fs::write(&rate_file, serde_json::to_string(&payload).unwrap()).unwrap();
+        let entries = vec![
+            entry(950.0, "gpt-5.2-codex", 10),
+            entry(980.0, "gpt-5.2-codex", 20),
+            entry(990.0, "gpt-5.2-codex", 30),
+        ];
 
         let sleep_seconds =
-            rate_limit_sleep_seconds_at(&rate_file, "gpt-5.2-codex", window, 0, 2, 0, now);
+            rate_limit_sleep_seconds_at(&entries, "gpt-5.2-codex", window, 0, 2, 0, 1000.0);
 
         assert_eq!(sleep_seconds, 40);
     }
 
     #[test]
     fn sleep_respects_tpm_limit() {
-        let rate_file = temp_path("tpm");
         let window = Duration::from_secs(60);
-        let now = 1000.0;
+        let entries = vec![
+            entry(950.0, "gpt-5.2-codex", 50),
+            entry(980.0, "gpt-5.2-codex", 30),
+        ];
 
-        let payload = json!({
-            "requests": [
-                { "ts": 950.0, "model": "gpt-5.2-codex", "tokens": 50 },
-                { "ts": 980.0, "model": "gpt-5.2-codex", "tokens": 30 }
+        let sleep_seconds =
+            rate_limit_sleep_seconds_at(&entries, "gpt-5.2-codex", window, 100, 0, 40, 1000.0);
+
+        assert_eq!(sleep_seconds, 10);
+    }
+
+    #[test]
+    fn record_rate_usage_prunes_entries_past_retention() {
+        let usage_file = temp_path("record").join("usage.jsonl");
+        fs::create_dir_all(usage_file.parent().unwrap()).unwrap();
+        let now = 100.0 * SECONDS_PER_DAY;
+        let old = now - (USAGE_RETENTION_DAYS + 1.0) * SECONDS_PER_DAY;
+        fs::write(
+            &usage_file,
+            format!(
+                "{}{}not json\n",
+                usage_entry_line(&entry(old, "gpt-5.2-codex", 10)).unwrap(),
+                usage_entry_line(&entry(now - 10.0, "gpt-5.1-codex", 20)).unwrap()
+            ),
+        )
+        .unwrap();
+
+        record_rate_usage_at(&usage_file, UsageEntry::new("gpt-5.2-codex", 5), now).unwrap();
+
+        let entries = read_usage_entries(&usage_file).unwrap();
+        assert_eq!(
+            entries,
+            vec![
+                entry(now - 10.0, "gpt-5.1-codex", 20),
+                entry(now, "gpt-5.2-codex", 5)
             ]
-        });
+        );
+    }
 
-        fs::create_dir_all(rate_file.parent().unwrap()).unwrap();
-        fs::write(&rate_file, serde_json::to_string(&payload).unwrap()).unwrap();
+    #[test]
+    fn concurrent_writers_do_not_lose_entries() {
+        let usage_file = Arc::new(temp_path("concurrent").join("usage.jsonl"));
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let usage_file = Arc::clone(&usage_file);
+                thread::spawn(move || {
+                    for _ in 0..25 {
+                        record_rate_usage(&usage_file, UsageEntry::new("gpt-5.2-codex", 1))
+                            .unwrap();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
 
-        let sleep_seconds =
-            rate_limit_sleep_seconds_at(&rate_file, "gpt-5.2-codex", window, 100, 0, 40, now);
+        let entries = read_usage_entries(&usage_file).unwrap();
+        assert_eq!(entries.len(), 200);
+    }
 
-        assert_eq!(sleep_seconds, 10);
+    #[test]
+    fn daily_cap_counts_only_current_utc_day() {
+        let now = 10.0 * SECONDS_PER_DAY + 3_600.0;
+        let entries = vec![
+            entry(now - 7_200.0, "gpt-5.2-codex", 900),
+            entry(now - 60.0, "gpt-5.2-codex", 50),
+            entry(now - 60.0, "gpt-5.1-codex", 500),
+        ];
+
+        assert_eq!(
+            daily_cap_exceeded_at(&entries, "gpt-5.2-codex", 100, 50, now),
+            None
+        );
+        assert_eq!(
+            daily_cap_exceeded_at(&entries, "gpt-5.2-codex", 100, 51, now),
+            Some(DailyCapExceeded {
+                used: 50,
+                cap: 100,
+                resets_in_seconds: 82_800,
+            })
+        );
     }
 
     #[test]
-    fn record_rate_usage_prunes_old_entries() {
-        let rate_file = temp_path("record");
-        let window = Duration::from_secs(60);
-        let now = 1000.0;
-
-        let payload = json!({
-            "requests": [
-                { "ts": 800.0, "model": "gpt-5.2-codex", "tokens": 10 },
-                { "ts": 990.0, "model": "gpt-5.1-codex", "tokens": 20 }
-            ],
-            "extra": "keep"
-        });
-
-        fs::create_dir_all(rate_file.parent().unwrap()).unwrap();
-        fs::write(&rate_file, serde_json::to_string(&payload).unwrap()).unwrap();
-
-        record_rate_usage_at(&rate_file, "gpt-5.2-codex", window, 5, now).unwrap();
-
-        let written: Value =
-            serde_json::from_str(&fs::read_to_string(&rate_file).unwrap()).unwrap();
-        let requests = written.get("requests").and_then(Value::as_array).unwrap();
-        assert_eq!(written.get("extra").and_then(Value::as_str), Some("keep"));
-        assert_eq!(requests.len(), 2);
+    fn daily_cap_reservations_hold_headroom_until_recorded_or_dropped() {
+        let usage_file = temp_path("reserve").join("usage.jsonl");
+        let limits = ModelLimits {
+            tpm: 0,
+            rpm: 0,
+            daily_tokens: Some(100),
+        };
+        let reserve = |run_id: &str| {
+            reserve_daily_cap(&usage_file, "gpt-5.2-codex", limits, 60, run_id).unwrap()
+        };
+
+        let DailyCapCheck::Reserved(first) = reserve("r1") else {
+            panic!("expected the first run to fit");
+        };
+        assert!(read_usage_entries(&usage_file).unwrap().is_empty());
+        let DailyCapCheck::Exceeded(exceeded) = reserve("r2") else {
+            panic!("expected the reservation to count against the cap");
+        };
+        assert_eq!(exceeded.used, 60);
+
+        // The recorded entry replaces the reservation.
+        record_rate_usage(
+            &usage_file,
+            UsageEntry {
+                run_id: Some("r1".to_string()),
+                ..UsageEntry::new("gpt-5.2-codex", 30)
+            },
+        )
+        .unwrap();
+        drop(first);
+        let entries = read_usage_entries_unlocked(&usage_file).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!((entries[0].tokens, entries[0].reserved), (30, false));
+
+        // A run that ends without recording gives its headroom back.
+        let DailyCapCheck::Reserved(second) = reserve("r2") else {
+            panic!("expected the second run to fit after r1 recorded 30 tokens");
+        };
+        assert!(matches!(reserve("r3"), DailyCapCheck::Exceeded(_)));
+        drop(second);
+        assert!(matches!(reserve("r3"), DailyCapCheck::Reserved(_)));
+    }
+
+    #[test]
+    fn summarize_usage_groups_by_model_window_and_day() {
+        let now = 10.0 * SECONDS_PER_DAY + 3_600.0;
+        let entries = vec![
+            entry(now - 30.0, "gpt-5.2-codex", 10),
+            entry(now - 120.0, "gpt-5.2-codex", 20),
+            entry(now - SECONDS_PER_DAY, "gpt-5.2-codex", 40),
+            entry(now - 5.0 * SECONDS_PER_DAY, "gpt-5.2-codex", 80),
+        ];
+
+        let summary = summarize_usage_at(&entries, Duration::from_secs(60), 2, now);
+        let usage = &summary["gpt-5.2-codex"];
+        assert_eq!(
+            usage.window,
+            UsageTotals {
+                requests: 1,
+                tokens: 10,
+                cost_usd: 0.0,
+            }
+        );
+        assert_eq!(usage.days.len(), 2);
+        assert_eq!(usage.days[&10].tokens, 30);
+        assert_eq!(usage.days[&9].tokens, 40);
+    }
+
+    #[test]
+    fn day_label_formats_utc_dates() {
+        assert_eq!(day_label(0), "1970-01-01");
+        assert_eq!(day_label(20_744), "2026-10-18");
+    }
+
+    #[test]
+    fn model_limits_apply_config_overrides() {
+        let mut config = RateLimitConfig::default();
+        config.models.insert(
+            "gpt-5.2-codex".to_string(),
+            ModelRateLimit {
+                tpm: Some(1_000),
+                rpm: None,
+                daily_tokens: Some(10_000),
+            },
+        );
+
         assert_eq!(
-            requests[0].get("model").and_then(Value::as_str),
-            Some("gpt-5.1-codex")
+            model_limits(&config, "gpt-5.2-codex"),
+            ModelLimits {
+                tpm: 1_000,
+                rpm: 500,
+                daily_tokens: Some(10_000),
+            }
         );
         assert_eq!(
-            requests[1].get("model").and_then(Value::as_str),
-            Some("gpt-5.2-codex")
+            model_limits(&config, "gpt-5.1-codex-mini"),
+            ModelLimits {
+                tpm: 200_000,
+                rpm: 500,
+                daily_tokens: None,
+            }
         );
     }
 
     #[test]
     fn public_helpers_smoke() {
-        let rate_file = temp_path("public");
-        let prompt_path = temp_path("prompt");
-        fs::create_dir_all(prompt_path.parent().unwrap()).unwrap();
-        fs::write(&prompt_path, "abc").unwrap();
+        let usage_file = temp_path("public").join("usage.jsonl");
 
-        let (tpm, rpm) = rate_limit_settings("gpt-5.2-codex");
-        assert_eq!(tpm, 500_000);
-        assert_eq!(rpm, 500);
-        assert!(estimate_prompt_tokens(&prompt_path) >= 1000);
+        let limits = model_limits(&RateLimitConfig::default(), "gpt-5.2-codex");
+        assert_eq!(limits.tpm, 500_000);
+        assert_eq!(limits.rpm, 500);
 
         let sleep_seconds = rate_limit_sleep_seconds(
-            &rate_file,
+            &usage_file,
             "gpt-5.2-codex",
             Duration::from_secs(60),
-            tpm,
-            rpm,
+            limits,
             0,
         )
         .unwrap();
         assert_eq!(sleep_seconds, 0);
-
-        record_rate_usage(&rate_file, "gpt-5.2-codex", Duration::from_secs(60), 25).unwrap();
-        let written: Value =
-            serde_json::from_str(&fs::read_to_string(&rate_file).unwrap()).unwrap();
-        let requests = written.get("requests").and_then(Value::as_array).unwrap();
-        assert_eq!(requests.len(), 1);
+        assert!(matches!(
+            reserve_daily_cap(&usage_file, "gpt-5.2-codex", limits, 1_000_000, "r1").unwrap(),
+            DailyCapCheck::Unlimited
+        ));
+
+        let mut recorded = UsageEntry::new("gpt-5.2-codex", 25);
+        recorded.cost_usd = Some(0.5);
+        recorded.task_id = Some("T1".to_string());
+        recorded.session_id = Some("S1".to_string());
+        record_rate_usage(&usage_file, recorded).unwrap();
+        let entries = read_usage_entries(&usage_file).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].tokens, 25);
+        assert_eq!(entries[0].cost_usd, Some(0.5));
+        assert_eq!(entries[0].task_id.as_deref(), Some("T1"));
+        assert_eq!(entries[0].session_id.as_deref(), Some("S1"));
+        assert_eq!(entries[0].run_id, None);
     }
 }
@@ -0,0 +1,250 @@
+use std::collections::BTreeSet;
+
+use serde_json::{json, Value};
+
+use crate::task_result::files_changed;
+
+/// One claim from `result.json` checked against the run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClaimCheck {
+    pub kind: &'static str,
+    pub ok: bool,
+    pub detail: String,
+}
+
+impl ClaimCheck {
+    fn new(kind: &'static str, ok: bool, detail: String) -> Self {
+        Self { kind, ok, detail }
+    }
+}
+
+/// Every claim check made for one run; written to `claims.json`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClaimsReport {
+    pub checks: Vec<ClaimCheck>,
+}
+
+impl ClaimsReport {
+    pub fn discrepancies(&self) -> impl Iterator<Item = &ClaimCheck> {
+        self.checks.iter().filter(|check| !check.ok)
+    }
+
+    /// True when a test command the agent reported as passing failed again.
+    pub fn rerun_failed(&self) -> bool {
+        self.discrepancies()
+            .any(|check| check.kind == "tests_rerun")
+    }
+
+    pub fn to_json(&self, task_id: &str, run_id: &str) -> Value {
+        json!({
+            "task_id": task_id,
+            "run_id": run_id,
+            "discrepancies": self.discrepancies().count(),
+            "checks": self
+                .checks
+                .iter()
+                .map(|check| json!({
+                    "kind": check.kind,
+                    "ok": check.ok,
+                    "detail": check.detail,
+                }))
+                .collect::<Vec<_>>(),
+        })
+    }
+
+    /// Appends `claim_discrepancies=<kinds>` to a task note.
+    pub fn append_note(&self, note: &str) -> String {
+        let mut kinds: Vec<&str> = Vec::new();
+        for check in self.discrepancies() {
+            if !kinds.contains(&check.kind) {
+                kinds.push(check.kind);
+            }
+        }
+        if kinds.is_empty() {
+            return note.to_string();
+        }
+        format!(
+            "{}; claim_discrepancies={}",
+            note.trim_end(),
+            kinds.join(",")
+        )
+    }
+}
+
+/// The `tests` object from `result.json`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TestsClaim {
+    pub ran: bool,
+    pub passed: bool,
+    pub commands: Vec<String>,
+}
+
+impl TestsClaim {
+    pub fn from_result(result: &Value) -> Self {
+        let tests = result.get("tests");
+        let flag = |key: &str| {
+            tests
+                .and_then(|tests| tests.get(key))
+                .and_then(Value::as_bool)
+                .unwrap_or(false)
+        };
+        Self {
+            ran: flag("ran"),
+            passed: flag("passed"),
+            commands: tests
+                .and_then(|tests| tests.get("commands"))
+                .and_then(Value::as_array)
+                .map(|commands| {
+                    commands
+                        .iter()
+                        .filter_map(Value::as_str)
+                        .map(str::trim)
+                        .filter(|command| !command.is_empty())
+                        .map(str::to_string)
+                        .collect()
+                })
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Re-runs each reported command through `rerun` (true when it passes)
+    /// when the agent claims the tests ran and passed.
+    pub fn check<E>(
+        &self,
+        mut rerun: impl FnMut(&str) -> Result<bool, E>,
+    ) -> Result<Vec<ClaimCheck>, E> {
+        if !self.ran {
+            return Ok(Vec::new());
+        }
+        if self.commands.is_empty() {
+            return Ok(vec![ClaimCheck::new(
+                "tests_commands",
+                false,
+                "tests.ran is true but tests.commands is empty".to_string(),
+            )]);
+        }
+        let mut checks = Vec::new();
+        if !self.passed {
+            return Ok(checks);
+        }
+        for command in &self.commands {
+            let passed = rerun(command)?;
+            let detail = if passed {
+                format!("`{}` passed on re-run", command)
+            } else {
+                format!("`{}` reported passing but failed on re-run", command)
+            };
+            checks.push(ClaimCheck::new("tests_rerun", passed, detail));
+        }
+        Ok(checks)
+    }
+}
+
+/// Compares completion and `files_changed` claims with the files the run
+/// actually changed.
+pub fn check_diff(result: &Value, changed: &BTreeSet<String>) -> Vec<ClaimCheck> {
+    let mut checks = Vec::new();
+    let claims_done = result.get("outcome").and_then(Value::as_str) == Some("completed")
+        || result.get("dod_met").and_then(Value::as_bool) == Some(true);
+    if claims_done {
+        let detail = if changed.is_empty() {
+            "claimed completion with an empty diff".to_string()
+        } else {
+            format!("claimed completion with {} changed file(s)", changed.len())
+        };
+        checks.push(ClaimCheck::new("empty_diff", !changed.is_empty(), detail));
+    }
+
+    let reported = files_changed(result);
+    let missing: Vec<&String> = reported
+        .iter()
+        .filter(|file| !changed.contains(file.trim_start_matches("./")))
+        .collect();
+    if !reported.is_empty() {
+        let detail = if missing.is_empty() {
+            format!("all {} reported file(s) are in the diff", reported.len())
+        } else {
+            format!(
+                "reported but unchanged: {}",
+                missing
+                    .iter()
+                    .map(|file| file.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        };
+        checks.push(ClaimCheck::new("files_changed", missing.is_empty(), detail));
+    }
+    checks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn changed(files: &[&str]) -> BTreeSet<String> {
+        files.iter().map(|file| file.to_string()).collect()
+    }
+
+    #[test]
+    fn completion_with_empty_diff_is_flagged() {
+        let result = json!({ "outcome": "completed", "dod_met": true });
+        let checks = check_diff(&result, &BTreeSet::new());
+        assert_eq!(
+            checks,
+            vec![ClaimCheck::new(
+                "empty_diff",
+                false,
+                "claimed completion with an empty diff".to_string()
+            )]
+        );
+        assert!(check_diff(&result, &changed(&["src/lib.rs"]))[0].ok);
+        assert!(check_diff(&json!({ "outcome": "started" }), &BTreeSet::new()).is_empty());
+    }
+
+    #[test]
+    fn reported_files_must_be_in_the_diff() {
+        let result = json!({
+            "outcome": "started",
+            "files_changed": ["./src/lib.rs", "src/ghost.rs"]
+        });
+        let checks = check_diff(&result, &changed(&["src/lib.rs"]));
+        assert_eq!(checks.len(), 1);
+        assert!(!checks[0].ok);
+        assert_eq!(checks[0].detail, "reported but unchanged: src/ghost.rs");
+    }
+
+    #[test]
+    fn passing_test_claims_are_rerun() {
+        let claim = TestsClaim::from_result(&json!({
+            "tests": { "ran": true, "passed": true, "commands": ["cargo test", "make lint"] }
+        }));
+        let mut rerun = Vec::new();
+        let checks = claim
+            .check(|command| {
+                rerun.push(command.to_string());
+                Ok::<_, ()>(command == "cargo test")
+            })
+            .unwrap();
+        assert_eq!(rerun, vec!["cargo test", "make lint"]);
+        let report = ClaimsReport { checks };
+        assert!(report.rerun_failed());
+        assert_eq!(report.discrepancies().count(), 1);
+        assert_eq!(
+            report.append_note("Run r1 progress"),
+            "Run r1 progress; claim_discrepancies=tests_rerun"
+        );
+
+        let claim = TestsClaim::from_result(&json!({
+            "tests": { "ran": true, "passed": true, "commands": [] }
+        }));
+        let checks = claim.check(|_| Ok::<_, ()>(true)).unwrap();
+        assert_eq!(checks[0].kind, "tests_commands");
+        assert!(!checks[0].ok);
+
+        let claim = TestsClaim::from_result(&json!({
+            "tests": { "ran": false, "passed": false, "commands": [] }
+        }));
+        assert!(claim.check(|_| Ok::<_, ()>(true)).unwrap().is_empty());
+    }
+}
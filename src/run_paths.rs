@@ -9,6 +9,8 @@ pub struct RunPaths {
     pub prompt_path: PathBuf,
     pub result_path_rel: PathBuf,
     pub result_path_abs: PathBuf,
+    pub result_errors_path_rel: PathBuf,
+    pub result_errors_path_abs: PathBuf,
     pub codex_log_rel: PathBuf,
     pub codex_log_abs: PathBuf,
     pub task_snapshot_path: PathBuf,
@@ -17,6 +19,16 @@ pub struct RunPaths {
     pub assembly_stdout_path: PathBuf,
     pub assembly_stderr_path: PathBuf,
     pub context_compile_path: PathBuf,
+    pub tasks_tamper_path: PathBuf,
+    pub prompt_tokens_path: PathBuf,
+    pub prompt_budget_path: PathBuf,
+    pub prompt_profile_path: PathBuf,
+    pub sarif_dir_abs: PathBuf,
+    pub findings_sarif_path: PathBuf,
+    pub dod_verification_path: PathBuf,
+    pub claims_path: PathBuf,
+    pub verification_path: PathBuf,
+    pub sandbox_path: PathBuf,
 }
 
 pub fn run_paths(workspace: &Path, task_id: &str, run_id: &str) -> RunPaths {
@@ -30,6 +42,8 @@ pub fn run_paths(workspace: &Path, task_id: &str, run_id: &str) -> RunPaths {
     let prompt_path = run_dir_abs.join("prompt.md");
     let result_path_rel = run_dir_rel.join("result.json");
     let result_path_abs = workspace.join(&result_path_rel);
+    let result_errors_path_rel = run_dir_rel.join("result-errors.json");
+    let result_errors_path_abs = workspace.join(&result_errors_path_rel);
     let codex_log_rel = run_dir_rel.join("codex.jsonl");
     let codex_log_abs = workspace.join(&codex_log_rel);
     let task_snapshot_path = run_dir_abs.join("task.json");
@@ -38,6 +52,16 @@ pub fn run_paths(workspace: &Path, task_id: &str, run_id: &str) -> RunPaths {
     let assembly_stdout_path = run_dir_abs.join("assembly.stdout.log");
     let assembly_stderr_path = run_dir_abs.join("assembly.stderr.log");
     let context_compile_path = run_dir_abs.join("context-compile.json");
+    let tasks_tamper_path = run_dir_abs.join("tasks-tamper.json");
+    let prompt_tokens_path = run_dir_abs.join("prompt-tokens.json");
+    let prompt_budget_path = run_dir_abs.join("prompt-budget.json");
+    let prompt_profile_path = run_dir_abs.join("prompt-profile.json");
+    let sarif_dir_abs = run_dir_abs.join("sarif");
+    let findings_sarif_path = run_dir_abs.join("findings.sarif");
+    let dod_verification_path = run_dir_abs.join("dod-verification.json");
+    let claims_path = run_dir_abs.join("claims.json");
+    let verification_path = run_dir_abs.join("verification.json");
+    let sandbox_path = run_dir_abs.join("sandbox.json");
 
     RunPaths {
         run_dir_rel,
@@ -47,6 +71,8 @@ pub fn run_paths(workspace: &Path, task_id: &str, run_id: &str) -> RunPaths {
         prompt_path,
         result_path_rel,
         result_path_abs,
+        result_errors_path_rel,
+        result_errors_path_abs,
         codex_log_rel,
         codex_log_abs,
         task_snapshot_path,
@@ -55,6 +81,16 @@ pub fn run_paths(workspace: &Path, task_id: &str, run_id: &str) -> RunPaths {
         assembly_stdout_path,
         assembly_stderr_path,
         context_compile_path,
+        tasks_tamper_path,
+        prompt_tokens_path,
+        prompt_budget_path,
+        prompt_profile_path,
+        sarif_dir_abs,
+        findings_sarif_path,
+        dod_verification_path,
+        claims_path,
+        verification_path,
+        sandbox_path,
     }
 }
 
@@ -87,6 +123,10 @@ mod tests {
             paths.result_path_rel,
             PathBuf::from(".ralph/runs/TASK-1/run-123/result.json")
         );
+        assert_eq!(
+            paths.result_errors_path_rel,
+            PathBuf::from(".ralph/runs/TASK-1/run-123/result-errors.json")
+        );
         assert_eq!(
             paths.codex_log_rel,
             PathBuf::from(".ralph/runs/TASK-1/run-123/codex.jsonl")
@@ -115,5 +155,45 @@ mod tests {
             paths.context_compile_path,
             PathBuf::from("workspace/.ralph/runs/TASK-1/run-123/context-compile.json")
         );
+        assert_eq!(
+            paths.tasks_tamper_path,
+            PathBuf::from("workspace/.ralph/runs/TASK-1/run-123/tasks-tamper.json")
+        );
+        assert_eq!(
+            paths.prompt_tokens_path,
+            PathBuf::from("workspace/.ralph/runs/TASK-1/run-123/prompt-tokens.json")
+        );
+        assert_eq!(
+            paths.prompt_budget_path,
+            PathBuf::from("workspace/.ralph/runs/TASK-1/run-123/prompt-budget.json")
+        );
+        assert_eq!(
+            paths.prompt_profile_path,
+            PathBuf::from("workspace/.ralph/runs/TASK-1/run-123/prompt-profile.json")
+        );
+        assert_eq!(
+            paths.sarif_dir_abs,
+            PathBuf::from("workspace/.ralph/runs/TASK-1/run-123/sarif")
+        );
+        assert_eq!(
+            paths.findings_sarif_path,
+            PathBuf::from("workspace/.ralph/runs/TASK-1/run-123/findings.sarif")
+        );
+        assert_eq!(
+            paths.dod_verification_path,
+            PathBuf::from("workspace/.ralph/runs/TASK-1/run-123/dod-verification.json")
+        );
+        assert_eq!(
+            paths.claims_path,
+            PathBuf::from("workspace/.ralph/runs/TASK-1/run-123/claims.json")
+        );
+        assert_eq!(
+            paths.verification_path,
+            PathBuf::from("workspace/.ralph/runs/TASK-1/run-123/verification.json")
+        );
+        assert_eq!(
+            paths.sandbox_path,
+            PathBuf::from("workspace/.ralph/runs/TASK-1/run-123/sandbox.json")
+        );
     }
 }
@@ -0,0 +1,495 @@
+use std::{
+    env,
+    error::Error,
+    ffi::{OsStr, OsString},
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+type DynError = Box<dyn Error + Send + Sync + 'static>;
+
+/// Bubblewrap, used to confine sandboxed processes.
+pub const SANDBOX_PROGRAM: &str = "bwrap";
+
+/// New namespaces, fresh `/dev` and `/proc`, and a private `/tmp`. The rest
+/// of the filesystem is bound path by path on top.
+const BWRAP_ARGS: &[&str] = &[
+    "--die-with-parent",
+    "--unshare-all",
+    "--dev",
+    "/dev",
+    "--proc",
+    "/proc",
+    "--tmpfs",
+    "/tmp",
+];
+
+/// Host paths every sandboxed process can read: system binaries and
+/// libraries plus the `/etc` files needed for users, DNS, and TLS. Home
+/// directories and the rest of the host are not visible.
+const SYSTEM_PATHS: &[&str] = &[
+    "/usr",
+    "/bin",
+    "/sbin",
+    "/lib",
+    "/lib32",
+    "/lib64",
+    "/libx32",
+    "/etc/alternatives",
+    "/etc/ca-certificates",
+    "/etc/group",
+    "/etc/hosts",
+    "/etc/ld.so.cache",
+    "/etc/ld.so.conf",
+    "/etc/ld.so.conf.d",
+    "/etc/localtime",
+    "/etc/nsswitch.conf",
+    "/etc/passwd",
+    "/etc/pki",
+    "/etc/resolv.conf",
+    "/etc/ssl",
+];
+
+/// Linux sandbox for verification commands and, with looser defaults, Codex.
+/// Set per task (`sandbox`) or as the `lever.json` default.
+///
+/// Sandboxed processes see the system paths above, `readable` paths, and the
+/// directory of the program they run, all read-only; the workspace, the
+/// command's directory, `writable` paths, and a private `/tmp` can be
+/// written. Verification has no network unless `network` is set. Codex
+/// keeps the network while `agent_network` is set (the default, since it
+/// calls the model API) and may also write its home directory (`$CODEX_HOME`
+/// or `~/.codex`). `cpu_seconds` and `memory_mb` become `ulimit`s; wall-clock
+/// limits come from `timeouts`, as for unsandboxed runs.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SandboxConfig {
+    pub enabled: bool,
+    pub network: bool,
+    pub agent_network: bool,
+    pub cpu_seconds: Option<u64>,
+    pub memory_mb: Option<u64>,
+    pub readable: Vec<String>,
+    pub writable: Vec<String>,
+}
+
+impl Default for SandboxConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            network: false,
+            agent_network: true,
+            cpu_seconds: None,
+            memory_mb: None,
+            readable: Vec::new(),
+            writable: Vec::new(),
+        }
+    }
+}
+
+impl SandboxConfig {
+    pub fn validate(&self, field: &str) -> Result<(), DynError> {
+        for (name, value) in [
+            ("cpu_seconds", self.cpu_seconds),
+            ("memory_mb", self.memory_mb),
+        ] {
+            if value == Some(0) {
+                return Err(format!("{}.{} must be >= 1", field, name).into());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An enabled sandbox resolved for one run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sandbox {
+    pub config: SandboxConfig,
+    /// `task` or `config`.
+    pub source: &'static str,
+    workspace: PathBuf,
+}
+
+impl Sandbox {
+    /// The task's `sandbox` wins over the workspace default; either one can
+    /// turn the sandbox off with `"enabled": false`.
+    pub fn resolve(
+        workspace: &Path,
+        task: &Value,
+        default: Option<&SandboxConfig>,
+    ) -> Result<Option<Self>, DynError> {
+        let (config, source) = match task.get("sandbox") {
+            Some(raw) => {
+                let config: SandboxConfig = serde_json::from_value(raw.clone())
+                    .map_err(|err| format!("Invalid task sandbox: {}", err))?;
+                config.validate("sandbox")?;
+                (config, "task")
+            }
+            None => match default {
+                Some(config) => (config.clone(), "config"),
+                None => return Ok(None),
+            },
+        };
+        if !config.enabled {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            config,
+            source,
+            workspace: workspace.to_path_buf(),
+        }))
+    }
+
+    /// Wraps a verification command; network follows `network`.
+    pub fn wrap(&self, command: Command) -> Command {
+        self.wrap_with(command, self.config.network, &[])
+    }
+
+    /// Wraps the Codex process. Network follows `agent_network`, and its home
+    /// directory (`$CODEX_HOME` or `~/.codex`) stays writable for auth and
+    /// session state.
+    pub fn wrap_agent(&self, command: Command) -> Command {
+        let codex_home = env::var_os("CODEX_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".codex")))
+            .filter(|path| path.is_dir());
+        self.wrap_with(command, self.config.agent_network, codex_home.as_slice())
+    }
+
+    fn wrap_with(&self, command: Command, network: bool, extra_writable: &[PathBuf]) -> Command {
+        let cwd = command
+            .get_current_dir()
+            .map_or_else(|| self.workspace.clone(), Path::to_path_buf);
+        let mut writable = vec![self.workspace.clone()];
+        if cwd != self.workspace {
+            writable.push(cwd.clone());
+        }
+        writable.extend(
+            self.config
+                .writable
+                .iter()
+                .map(|path| self.workspace.join(path)),
+        );
+        writable.extend(extra_writable.iter().cloned());
+        let mut readable: Vec<PathBuf> = self
+            .config
+            .readable
+            .iter()
+            .map(|path| self.workspace.join(path))
+            .collect();
+        if let Some(dir) = program_dir(command.get_program(), &cwd) {
+            let visible = SYSTEM_PATHS
+                .iter()
+                .map(Path::new)
+                .chain(writable.iter().map(PathBuf::as_path))
+                .any(|bound| dir.starts_with(bound));
+            if !visible {
+                readable.push(dir);
+            }
+        }
+
+        let mut args = system_binds();
+        args.extend(BWRAP_ARGS.iter().map(OsString::from));
+        if network {
+            args.push("--share-net".into());
+        }
+        for path in &readable {
+            args.extend(["--ro-bind".into(), path.into(), path.into()]);
+        }
+        for path in &writable {
+            args.extend(["--bind".into(), path.into(), path.into()]);
+        }
+        // Lever runs git on the host after the sandboxed process exits, so
+        // hooks and config under `.git` must not be writable from inside.
+        for path in writable.iter().flat_map(|path| git_dirs(path)) {
+            args.extend(["--ro-bind".into(), path.clone().into(), path.into()]);
+        }
+        args.extend(["--chdir".into(), cwd.clone().into(), "--".into()]);
+        let limits = self.ulimits();
+        if !limits.is_empty() {
+            args.extend([
+                "bash".into(),
+                "-c".into(),
+                format!("{}exec \"$@\"", limits).into(),
+                "lever-sandbox".into(),
+            ]);
+        }
+        args.push(command.get_program().to_os_string());
+        args.extend(command.get_args().map(|arg| arg.to_os_string()));
+
+        let mut wrapped = Command::new(SANDBOX_PROGRAM);
+        wrapped.args(args).current_dir(&cwd);
+        for (key, value) in command.get_envs() {
+            match value {
+                Some(value) => wrapped.env(key, value),
+                None => wrapped.env_remove(key),
+            };
+        }
+        wrapped
+    }
+
+    fn ulimits(&self) -> String {
+        let mut limits = String::new();
+        if let Some(seconds) = self.config.cpu_seconds {
+            limits.push_str(&format!("ulimit -t {} && ", seconds));
+        }
+        if let Some(mb) = self.config.memory_mb {
+            limits.push_str(&format!("ulimit -v {} && ", mb * 1024));
+        }
+        limits
+    }
+
+    /// Contents of the run's `sandbox.json`.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "enabled": true,
+            "source": self.source,
+            "backend": SANDBOX_PROGRAM,
+            "network": self.config.network,
+            "agent_network": self.config.agent_network,
+            "cpu_seconds": self.config.cpu_seconds,
+            "memory_mb": self.config.memory_mb,
+            "readable": self.config.readable,
+            "writable": self.config.writable,
+        })
+    }
+}
+
+/// Read-only binds for the `SYSTEM_PATHS` present on this host. Symlinked
+/// ones (such as `/bin` on merged-`/usr` systems) are recreated as links.
+fn system_binds() -> Vec<OsString> {
+    let mut args: Vec<OsString> = Vec::new();
+    for path in SYSTEM_PATHS {
+        let Ok(metadata) = fs::symlink_metadata(path) else {
+            continue;
+        };
+        if metadata.file_type().is_symlink() {
+            if let Ok(target) = fs::read_link(path) {
+                args.extend(["--symlink".into(), target.into(), (*path).into()]);
+            }
+        } else {
+            args.extend(["--ro-bind".into(), (*path).into(), (*path).into()]);
+        }
+    }
+    args
+}
+
+/// The git directories behind `dir/.git`: the directory itself, or for a
+/// linked worktree the `gitdir:` it points to and that gitdir's common
+/// directory, which holds the hooks and config.
+fn git_dirs(dir: &Path) -> Vec<PathBuf> {
+    let dot_git = dir.join(".git");
+    if dot_git.is_dir() {
+        return vec![dot_git];
+    }
+    let Ok(raw) = fs::read_to_string(&dot_git) else {
+        return Vec::new();
+    };
+    let Some(gitdir) = raw
+        .lines()
+        .find_map(|line| line.strip_prefix("gitdir:"))
+        .map(|gitdir| dir.join(gitdir.trim()))
+    else {
+        return Vec::new();
+    };
+    let mut dirs = Vec::new();
+    if let Ok(common) = fs::read_to_string(gitdir.join("commondir")) {
+        if let Ok(common) = fs::canonicalize(gitdir.join(common.trim())) {
+            dirs.push(common);
+        }
+    }
+    if gitdir.is_dir() {
+        dirs.push(gitdir);
+    }
+    dirs
+}
+
+/// The directory holding `program`, searched on `PATH` for bare names.
+fn program_dir(program: &OsStr, cwd: &Path) -> Option<PathBuf> {
+    let program = Path::new(program);
+    let resolved = if program.components().count() > 1 {
+        Some(cwd.join(program))
+    } else {
+        env::var_os("PATH").and_then(|path| {
+            env::split_paths(&path)
+                .map(|dir| dir.join(program))
+                .find(|candidate| candidate.is_file())
+        })
+    }?;
+    resolved.parent().map(Path::to_path_buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn arg_strings(command: &Command) -> Vec<String> {
+        command
+            .get_args()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn task_sandbox_overrides_the_workspace_default() {
+        let workspace = Path::new("/work");
+        let default = SandboxConfig {
+            network: true,
+            ..SandboxConfig::default()
+        };
+        assert_eq!(Sandbox::resolve(workspace, &json!({}), None).unwrap(), None);
+
+        let sandbox = Sandbox::resolve(workspace, &json!({}), Some(&default))
+            .unwrap()
+            .unwrap();
+        assert_eq!(sandbox.source, "config");
+        assert!(sandbox.config.network);
+
+        let task = json!({ "sandbox": { "cpu_seconds": 30 } });
+        let sandbox = Sandbox::resolve(workspace, &task, Some(&default))
+            .unwrap()
+            .unwrap();
+        assert_eq!(sandbox.source, "task");
+        assert!(!sandbox.config.network);
+
+        let task = json!({ "sandbox": { "enabled": false } });
+        assert_eq!(
+            Sandbox::resolve(workspace, &task, Some(&default)).unwrap(),
+            None
+        );
+        let task = json!({ "sandbox": { "memory_mb": 0 } });
+        assert!(Sandbox::resolve(workspace, &task, None).is_err());
+    }
+
+    #[test]
+    fn wrapped_commands_run_under_bwrap_with_limits() {
+        let sandbox = Sandbox::resolve(
+            Path::new("/work"),
+            &json!({ "sandbox": { "cpu_seconds": 60, "memory_mb": 512 } }),
+            None,
+        )
+        .unwrap()
+        .unwrap();
+        let mut command = Command::new("bash");
+        command.arg("-lc").arg("make test").current_dir("/work");
+        let wrapped = sandbox.wrap(command);
+        assert_eq!(wrapped.get_program(), "bwrap");
+        let args = arg_strings(&wrapped);
+        assert!(!args.contains(&"--share-net".to_string()));
+        assert!(args
+            .windows(3)
+            .any(|window| window == ["--bind", "/work", "/work"]));
+        assert!(args.contains(&"ulimit -t 60 && ulimit -v 524288 && exec \"$@\"".to_string()));
+        assert_eq!(&args[args.len() - 3..], &["bash", "-lc", "make test"]);
+
+        let agent = sandbox.wrap_agent(Command::new("codex"));
+        assert!(arg_strings(&agent).contains(&"--share-net".to_string()));
+
+        let offline = Sandbox::resolve(
+            Path::new("/work"),
+            &json!({ "sandbox": { "agent_network": false } }),
+            None,
+        )
+        .unwrap()
+        .unwrap();
+        let agent = offline.wrap_agent(Command::new("codex"));
+        assert!(!arg_strings(&agent).contains(&"--share-net".to_string()));
+    }
+
+    /// Whether any mount in the bwrap arguments makes `path` visible.
+    fn visible(args: &[String], path: &str) -> bool {
+        let mut mounts = Vec::new();
+        let mut index = 0;
+        while index < args.len() && args[index] != "--" {
+            match args[index].as_str() {
+                "--ro-bind" | "--bind" | "--symlink" => {
+                    mounts.push(args[index + 2].clone());
+                    index += 3;
+                }
+                "--dev" | "--proc" | "--tmpfs" | "--chdir" => index += 2,
+                _ => index += 1,
+            }
+        }
+        mounts
+            .iter()
+            .any(|mount| Path::new(path).starts_with(mount))
+    }
+
+    #[test]
+    fn paths_outside_the_workspace_are_not_visible() {
+        let sandbox = Sandbox::resolve(
+            Path::new("/work/repo"),
+            &json!({ "sandbox": { "readable": ["/opt/toolchain"], "writable": ["../cache"] } }),
+            None,
+        )
+        .unwrap()
+        .unwrap();
+        let mut command = Command::new("bash");
+        command
+            .arg("-lc")
+            .arg("make test")
+            .current_dir("/work/repo");
+        let args = arg_strings(&sandbox.wrap(command));
+
+        assert!(!args
+            .windows(3)
+            .any(|window| window == ["--ro-bind", "/", "/"]));
+        assert!(visible(&args, "/usr/bin/env"));
+        assert!(visible(&args, "/work/repo/src/lib.rs"));
+        assert!(visible(&args, "/work/repo/../cache/objects"));
+        assert!(visible(&args, "/opt/toolchain/bin/cc"));
+        assert!(args
+            .windows(3)
+            .any(|window| window == ["--ro-bind", "/opt/toolchain", "/opt/toolchain"]));
+        for hidden in [
+            "/root/.ssh/id_ed25519",
+            "/home/dev/.aws/credentials",
+            "/work/other-repo/.env",
+            "/etc/shadow",
+            "/var/lib/secrets",
+        ] {
+            assert!(!visible(&args, hidden), "{} is visible", hidden);
+        }
+    }
+
+    #[test]
+    fn git_directories_are_bound_read_only_over_the_workspace() {
+        let root = env::temp_dir().join(format!("lever-sandbox-git-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let repo = root.join("repo");
+        let gitdir = repo.join(".git").join("worktrees").join("wt");
+        fs::create_dir_all(&gitdir).unwrap();
+        fs::write(gitdir.join("commondir"), "../..\n").unwrap();
+        let worktree = root.join("wt");
+        fs::create_dir_all(&worktree).unwrap();
+        fs::write(
+            worktree.join(".git"),
+            format!("gitdir: {}\n", gitdir.display()),
+        )
+        .unwrap();
+
+        let sandbox = Sandbox::resolve(&repo, &json!({ "sandbox": {} }), None)
+            .unwrap()
+            .unwrap();
+        let mut command = Command::new("bash");
+        command.arg("-lc").arg("make test").current_dir(&worktree);
+        let args = arg_strings(&sandbox.wrap(command));
+        let position = |flag: &str, path: &Path| {
+            let path = path.to_string_lossy().into_owned();
+            args.windows(3)
+                .position(|window| window[0] == flag && window[1] == path && window[2] == path)
+        };
+
+        let workspace_bind = position("--bind", &repo).unwrap();
+        let repo_git = position("--ro-bind", &repo.join(".git")).unwrap();
+        assert!(repo_git > workspace_bind);
+        assert!(position("--bind", &worktree).unwrap() < repo_git);
+        assert!(position("--ro-bind", &gitdir).is_some());
+        assert!(position("--ro-bind", &fs::canonicalize(repo.join(".git")).unwrap()).is_some());
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,174 @@
+use std::{error::Error, fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+type DynError = Box<dyn Error + Send + Sync + 'static>;
+
+pub const STASH_LEDGER_FILE: &str = ".ralph/stashes.json";
+
+pub const STASH_STATUS_PENDING: &str = "pending";
+pub const STASH_STATUS_RESTORED: &str = "restored";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StashRecord {
+    pub commit: String,
+    pub message: String,
+    pub created_utc: String,
+    #[serde(default)]
+    pub task_id: Option<String>,
+    pub orig_branch: String,
+    pub orig_head: String,
+    #[serde(default)]
+    pub files: Vec<String>,
+    pub status: String,
+    #[serde(default)]
+    pub note: String,
+}
+
+impl StashRecord {
+    pub fn short_id(&self) -> &str {
+        &self.commit[..self.commit.len().min(12)]
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.status == STASH_STATUS_PENDING
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct StashLedger {
+    #[serde(default)]
+    stashes: Vec<StashRecord>,
+}
+
+pub fn load_stash_records(workspace: &Path) -> Result<Vec<StashRecord>, DynError> {
+    Ok(read_ledger(workspace)?.stashes)
+}
+
+pub fn append_stash_record(workspace: &Path, record: StashRecord) -> Result<(), DynError> {
+    let mut ledger = read_ledger(workspace)?;
+    ledger.stashes.push(record);
+    write_ledger(workspace, &ledger)
+}
+
+pub fn update_stash_record(
+    workspace: &Path,
+    commit: &str,
+    status: &str,
+    note: &str,
+) -> Result<(), DynError> {
+    let mut ledger = read_ledger(workspace)?;
+    let record = ledger
+        .stashes
+        .iter_mut()
+        .find(|record| record.commit == commit)
+        .ok_or_else(|| format!("Stash {} is not recorded in {}", commit, STASH_LEDGER_FILE))?;
+    record.status = status.to_string();
+    record.note = note.to_string();
+    write_ledger(workspace, &ledger)
+}
+
+/// Resolves a user-supplied stash id (a commit SHA prefix) against the
+/// pending records, rejecting ambiguous prefixes.
+pub fn find_pending_record<'a>(
+    records: &'a [StashRecord],
+    id: &str,
+) -> Result<&'a StashRecord, DynError> {
+    let id = id.trim();
+    if id.is_empty() {
+        return Err("Stash id must not be empty".to_string().into());
+    }
+    let matches: Vec<&StashRecord> = records
+        .iter()
+        .filter(|record| record.is_pending() && record.commit.starts_with(id))
+        .collect();
+    match matches.as_slice() {
+        [record] => Ok(record),
+        [] => Err(format!("No pending lever stash matches {}", id).into()),
+        _ => Err(format!("Stash id {} is ambiguous; use a longer prefix", id).into()),
+    }
+}
+
+fn read_ledger(workspace: &Path) -> Result<StashLedger, DynError> {
+    let path = workspace.join(STASH_LEDGER_FILE);
+    if !path.is_file() {
+        return Ok(StashLedger::default());
+    }
+    let raw = fs::read_to_string(&path)
+        .map_err(|err| format!("Failed to read stash ledger {}: {}", path.display(), err))?;
+    serde_json::from_str(&raw)
+        .map_err(|err| format!("Failed to parse stash ledger {}: {}", path.display(), err).into())
+}
+
+fn write_ledger(workspace: &Path, ledger: &StashLedger) -> Result<(), DynError> {
+    let path = workspace.join(STASH_LEDGER_FILE);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, serde_json::to_string_pretty(ledger)?)
+        .map_err(|err| format!("Failed to write stash ledger {}: {}", path.display(), err))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn temp_workspace(name: &str) -> PathBuf {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let path = std::env::temp_dir().join(format!("lever-stash-{}-{}", name, nanos));
+        fs::create_dir_all(&path).unwrap();
+        path
+    }
+
+    fn record(commit: &str) -> StashRecord {
+        StashRecord {
+            commit: commit.to_string(),
+            message: "ralph(task-agent): auto-stash".to_string(),
+            created_utc: "2026-01-01T00:00:00Z".to_string(),
+            task_id: Some("T1".to_string()),
+            orig_branch: "main".to_string(),
+            orig_head: "abc".to_string(),
+            files: vec!["README.md".to_string()],
+            status: STASH_STATUS_PENDING.to_string(),
+            note: String::new(),
+        }
+    }
+
+    #[test]
+    fn ledger_round_trips_and_updates_status() {
+        let workspace = temp_workspace("roundtrip");
+        assert!(load_stash_records(&workspace).unwrap().is_empty());
+
+        append_stash_record(&workspace, record("aaaa1111")).unwrap();
+        append_stash_record(&workspace, record("bbbb2222")).unwrap();
+        update_stash_record(&workspace, "aaaa1111", STASH_STATUS_RESTORED, "applied").unwrap();
+
+        let records = load_stash_records(&workspace).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].status, STASH_STATUS_RESTORED);
+        assert_eq!(records[0].note, "applied");
+        assert!(records[1].is_pending());
+    }
+
+    #[test]
+    fn find_pending_record_matches_unique_prefix() {
+        let records = vec![record("aaaa1111"), record("aabb2222"), {
+            let mut restored = record("cccc3333");
+            restored.status = STASH_STATUS_RESTORED.to_string();
+            restored
+        }];
+
+        assert_eq!(
+            find_pending_record(&records, "aab").unwrap().commit,
+            "aabb2222"
+        );
+        assert!(find_pending_record(&records, "aa").is_err());
+        assert!(find_pending_record(&records, "cccc").is_err());
+    }
+}
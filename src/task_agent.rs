@@ -1,49 +1,95 @@
 use std::{
+    collections::{BTreeMap, BTreeSet},
     error::Error,
-    ffi::OsString,
     fs,
     fs::File,
     io::{self, BufRead, IsTerminal, Read, Write},
     path::{Path, PathBuf},
-    process::Command,
+    process::{Command, ExitStatus},
     sync::{
         atomic::{AtomicBool, Ordering},
         Arc,
     },
     thread,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use serde_json::{json, Map, Value};
 
 use lever::assembly_contract::REQUIRED_PACK_FILES;
-use lever::context_compile::{ContextCompileConfig, ContextFailurePolicy};
-
-use crate::rate_limit;
-use crate::run_paths::run_paths;
-use crate::task_metadata::validate_task_metadata;
+use lever::assembly_pack::{validate_pack_contents, PackIndex, INDEX_FILE};
+use lever::context_compile::{
+    ContextCompileConfig, ContextCompilerKind, ContextFailurePolicy, TaskContextHints,
+};
+use lever::token_estimate::default_estimator;
+
+use crate::budget::{self, BudgetExceeded, SessionBudget, Spend};
+use crate::config::{LeverConfig, VerificationConfig};
+use crate::context_compiler::{context_compiler, CompileOutcome};
+use crate::flaky_ledger::record_flakes;
+use crate::lint_report::{
+    write_sarif, LintConfig, LintFailMode, LintReport, SarifLog, CONTEXT_LINT_TOOL, LINT_FILE,
+    SARIF_DIR_ENV,
+};
+use crate::pack_cache::{pack_cache_key, restore_cached_pack, store_pack};
+use crate::process_group::{spawn_in_group, wait_with_deadline, Deadline, WaitOutcome};
+use crate::prompt_budget::{
+    budget_report_json, context_blocks, elide_task_json, enforce_prompt_budget, section_priorities,
+    PromptCut, PromptSection, SectionBody,
+};
+use crate::prompt_profile::{
+    select_profile, ProfileSelection, PromptProfiles, PROMPTS_DIR, PROMPT_PROFILE_SUFFIX,
+};
+use crate::prompt_template::PromptTemplate;
+use crate::rate_limit::{self, DailyCapCheck, ModelLimits, UsageEntry, USAGE_FILE};
+use crate::result_claims::{check_diff, ClaimsReport, TestsClaim};
+use crate::run_paths::{run_paths, RunPaths};
+use crate::sandbox::{Sandbox, SANDBOX_PROGRAM};
+use crate::task_metadata::{definition_of_done, validate_task_metadata, DodItem};
+use crate::task_result::{
+    dod_items, ensure_schema_file, files_changed, follow_up_tasks, load_result_schema,
+    validate_result, ResultSchema, SchemaUpdate, RESULT_SCHEMA_VERSION, SCHEMA_PATH,
+};
+use crate::tasks_guard::{write_tamper_report, TamperEvent, TasksFileSnapshot};
+use crate::verification_baseline::{
+    baseline_cache_key, load_cached_baseline, store_baseline, Baseline, BaselineComparison,
+    StepOutcome,
+};
+use crate::verification_detectors;
 
 type DynError = Box<dyn Error + Send + Sync + 'static>;
 
 const MAX_RUN_ATTEMPTS: u64 = 3;
-const RATE_LIMIT_FILE: &str = ".ralph/rate_limit.json";
-const RATE_LIMIT_WINDOW_SECONDS: u64 = 60;
-const SCHEMA_PATH: &str = ".ralph/task_result.schema.json";
 const ASSEMBLY_REQUIRED_FAILURE_EXIT_CODE: i32 = 13;
+pub const DAILY_TOKEN_CAP_EXIT_CODE: i32 = 14;
+pub const BUDGET_EXCEEDED_EXIT_CODE: i32 = 15;
+pub const LINT_THRESHOLD_EXIT_CODE: i32 = 16;
+pub const INVALID_RESULT_EXIT_CODE: i32 = 17;
+pub const TIMEOUT_EXIT_CODE: i32 = 18;
+pub const PROMPT_BUDGET_EXIT_CODE: i32 = 19;
 
 #[derive(Debug)]
 struct PackValidationError {
     pack_dir: PathBuf,
     missing: Vec<String>,
+    problems: Vec<String>,
 }
 
 impl std::fmt::Display for PackValidationError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !self.missing.is_empty() {
+            return write!(
+                f,
+                "Missing required pack files in {}: {}",
+                self.pack_dir.display(),
+                self.missing.join(", ")
+            );
+        }
         write!(
             f,
-            "Missing required pack files in {}: {}",
+            "Invalid pack contents in {}: {}",
             self.pack_dir.display(),
-            self.missing.join(", ")
+            self.problems.join("; ")
         )
     }
 }
@@ -53,11 +99,17 @@ impl std::error::Error for PackValidationError {}
 pub struct TaskAgentConfig {
     pub tasks_path: PathBuf,
     pub prompt_path: PathBuf,
+    /// The `--prompt` file that `prompt_path` was copied from.
+    pub prompt_origin: PathBuf,
     pub workspace: PathBuf,
     pub reset_task: bool,
     pub explicit_task_id: Option<String>,
     pub context_compile: ContextCompileConfig,
     pub include_lint_summary: bool,
+    pub prompt_token_budget: Option<u64>,
+    pub prompt_profiles: PromptProfiles,
+    pub lever_config: LeverConfig,
+    pub session_budget: SessionBudget,
 }
 
 pub fn run_task_agent(
@@ -104,6 +156,41 @@ pub fn run_task_agent(
         return Ok(2);
     }
 
+    let sandbox = Sandbox::resolve(
+        &config.workspace,
+        &selection.raw,
+        config.lever_config.sandbox.as_ref(),
+    )?;
+    if sandbox.is_some() {
+        ensure_command_available(SANDBOX_PROGRAM)?;
+    }
+    let timeouts = config.lever_config.timeouts.for_task(&selection.raw)?;
+    let verify_limits = StepLimits {
+        sandbox: sandbox.as_ref(),
+        deadline: Deadline::new(timeouts.verification_seconds, timeouts.kill_grace_seconds),
+    };
+
+    let profile_selection = select_profile(
+        selection.raw.get("prompt").and_then(Value::as_str),
+        &selection.model,
+        &config.lever_config.prompt_profiles.models,
+    );
+    let prompt_profile = match resolve_prompt_profile(config, &profile_selection)? {
+        Some(profile) => profile,
+        None => {
+            eprintln!(
+                "Prompt profile not found for task {}: {} (selected by {}; expected {}/{}{})",
+                selection.task_id,
+                profile_selection.label(),
+                profile_selection.source,
+                PROMPTS_DIR,
+                profile_selection.label(),
+                PROMPT_PROFILE_SUFFIX
+            );
+            return Ok(2);
+        }
+    };
+
     let run_id = run_id()?;
 
     if config.reset_task {
@@ -151,13 +238,37 @@ pub fn run_task_agent(
     fs::create_dir_all(&paths.pack_dir_abs)?;
     let mut compiled_context_path: Option<PathBuf> = None;
     let mut lint_summary_path: Option<PathBuf> = None;
+    let mut context_lint: Option<LintReport> = None;
     let mut context_report = ContextCompileReport::new(&paths, &config.context_compile);
+    let mut assembly_elapsed: Option<Duration> = None;
 
     fs::write(
         &paths.task_snapshot_path,
         format!("{}\n", selection.raw_json),
     )?;
-    let assembly_task_input = build_assembly_task_input(&selection);
+    fs::write(
+        &paths.prompt_profile_path,
+        serde_json::to_string_pretty(&profile_selection.to_json(&prompt_profile.label))?,
+    )?;
+    if let Some(sandbox) = &sandbox {
+        fs::write(
+            &paths.sandbox_path,
+            serde_json::to_string_pretty(&sandbox.to_json())?,
+        )?;
+        log_line(
+            "INFO",
+            "Sandbox enabled",
+            &[
+                format!("task_id={}", selection.task_id),
+                format!("run_id={}", run_id),
+                format!("source={}", sandbox.source),
+                format!("network={}", sandbox.config.network),
+            ],
+        );
+    }
+    let context_hints = TaskContextHints::from_task(&selection.raw);
+    let context_config = config.context_compile.with_task_hints(&context_hints);
+    let assembly_task_input = build_assembly_task_input(&selection, &context_hints);
     let assembly_task_json = serde_json::to_string_pretty(&assembly_task_input)?;
     fs::write(
         &paths.assembly_task_path,
@@ -175,51 +286,106 @@ pub fn run_task_agent(
             );
         }
 
-        let assembly_outcome = match run_assembly(
+        let compiler = context_compiler(&context_config);
+        let cache_key = match pack_cache_key(
             &config.workspace,
-            &selection.task_id,
-            &paths,
-            &config.context_compile,
-            shutdown_flag,
+            &config.tasks_path,
+            &paths.assembly_task_path,
+            &context_config,
         ) {
-            Ok(outcome) => outcome,
-            Err(err) => AssemblyOutcome::Failed {
-                code: None,
-                message: format!(
-                    "Failed to run assembly {}: {}",
-                    config.context_compile.assembly_path.display(),
-                    err
-                ),
-            },
-        };
-
-        match assembly_outcome {
-            AssemblyOutcome::Success => {
+            Ok(key) if compiler.cacheable() => Some(key),
+            Ok(_) => None,
+            Err(err) => {
                 log_line(
-                    "INFO",
-                    "Assembly build succeeded",
+                    "WARN",
+                    "Context pack cache key unavailable",
                     &[
                         format!("task_id={}", selection.task_id),
                         format!("run_id={}", run_id),
-                        format!("stdout={}", paths.assembly_stdout_path.display()),
-                        format!("stderr={}", paths.assembly_stderr_path.display()),
+                        format!("error={}", err),
                     ],
                 );
+                None
+            }
+        };
+        let cache_hit = match cache_key.as_deref() {
+            Some(key) => restore_pack_from_cache(
+                &config.workspace,
+                key,
+                &paths,
+                &selection.task_id,
+                &run_id,
+            )?,
+            None => false,
+        };
+        context_report.set_cache(cache_key.clone(), cache_hit);
+
+        let compile_outcome = if cache_hit {
+            CompileOutcome::Success
+        } else {
+            let started = Instant::now();
+            let outcome = match compiler.compile(
+                &config.workspace,
+                &selection.task_id,
+                &paths,
+                Deadline::new(timeouts.assembly_seconds, timeouts.kill_grace_seconds),
+                shutdown_flag,
+            ) {
+                Ok(outcome) => outcome,
+                Err(err) => CompileOutcome::Failed {
+                    code: None,
+                    message: format!("Failed to run {}: {}", compiler.name(), err),
+                },
+            };
+            assembly_elapsed = Some(started.elapsed());
+            outcome
+        };
+
+        match compile_outcome {
+            CompileOutcome::Success => {
+                if cache_hit {
+                    log_line(
+                        "INFO",
+                        "Context pack reused from cache",
+                        &[
+                            format!("task_id={}", selection.task_id),
+                            format!("run_id={}", run_id),
+                            format!("cache_key={}", cache_key.as_deref().unwrap_or_default()),
+                        ],
+                    );
+                } else {
+                    log_line(
+                        "INFO",
+                        &format!("{} build succeeded", compiler.name()),
+                        &[
+                            format!("task_id={}", selection.task_id),
+                            format!("run_id={}", run_id),
+                            format!("stdout={}", paths.assembly_stdout_path.display()),
+                            format!("stderr={}", paths.assembly_stderr_path.display()),
+                        ],
+                    );
+                }
 
-                if let Err(err) = validate_pack_outputs(&paths.pack_dir_abs) {
+                if let Err(err) =
+                    validate_pack_outputs(&paths, &selection.task_id, context_config.token_budget)
+                {
                     let note = format!(
                         "{}. See stdout={} stderr={}",
                         err,
                         paths.assembly_stdout_path.display(),
                         paths.assembly_stderr_path.display()
                     );
-                    let policy_outcome =
-                        if config.context_compile.policy == ContextFailurePolicy::Required {
-                            "blocked"
-                        } else {
-                            "continued"
-                        };
-                    context_report.mark_failed(err.missing.clone(), policy_outcome);
+                    let policy_outcome = if context_config.policy == ContextFailurePolicy::Required
+                    {
+                        "blocked"
+                    } else {
+                        "continued"
+                    };
+                    context_report.mark_failed(
+                        err.missing.clone(),
+                        err.problems.clone(),
+                        policy_outcome,
+                    );
                     emit_context_compile_report(
                         &context_report,
                         &paths,
@@ -227,7 +393,7 @@ pub fn run_task_agent(
                         &run_id,
                     )?;
                     let note = append_context_compile_note(&note, &context_report);
-                    if config.context_compile.policy == ContextFailurePolicy::Required {
+                    if context_config.policy == ContextFailurePolicy::Required {
                         increment_attempt_count(&config.tasks_path, &selection.task_id)?;
                         update_task_status(
                             &config.tasks_path,
@@ -249,6 +415,7 @@ pub fn run_task_agent(
                                 format!("run_id={}", run_id),
                                 format!("pack_dir={}", paths.pack_dir_abs.display()),
                                 format!("missing={}", err.missing.join(", ")),
+                                format!("problems={}", err.problems.len()),
                             ],
                         );
                         eprintln!("Blocked: {}", note);
@@ -263,14 +430,48 @@ pub fn run_task_agent(
                             format!("run_id={}", run_id),
                             format!("pack_dir={}", paths.pack_dir_abs.display()),
                             format!("missing={}", err.missing.join(", ")),
+                            format!("problems={}", err.problems.len()),
                         ],
                     );
                     warn_context_compile_failure(&selection.task_id, &run_id);
                     eprintln!("Warning: {}", note);
                 } else {
+                    if let (Some(key), false) = (cache_key.as_deref(), cache_hit) {
+                        if let Err(err) = store_pack(
+                            &config.workspace,
+                            key,
+                            &paths.pack_dir_abs,
+                            &paths.assembly_summary_path,
+                        ) {
+                            log_line(
+                                "WARN",
+                                "Failed to cache context pack",
+                                &[
+                                    format!("task_id={}", selection.task_id),
+                                    format!("run_id={}", run_id),
+                                    format!("error={}", err),
+                                ],
+                            );
+                        }
+                    }
                     compiled_context_path = Some(paths.pack_dir_abs.join("context.md"));
                     if config.include_lint_summary {
-                        lint_summary_path = Some(paths.pack_dir_abs.join("lint.json"));
+                        lint_summary_path = Some(paths.pack_dir_abs.join(LINT_FILE));
+                    }
+                    match LintReport::read(&paths.pack_dir_abs.join(LINT_FILE)) {
+                        Ok(report) => context_lint = Some(report),
+                        Err(reason) => {
+                            log_line(
+                                "WARN",
+                                "Lint report is malformed",
+                                &[
+                                    format!("task_id={}", selection.task_id),
+                                    format!("run_id={}", run_id),
+                                    format!("reason={}", reason),
+                                ],
+                            );
+                            context_report.warn(format!("{}: {}", LINT_FILE, reason));
+                        }
                     }
                     context_report.mark_success();
                     emit_context_compile_report(
@@ -281,7 +482,7 @@ pub fn run_task_agent(
                     )?;
                 }
             }
-            AssemblyOutcome::Interrupted => {
+            CompileOutcome::Interrupted => {
                 return handle_interrupt(
                     &config.tasks_path,
                     &config.workspace,
@@ -291,13 +492,55 @@ pub fn run_task_agent(
                     run_attempt,
                 );
             }
-            AssemblyOutcome::Failed { code, message } => {
+            CompileOutcome::TimedOut => {
+                let note = format!(
+                    "{} timed out after {}s for run {}. See stdout={} stderr={}",
+                    compiler.name(),
+                    timeouts.assembly_seconds.unwrap_or_default(),
+                    run_id,
+                    paths.assembly_stdout_path.display(),
+                    paths.assembly_stderr_path.display()
+                );
+                let missing = pack_missing_files(&paths.pack_dir_abs);
+                let policy_outcome = if context_config.policy == ContextFailurePolicy::Required {
+                    "timed_out"
+                } else {
+                    "continued"
+                };
+                context_report.mark_failed(missing, Vec::new(), policy_outcome);
+                emit_context_compile_report(&context_report, &paths, &selection.task_id, &run_id)?;
+                let note = append_context_compile_note(&note, &context_report);
+                if context_config.policy == ContextFailurePolicy::Required {
+                    record_elapsed(
+                        &config.tasks_path,
+                        &selection.task_id,
+                        assembly_elapsed,
+                        None,
+                    )?;
+                    return handle_timeout(config, &selection, &run_id, compiler.name(), &note);
+                }
+
+                log_line(
+                    "WARN",
+                    &format!("{} build timed out (best-effort)", compiler.name()),
+                    &[
+                        format!("task_id={}", selection.task_id),
+                        format!("run_id={}", run_id),
+                        format!("stdout={}", paths.assembly_stdout_path.display()),
+                        format!("stderr={}", paths.assembly_stderr_path.display()),
+                    ],
+                );
+                warn_context_compile_failure(&selection.task_id, &run_id);
+                eprintln!("Warning: {}", note);
+            }
+            CompileOutcome::Failed { code, message } => {
                 let exit_detail = match code {
                     Some(value) => format!("exit={}", value),
                     None => "exit=signal".to_string(),
                 };
                 let note = format!(
-                    "Assembly failed ({}) for run {}. {} See stdout={} stderr={}",
+                    "{} failed ({}) for run {}. {} See stdout={} stderr={}",
+                    compiler.name(),
                     exit_detail.as_str(),
                     run_id,
                     message,
@@ -305,16 +548,15 @@ pub fn run_task_agent(
                     paths.assembly_stderr_path.display()
                 );
                 let missing = pack_missing_files(&paths.pack_dir_abs);
-                let policy_outcome =
-                    if config.context_compile.policy == ContextFailurePolicy::Required {
-                        "blocked"
-                    } else {
-                        "continued"
-                    };
-                context_report.mark_failed(missing, policy_outcome);
+                let policy_outcome = if context_config.policy == ContextFailurePolicy::Required {
+                    "blocked"
+                } else {
+                    "continued"
+                };
+                context_report.mark_failed(missing, Vec::new(), policy_outcome);
                 emit_context_compile_report(&context_report, &paths, &selection.task_id, &run_id)?;
                 let note = append_context_compile_note(&note, &context_report);
-                if config.context_compile.policy == ContextFailurePolicy::Required {
+                if context_config.policy == ContextFailurePolicy::Required {
                     increment_attempt_count(&config.tasks_path, &selection.task_id)?;
                     update_task_status(
                         &config.tasks_path,
@@ -326,7 +568,7 @@ pub fn run_task_agent(
                     git_commit_progress(&config.workspace, &selection.title, &selection.task_id)?;
                     log_line(
                         "ERROR",
-                        "Assembly build failed",
+                        &format!("{} build failed", compiler.name()),
                         &[
                             format!("task_id={}", selection.task_id),
                             format!("run_id={}", run_id),
@@ -341,7 +583,7 @@ pub fn run_task_agent(
 
                 log_line(
                     "WARN",
-                    "Assembly build failed (best-effort)",
+                    &format!("{} build failed (best-effort)", compiler.name()),
                     &[
                         format!("task_id={}", selection.task_id),
                         format!("run_id={}", run_id),
@@ -359,7 +601,59 @@ pub fn run_task_agent(
         emit_context_compile_report(&context_report, &paths, &selection.task_id, &run_id)?;
     }
 
-    ensure_schema_file(&config.workspace)?;
+    let lint_errors = context_lint.as_ref().map(LintReport::error_count);
+    let lint_failure =
+        lint_errors.and_then(|errors| config.lever_config.lint.threshold_failure(errors));
+    if let (Some(failure), LintFailMode::Run) = (&lint_failure, config.lever_config.lint.fail) {
+        let note = format!(
+            "Run {} blocked: {}. See {}",
+            run_id,
+            failure,
+            paths.pack_dir_rel.join(LINT_FILE).display()
+        );
+        increment_attempt_count(&config.tasks_path, &selection.task_id)?;
+        update_task_status(
+            &config.tasks_path,
+            &selection.task_id,
+            "blocked",
+            &run_id,
+            &note,
+        )?;
+        git_commit_progress(&config.workspace, &selection.title, &selection.task_id)?;
+        log_line(
+            "ERROR",
+            "Lint errors exceed threshold",
+            &[
+                format!("task_id={}", selection.task_id),
+                format!("run_id={}", run_id),
+                format!("errors={}", lint_errors.unwrap_or_default()),
+            ],
+        );
+        eprintln!("Blocked: {}", note);
+        return Ok(LINT_THRESHOLD_EXIT_CODE);
+    }
+
+    match ensure_schema_file(&config.workspace)? {
+        SchemaUpdate::Upgraded { from } => log_line(
+            "INFO",
+            "Upgraded result schema",
+            &[
+                format!("path={}", SCHEMA_PATH),
+                format!("from=v{}", from),
+                format!("to=v{}", RESULT_SCHEMA_VERSION),
+            ],
+        ),
+        SchemaUpdate::Custom => log_line(
+            "WARN",
+            "Keeping custom result schema",
+            &[
+                format!("path={}", SCHEMA_PATH),
+                format!("current=v{}", RESULT_SCHEMA_VERSION),
+            ],
+        ),
+        SchemaUpdate::Created | SchemaUpdate::Current => {}
+    }
+    let result_schema = load_result_schema(&config.workspace)?;
 
     if is_shutdown(shutdown_flag) {
         return handle_interrupt(
@@ -383,24 +677,173 @@ pub fn run_task_agent(
         ],
     );
 
-    build_prompt(PromptBuildInput {
+    let token_budget = config
+        .prompt_token_budget
+        .or(config.lever_config.prompt_budget.max_tokens);
+    let prompt_tokens = build_prompt(PromptBuildInput {
         workspace: &config.workspace,
-        base_prompt: &config.prompt_path,
+        base_prompt: &prompt_profile.content,
+        base_prompt_label: &prompt_profile.label,
         prompt_path: &paths.prompt_path,
-        title: &selection.title,
-        dod: &selection.definition_of_done,
-        recommended: &selection.recommended_approach,
+        task: &selection,
         task_snapshot: &paths.task_snapshot_path,
         lint_summary: lint_summary_path.as_deref(),
+        lint: &config.lever_config.lint,
         compiled_context: compiled_context_path.as_deref(),
+        token_budget,
+        section_priorities: &config.lever_config.prompt_budget.priorities,
     })?;
+    fs::write(
+        &paths.prompt_tokens_path,
+        serde_json::to_string_pretty(&prompt_tokens.to_json())?,
+    )?;
+    if let Some(report) = prompt_tokens.budget_json() {
+        fs::write(
+            &paths.prompt_budget_path,
+            serde_json::to_string_pretty(&report)?,
+        )?;
+    }
+    log_line(
+        "INFO",
+        "Prompt built",
+        &[
+            format!("task_id={}", selection.task_id),
+            format!("run_id={}", run_id),
+            format!("tokens={}", prompt_tokens.total_tokens),
+            format!(
+                "budget={}",
+                token_budget
+                    .map(|budget| budget.to_string())
+                    .unwrap_or_else(|| "none".to_string())
+            ),
+            format!("cuts={}", prompt_tokens.cuts.len()),
+            format!("profile={}", profile_selection.label()),
+            format!("estimator={}", default_estimator().name()),
+        ],
+    );
+    if !prompt_tokens.fits {
+        // Retrying cannot help until the budget or the required sections change.
+        let note = format!(
+            "Run {} blocked: prompt exceeds token budget (required sections need {} tokens, budget is {}). See {}",
+            run_id,
+            prompt_tokens.total_tokens,
+            token_budget.unwrap_or_default(),
+            paths.run_dir_rel.join("prompt-budget.json").display()
+        );
+        update_task_status(
+            &config.tasks_path,
+            &selection.task_id,
+            "blocked",
+            &run_id,
+            &note,
+        )?;
+        git_commit_progress(&config.workspace, &selection.title, &selection.task_id)?;
+        log_line(
+            "ERROR",
+            "Prompt exceeds token budget",
+            &[
+                format!("task_id={}", selection.task_id),
+                format!("run_id={}", run_id),
+                format!("tokens={}", prompt_tokens.total_tokens),
+                format!("budget={}", token_budget.unwrap_or_default()),
+            ],
+        );
+        eprintln!("Blocked: {}", note);
+        return Ok(PROMPT_BUDGET_EXIT_CODE);
+    }
 
     let codex_stream = CodexLogStream::start(&paths.codex_log_abs, &selection.task_id, &run_id)?;
 
-    let estimated_tokens = rate_limit::estimate_prompt_tokens(&paths.prompt_path);
+    let estimated_tokens = prompt_tokens.total_tokens;
+    let usage_file = config.workspace.join(USAGE_FILE);
+    let rate_limits = &config.lever_config.rate_limits;
+    let model_limits = rate_limit::model_limits(rate_limits, &selection.model);
+    // Held until the run records its usage (or returns early).
+    let _daily_cap_reservation = match rate_limit::reserve_daily_cap(
+        &usage_file,
+        &selection.model,
+        model_limits,
+        estimated_tokens,
+        &run_id,
+    )? {
+        DailyCapCheck::Unlimited => None,
+        DailyCapCheck::Reserved(reservation) => Some(reservation),
+        DailyCapCheck::Exceeded(exceeded) => {
+            codex_stream.stop();
+            let note = format!(
+            "Daily token cap reached for {} ({}/{} tokens used, ~{} estimated); resets in {}s (UTC midnight).",
+            selection.model,
+            exceeded.used,
+            exceeded.cap,
+            estimated_tokens,
+            exceeded.resets_in_seconds
+        );
+            update_task_status(
+                &config.tasks_path,
+                &selection.task_id,
+                &selection.status,
+                &run_id,
+                &note,
+            )?;
+            git_commit_progress(&config.workspace, &selection.title, &selection.task_id)?;
+            log_line(
+                "WARN",
+                "Daily token cap reached",
+                &[
+                    format!("task_id={}", selection.task_id),
+                    format!("run_id={}", run_id),
+                    format!("model={}", selection.model),
+                    format!("used={}", exceeded.used),
+                    format!("cap={}", exceeded.cap),
+                ],
+            );
+            return Ok(DAILY_TOKEN_CAP_EXIT_CODE);
+        }
+    };
+    let pricing = config.lever_config.pricing.get(&selection.model).copied();
+    let task_budget = budget::task_budget(&selection.raw);
+    let session_limit = config.session_budget.limit;
+    if pricing.is_none()
+        && (task_budget.max_cost_usd.is_some() || session_limit.max_cost_usd.is_some())
+    {
+        codex_stream.stop();
+        eprintln!(
+            "Cost budget for task {} requires pricing for model {} in lever.json",
+            selection.task_id, selection.model
+        );
+        return Ok(2);
+    }
+    let estimate = Spend {
+        tokens: estimated_tokens,
+        cost_usd: pricing
+            .map(|pricing| pricing.cost_usd(estimated_tokens, 0))
+            .unwrap_or(0.0),
+    };
+    let task_exceeded = budget::check_budget(
+        "task",
+        task_budget,
+        budget::task_spend(&selection.raw),
+        estimate,
+    );
+    let budget_exceeded = match task_exceeded {
+        Some(exceeded) => Some(exceeded),
+        None if !session_limit.is_unlimited() => budget::check_budget(
+            "loop",
+            session_limit,
+            budget::session_spend(&usage_file, &config.session_budget.session_id)?,
+            estimate,
+        ),
+        None => None,
+    };
+    if let Some(exceeded) = budget_exceeded {
+        codex_stream.stop();
+        return handle_budget_exceeded(config, &selection, &run_id, &exceeded);
+    }
     rate_limit_sleep(
-        &config.workspace.join(RATE_LIMIT_FILE),
+        &usage_file,
         &selection.model,
+        Duration::from_secs(rate_limits.window_seconds),
+        model_limits,
         estimated_tokens,
         shutdown_flag,
     )?;
@@ -416,7 +859,31 @@ pub fn run_task_agent(
         );
     }
 
+    let tasks_snapshot = TasksFileSnapshot::capture(&config.tasks_path)?;
+    let run_start_head = git_output(&config.workspace, &["rev-parse", "HEAD"])
+        .map(|head| head.trim().to_string())
+        .ok();
+    let baseline = match (&run_start_head, config.lever_config.verification.baseline) {
+        (Some(head), true) => {
+            // The task branch may already carry commits from earlier runs;
+            // the baseline is where it forked from the base branch.
+            let fork_point = git_output(&config.workspace, &["merge-base", "HEAD", &base_branch()])
+                .map(|commit| commit.trim().to_string())
+                .unwrap_or_else(|_| head.clone());
+            Some(verification_baseline(
+                config,
+                &selection,
+                &paths,
+                &run_id,
+                &fork_point,
+                verify_limits,
+            )?)
+        }
+        _ => None,
+    };
     let mut codex_exit = 1;
+    let mut codex_timed_out = false;
+    let codex_started = Instant::now();
     for attempt in 1..=3 {
         log_line(
             "INFO",
@@ -428,15 +895,23 @@ pub fn run_task_agent(
                 format!("model={}", selection.model),
             ],
         );
-        codex_exit = run_codex(
+        let outcome = run_codex(
             &config.workspace,
             &selection.model,
-            &paths.prompt_path,
+            &paths,
             Path::new(SCHEMA_PATH),
-            &paths.result_path_rel,
-            &paths.codex_log_rel,
+            sandbox.as_ref(),
+            Deadline::new(timeouts.codex_seconds, timeouts.kill_grace_seconds),
             shutdown_flag,
         )?;
+        codex_exit = match outcome {
+            WaitOutcome::Exited(status) => status.code().unwrap_or(1),
+            WaitOutcome::Interrupted => 130,
+            WaitOutcome::TimedOut => {
+                codex_timed_out = true;
+                TIMEOUT_EXIT_CODE
+            }
+        };
         log_line(
             "INFO",
             "Codex exec end",
@@ -445,11 +920,25 @@ pub fn run_task_agent(
                 format!("run_id={}", run_id),
                 format!("attempt={}", attempt),
                 format!("exit={}", codex_exit),
+                format!("timed_out={}", codex_timed_out),
             ],
         );
 
         if codex_exit == 130 || is_shutdown(shutdown_flag) {
             codex_stream.stop();
+            guard_tasks_file(
+                &tasks_snapshot,
+                &config.tasks_path,
+                &paths,
+                &selection.task_id,
+                &run_id,
+            )?;
+            guard_result_schema(
+                &result_schema,
+                &config.workspace,
+                &selection.task_id,
+                &run_id,
+            )?;
             return handle_interrupt(
                 &config.tasks_path,
                 &config.workspace,
@@ -460,13 +949,14 @@ pub fn run_task_agent(
             );
         }
 
-        if paths.result_path_abs.is_file()
-            && paths
-                .result_path_abs
-                .metadata()
-                .map(|m| m.len())
-                .unwrap_or(0)
-                > 0
+        if codex_timed_out
+            || paths.result_path_abs.is_file()
+                && paths
+                    .result_path_abs
+                    .metadata()
+                    .map(|m| m.len())
+                    .unwrap_or(0)
+                    > 0
         {
             break;
         }
@@ -486,13 +976,69 @@ pub fn run_task_agent(
     }
 
     codex_stream.stop();
-
-    let tokens_used = parse_usage_tokens(&paths.codex_log_abs).unwrap_or(estimated_tokens);
-    record_rate_usage(
-        &config.workspace.join(RATE_LIMIT_FILE),
-        &selection.model,
-        tokens_used,
+    let tamper_events = guard_tasks_file(
+        &tasks_snapshot,
+        &config.tasks_path,
+        &paths,
+        &selection.task_id,
+        &run_id,
+    )?;
+    guard_result_schema(
+        &result_schema,
+        &config.workspace,
+        &selection.task_id,
+        &run_id,
     )?;
+    let run_changes = run_start_head
+        .as_deref()
+        .and_then(|head| run_changed_files(&config.workspace, head, &config.tasks_path));
+
+    let usage = parse_token_usage(&paths.codex_log_abs).unwrap_or(TokenUsage {
+        input_tokens: estimated_tokens,
+        output_tokens: 0,
+        total_tokens: estimated_tokens,
+    });
+    let cost_usd = pricing.map(|pricing| pricing.cost_usd(usage.input_tokens, usage.output_tokens));
+    rate_limit::record_rate_usage(
+        &usage_file,
+        UsageEntry {
+            input_tokens: Some(usage.input_tokens),
+            output_tokens: Some(usage.output_tokens),
+            cost_usd,
+            task_id: Some(selection.task_id.clone()),
+            run_id: Some(run_id.clone()),
+            session_id: Some(config.session_budget.session_id.clone()),
+            ..UsageEntry::new(&selection.model, usage.total_tokens)
+        },
+    )?;
+    record_task_spend(
+        &config.tasks_path,
+        &selection.task_id,
+        Spend {
+            tokens: usage.total_tokens,
+            cost_usd: cost_usd.unwrap_or(0.0),
+        },
+    )?;
+    record_elapsed(
+        &config.tasks_path,
+        &selection.task_id,
+        assembly_elapsed,
+        Some(codex_started.elapsed()),
+    )?;
+
+    if codex_timed_out {
+        let note = append_context_compile_note(
+            &format!(
+                "Codex timed out after {}s for run {}. See {}",
+                timeouts.codex_seconds.unwrap_or_default(),
+                run_id,
+                paths.codex_log_rel.display()
+            ),
+            &context_report,
+        );
+        let note = append_tamper_note(&note, &tamper_events);
+        return handle_timeout(config, &selection, &run_id, "Codex", &note);
+    }
 
     if !paths.result_path_abs.is_file()
         || paths
@@ -510,6 +1056,7 @@ pub fn run_task_agent(
             ),
             &context_report,
         );
+        let note = append_tamper_note(&note, &tamper_events);
         increment_attempt_count(&config.tasks_path, &selection.task_id)?;
         update_task_status(
             &config.tasks_path,
@@ -535,20 +1082,75 @@ pub fn run_task_agent(
         return Ok(10);
     }
 
-    let result: Value = serde_json::from_str(&fs::read_to_string(&paths.result_path_abs)?)?;
-    let reported_outcome = result
-        .get("outcome")
-        .and_then(Value::as_str)
-        .unwrap_or("")
-        .to_string();
-    let dod_met = result
-        .get("dod_met")
-        .and_then(Value::as_bool)
-        .unwrap_or(false);
-
-    if let Some(summary) = result.get("summary").and_then(Value::as_str) {
-        if !summary.trim().is_empty() {
-            log_line(
+    // result.json stays untouched; problems go to result-errors.json beside it.
+    let raw_result = String::from_utf8_lossy(&fs::read(&paths.result_path_abs)?).into_owned();
+    let result = match validate_result(&raw_result, &result_schema.schema, &selection.task_id) {
+        Ok(result) => result,
+        Err(errors) => {
+            let report = json!({
+                "task_id": selection.task_id,
+                "run_id": run_id,
+                "schema": SCHEMA_PATH,
+                "result": paths.result_path_rel.display().to_string(),
+                "errors": errors,
+            });
+            fs::write(
+                &paths.result_errors_path_abs,
+                serde_json::to_string_pretty(&report)?,
+            )?;
+            let mut reason = errors[0].clone();
+            if errors.len() > 1 {
+                reason.push_str(&format!(" (+{} more)", errors.len() - 1));
+            }
+            let note = append_context_compile_note(
+                &format!(
+                    "Codex produced an invalid result.json: {}. See {}",
+                    reason,
+                    paths.result_errors_path_rel.display()
+                ),
+                &context_report,
+            );
+            let note = append_tamper_note(&note, &tamper_events);
+            increment_attempt_count(&config.tasks_path, &selection.task_id)?;
+            update_task_status(
+                &config.tasks_path,
+                &selection.task_id,
+                "blocked",
+                &run_id,
+                &note,
+            )?;
+            git_commit_progress(&config.workspace, &selection.title, &selection.task_id)?;
+            log_line(
+                "ERROR",
+                "Invalid result.json",
+                &[
+                    format!("task_id={}", selection.task_id),
+                    format!("run_id={}", run_id),
+                    format!("errors={}", errors.len()),
+                    format!("report={}", paths.result_errors_path_rel.display()),
+                ],
+            );
+            eprintln!(
+                "Blocked: invalid result.json ({}). See {}",
+                reason,
+                paths.result_errors_path_rel.display()
+            );
+            return Ok(INVALID_RESULT_EXIT_CODE);
+        }
+    };
+    let reported_outcome = result
+        .get("outcome")
+        .and_then(Value::as_str)
+        .unwrap_or("")
+        .to_string();
+    let dod_met = result
+        .get("dod_met")
+        .and_then(Value::as_bool)
+        .unwrap_or(false);
+
+    if let Some(summary) = result.get("summary").and_then(Value::as_str) {
+        if !summary.trim().is_empty() {
+            log_line(
                 "INFO",
                 &format!("Result summary: {}", compact_text(summary, 220)),
                 &[
@@ -604,15 +1206,155 @@ pub fn run_task_agent(
         }
     }
 
-    let verify = if dod_met {
-        run_verification(
+    append_follow_up_tasks(&config.tasks_path, &selection.task_id, &result, &run_id)?;
+
+    let clean_checkout = if dod_met && config.lever_config.verification.clean_checkout {
+        git_commit_progress(&config.workspace, &selection.title, &selection.task_id)?;
+        Some(CleanCheckout::create(
             &config.workspace,
+            "HEAD",
+            &selection.task_id,
+            &run_id,
+        )?)
+    } else {
+        None
+    };
+    let verify_root = clean_checkout
+        .as_ref()
+        .map_or(config.workspace.as_path(), |checkout| {
+            checkout.path.as_path()
+        });
+    let mut verify = if dod_met {
+        run_verification(
+            verify_root,
             &paths.run_dir_abs,
+            &paths.sarif_dir_abs,
             &selection.verification_commands,
+            &selection.definition_of_done,
+            &config.lever_config.verification,
+            verify_limits,
         )?
     } else {
         VerificationResult::skipped()
     };
+    let mut claims = ClaimsReport::default();
+    if let Some(changed) = &run_changes {
+        claims.checks.extend(check_diff(&result, changed));
+    }
+    if dod_met {
+        let retries = config.lever_config.verification.retries;
+        let flaky = &mut verify.flaky;
+        claims
+            .checks
+            .extend(TestsClaim::from_result(&result).check(|command| {
+                rerun_claimed_command(
+                    verify_root,
+                    &paths.run_dir_abs,
+                    &paths.sarif_dir_abs,
+                    command,
+                    retries,
+                    verify_limits,
+                    flaky,
+                )
+            })?);
+        if claims.rerun_failed() {
+            verify.ok = false;
+            verify.log_command = Some(match verify.log_command.take() {
+                Some(command) => format!("{} + tests.commands", command),
+                None => "tests.commands".to_string(),
+            });
+        }
+    }
+    drop(clean_checkout);
+    if !claims.checks.is_empty() {
+        record_claims(
+            &config.tasks_path,
+            &paths.claims_path,
+            &selection.task_id,
+            &run_id,
+            &claims,
+        )?;
+    }
+    if !verify.dod_checks.is_empty() {
+        record_dod_checks(
+            &config.tasks_path,
+            &paths.dod_verification_path,
+            &selection.task_id,
+            &run_id,
+            &verify.dod_checks,
+        )?;
+    }
+    if let (true, Some(failure), LintFailMode::Verification) =
+        (dod_met, &lint_failure, config.lever_config.lint.fail)
+    {
+        let verify_log = paths.run_dir_abs.join("verify.log");
+        let mut log = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&verify_log)?;
+        writeln!(log, "lever: {}", failure)?;
+        verify.ok = false;
+        log_line(
+            "WARN",
+            "Verification failed: lint errors exceed threshold",
+            &[
+                format!("task_id={}", selection.task_id),
+                format!("run_id={}", run_id),
+                format!("errors={}", lint_errors.unwrap_or_default()),
+                format!("log={}", verify_log.display()),
+            ],
+        );
+    }
+
+    let baseline_comparison = baseline
+        .as_ref()
+        .filter(|_| dod_met)
+        .map(|(baseline, _)| BaselineComparison::new(baseline, &verify.steps()));
+    if dod_met {
+        fs::write(
+            &paths.verification_path,
+            serde_json::to_string_pretty(&json!({
+                "task_id": selection.task_id,
+                "run_id": run_id,
+                "ok": verify.ok,
+                "source": match (verify.detector, verify.commands.is_empty()) {
+                    (Some(_), _) => "detector",
+                    (None, false) => "task",
+                    (None, true) => "none",
+                },
+                "detector": verify.detector,
+                "commands": verify.commands,
+                "clean_checkout": config.lever_config.verification.clean_checkout,
+                "retries": config.lever_config.verification.retries,
+                "timeout_seconds": timeouts.verification_seconds,
+                "flaky": verify
+                    .flaky
+                    .iter()
+                    .map(|flaky| json!({ "command": flaky.command, "attempts": flaky.attempts }))
+                    .collect::<Vec<_>>(),
+                "baseline": baseline
+                    .as_ref()
+                    .zip(baseline_comparison.as_ref())
+                    .map(|((baseline, cached), comparison)| comparison.to_json(baseline, *cached)),
+            }))?,
+        )?;
+    }
+    if !verify.flaky.is_empty() {
+        record_flaky_commands(
+            &config.workspace,
+            &verify.flaky,
+            &selection.task_id,
+            &run_id,
+        )?;
+    }
+
+    write_findings_sarif(
+        &config.workspace,
+        &paths,
+        context_lint.as_ref(),
+        &selection.task_id,
+        &run_id,
+    );
 
     if !verify.log_command.as_deref().unwrap_or("").is_empty() {
         if verify.ok {
@@ -623,6 +1365,7 @@ pub fn run_task_agent(
                     format!("task_id={}", selection.task_id),
                     format!("run_id={}", run_id),
                     format!("command={}", verify.log_command.as_deref().unwrap_or("")),
+                    format!("detector={}", verify.detector.unwrap_or("none")),
                     format!("log={}", paths.run_dir_abs.join("verify.log").display()),
                 ],
             );
@@ -634,6 +1377,7 @@ pub fn run_task_agent(
                     format!("task_id={}", selection.task_id),
                     format!("run_id={}", run_id),
                     format!("command={}", verify.log_command.as_deref().unwrap_or("")),
+                    format!("detector={}", verify.detector.unwrap_or("none")),
                     format!("log={}", paths.run_dir_abs.join("verify.log").display()),
                 ],
             );
@@ -643,6 +1387,9 @@ pub fn run_task_agent(
     if dod_met && verify.ok {
         let note =
             append_context_compile_note(&format!("Run {} completed", run_id), &context_report);
+        let note = append_tamper_note(&note, &tamper_events);
+        let note = claims.append_note(&note);
+        let note = append_baseline_note(&note, baseline_comparison.as_ref());
         increment_attempt_count(&config.tasks_path, &selection.task_id)?;
         update_task_status(
             &config.tasks_path,
@@ -683,15 +1430,26 @@ pub fn run_task_agent(
         );
     }
 
-    let note = format!(
-        "Run {} progress. reported_outcome={} dod_met={} verify_ok={}. See {}",
-        run_id,
-        reported_outcome,
-        dod_met,
-        verify.ok,
-        paths.result_path_rel.display()
+    let mut note = format!(
+        "Run {} progress. reported_outcome={} dod_met={} verify_ok={}",
+        run_id, reported_outcome, dod_met, verify.ok,
     );
+    if !verify.dod_checks.is_empty() {
+        note.push_str(&format!(
+            " dod_checks={}/{}",
+            verify
+                .dod_checks
+                .iter()
+                .filter(|check| check.passed())
+                .count(),
+            verify.dod_checks.len()
+        ));
+    }
+    note.push_str(&format!(". See {}", paths.result_path_rel.display()));
     let note = append_context_compile_note(&note, &context_report);
+    let note = append_tamper_note(&note, &tamper_events);
+    let note = claims.append_note(&note);
+    let note = append_baseline_note(&note, baseline_comparison.as_ref());
     increment_attempt_count(&config.tasks_path, &selection.task_id)?;
     update_task_status(
         &config.tasks_path,
@@ -737,7 +1495,7 @@ struct SelectedTask {
     status: String,
     model: String,
     title: String,
-    definition_of_done: Vec<String>,
+    definition_of_done: Vec<DodItem>,
     recommended_approach: String,
     verification_commands: Vec<String>,
     raw: Value,
@@ -811,17 +1569,7 @@ fn select_task(
         .unwrap_or("")
         .to_string();
 
-    let definition_of_done = first_task
-        .get("definition_of_done")
-        .and_then(Value::as_array)
-        .map(|items| {
-            items
-                .iter()
-                .filter_map(Value::as_str)
-                .map(str::to_string)
-                .collect::<Vec<_>>()
-        })
-        .unwrap_or_default();
+    let definition_of_done = definition_of_done(first_task);
 
     let recommended_approach = first_task
         .get("recommended")
@@ -888,45 +1636,6 @@ fn utc_timestamp(format: &str) -> Result<String, DynError> {
     Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
 }
 
-fn ensure_schema_file(workspace: &Path) -> Result<(), DynError> {
-    let schema_path = workspace.join(SCHEMA_PATH);
-    if schema_path.is_file() {
-        return Ok(());
-    }
-
-    if let Some(parent) = schema_path.parent() {
-        fs::create_dir_all(parent)?;
-    }
-
-    let schema = r#"{
-  "$schema": "https://json-schema.org/draft/2020-12/schema",
-  "type": "object",
-  "additionalProperties": false,
-  "required": ["task_id", "outcome", "dod_met", "summary", "tests", "notes", "blockers"],
-  "properties": {
-    "task_id": { "type": "string" },
-    "outcome": { "type": "string", "enum": ["completed", "blocked", "started"] },
-    "dod_met": { "type": "boolean" },
-    "summary": { "type": "string" },
-    "tests": {
-      "type": "object",
-      "additionalProperties": false,
-      "required": ["ran", "commands", "passed"],
-      "properties": {
-        "ran": { "type": "boolean" },
-        "commands": { "type": "array", "items": { "type": "string" } },
-        "passed": { "type": "boolean" }
-      }
-    },
-    "notes": { "type": "string" },
-    "blockers": { "type": "array", "items": { "type": "string" } }
-  }
-}
-"#;
-    fs::write(schema_path, schema)?;
-    Ok(())
-}
-
 fn pack_missing_files(pack_dir: &Path) -> Vec<String> {
     let mut missing = Vec::new();
     for required in REQUIRED_PACK_FILES {
@@ -952,15 +1661,27 @@ fn context_failure_policy_label(policy: ContextFailurePolicy) -> &'static str {
     }
 }
 
+fn context_compiler_label(kind: ContextCompilerKind) -> &'static str {
+    match kind {
+        ContextCompilerKind::Assembly => "assembly",
+        ContextCompilerKind::Native => "native",
+    }
+}
+
 #[derive(Debug, Clone)]
 struct ContextCompileReport {
     enabled: bool,
     status: String,
     policy: String,
     policy_outcome: String,
+    compiler: String,
     pack_dir: String,
     pack_files: Vec<String>,
     pack_missing: Vec<String>,
+    pack_errors: Vec<String>,
+    warnings: Vec<String>,
+    cache_key: Option<String>,
+    cache_hit: bool,
 }
 
 impl ContextCompileReport {
@@ -970,28 +1691,45 @@ impl ContextCompileReport {
             status: "skipped".to_string(),
             policy: context_failure_policy_label(config.policy).to_string(),
             policy_outcome: "skipped".to_string(),
+            compiler: context_compiler_label(config.compiler).to_string(),
             pack_dir: paths.pack_dir_rel.display().to_string(),
             pack_files: pack_expected_paths(&paths.pack_dir_rel),
             pack_missing: Vec::new(),
+            pack_errors: Vec::new(),
+            warnings: Vec::new(),
+            cache_key: None,
+            cache_hit: false,
         }
     }
 
+    fn set_cache(&mut self, key: Option<String>, hit: bool) {
+        self.cache_key = key;
+        self.cache_hit = hit;
+    }
+
+    fn warn(&mut self, warning: String) {
+        self.warnings.push(warning);
+    }
+
     fn mark_skipped(&mut self) {
         self.status = "skipped".to_string();
         self.policy_outcome = "skipped".to_string();
         self.pack_missing.clear();
+        self.pack_errors.clear();
     }
 
     fn mark_success(&mut self) {
         self.status = "succeeded".to_string();
         self.policy_outcome = "proceeded".to_string();
         self.pack_missing.clear();
+        self.pack_errors.clear();
     }
 
-    fn mark_failed(&mut self, missing: Vec<String>, policy_outcome: &str) {
+    fn mark_failed(&mut self, missing: Vec<String>, errors: Vec<String>, policy_outcome: &str) {
         self.status = "failed".to_string();
         self.policy_outcome = policy_outcome.to_string();
         self.pack_missing = missing;
+        self.pack_errors = errors;
     }
 
     fn log_level(&self) -> &'static str {
@@ -1010,8 +1748,12 @@ impl ContextCompileReport {
             format!("status={}", self.status),
             format!("policy={}", self.policy),
             format!("policy_outcome={}", self.policy_outcome),
+            format!("compiler={}", self.compiler),
             format!("pack_dir={}", self.pack_dir),
             format!("pack_missing={}", self.pack_missing.join(",")),
+            format!("pack_errors={}", self.pack_errors.len()),
+            format!("warnings={}", self.warnings.len()),
+            format!("cache_hit={}", self.cache_hit),
         ]
     }
 
@@ -1034,9 +1776,14 @@ impl ContextCompileReport {
             "status": self.status,
             "policy": self.policy,
             "policy_outcome": self.policy_outcome,
+            "compiler": self.compiler,
             "pack_dir": self.pack_dir,
             "pack_files": self.pack_files,
             "pack_missing": self.pack_missing,
+            "pack_errors": self.pack_errors,
+            "warnings": self.warnings,
+            "cache_key": self.cache_key,
+            "cache_hit": self.cache_hit,
         });
         fs::write(path, serde_json::to_string_pretty(&payload)?)?;
         Ok(())
@@ -1072,296 +1819,547 @@ fn append_context_compile_note(note: &str, report: &ContextCompileReport) -> Str
     }
 }
 
-fn validate_pack_outputs(pack_dir: &Path) -> Result<(), PackValidationError> {
+fn guard_tasks_file(
+    snapshot: &TasksFileSnapshot,
+    tasks_path: &Path,
+    paths: &crate::run_paths::RunPaths,
+    task_id: &str,
+    run_id: &str,
+) -> Result<Vec<TamperEvent>, DynError> {
+    let events = snapshot.detect(tasks_path);
+    if events.is_empty() {
+        return Ok(events);
+    }
+    snapshot.restore(tasks_path, &events)?;
+    write_tamper_report(&paths.tasks_tamper_path, run_id, tasks_path, &events)?;
+    log_line(
+        "WARN",
+        "Tasks file changed during agent run; restored changed fields",
+        &[
+            format!("task_id={}", task_id),
+            format!("run_id={}", run_id),
+            format!(
+                "events={}",
+                events
+                    .iter()
+                    .map(TamperEvent::describe)
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            ),
+            format!("report={}", paths.tasks_tamper_path.display()),
+        ],
+    );
+    Ok(events)
+}
+
+fn guard_result_schema(
+    result_schema: &ResultSchema,
+    workspace: &Path,
+    task_id: &str,
+    run_id: &str,
+) -> Result<(), DynError> {
+    if result_schema.restore(workspace)? {
+        log_line(
+            "WARN",
+            "Result schema changed during agent run; restored it",
+            &[
+                format!("task_id={}", task_id),
+                format!("run_id={}", run_id),
+                format!("path={}", SCHEMA_PATH),
+            ],
+        );
+    }
+    Ok(())
+}
+
+fn append_tamper_note(note: &str, events: &[TamperEvent]) -> String {
+    if events.is_empty() {
+        return note.to_string();
+    }
+    let described = events
+        .iter()
+        .map(TamperEvent::describe)
+        .collect::<Vec<_>>()
+        .join(" ");
+    format!("{}; tasks_tamper_reverted={}", note.trim_end(), described)
+}
+
+/// Checks that every required pack file exists, then validates the summary,
+/// manifest, and index contents against the task and token budget.
+fn validate_pack_outputs(
+    paths: &crate::run_paths::RunPaths,
+    task_id: &str,
+    token_budget: u64,
+) -> Result<(), PackValidationError> {
+    let pack_dir = &paths.pack_dir_abs;
     let missing = pack_missing_files(pack_dir);
-    if missing.is_empty() {
+    let problems = if missing.is_empty() {
+        validate_pack_contents(
+            pack_dir,
+            &paths.assembly_summary_path,
+            task_id,
+            token_budget,
+        )
+        .iter()
+        .map(ToString::to_string)
+        .collect()
+    } else {
+        Vec::new()
+    };
+    if missing.is_empty() && problems.is_empty() {
         Ok(())
     } else {
         Err(PackValidationError {
             pack_dir: pack_dir.to_path_buf(),
             missing,
+            problems,
         })
     }
 }
 
+struct ResolvedPrompt {
+    content: String,
+    label: String,
+}
+
+/// Returns the prompt text for `selection`, or `None` when the named
+/// profile has no `prompts/<name>.prompt.md`.
+fn resolve_prompt_profile(
+    config: &TaskAgentConfig,
+    selection: &ProfileSelection,
+) -> Result<Option<ResolvedPrompt>, DynError> {
+    match &selection.name {
+        Some(name) => Ok(config
+            .prompt_profiles
+            .get(name)
+            .map(|profile| ResolvedPrompt {
+                content: profile.content.clone(),
+                label: profile.path.display().to_string(),
+            })),
+        None => Ok(Some(ResolvedPrompt {
+            content: fs::read_to_string(&config.prompt_path)?,
+            label: display_workspace_path(&config.prompt_origin, &config.workspace),
+        })),
+    }
+}
+
 struct PromptBuildInput<'a> {
     workspace: &'a Path,
-    base_prompt: &'a Path,
+    base_prompt: &'a str,
+    base_prompt_label: &'a str,
     prompt_path: &'a Path,
-    title: &'a str,
-    dod: &'a [String],
-    recommended: &'a str,
+    task: &'a SelectedTask,
     task_snapshot: &'a Path,
     lint_summary: Option<&'a Path>,
+    lint: &'a LintConfig,
     compiled_context: Option<&'a Path>,
-}
-
-fn build_prompt(input: PromptBuildInput<'_>) -> Result<(), DynError> {
-    let mut prompt = fs::read_to_string(input.base_prompt)?;
-    prompt.push_str("\n\n");
-    prompt.push_str(&format!("Task title: {}\n", input.title));
-    prompt.push_str("\nDefinition of done:\n");
-    for item in input.dod {
-        prompt.push_str(&format!("  - {}\n", item));
-    }
-    prompt.push_str("\nRecommended approach:\n");
-    prompt.push_str(input.recommended);
-    prompt.push('\n');
-    prompt.push_str("\nTask JSON (authoritative):\n");
-    prompt.push_str(&fs::read_to_string(input.task_snapshot)?);
-    if !prompt.ends_with('\n') {
-        prompt.push('\n');
-    }
-    append_lint_summary(&mut prompt, input.lint_summary, input.workspace)?;
-    append_compiled_context(&mut prompt, input.compiled_context, input.workspace)?;
-    fs::write(input.prompt_path, prompt)?;
-    Ok(())
-}
+    token_budget: Option<u64>,
+    section_priorities: &'a BTreeMap<String, u32>,
+}
+
+struct PromptTokenReport {
+    template: &'static str,
+    budget: Option<u64>,
+    total_tokens: u64,
+    sections: Vec<PromptSection>,
+    cuts: Vec<PromptCut>,
+    fits: bool,
+}
+
+impl PromptTokenReport {
+    fn to_json(&self) -> Value {
+        let estimator = default_estimator();
+        let sections: Vec<Value> = self
+            .sections
+            .iter()
+            .map(|section| {
+                json!({
+                    "name": section.name,
+                    "tokens": estimator.count(&section.text),
+                    "original_tokens": section.original_tokens,
+                    "status": section.status,
+                })
+            })
+            .collect();
+        json!({
+            "estimator": estimator.name(),
+            "template": self.template,
+            "budget": self.budget,
+            "total_tokens": self.total_tokens,
+            "sections": sections,
+        })
+    }
 
-struct LintIssue {
-    severity: String,
-    message: String,
-    path: Option<String>,
-    line: Option<u64>,
-    column: Option<u64>,
-    rule: Option<String>,
+    fn budget_json(&self) -> Option<Value> {
+        self.budget.map(|budget| {
+            budget_report_json(
+                default_estimator(),
+                budget,
+                self.fits,
+                &self.sections,
+                &self.cuts,
+            )
+        })
+    }
 }
 
-fn append_lint_summary(
-    prompt: &mut String,
-    lint_path: Option<&Path>,
-    workspace: &Path,
-) -> Result<(), DynError> {
-    let Some(path) = lint_path else {
-        return Ok(());
-    };
-    if !path.is_file() {
-        return Ok(());
+fn build_prompt(input: PromptBuildInput<'_>) -> Result<PromptTokenReport, DynError> {
+    let estimator = default_estimator();
+    let priorities = section_priorities(input.section_priorities);
+    let template = PromptTemplate::from_prompt(input.base_prompt.to_string());
+    let referenced = template.referenced_sections().map_err(|err| {
+        format!(
+            "Invalid prompt template {}: {}",
+            input.base_prompt_label, err
+        )
+    })?;
+    let task = input.task;
+
+    let mut task_text = format!("Task title: {}\n", task.title);
+    task_text.push_str("\nDefinition of done:\n");
+    for item in &task.definition_of_done {
+        match &item.verify {
+            Some(command) => {
+                task_text.push_str(&format!("  - {} (verified by `{}`)\n", item.text, command))
+            }
+            None => task_text.push_str(&format!("  - {}\n", item.text)),
+        }
     }
-    let raw = match fs::read_to_string(path) {
-        Ok(raw) => raw,
-        Err(_) => return Ok(()),
-    };
-    let value: Value = match serde_json::from_str(&raw) {
-        Ok(value) => value,
-        Err(_) => return Ok(()),
+    task_text.push_str("\nRecommended approach:\n");
+    task_text.push_str(&task.recommended_approach);
+    task_text.push('\n');
+
+    let raw_task = fs::read_to_string(input.task_snapshot)?;
+    let mut task_json = format!("\nTask JSON (authoritative):\n{}", raw_task);
+    if !task_json.ends_with('\n') {
+        task_json.push('\n');
+    }
+
+    let mut sections = Vec::new();
+    if let Some(base) = template.base_prompt() {
+        sections.push(PromptSection::new(
+            "base_prompt",
+            None,
+            SectionBody::Text(format!("{}\n\n", base)),
+            estimator,
+        ));
+    }
+    sections.push(PromptSection::new(
+        "task",
+        None,
+        SectionBody::Text(task_text),
+        estimator,
+    ));
+    sections.push(PromptSection::new(
+        "task_json",
+        Some(priorities["task_json"]),
+        SectionBody::TaskJson {
+            full: task_json,
+            elided: elide_task_json(&raw_task),
+        },
+        estimator,
+    ));
+    if let Some(previous) = previous_attempt_text(input.workspace, task) {
+        sections.push(PromptSection::new(
+            "previous_attempt",
+            Some(priorities["previous_attempt"]),
+            SectionBody::Text(previous),
+            estimator,
+        ));
+    }
+    if let Some(body) = verification_findings_body(input.workspace, task, input.lint) {
+        sections.push(PromptSection::new(
+            "verification_findings",
+            Some(priorities["verification_findings"]),
+            body,
+            estimator,
+        ));
+    }
+    if input.lint_summary.is_some() {
+        sections.push(PromptSection::new(
+            "lint_summary",
+            Some(priorities["lint_summary"]),
+            lint_summary_body(input.lint_summary, input.lint, input.workspace),
+            estimator,
+        ));
+    }
+    if let Some((header, body)) = compiled_context_parts(input.compiled_context, input.workspace)? {
+        let index = input
+            .compiled_context
+            .and_then(Path::parent)
+            .and_then(|pack_dir| fs::read_to_string(pack_dir.join("index.json")).ok())
+            .and_then(|raw| serde_json::from_str::<Value>(&raw).ok());
+        sections.push(PromptSection::new(
+            "compiled_context",
+            Some(priorities["compiled_context"]),
+            SectionBody::Context {
+                header,
+                blocks: context_blocks(&body, index.as_ref()),
+            },
+            estimator,
+        ));
+    }
+    sections.retain(|section| referenced.contains(&section.name));
+
+    // Text the template adds around its sections is required.
+    let values = prompt_template_values(input.workspace, task);
+    let render_error = |err: DynError| {
+        format!(
+            "Invalid prompt template {}: {}",
+            input.base_prompt_label, err
+        )
     };
-    let mut issues = parse_lint_issues(&value);
-    if issues.is_empty() {
-        prompt.push_str("\nLint summary:\nNo lint findings.\n");
-        return Ok(());
+    let skeleton = template.render(&values, &[]).map_err(render_error)?;
+    if !skeleton.is_empty() {
+        sections.insert(
+            0,
+            PromptSection::new("template", None, SectionBody::Text(skeleton), estimator),
+        );
     }
 
-    issues.sort_by(|a, b| lint_issue_sort_key(a).cmp(&lint_issue_sort_key(b)));
-    let (error_count, warning_count, note_count, info_count, other_count) =
-        lint_issue_counts(&issues);
-    let total = issues.len();
-    let show = total.min(5);
+    let (cuts, fits) = match input.token_budget {
+        Some(budget) => enforce_prompt_budget(&mut sections, budget, estimator),
+        None => (Vec::new(), true),
+    };
 
-    prompt.push_str("\nLint summary:\n");
-    prompt.push_str(&format!(
-        "Totals: error={} warning={} note={} info={} other={}\n",
-        error_count, warning_count, note_count, info_count, other_count
-    ));
-    prompt.push_str(&format!("Findings (showing {} of {}):\n", show, total));
-    for issue in issues.iter().take(show) {
-        prompt.push_str(&format!("- {}\n", format_lint_issue(issue, workspace)));
-    }
-    Ok(())
+    let rendered: Vec<(&str, &str)> = sections
+        .iter()
+        .map(|section| (section.name, section.text.as_str()))
+        .collect();
+    let prompt = template.render(&values, &rendered).map_err(render_error)?;
+    fs::write(input.prompt_path, &prompt)?;
+    Ok(PromptTokenReport {
+        template: if template.is_default() {
+            "default"
+        } else {
+            "custom"
+        },
+        budget: input.token_budget,
+        total_tokens: estimator.count(&prompt),
+        sections,
+        cuts,
+        fits,
+    })
 }
 
-fn parse_lint_issues(value: &Value) -> Vec<LintIssue> {
-    let entries = lint_issue_entries(value);
-    let mut issues = Vec::new();
-    for entry in entries {
-        let Value::Object(map) = entry else {
-            continue;
-        };
-        let severity = map
-            .get("severity")
-            .or_else(|| map.get("level"))
-            .or_else(|| map.get("kind"))
-            .and_then(Value::as_str)
-            .unwrap_or("info")
-            .trim()
-            .to_lowercase();
-        let message = map
-            .get("message")
-            .or_else(|| map.get("text"))
-            .or_else(|| map.get("description"))
-            .or_else(|| map.get("summary"))
-            .and_then(Value::as_str)
-            .unwrap_or("")
-            .trim()
-            .to_string();
-        let rule = map
-            .get("rule")
-            .or_else(|| map.get("rule_id"))
-            .or_else(|| map.get("code"))
-            .or_else(|| map.get("id"))
-            .and_then(Value::as_str)
-            .map(|value| value.trim().to_string())
-            .filter(|value| !value.is_empty());
-        let path = map
-            .get("path")
-            .or_else(|| map.get("file"))
-            .or_else(|| map.get("filename"))
-            .and_then(Value::as_str)
-            .map(|value| value.trim().to_string())
-            .filter(|value| !value.is_empty());
-        let line = map
-            .get("line")
-            .or_else(|| map.get("line_start"))
-            .and_then(Value::as_u64);
-        let column = map
-            .get("column")
-            .or_else(|| map.get("col"))
-            .and_then(Value::as_u64);
-
-        if message.is_empty() && rule.is_none() && path.is_none() {
-            continue;
-        }
+fn dod_texts(items: &[DodItem]) -> Vec<&str> {
+    items.iter().map(|item| item.text.as_str()).collect()
+}
 
-        issues.push(LintIssue {
-            severity,
-            message,
-            path,
-            line,
-            column,
-            rule,
-        });
+/// Values for template placeholders other than the prompt sections.
+fn prompt_template_values(workspace: &Path, task: &SelectedTask) -> Map<String, Value> {
+    let git = |args: &[&str]| {
+        git_output(workspace, args)
+            .map(|output| output.trim().to_string())
+            .unwrap_or_default()
+    };
+    let values = json!({
+        "task_id": task.task_id,
+        "title": task.title,
+        "definition_of_done": dod_texts(&task.definition_of_done),
+        "approach": task.recommended_approach,
+        "repo": {
+            "root": workspace.display().to_string(),
+            "name": workspace
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            "branch": git(&["rev-parse", "--abbrev-ref", "HEAD"]),
+            "commit": git(&["rev-parse", "--short=12", "HEAD"]),
+        },
+    });
+    match values {
+        Value::Object(map) => map,
+        _ => Map::new(),
     }
-    issues
 }
 
-fn lint_issue_entries(value: &Value) -> Vec<&Value> {
-    match value {
-        Value::Array(items) => items.iter().collect(),
-        Value::Object(map) => {
-            for key in ["issues", "findings", "diagnostics", "results", "items"] {
-                if let Some(Value::Array(items)) = map.get(key) {
-                    return items.iter().collect();
-                }
-            }
-            Vec::new()
-        }
-        _ => Vec::new(),
-    }
-}
-
-fn lint_issue_counts(issues: &[LintIssue]) -> (usize, usize, usize, usize, usize) {
-    let mut error_count = 0;
-    let mut warning_count = 0;
-    let mut note_count = 0;
-    let mut info_count = 0;
-    let mut other_count = 0;
-    for issue in issues {
-        match severity_bucket(&issue.severity) {
-            "error" => error_count += 1,
-            "warning" => warning_count += 1,
-            "note" => note_count += 1,
-            "info" => info_count += 1,
-            _ => other_count += 1,
-        }
-    }
-    (
-        error_count,
-        warning_count,
-        note_count,
-        info_count,
-        other_count,
-    )
+fn last_run_id(task: &SelectedTask) -> Option<&str> {
+    task.raw
+        .get("observability")?
+        .get("last_run_id")
+        .and_then(Value::as_str)
+        .filter(|run_id| !run_id.is_empty())
 }
 
-fn lint_issue_sort_key(issue: &LintIssue) -> (u8, &str, u64, u64, &str, &str) {
-    let rank = severity_rank(&issue.severity);
-    let path = issue.path.as_deref().unwrap_or("");
-    let line = issue.line.unwrap_or(u64::MAX);
-    let column = issue.column.unwrap_or(u64::MAX);
-    let rule = issue.rule.as_deref().unwrap_or("");
-    let message = issue.message.as_str();
-    (rank, path, line, column, rule, message)
-}
-
-fn format_lint_issue(issue: &LintIssue, workspace: &Path) -> String {
-    let severity = severity_bucket(&issue.severity);
-    let mut line = format!("[{}]", severity);
-    if let Some(path) = issue.path.as_deref() {
-        let display_path = display_issue_path(path, workspace);
-        let mut location = display_path;
-        if let Some(line_no) = issue.line {
-            location.push(':');
-            location.push_str(&line_no.to_string());
-            if let Some(col) = issue.column {
-                location.push(':');
-                location.push_str(&col.to_string());
+/// Summarizes the task's last run from its observability and `result.json`.
+fn previous_attempt_text(workspace: &Path, task: &SelectedTask) -> Option<String> {
+    let obs = task.raw.get("observability")?.as_object()?;
+    let run_id = last_run_id(task)?;
+    let mut text = format!(
+        "\nPrevious attempt:\nRun: {} (status: {})\n",
+        run_id, task.status
+    );
+    if let Some(note) = obs
+        .get("last_note")
+        .and_then(Value::as_str)
+        .filter(|note| !note.trim().is_empty())
+    {
+        text.push_str(&format!("Note: {}\n", note.trim()));
+    }
+    let result_path = run_paths(workspace, &task.task_id, run_id).result_path_abs;
+    let result = fs::read_to_string(result_path)
+        .ok()
+        .and_then(|raw| serde_json::from_str::<Value>(&raw).ok());
+    if let Some(result) = result {
+        if let Some(outcome) = result.get("outcome").and_then(Value::as_str) {
+            text.push_str(&format!("Outcome: {}\n", outcome));
+        }
+        if let Some(summary) = result.get("summary").and_then(Value::as_str) {
+            text.push_str(&format!("Summary: {}\n", summary.trim()));
+        }
+        let blockers: Vec<&str> = result
+            .get("blockers")
+            .and_then(Value::as_array)
+            .map(|items| items.iter().filter_map(Value::as_str).collect())
+            .unwrap_or_default();
+        if !blockers.is_empty() {
+            text.push_str("Blockers:\n");
+            for blocker in blockers {
+                text.push_str(&format!("  - {}\n", blocker));
             }
         }
-        line.push(' ');
-        line.push_str(&location);
-    }
-    if let Some(rule) = issue.rule.as_deref() {
-        if !rule.is_empty() {
-            line.push(' ');
-            line.push_str(rule);
+        let items = dod_items(&result);
+        if !items.is_empty() {
+            text.push_str("Definition of done status:\n");
+            for item in items {
+                text.push_str(&format!("  - [{}] {}\n", item.status, item.item));
+            }
         }
-    }
-    if !issue.message.is_empty() {
-        let message = compact_text(&issue.message, 160);
-        if !line.ends_with(' ') {
-            line.push(' ');
+        let files = files_changed(&result);
+        if !files.is_empty() {
+            text.push_str(&format!("Files changed: {}\n", files.join(", ")));
         }
-        line.push_str("- ");
-        line.push_str(&message);
     }
-    line
+    Some(text)
 }
 
-fn display_issue_path(path: &str, workspace: &Path) -> String {
-    let candidate = Path::new(path);
-    if candidate.is_absolute() {
-        display_workspace_path(candidate, workspace)
-    } else {
-        path.to_string()
-    }
+/// Renders the SARIF findings the last run's verification commands wrote,
+/// so the next attempt can repair them. `None` when there are none.
+fn verification_findings_body(
+    workspace: &Path,
+    task: &SelectedTask,
+    lint: &LintConfig,
+) -> Option<SectionBody> {
+    let run_id = last_run_id(task)?;
+    let sarif_dir = run_paths(workspace, &task.task_id, run_id).sarif_dir_abs;
+    let (sarif, _) = SarifLog::read_dir(&sarif_dir, workspace);
+    if sarif.report.issues.is_empty() {
+        return None;
+    }
+    let mut head = format!(
+        "\nVerification findings from run {}:\nTotals: {}\n",
+        run_id,
+        sarif.report.totals()
+    );
+    if let Some(min) = lint.min_severity {
+        head.push_str(&format!("Showing {} and above.\n", min.label()));
+    }
+    let findings = sarif.report.findings(lint, &BTreeSet::new(), workspace);
+    Some(SectionBody::Lint {
+        head,
+        shown: findings.len().min(lint.max_findings),
+        findings,
+    })
 }
 
-fn severity_bucket(severity: &str) -> &'static str {
-    match severity {
-        "error" | "err" => "error",
-        "warning" | "warn" => "warning",
-        "note" => "note",
-        "info" | "information" => "info",
-        "hint" => "hint",
-        _ => "other",
+/// Merges the pack's lint findings and the SARIF logs verification commands
+/// wrote under `$LEVER_SARIF_DIR` into the run's `findings.sarif`.
+fn write_findings_sarif(
+    workspace: &Path,
+    paths: &RunPaths,
+    context_lint: Option<&LintReport>,
+    task_id: &str,
+    run_id: &str,
+) {
+    let mut runs = Vec::new();
+    if let Some(report) = context_lint {
+        runs.push(report.sarif_run(CONTEXT_LINT_TOOL));
+    }
+    let (verification, problems) = SarifLog::read_dir(&paths.sarif_dir_abs, workspace);
+    for problem in problems {
+        log_line(
+            "WARN",
+            "Ignoring invalid SARIF file",
+            &[
+                format!("task_id={}", task_id),
+                format!("run_id={}", run_id),
+                format!("reason={}", problem),
+            ],
+        );
+    }
+    let verification_findings = verification.report.issues.len();
+    runs.extend(verification.runs);
+    let run_count = runs.len();
+    match write_sarif(&paths.findings_sarif_path, runs) {
+        Ok(()) => log_line(
+            "INFO",
+            "Wrote findings",
+            &[
+                format!("task_id={}", task_id),
+                format!("run_id={}", run_id),
+                format!("runs={}", run_count),
+                format!(
+                    "context_findings={}",
+                    context_lint.map_or(0, |report| report.issues.len())
+                ),
+                format!("verification_findings={}", verification_findings),
+                format!("path={}", paths.findings_sarif_path.display()),
+            ],
+        ),
+        Err(err) => log_line(
+            "WARN",
+            "Failed to write findings",
+            &[
+                format!("task_id={}", task_id),
+                format!("run_id={}", run_id),
+                format!("error={}", err),
+            ],
+        ),
     }
 }
 
-fn severity_rank(severity: &str) -> u8 {
-    match severity_bucket(severity) {
-        "error" => 0,
-        "warning" => 1,
-        "note" => 2,
-        "info" => 3,
-        "hint" => 4,
-        _ => 5,
-    }
+/// Renders `lint.json` as a prompt section whose findings can be trimmed.
+/// A missing or malformed file yields an empty section; the context compile
+/// report already carries the warning.
+fn lint_summary_body(lint_path: Option<&Path>, lint: &LintConfig, workspace: &Path) -> SectionBody {
+    let report = lint_path.and_then(|path| LintReport::read(path).ok());
+    let Some(report) = report else {
+        return SectionBody::Lint {
+            head: String::new(),
+            findings: Vec::new(),
+            shown: 0,
+        };
+    };
+    let context_files: BTreeSet<String> = lint_path
+        .and_then(Path::parent)
+        .and_then(|pack_dir| fs::read_to_string(pack_dir.join(INDEX_FILE)).ok())
+        .and_then(|raw| serde_json::from_str::<PackIndex>(&raw).ok())
+        .map(|index| {
+            index
+                .entries()
+                .iter()
+                .map(|entry| entry.path.clone())
+                .collect()
+        })
+        .unwrap_or_default();
+    report.prompt_section(lint, &context_files, workspace)
 }
 
-fn append_compiled_context(
-    prompt: &mut String,
+/// Returns the compiled context as (header with provenance, body).
+fn compiled_context_parts(
     compiled_context: Option<&Path>,
     workspace: &Path,
-) -> Result<(), DynError> {
+) -> Result<Option<(String, String)>, DynError> {
     let Some(path) = compiled_context else {
-        return Ok(());
+        return Ok(None);
     };
     if !path.is_file() {
-        return Ok(());
+        return Ok(None);
     }
-    let context = fs::read_to_string(path)?;
+    let mut context = fs::read_to_string(path)?;
     if context.trim().is_empty() {
-        return Ok(());
+        return Ok(None);
     }
     let manifest_path = path.parent().ok_or_else(|| {
         format!(
@@ -1373,16 +2371,14 @@ fn append_compiled_context(
     let manifest_display = display_workspace_path(&manifest_path, workspace);
     let commit_sha = git_output(workspace, &["rev-parse", "--short=12", "HEAD"])?;
     let commit_sha = commit_sha.trim();
-    prompt.push_str("\nCompiled context:\n");
-    prompt.push_str(&format!(
-        "Provenance: manifest={} commit={}\n",
+    let header = format!(
+        "\nCompiled context:\nProvenance: manifest={} commit={}\n",
         manifest_display, commit_sha
-    ));
-    prompt.push_str(&context);
-    if !prompt.ends_with('\n') {
-        prompt.push('\n');
+    );
+    if !context.ends_with('\n') {
+        context.push('\n');
     }
-    Ok(())
+    Ok(Some((header, context)))
 }
 
 fn display_workspace_path(path: &Path, workspace: &Path) -> String {
@@ -1393,13 +2389,13 @@ fn display_workspace_path(path: &Path, workspace: &Path) -> String {
     }
 }
 
-fn build_assembly_task_input(selection: &SelectedTask) -> Value {
+fn build_assembly_task_input(selection: &SelectedTask, context_hints: &TaskContextHints) -> Value {
     let mut payload = json!({
         "task_id": selection.task_id.clone(),
         "title": selection.title.clone(),
         "status": selection.status.clone(),
         "model": selection.model.clone(),
-        "definition_of_done": selection.definition_of_done.clone(),
+        "definition_of_done": dod_texts(&selection.definition_of_done),
         "recommended": {
             "approach": selection.recommended_approach.clone(),
         },
@@ -1416,64 +2412,25 @@ fn build_assembly_task_input(selection: &SelectedTask) -> Value {
         }
     }
 
-    payload
-}
+    if !context_hints.is_empty() {
+        if let Some(map) = payload.as_object_mut() {
+            map.insert("context".to_string(), context_hints.to_json());
+        }
+    }
 
-fn build_assembly_command_args(
-    workspace: &Path,
-    task_id: &str,
-    task_input: &Path,
-    pack_dir: &Path,
-    summary_json: &Path,
-    config: &ContextCompileConfig,
-) -> Vec<OsString> {
-    let mut args = vec![
-        OsString::from("build"),
-        OsString::from("--repo"),
-        workspace.as_os_str().to_os_string(),
-        OsString::from("--task"),
-    ];
-    let mut task_arg = OsString::from("@");
-    task_arg.push(task_input);
-    args.push(task_arg);
-    args.push("--task-id".into());
-    args.push(task_id.into());
-    args.push("--out".into());
-    args.push(pack_dir.as_os_str().to_os_string());
-    args.push("--token-budget".into());
-    args.push(config.token_budget.to_string().into());
-
-    for glob in &config.exclude_globs {
-        args.push("--exclude".into());
-        args.push(glob.into());
-    }
-
-    for glob in &config.exclude_runtime_globs {
-        args.push("--exclude-runtime".into());
-        args.push(glob.into());
-    }
-
-    args.push("--summary-json".into());
-    args.push(summary_json.as_os_str().to_os_string());
-
-    args
+    payload
 }
 
 fn rate_limit_sleep(
-    rate_file: &Path,
+    usage_file: &Path,
     model: &str,
+    window: Duration,
+    limits: ModelLimits,
     estimated_tokens: u64,
     shutdown_flag: Option<&AtomicBool>,
 ) -> Result<(), DynError> {
-    let (tpm, rpm) = rate_limit::rate_limit_settings(model);
-    let sleep_seconds = rate_limit::rate_limit_sleep_seconds(
-        rate_file,
-        model,
-        Duration::from_secs(RATE_LIMIT_WINDOW_SECONDS),
-        tpm,
-        rpm,
-        estimated_tokens,
-    )?;
+    let sleep_seconds =
+        rate_limit::rate_limit_sleep_seconds(usage_file, model, window, limits, estimated_tokens)?;
     if sleep_seconds > 0 {
         eprintln!(
             "Rate limit throttle: sleeping {}s for {}.",
@@ -1496,29 +2453,21 @@ fn rate_limit_sleep(
     Ok(())
 }
 
-fn record_rate_usage(rate_file: &Path, model: &str, tokens: u64) -> Result<(), DynError> {
-    rate_limit::record_rate_usage(
-        rate_file,
-        model,
-        Duration::from_secs(RATE_LIMIT_WINDOW_SECONDS),
-        tokens,
-    )
-}
-
 fn run_codex(
     workspace: &Path,
     model: &str,
-    prompt_path: &Path,
+    paths: &RunPaths,
     schema_path: &Path,
-    result_path: &Path,
-    log_path: &Path,
+    sandbox: Option<&Sandbox>,
+    deadline: Deadline,
     shutdown_flag: Option<&AtomicBool>,
-) -> Result<i32, DynError> {
-    let prompt_file = File::open(prompt_path)?;
-    let log_file = File::create(workspace.join(log_path))?;
+) -> Result<WaitOutcome, DynError> {
+    let prompt_file = File::open(&paths.prompt_path)?;
+    let log_file = File::create(workspace.join(&paths.codex_log_rel))?;
     let log_file_err = log_file.try_clone()?;
 
-    let mut child = Command::new("codex")
+    let mut command = Command::new("codex");
+    command
         .current_dir(workspace)
         .arg("exec")
         .arg("--yolo")
@@ -1527,89 +2476,64 @@ fn run_codex(
         .arg("--output-schema")
         .arg(schema_path)
         .arg("--output-last-message")
-        .arg(result_path)
+        .arg(&paths.result_path_rel)
         .arg("--json")
         .arg("--skip-git-repo-check")
-        .arg("-")
-        .stdin(prompt_file)
-        .stdout(log_file)
-        .stderr(log_file_err)
-        .spawn()?;
-
-    loop {
-        if let Some(flag) = shutdown_flag {
-            if flag.load(Ordering::SeqCst) {
-                let _ = child.kill();
-                let _ = child.wait();
-                return Ok(130);
-            }
-        }
-
-        match child.try_wait()? {
-            Some(status) => return Ok(status.code().unwrap_or(1)),
-            None => thread::sleep(Duration::from_millis(100)),
-        }
-    }
-}
-
-enum AssemblyOutcome {
-    Success,
-    Failed { code: Option<i32>, message: String },
-    Interrupted,
+        .arg("-");
+    if let Some(sandbox) = sandbox {
+        command = sandbox.wrap_agent(command);
+    }
+    let mut child = spawn_in_group(
+        command
+            .stdin(prompt_file)
+            .stdout(log_file)
+            .stderr(log_file_err),
+    )?;
+    wait_with_deadline(&mut child, deadline, shutdown_flag)
 }
 
-fn run_assembly(
+/// A failed restore leaves the run to a normal Assembly build, so the pack
+/// directory is emptied again before returning a miss.
+fn restore_pack_from_cache(
     workspace: &Path,
-    task_id: &str,
+    key: &str,
     paths: &crate::run_paths::RunPaths,
-    config: &ContextCompileConfig,
-    shutdown_flag: Option<&AtomicBool>,
-) -> Result<AssemblyOutcome, DynError> {
-    let args = build_assembly_command_args(
+    task_id: &str,
+    run_id: &str,
+) -> Result<bool, DynError> {
+    match restore_cached_pack(
         workspace,
-        task_id,
-        &paths.assembly_task_path,
+        key,
         &paths.pack_dir_abs,
         &paths.assembly_summary_path,
-        config,
-    );
-
-    let stdout_file = File::create(&paths.assembly_stdout_path)?;
-    let stderr_file = File::create(&paths.assembly_stderr_path)?;
-
-    let mut child = Command::new(&config.assembly_path)
-        .current_dir(workspace)
-        .args(args)
-        .stdout(stdout_file)
-        .stderr(stderr_file)
-        .spawn()?;
-
-    loop {
-        if let Some(flag) = shutdown_flag {
-            if flag.load(Ordering::SeqCst) {
-                let _ = child.kill();
-                let _ = child.wait();
-                return Ok(AssemblyOutcome::Interrupted);
-            }
-        }
-
-        match child.try_wait()? {
-            Some(status) => {
-                if status.success() {
-                    return Ok(AssemblyOutcome::Success);
-                }
-                return Ok(AssemblyOutcome::Failed {
-                    code: status.code(),
-                    message: "Assembly exited with non-zero status".to_string(),
-                });
-            }
-            None => thread::sleep(Duration::from_millis(100)),
+    ) {
+        Ok(hit) => Ok(hit),
+        Err(err) => {
+            log_line(
+                "WARN",
+                "Failed to restore cached context pack",
+                &[
+                    format!("task_id={}", task_id),
+                    format!("run_id={}", run_id),
+                    format!("cache_key={}", key),
+                    format!("error={}", err),
+                ],
+            );
+            fs::remove_dir_all(&paths.pack_dir_abs)?;
+            fs::create_dir_all(&paths.pack_dir_abs)?;
+            Ok(false)
         }
     }
 }
 
-fn parse_usage_tokens(log_path: &Path) -> Option<u64> {
-    let mut usage_tokens = None;
+struct TokenUsage {
+    input_tokens: u64,
+    output_tokens: u64,
+    total_tokens: u64,
+}
+
+fn parse_token_usage(log_path: &Path) -> Option<TokenUsage> {
+    let mut token_usage = None;
     let file = File::open(log_path).ok()?;
     let reader = io::BufReader::new(file);
     for line in reader.lines().map_while(Result::ok) {
@@ -1627,22 +2551,26 @@ fn parse_usage_tokens(log_path: &Path) -> Option<u64> {
         let input_tokens = usage
             .get("input_tokens")
             .or_else(|| usage.get("prompt_tokens"))
-            .and_then(Value::as_i64)
+            .and_then(Value::as_u64)
             .unwrap_or(0);
         let output_tokens = usage
             .get("output_tokens")
             .or_else(|| usage.get("completion_tokens"))
-            .and_then(Value::as_i64)
+            .and_then(Value::as_u64)
             .unwrap_or(0);
         let total = usage
             .get("total_tokens")
-            .and_then(Value::as_i64)
+            .and_then(Value::as_u64)
             .unwrap_or(input_tokens + output_tokens);
         if total > 0 {
-            usage_tokens = Some(total as u64);
+            token_usage = Some(TokenUsage {
+                input_tokens,
+                output_tokens,
+                total_tokens: total,
+            });
         }
     }
-    usage_tokens
+    token_usage
 }
 
 fn rate_limit_retry_delay(log_path: &Path) -> Result<Option<u64>, DynError> {
@@ -1683,6 +2611,56 @@ fn is_shutdown(shutdown_flag: Option<&AtomicBool>) -> bool {
         .unwrap_or(false)
 }
 
+fn handle_budget_exceeded(
+    config: &TaskAgentConfig,
+    selection: &SelectedTask,
+    run_id: &str,
+    exceeded: &BudgetExceeded,
+) -> Result<i32, DynError> {
+    // A task over its own budget needs a human to raise it; a loop cap only
+    // pauses the task.
+    let status = if exceeded.scope == "task" {
+        "blocked"
+    } else {
+        selection.status.as_str()
+    };
+    update_task_status(
+        &config.tasks_path,
+        &selection.task_id,
+        status,
+        run_id,
+        &format!("Run not started: {}.", exceeded.describe()),
+    )?;
+    git_commit_progress(&config.workspace, &selection.title, &selection.task_id)?;
+    log_line(
+        "WARN",
+        "Budget exceeded",
+        &[
+            format!("task_id={}", selection.task_id),
+            format!("run_id={}", run_id),
+            format!("scope={}", exceeded.scope),
+            format!("dimension={}", exceeded.dimension),
+            format!("spent={}", exceeded.spent),
+            format!("estimate={}", exceeded.estimate),
+            format!("limit={}", exceeded.limit),
+        ],
+    );
+    Ok(BUDGET_EXCEEDED_EXIT_CODE)
+}
+
+fn record_task_spend(tasks_path: &Path, task_id: &str, spend: Spend) -> Result<(), DynError> {
+    let mut root = load_tasks_root(tasks_path)?;
+    let tasks = tasks_array_mut(&mut root).ok_or("Tasks file is not a list")?;
+    let task = tasks
+        .iter_mut()
+        .find(|task| task.get("task_id").and_then(Value::as_str) == Some(task_id))
+        .ok_or_else(|| format!("Task {} not found in {}", task_id, tasks_path.display()))?;
+
+    let task_obj = task_object_mut(task)?;
+    budget::add_spend_to_observability(ensure_observability(task_obj), spend);
+    write_tasks_root(tasks_path, &root)
+}
+
 fn handle_interrupt(
     tasks_path: &Path,
     workspace: &Path,
@@ -2048,6 +3026,418 @@ fn update_task_status(
     write_tasks_root(tasks_path, &root)
 }
 
+/// Verification results for `commit`, read from the
+/// baseline cache or computed in a clean checkout before Codex runs.
+/// Returns the baseline and whether it came from the cache.
+fn verification_baseline(
+    config: &TaskAgentConfig,
+    selection: &SelectedTask,
+    paths: &RunPaths,
+    run_id: &str,
+    commit: &str,
+    limits: StepLimits,
+) -> Result<(Baseline, bool), DynError> {
+    let settings = &config.lever_config.verification;
+    let setup = json!({
+        "verification_commands": selection.verification_commands,
+        "definition_of_done": selection
+            .definition_of_done
+            .iter()
+            .filter(|item| item.verify.is_some())
+            .map(|item| json!({ "item": item.text, "verify": item.verify }))
+            .collect::<Vec<_>>(),
+        "detectors": settings.detectors,
+        "disabled_detectors": settings.disabled_detectors,
+    });
+    let key = baseline_cache_key(commit, &setup)?;
+    let (baseline, cached) = match load_cached_baseline(&config.workspace, &key) {
+        Some(baseline) => (baseline, true),
+        None => {
+            let checkout =
+                CleanCheckout::create(&config.workspace, commit, &selection.task_id, run_id)?;
+            let baseline_dir = paths.run_dir_abs.join("baseline");
+            fs::create_dir_all(&baseline_dir)?;
+            let verify = run_verification(
+                &checkout.path,
+                &baseline_dir,
+                &baseline_dir.join("sarif"),
+                &selection.verification_commands,
+                &selection.definition_of_done,
+                settings,
+                limits,
+            )?;
+            drop(checkout);
+            let baseline = Baseline {
+                commit: commit.to_string(),
+                steps: verify.steps(),
+            };
+            store_baseline(&config.workspace, &key, &baseline)?;
+            (baseline, false)
+        }
+    };
+    log_line(
+        "INFO",
+        "Verification baseline",
+        &[
+            format!("task_id={}", selection.task_id),
+            format!("run_id={}", run_id),
+            format!("commit={}", commit),
+            format!("cached={}", cached),
+            format!(
+                "failing={}",
+                baseline.steps.iter().filter(|step| !step.passed).count()
+            ),
+        ],
+    );
+    Ok((baseline, cached))
+}
+
+/// Ends a run whose Codex or Assembly process hit its wall-clock limit. The
+/// task stays `started` so the next run retries it.
+fn handle_timeout(
+    config: &TaskAgentConfig,
+    selection: &SelectedTask,
+    run_id: &str,
+    process: &str,
+    note: &str,
+) -> Result<i32, DynError> {
+    increment_attempt_count(&config.tasks_path, &selection.task_id)?;
+    update_task_status(
+        &config.tasks_path,
+        &selection.task_id,
+        "started",
+        run_id,
+        note,
+    )?;
+    git_commit_progress(&config.workspace, &selection.title, &selection.task_id)?;
+    log_line(
+        "WARN",
+        "Process timed out",
+        &[
+            format!("task_id={}", selection.task_id),
+            format!("run_id={}", run_id),
+            format!("process={}", process),
+        ],
+    );
+    eprintln!("Timed out: {}", note);
+    Ok(TIMEOUT_EXIT_CODE)
+}
+
+/// Stores this run's Assembly and Codex wall-clock times (seconds) in
+/// `observability.elapsed_seconds`.
+fn record_elapsed(
+    tasks_path: &Path,
+    task_id: &str,
+    assembly: Option<Duration>,
+    codex: Option<Duration>,
+) -> Result<(), DynError> {
+    let mut elapsed = Map::new();
+    for (process, duration) in [("assembly", assembly), ("codex", codex)] {
+        if let Some(duration) = duration {
+            let seconds = (duration.as_secs_f64() * 10.0).round() / 10.0;
+            elapsed.insert(process.to_string(), json!(seconds));
+        }
+    }
+    if elapsed.is_empty() {
+        return Ok(());
+    }
+    let mut root = load_tasks_root(tasks_path)?;
+    let tasks = tasks_array_mut(&mut root).ok_or("Tasks file is not a list")?;
+    let task = tasks
+        .iter_mut()
+        .find(|task| task.get("task_id").and_then(Value::as_str) == Some(task_id))
+        .ok_or_else(|| format!("Task {} not found in {}", task_id, tasks_path.display()))?;
+    let obs = ensure_observability(task_object_mut(task)?);
+    obs.insert("elapsed_seconds".to_string(), Value::Object(elapsed));
+    write_tasks_root(tasks_path, &root)
+}
+
+fn append_baseline_note(note: &str, comparison: Option<&BaselineComparison>) -> String {
+    match comparison {
+        Some(comparison) => comparison.append_note(note),
+        None => note.to_string(),
+    }
+}
+
+/// A detached `git worktree` of a commit outside the workspace, so verification
+/// only sees committed changes. Removed on drop.
+struct CleanCheckout {
+    workspace: PathBuf,
+    path: PathBuf,
+}
+
+impl CleanCheckout {
+    fn create(
+        workspace: &Path,
+        commit: &str,
+        task_id: &str,
+        run_id: &str,
+    ) -> Result<Self, DynError> {
+        let path = std::env::temp_dir().join(format!("lever-verify-{}-{}", task_id, run_id));
+        if path.exists() {
+            fs::remove_dir_all(&path)?;
+        }
+        let path_arg = path.to_string_lossy().to_string();
+        git_status(
+            workspace,
+            &["worktree", "add", "--detach", &path_arg, commit],
+        )?;
+        log_line(
+            "INFO",
+            "Verifying in a clean checkout",
+            &[
+                format!("task_id={}", task_id),
+                format!("run_id={}", run_id),
+                format!("path={}", path.display()),
+            ],
+        );
+        Ok(Self {
+            workspace: workspace.to_path_buf(),
+            path,
+        })
+    }
+}
+
+impl Drop for CleanCheckout {
+    fn drop(&mut self) {
+        let path_arg = self.path.to_string_lossy().to_string();
+        if let Err(err) = git_status(
+            &self.workspace,
+            &["worktree", "remove", "--force", &path_arg],
+        ) {
+            eprintln!("Warning: failed to remove verification checkout: {}", err);
+            let _ = fs::remove_dir_all(&self.path);
+            let _ = git_status(&self.workspace, &["worktree", "prune"]);
+        }
+    }
+}
+
+/// Files the run changed: committed since `base`, modified in the working
+/// tree, or untracked. Lever's own `.ralph/` files and the tasks file are
+/// left out. `None` when git cannot tell.
+fn run_changed_files(workspace: &Path, base: &str, tasks_path: &Path) -> Option<BTreeSet<String>> {
+    let diff = git_output(workspace, &["diff", "--name-only", base]).ok()?;
+    let untracked = git_output(workspace, &["ls-files", "--others", "--exclude-standard"]).ok()?;
+    let tasks_rel = tasks_path
+        .strip_prefix(workspace)
+        .ok()
+        .map(|path| path.to_string_lossy().to_string());
+    Some(
+        diff.lines()
+            .chain(untracked.lines())
+            .map(str::trim)
+            .filter(|path| !path.is_empty() && !path.starts_with(".ralph/"))
+            .filter(|path| Some(*path) != tasks_rel.as_deref())
+            .map(str::to_string)
+            .collect(),
+    )
+}
+
+/// Re-runs a test command the agent reported as passing, appending its
+/// output to `verify.log`.
+fn rerun_claimed_command(
+    workspace: &Path,
+    run_dir: &Path,
+    sarif_dir: &Path,
+    command: &str,
+    retries: u32,
+    limits: StepLimits,
+    flaky: &mut Vec<FlakyCommand>,
+) -> Result<bool, DynError> {
+    let log_file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(run_dir.join("verify.log"))?;
+    let mut log = &log_file;
+    writeln!(log, "lever: claimed test command")?;
+    writeln!(log, "lever: $ {}", command)?;
+    let status = run_verification_step(&log_file, retries, limits, command, flaky, || {
+        bash_command(workspace, sarif_dir, command)
+    })?;
+    writeln!(
+        log,
+        "lever: {} ({})",
+        if status.success() { "passed" } else { "failed" },
+        status
+    )?;
+    Ok(status.success())
+}
+
+/// Counts this run's flaky verification steps in the workspace flaky ledger
+/// and warns about each one.
+fn record_flaky_commands(
+    workspace: &Path,
+    flaky: &[FlakyCommand],
+    task_id: &str,
+    run_id: &str,
+) -> Result<(), DynError> {
+    let commands: Vec<String> = flaky.iter().map(|flaky| flaky.command.clone()).collect();
+    let records = record_flakes(
+        workspace,
+        &commands,
+        task_id,
+        run_id,
+        &utc_timestamp("%Y-%m-%dT%H:%M:%SZ")?,
+    )?;
+    for record in records {
+        log_line(
+            "WARN",
+            "Flaky verification command",
+            &[
+                format!("task_id={}", task_id),
+                format!("run_id={}", run_id),
+                format!("command={}", record.command),
+                format!("flakes={}", record.flakes),
+            ],
+        );
+    }
+    Ok(())
+}
+
+/// Writes `claims.json`, logs each discrepancy, and adds them to the task's
+/// `observability.claim_discrepancies` tally.
+fn record_claims(
+    tasks_path: &Path,
+    report_path: &Path,
+    task_id: &str,
+    run_id: &str,
+    claims: &ClaimsReport,
+) -> Result<(), DynError> {
+    fs::write(
+        report_path,
+        serde_json::to_string_pretty(&claims.to_json(task_id, run_id))?,
+    )?;
+    let discrepancies = claims.discrepancies().count() as u64;
+    for check in claims.discrepancies() {
+        log_line(
+            "WARN",
+            "Result claim does not hold",
+            &[
+                format!("task_id={}", task_id),
+                format!("run_id={}", run_id),
+                format!("claim={}", check.kind),
+                format!("detail={}", check.detail),
+            ],
+        );
+    }
+    if discrepancies == 0 {
+        return Ok(());
+    }
+
+    let mut root = load_tasks_root(tasks_path)?;
+    let tasks = tasks_array_mut(&mut root).ok_or("Tasks file is not a list")?;
+    let task = tasks
+        .iter_mut()
+        .find(|task| task.get("task_id").and_then(Value::as_str) == Some(task_id))
+        .ok_or_else(|| format!("Task {} not found in {}", task_id, tasks_path.display()))?;
+    let obs = ensure_observability(task_object_mut(task)?);
+    let total = obs
+        .get("claim_discrepancies")
+        .and_then(Value::as_u64)
+        .unwrap_or(0)
+        + discrepancies;
+    obs.insert("claim_discrepancies".to_string(), Value::from(total));
+    write_tasks_root(tasks_path, &root)
+}
+
+/// Writes `dod-verification.json` and the per-item pass/fail summary in the
+/// task's `observability.dod_results`.
+fn record_dod_checks(
+    tasks_path: &Path,
+    report_path: &Path,
+    task_id: &str,
+    run_id: &str,
+    checks: &[DodCheck],
+) -> Result<(), DynError> {
+    let passed = checks.iter().filter(|check| check.passed()).count();
+    let report = json!({
+        "task_id": task_id,
+        "run_id": run_id,
+        "passed": passed,
+        "total": checks.len(),
+        "items": checks.iter().map(DodCheck::to_json).collect::<Vec<_>>(),
+    });
+    fs::write(report_path, serde_json::to_string_pretty(&report)?)?;
+
+    let mut root = load_tasks_root(tasks_path)?;
+    let tasks = tasks_array_mut(&mut root).ok_or("Tasks file is not a list")?;
+    let task = tasks
+        .iter_mut()
+        .find(|task| task.get("task_id").and_then(Value::as_str) == Some(task_id))
+        .ok_or_else(|| format!("Task {} not found in {}", task_id, tasks_path.display()))?;
+    let obs = ensure_observability(task_object_mut(task)?);
+    let results: Vec<Value> = checks
+        .iter()
+        .map(|check| json!({ "item": check.item, "passed": check.passed() }))
+        .collect();
+    obs.insert("dod_results".to_string(), Value::Array(results));
+    write_tasks_root(tasks_path, &root)?;
+
+    log_line(
+        if passed == checks.len() {
+            "INFO"
+        } else {
+            "WARN"
+        },
+        "Definition of done checks",
+        &[
+            format!("task_id={}", task_id),
+            format!("run_id={}", run_id),
+            format!("passed={}", passed),
+            format!("total={}", checks.len()),
+            format!("report={}", report_path.display()),
+        ],
+    );
+    Ok(())
+}
+
+/// Appends the result's accepted `follow_ups` to the tasks file as
+/// `unstarted` tasks linked to the parent through `parent_task_id`.
+fn append_follow_up_tasks(
+    tasks_path: &Path,
+    task_id: &str,
+    result: &Value,
+    run_id: &str,
+) -> Result<(), DynError> {
+    let mut root = load_tasks_root(tasks_path)?;
+    let tasks = tasks_array_mut(&mut root).ok_or("Tasks file is not a list")?;
+    let parent = tasks
+        .iter()
+        .find(|task| task.get("task_id").and_then(Value::as_str) == Some(task_id))
+        .ok_or_else(|| format!("Task {} not found in {}", task_id, tasks_path.display()))?;
+    let follow_ups = follow_up_tasks(result, parent, tasks);
+    for reason in &follow_ups.rejected {
+        log_line(
+            "WARN",
+            "Rejected follow-up task",
+            &[
+                format!("task_id={}", task_id),
+                format!("run_id={}", run_id),
+                format!("reason={}", reason),
+            ],
+        );
+    }
+    if follow_ups.accepted.is_empty() {
+        return Ok(());
+    }
+    for task in &follow_ups.accepted {
+        log_line(
+            "INFO",
+            "Added follow-up task",
+            &[
+                format!("task_id={}", task_id),
+                format!("run_id={}", run_id),
+                format!(
+                    "follow_up={}",
+                    task.get("task_id").and_then(Value::as_str).unwrap_or("")
+                ),
+            ],
+        );
+    }
+    tasks.extend(follow_ups.accepted);
+    write_tasks_root(tasks_path, &root)
+}
+
 fn task_object_mut(task: &mut Value) -> Result<&mut Map<String, Value>, DynError> {
     task.as_object_mut()
         .ok_or_else(|| "Task entry is not an object".to_string().into())
@@ -2074,199 +3464,318 @@ fn write_tasks_root(path: &Path, root: &Value) -> Result<(), DynError> {
 
 struct VerificationResult {
     ok: bool,
+    /// Whether the suite (task commands or detector) passed, before the
+    /// definition-of-done checks.
+    suite_ok: bool,
     log_command: Option<String>,
+    /// Auto-detector that chose `commands`; `None` for task commands.
+    detector: Option<&'static str>,
+    commands: Vec<String>,
+    dod_checks: Vec<DodCheck>,
+    flaky: Vec<FlakyCommand>,
+}
+
+/// A verification step that failed and then passed on a retry.
+struct FlakyCommand {
+    command: String,
+    attempts: u32,
 }
 
 impl VerificationResult {
     fn skipped() -> Self {
         Self {
             ok: true,
+            suite_ok: true,
             log_command: None,
+            detector: None,
+            commands: Vec::new(),
+            dod_checks: Vec::new(),
+            flaky: Vec::new(),
         }
     }
+
+    /// Per-step outcomes compared against the pre-run baseline.
+    fn steps(&self) -> Vec<StepOutcome> {
+        let mut steps = Vec::new();
+        if !self.commands.is_empty() {
+            steps.push(StepOutcome {
+                step: format!("verification: {}", self.commands.join("; ")),
+                passed: self.suite_ok,
+            });
+        }
+        steps.extend(self.dod_checks.iter().map(|check| StepOutcome {
+            step: format!("definition of done: {}", check.item),
+            passed: check.passed(),
+        }));
+        steps
+    }
+}
+
+/// Outcome of one definition-of-done item's `verify` command.
+struct DodCheck {
+    item: String,
+    command: String,
+    exit_code: Option<i32>,
+}
+
+impl DodCheck {
+    fn passed(&self) -> bool {
+        self.exit_code == Some(0)
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "item": self.item,
+            "command": self.command,
+            "passed": self.passed(),
+            "exit_code": self.exit_code,
+        })
+    }
 }
 
+/// Runs the task's verification suite, then every definition-of-done
+/// `verify` command on its own. All of them must pass.
 fn run_verification(
     workspace: &Path,
     run_dir: &Path,
+    sarif_dir: &Path,
+    task_verification_commands: &[String],
+    definition_of_done: &[DodItem],
+    settings: &VerificationConfig,
+    limits: StepLimits,
+) -> Result<VerificationResult, DynError> {
+    let mut result = run_verification_suite(
+        workspace,
+        run_dir,
+        sarif_dir,
+        task_verification_commands,
+        settings,
+        limits,
+    )?;
+    let checks: Vec<(&str, &str)> = definition_of_done
+        .iter()
+        .filter_map(|item| Some((item.text.as_str(), item.verify.as_deref()?)))
+        .collect();
+    if checks.is_empty() {
+        return Ok(result);
+    }
+
+    let log_file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(run_dir.join("verify.log"))?;
+    let mut log = &log_file;
+    for (item, command) in checks {
+        writeln!(log, "lever: definition of done: {}", item)?;
+        writeln!(log, "lever: $ {}", command)?;
+        let status = run_verification_step(
+            &log_file,
+            settings.retries,
+            limits,
+            command,
+            &mut result.flaky,
+            || bash_command(workspace, sarif_dir, command),
+        )?;
+        writeln!(
+            log,
+            "lever: {} ({})",
+            if status.success() { "passed" } else { "failed" },
+            status
+        )?;
+        result.dod_checks.push(DodCheck {
+            item: item.to_string(),
+            command: command.to_string(),
+            exit_code: status.code(),
+        });
+    }
+    result.ok = result.ok && result.dod_checks.iter().all(DodCheck::passed);
+    result.log_command = Some(match result.log_command {
+        Some(command) => format!("{} + definition_of_done.verify", command),
+        None => "definition_of_done.verify".to_string(),
+    });
+    Ok(result)
+}
+
+fn run_verification_suite(
+    workspace: &Path,
+    run_dir: &Path,
+    sarif_dir: &Path,
     task_verification_commands: &[String],
+    settings: &VerificationConfig,
+    limits: StepLimits,
 ) -> Result<VerificationResult, DynError> {
     let verify_log = run_dir.join("verify.log");
     let log_file = File::create(&verify_log)?;
-    let mut selected_cmd = None;
+    let mut log = &log_file;
+    fs::create_dir_all(sarif_dir)?;
+    let mut flaky = Vec::new();
 
     if !task_verification_commands.is_empty() {
-        let script = format!(
-            "set -euo pipefail\n{}\n",
-            task_verification_commands.join("\n")
-        );
-        let status = Command::new("bash")
-            .arg("-lc")
-            .arg(script)
-            .current_dir(workspace)
-            .stdout(log_file.try_clone()?)
-            .stderr(log_file)
-            .status()?;
+        let script = task_verification_commands.join("\n");
+        let status = run_verification_step(
+            &log_file,
+            settings.retries,
+            limits,
+            &task_verification_commands.join("; "),
+            &mut flaky,
+            || bash_command(workspace, sarif_dir, &script),
+        )?;
         return Ok(VerificationResult {
             ok: status.success(),
+            suite_ok: status.success(),
             log_command: Some("task.verification.commands".to_string()),
+            detector: None,
+            commands: task_verification_commands.to_vec(),
+            dod_checks: Vec::new(),
+            flaky,
         });
     }
 
-    if is_executable(&workspace.join("scripts/ci.sh")) {
-        selected_cmd = Some(vec!["./scripts/ci.sh".to_string()]);
-    } else if makefile_has_ci(&workspace.join("Makefile"))? {
-        selected_cmd = Some(vec!["make".to_string(), "ci".to_string()]);
-    } else if is_executable(&workspace.join("tests/run.sh")) {
-        selected_cmd = Some(vec!["./tests/run.sh".to_string()]);
-    } else if command_available("pytest") && has_python_tests(workspace)? {
-        selected_cmd = Some(vec!["pytest".to_string(), "-q".to_string()]);
-    }
-
-    let Some(cmd) = selected_cmd else {
-        return Ok(VerificationResult {
-            ok: true,
-            log_command: None,
-        });
+    let Some(suite) = verification_detectors::detect(
+        workspace,
+        settings.detectors.as_deref(),
+        &settings.disabled_detectors,
+    )?
+    else {
+        return Ok(VerificationResult::skipped());
     };
 
-    let mut command = Command::new(&cmd[0]);
-    if cmd.len() > 1 {
-        command.args(&cmd[1..]);
+    writeln!(log, "lever: verification detector: {}", suite.detector)?;
+    let mut ok = true;
+    for cmd in &suite.commands {
+        let label = cmd.join(" ");
+        writeln!(log, "lever: $ {}", label)?;
+        let status = run_verification_step(
+            &log_file,
+            settings.retries,
+            limits,
+            &label,
+            &mut flaky,
+            || {
+                let mut command = Command::new(&cmd[0]);
+                command
+                    .args(&cmd[1..])
+                    .current_dir(workspace)
+                    .env(SARIF_DIR_ENV, sarif_dir);
+                command
+            },
+        )?;
+        if !status.success() {
+            ok = false;
+            break;
+        }
     }
-    let status = command
-        .current_dir(workspace)
-        .stdout(log_file.try_clone()?)
-        .stderr(log_file)
-        .status()?;
 
-    let ok = status.success();
+    let commands: Vec<String> = suite.commands.iter().map(|cmd| cmd.join(" ")).collect();
     Ok(VerificationResult {
         ok,
-        log_command: Some(cmd.join(" ")),
+        suite_ok: ok,
+        log_command: Some(commands.join(" && ")),
+        detector: Some(suite.detector),
+        commands,
+        dod_checks: Vec::new(),
+        flaky,
     })
 }
 
-fn makefile_has_ci(path: &Path) -> Result<bool, DynError> {
-    if !path.is_file() {
-        return Ok(false);
-    }
-    let content = fs::read_to_string(path)?;
-    Ok(content
-        .lines()
-        .any(|line| line.trim_start().starts_with("ci:")))
+/// `bash -lc` with strict mode for a verification script.
+fn bash_command(workspace: &Path, sarif_dir: &Path, script: &str) -> Command {
+    let mut command = Command::new("bash");
+    command
+        .arg("-lc")
+        .arg(format!("set -euo pipefail\n{}\n", script))
+        .current_dir(workspace)
+        .env(SARIF_DIR_ENV, sarif_dir);
+    command
 }
 
-fn command_available(command: &str) -> bool {
-    Command::new(command)
-        .arg("--version")
-        .output()
-        .map(|out| out.status.success())
-        .unwrap_or(false)
+/// Applied to every verification step: the sandbox it runs in, if any, and
+/// the `timeouts.verification_seconds` deadline.
+#[derive(Clone, Copy)]
+struct StepLimits<'a> {
+    sandbox: Option<&'a Sandbox>,
+    deadline: Deadline,
 }
 
-fn has_python_tests(workspace: &Path) -> Result<bool, DynError> {
-    let root_markers = ["pytest.ini", "pyproject.toml", "setup.cfg", "tox.ini"];
-    if root_markers
-        .iter()
-        .any(|marker| workspace.join(marker).is_file())
-    {
-        return Ok(true);
-    }
+/// How a verification step ended.
+#[derive(Debug, Clone, Copy)]
+enum StepStatus {
+    Exited(ExitStatus),
+    /// Stopped at the `timeouts.verification_seconds` deadline.
+    TimedOut(u64),
+}
 
-    let tests_dir = workspace.join("tests");
-    if !tests_dir.is_dir() {
-        return Ok(false);
+impl StepStatus {
+    fn success(&self) -> bool {
+        matches!(self, Self::Exited(status) if status.success())
     }
-    dir_contains_py(&tests_dir)
-}
 
-fn dir_contains_py(path: &Path) -> Result<bool, DynError> {
-    for entry in fs::read_dir(path)? {
-        let entry = entry?;
-        let path = entry.path();
-        if path.is_dir() {
-            if dir_contains_py(&path)? {
-                return Ok(true);
-            }
-        } else if path.extension().and_then(|ext| ext.to_str()) == Some("py") {
-            return Ok(true);
+    fn code(&self) -> Option<i32> {
+        match self {
+            Self::Exited(status) => status.code(),
+            Self::TimedOut(_) => None,
         }
     }
-    Ok(false)
 }
 
-fn is_executable(path: &Path) -> bool {
-    if !path.is_file() {
-        return false;
-    }
-    #[cfg(unix)]
-    {
-        use std::os::unix::fs::PermissionsExt;
-        if let Ok(metadata) = fs::metadata(path) {
-            return metadata.permissions().mode() & 0o111 != 0;
+impl std::fmt::Display for StepStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Exited(status) => write!(f, "{}", status),
+            Self::TimedOut(seconds) => write!(f, "timed out after {}s", seconds),
         }
     }
-    true
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::ffi::OsString;
-
-    fn args_to_strings(args: Vec<OsString>) -> Vec<String> {
-        args.into_iter()
-            .map(|arg| arg.to_string_lossy().to_string())
-            .collect()
-    }
-
-    #[test]
-    fn assembly_command_args_include_expected_fields() {
-        let workspace = PathBuf::from("/repo");
-        let task_id = "T1";
-        let run_id = "run-123";
-        let paths = run_paths(&workspace, task_id, run_id);
-        let config = ContextCompileConfig {
-            enabled: true,
-            token_budget: 9001,
-            exclude_globs: vec![".git/**".to_string(), "node_modules/**".to_string()],
-            exclude_runtime_globs: vec![".ralph/**".to_string()],
-            ..Default::default()
+/// Runs one verification step in its own process group with its output
+/// appended to `log_file`, re-running it up to `retries` more times while it
+/// fails. A step past `limits.deadline` fails without a retry. A step that passes
+/// only on a retry is added to `flaky`.
+fn run_verification_step(
+    log_file: &File,
+    retries: u32,
+    limits: StepLimits,
+    label: &str,
+    flaky: &mut Vec<FlakyCommand>,
+    mut command: impl FnMut() -> Command,
+) -> Result<StepStatus, DynError> {
+    let mut log = log_file;
+    let mut attempts = 1;
+    loop {
+        let mut command = command();
+        if let Some(sandbox) = limits.sandbox {
+            command = sandbox.wrap(command);
+        }
+        let mut child = spawn_in_group(
+            command
+                .stdout(log_file.try_clone()?)
+                .stderr(log_file.try_clone()?),
+        )?;
+        let status = match wait_with_deadline(&mut child, limits.deadline, None)? {
+            WaitOutcome::Exited(status) => StepStatus::Exited(status),
+            WaitOutcome::TimedOut | WaitOutcome::Interrupted => {
+                let seconds = limits.deadline.timeout.unwrap_or_default().as_secs();
+                writeln!(log, "lever: timed out after {}s", seconds)?;
+                return Ok(StepStatus::TimedOut(seconds));
+            }
         };
-
-        let args = build_assembly_command_args(
-            &workspace,
-            task_id,
-            &paths.assembly_task_path,
-            &paths.pack_dir_abs,
-            &paths.assembly_summary_path,
-            &config,
-        );
-        let args = args_to_strings(args);
-
-        let expected = vec![
-            "build".to_string(),
-            "--repo".to_string(),
-            "/repo".to_string(),
-            "--task".to_string(),
-            format!("@{}", paths.assembly_task_path.display()),
-            "--task-id".to_string(),
-            "T1".to_string(),
-            "--out".to_string(),
-            paths.pack_dir_abs.display().to_string(),
-            "--token-budget".to_string(),
-            "9001".to_string(),
-            "--exclude".to_string(),
-            ".git/**".to_string(),
-            "--exclude".to_string(),
-            "node_modules/**".to_string(),
-            "--exclude-runtime".to_string(),
-            ".ralph/**".to_string(),
-            "--summary-json".to_string(),
-            paths.assembly_summary_path.display().to_string(),
-        ];
-
-        assert_eq!(args, expected);
+        if status.success() || attempts > retries {
+            if status.success() && attempts > 1 {
+                writeln!(log, "lever: flaky: passed on attempt {}", attempts)?;
+                flaky.push(FlakyCommand {
+                    command: label.to_string(),
+                    attempts,
+                });
+            }
+            return Ok(status);
+        }
+        attempts += 1;
+        writeln!(
+            log,
+            "lever: failed ({}); retrying (attempt {} of {})",
+            status,
+            attempts,
+            retries + 1
+        )?;
     }
 }
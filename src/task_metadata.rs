@@ -30,6 +30,52 @@ impl Display for TaskMetadataError {
 
 impl Error for TaskMetadataError {}
 
+/// A `definition_of_done` entry: plain text, or an `{"item", "verify"}`
+/// object whose shell command checks that item during verification.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DodItem {
+    pub text: String,
+    pub verify: Option<String>,
+}
+
+pub fn definition_of_done(raw: &Value) -> Vec<DodItem> {
+    let Some(Value::Array(items)) = raw.get("definition_of_done") else {
+        return Vec::new();
+    };
+    items
+        .iter()
+        .filter_map(|item| match item {
+            Value::String(text) => Some(DodItem {
+                text: text.clone(),
+                verify: None,
+            }),
+            Value::Object(map) => Some(DodItem {
+                text: map.get("item")?.as_str()?.to_string(),
+                verify: map
+                    .get("verify")
+                    .and_then(Value::as_str)
+                    .map(str::trim)
+                    .filter(|command| !command.is_empty())
+                    .map(str::to_string),
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+fn dod_item_valid(item: &Value) -> bool {
+    let non_empty = |value: Option<&Value>| matches!(value, Some(Value::String(text)) if !text.trim().is_empty());
+    match item {
+        Value::String(value) => !value.is_empty(),
+        Value::Object(map) => {
+            map.keys().all(|key| key == "item" || key == "verify")
+                && non_empty(map.get("item"))
+                && non_empty(map.get("verify"))
+        }
+        _ => false,
+    }
+}
+
 pub fn validate_task_metadata(task_id: &str, raw: &Value) -> Result<(), TaskMetadataError> {
     let title_valid = matches!(
         raw.get("title"),
@@ -37,13 +83,7 @@ pub fn validate_task_metadata(task_id: &str, raw: &Value) -> Result<(), TaskMeta
     );
 
     let dod_valid = match raw.get("definition_of_done") {
-        Some(Value::Array(items)) => {
-            !items.is_empty()
-                && items.iter().all(|item| match item {
-                    Value::String(value) => !value.is_empty(),
-                    _ => false,
-                })
-        }
+        Some(Value::Array(items)) => !items.is_empty() && items.iter().all(dod_item_valid),
         _ => false,
     };
 
@@ -81,3 +121,52 @@ pub fn validate_task_metadata(task_id: &str, raw: &Value) -> Result<(), TaskMeta
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn task(definition_of_done: Value) -> Value {
+        json!({
+            "title": "T",
+            "definition_of_done": definition_of_done,
+            "recommended": { "approach": "A" }
+        })
+    }
+
+    #[test]
+    fn definition_of_done_accepts_verifiable_items() {
+        let raw = task(json!([
+            "Docs updated",
+            { "item": "Parser handles strings", "verify": "cargo test parser" }
+        ]));
+        assert!(validate_task_metadata("T1", &raw).is_ok());
+        assert_eq!(
+            definition_of_done(&raw),
+            vec![
+                DodItem {
+                    text: "Docs updated".to_string(),
+                    verify: None
+                },
+                DodItem {
+                    text: "Parser handles strings".to_string(),
+                    verify: Some("cargo test parser".to_string())
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn definition_of_done_rejects_malformed_objects() {
+        for entry in [
+            json!({ "item": "No command" }),
+            json!({ "item": "", "verify": "true" }),
+            json!({ "item": "Extra", "verify": "true", "weight": 2 }),
+            json!(3),
+        ] {
+            let err = validate_task_metadata("T1", &task(json!([entry]))).unwrap_err();
+            assert_eq!(err.missing, vec!["definition_of_done"]);
+        }
+    }
+}
@@ -0,0 +1,558 @@
+use std::{error::Error, fs, path::Path};
+
+use jsonschema::validator_for;
+use serde_json::{json, Map, Value};
+
+type DynError = Box<dyn Error + Send + Sync + 'static>;
+
+pub const SCHEMA_PATH: &str = ".ralph/task_result.schema.json";
+/// Bumped whenever `result_schema` changes; stale schema files are rewritten.
+pub const RESULT_SCHEMA_VERSION: u64 = 2;
+/// Follow-ups accepted from one result; extra proposals are rejected.
+pub const MAX_FOLLOW_UPS_PER_RUN: usize = 5;
+const SCHEMA_TITLE: &str = "Lever task result";
+
+/// The output schema Codex must follow. `codex exec --output-schema` uses
+/// strict structured output, so every property is required; an empty list
+/// means nothing to report.
+pub fn result_schema() -> Value {
+    let strings = json!({ "type": "array", "items": { "type": "string" } });
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": format!("{} v{}", SCHEMA_TITLE, RESULT_SCHEMA_VERSION),
+        "type": "object",
+        "additionalProperties": false,
+        "required": [
+            "task_id", "outcome", "dod_met", "summary", "tests", "notes", "blockers",
+            "dod_items", "files_changed", "follow_ups"
+        ],
+        "properties": {
+            "task_id": { "type": "string" },
+            "outcome": { "type": "string", "enum": ["completed", "blocked", "started"] },
+            "dod_met": { "type": "boolean" },
+            "summary": { "type": "string" },
+            "tests": {
+                "type": "object",
+                "additionalProperties": false,
+                "required": ["ran", "commands", "passed"],
+                "properties": {
+                    "ran": { "type": "boolean" },
+                    "commands": strings,
+                    "passed": { "type": "boolean" }
+                }
+            },
+            "notes": { "type": "string" },
+            "blockers": strings,
+            "dod_items": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "additionalProperties": false,
+                    "required": ["item", "status", "evidence"],
+                    "properties": {
+                        "item": { "type": "string" },
+                        "status": { "type": "string", "enum": ["met", "partial", "not_met"] },
+                        "evidence": { "type": "string" }
+                    }
+                }
+            },
+            "files_changed": strings,
+            "follow_ups": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "additionalProperties": false,
+                    "required": ["title", "definition_of_done", "approach"],
+                    "properties": {
+                        "title": { "type": "string" },
+                        "definition_of_done": strings,
+                        "approach": { "type": "string" }
+                    }
+                }
+            }
+        }
+    })
+}
+
+/// The untitled schema Lever wrote before schemas were versioned.
+fn legacy_v1_schema() -> Value {
+    let strings = json!({ "type": "array", "items": { "type": "string" } });
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "type": "object",
+        "additionalProperties": false,
+        "required": ["task_id", "outcome", "dod_met", "summary", "tests", "notes", "blockers"],
+        "properties": {
+            "task_id": { "type": "string" },
+            "outcome": { "type": "string", "enum": ["completed", "blocked", "started"] },
+            "dod_met": { "type": "boolean" },
+            "summary": { "type": "string" },
+            "tests": {
+                "type": "object",
+                "additionalProperties": false,
+                "required": ["ran", "commands", "passed"],
+                "properties": {
+                    "ran": { "type": "boolean" },
+                    "commands": strings,
+                    "passed": { "type": "boolean" }
+                }
+            },
+            "notes": { "type": "string" },
+            "blockers": strings
+        }
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemaUpdate {
+    Created,
+    Current,
+    /// Replaced a schema file Lever wrote for an older version.
+    Upgraded {
+        from: u64,
+    },
+    /// Left an unversioned, newer, or customised schema file in place.
+    Custom,
+}
+
+/// Writes `SCHEMA_PATH` when it is missing or was written by Lever for an
+/// older schema version. Any other file is left alone.
+pub fn ensure_schema_file(workspace: &Path) -> Result<SchemaUpdate, DynError> {
+    let schema_path = workspace.join(SCHEMA_PATH);
+    let schema = result_schema();
+    let update = if schema_path.is_file() {
+        let existing = fs::read_to_string(&schema_path)
+            .ok()
+            .and_then(|raw| serde_json::from_str::<Value>(&raw).ok());
+        let Some(existing) = existing else {
+            return Ok(SchemaUpdate::Custom);
+        };
+        if existing == schema {
+            return Ok(SchemaUpdate::Current);
+        }
+        match lever_schema_version(&existing) {
+            Some(from) if from < RESULT_SCHEMA_VERSION => SchemaUpdate::Upgraded { from },
+            _ => return Ok(SchemaUpdate::Custom),
+        }
+    } else {
+        SchemaUpdate::Created
+    };
+
+    if let Some(parent) = schema_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut serialized = serde_json::to_string_pretty(&schema)?;
+    serialized.push('\n');
+    fs::write(schema_path, serialized)?;
+    Ok(update)
+}
+
+/// The schema file as it was before Codex ran.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResultSchema {
+    pub schema: Value,
+    raw: String,
+}
+
+impl ResultSchema {
+    /// Rewrites the schema file if it no longer matches the loaded copy.
+    /// Returns whether it had changed.
+    pub fn restore(&self, workspace: &Path) -> Result<bool, DynError> {
+        let schema_path = workspace.join(SCHEMA_PATH);
+        if fs::read_to_string(&schema_path).ok().as_deref() == Some(self.raw.as_str()) {
+            return Ok(false);
+        }
+        fs::write(schema_path, &self.raw)?;
+        Ok(true)
+    }
+}
+
+/// Reads the schema file Codex will be given. Callers load it before Codex
+/// runs and validate against this copy, so edits the agent makes to the file
+/// cannot loosen the check on its own result.
+pub fn load_result_schema(workspace: &Path) -> Result<ResultSchema, DynError> {
+    let schema_path = workspace.join(SCHEMA_PATH);
+    let raw = fs::read_to_string(&schema_path)
+        .map_err(|err| format!("Failed to read {}: {}", schema_path.display(), err))?;
+    let schema = serde_json::from_str(&raw)
+        .map_err(|err| format!("Invalid result schema {}: {}", schema_path.display(), err))?;
+    Ok(ResultSchema { schema, raw })
+}
+
+/// Parses `result.json` and checks it against `schema` and the selected
+/// task. The error lists every problem found.
+pub fn validate_result(raw: &str, schema: &Value, task_id: &str) -> Result<Value, Vec<String>> {
+    let result: Value =
+        serde_json::from_str(raw).map_err(|err| vec![format!("not valid JSON ({})", err)])?;
+    let validator =
+        validator_for(schema).map_err(|err| vec![format!("invalid result schema ({})", err)])?;
+    let mut errors: Vec<String> = validator
+        .iter_errors(&result)
+        .map(|error| {
+            let path = error.instance_path().to_string();
+            if path.is_empty() {
+                error.to_string()
+            } else {
+                format!("{}: {}", path, error)
+            }
+        })
+        .collect();
+    if let Some(reported) = result.get("task_id").and_then(Value::as_str) {
+        if reported != task_id {
+            errors.push(format!(
+                "task_id {:?} does not match the selected task {:?}",
+                reported, task_id
+            ));
+        }
+    }
+    if errors.is_empty() {
+        Ok(result)
+    } else {
+        Err(errors)
+    }
+}
+
+/// The version in a `Lever task result v<N>` title, or 1 for the untitled
+/// schema Lever used to write.
+fn lever_schema_version(schema: &Value) -> Option<u64> {
+    if *schema == legacy_v1_schema() {
+        return Some(1);
+    }
+    schema
+        .get("title")?
+        .as_str()?
+        .strip_prefix(SCHEMA_TITLE)?
+        .trim()
+        .strip_prefix('v')?
+        .parse()
+        .ok()
+}
+
+/// One `dod_items` entry from `result.json`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DodItemStatus {
+    pub item: String,
+    pub status: String,
+}
+
+pub fn dod_items(result: &Value) -> Vec<DodItemStatus> {
+    result
+        .get("dod_items")
+        .and_then(Value::as_array)
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|entry| {
+                    Some(DodItemStatus {
+                        item: entry.get("item")?.as_str()?.trim().to_string(),
+                        status: entry.get("status")?.as_str()?.trim().to_string(),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+pub fn files_changed(result: &Value) -> Vec<String> {
+    result
+        .get("files_changed")
+        .and_then(Value::as_array)
+        .map(|files| {
+            files
+                .iter()
+                .filter_map(Value::as_str)
+                .map(str::trim)
+                .filter(|file| !file.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Follow-up tasks built from a result's `follow_ups`, plus the reason each
+/// rejected proposal was dropped.
+#[derive(Debug, Default, PartialEq)]
+pub struct FollowUps {
+    pub accepted: Vec<Value>,
+    pub rejected: Vec<String>,
+}
+
+/// Turns `follow_ups` into `unstarted` tasks linked to `parent` through
+/// `parent_task_id`. Proposals need a title, definition of done, and
+/// approach, and must not repeat the title of an existing task.
+pub fn follow_up_tasks(result: &Value, parent: &Value, tasks: &[Value]) -> FollowUps {
+    let mut follow_ups = FollowUps::default();
+    let Some(proposals) = result.get("follow_ups").and_then(Value::as_array) else {
+        return follow_ups;
+    };
+    let parent_id = parent.get("task_id").and_then(Value::as_str).unwrap_or("");
+    let model = parent.get("model").cloned().unwrap_or(Value::Null);
+    let mut titles: Vec<String> = tasks
+        .iter()
+        .filter_map(|task| task.get("title").and_then(Value::as_str))
+        .map(normalized_title)
+        .collect();
+    let mut ids: Vec<String> = tasks
+        .iter()
+        .filter_map(|task| task.get("task_id").and_then(Value::as_str))
+        .map(str::to_string)
+        .collect();
+
+    for (position, proposal) in proposals.iter().enumerate() {
+        let text = |key: &str| {
+            proposal
+                .get(key)
+                .and_then(Value::as_str)
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+        };
+        let title = text("title");
+        let approach = text("approach");
+        let definition_of_done: Vec<&str> = proposal
+            .get("definition_of_done")
+            .and_then(Value::as_array)
+            .map(|items| {
+                items
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .map(str::trim)
+                    .filter(|item| !item.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+        let (Some(title), Some(approach), false) = (title, approach, definition_of_done.is_empty())
+        else {
+            follow_ups.rejected.push(format!(
+                "follow_ups[{}]: needs a title, definition_of_done, and approach",
+                position
+            ));
+            continue;
+        };
+        if titles.contains(&normalized_title(title)) {
+            follow_ups.rejected.push(format!(
+                "follow_ups[{}]: duplicate title {:?}",
+                position, title
+            ));
+            continue;
+        }
+        if follow_ups.accepted.len() == MAX_FOLLOW_UPS_PER_RUN {
+            follow_ups.rejected.push(format!(
+                "follow_ups[{}]: more than {} follow-ups",
+                position, MAX_FOLLOW_UPS_PER_RUN
+            ));
+            continue;
+        }
+
+        let task_id = (1..)
+            .map(|n| format!("{}-FU{}", parent_id, n))
+            .find(|id| !ids.contains(id))
+            .expect("unbounded id range");
+        let mut task = Map::new();
+        task.insert("task_id".to_string(), json!(task_id));
+        task.insert("title".to_string(), json!(title));
+        task.insert("status".to_string(), json!("unstarted"));
+        task.insert("model".to_string(), model.clone());
+        task.insert("definition_of_done".to_string(), json!(definition_of_done));
+        task.insert("recommended".to_string(), json!({ "approach": approach }));
+        task.insert("parent_task_id".to_string(), json!(parent_id));
+        titles.push(normalized_title(title));
+        ids.push(task_id);
+        follow_ups.accepted.push(Value::Object(task));
+    }
+    follow_ups
+}
+
+fn normalized_title(title: &str) -> String {
+    title.trim().to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_workspace(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("lever-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn schema_file_is_created_then_upgraded_when_stale() {
+        let workspace = temp_workspace("result-schema");
+        assert_eq!(
+            ensure_schema_file(&workspace).unwrap(),
+            SchemaUpdate::Created
+        );
+        assert_eq!(
+            ensure_schema_file(&workspace).unwrap(),
+            SchemaUpdate::Current
+        );
+
+        let path = workspace.join(SCHEMA_PATH);
+        fs::write(&path, legacy_v1_schema().to_string()).unwrap();
+        assert_eq!(
+            ensure_schema_file(&workspace).unwrap(),
+            SchemaUpdate::Upgraded { from: 1 }
+        );
+        let mut old = result_schema();
+        old["title"] = json!("Lever task result v1");
+        fs::write(&path, old.to_string()).unwrap();
+        assert_eq!(
+            ensure_schema_file(&workspace).unwrap(),
+            SchemaUpdate::Upgraded { from: 1 }
+        );
+        let written: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(written, result_schema());
+        let _ = fs::remove_dir_all(&workspace);
+    }
+
+    #[test]
+    fn custom_schema_files_are_left_in_place() {
+        let workspace = temp_workspace("result-schema-custom");
+        let path = workspace.join(SCHEMA_PATH);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+
+        let mut customised = result_schema();
+        customised["properties"]["ticket"] = json!({ "type": "string" });
+        for custom in [
+            json!({ "type": "object", "required": ["task_id"] }).to_string(),
+            customised.to_string(),
+            "not json".to_string(),
+        ] {
+            fs::write(&path, &custom).unwrap();
+            assert_eq!(
+                ensure_schema_file(&workspace).unwrap(),
+                SchemaUpdate::Custom
+            );
+            assert_eq!(fs::read_to_string(&path).unwrap(), custom);
+        }
+        let _ = fs::remove_dir_all(&workspace);
+    }
+
+    #[test]
+    fn loaded_schema_survives_edits_to_the_file() {
+        let workspace = temp_workspace("result-schema-restore");
+        ensure_schema_file(&workspace).unwrap();
+        let loaded = load_result_schema(&workspace).unwrap();
+        assert_eq!(loaded.schema, result_schema());
+        assert!(!loaded.restore(&workspace).unwrap());
+
+        let path = workspace.join(SCHEMA_PATH);
+        fs::write(&path, "{}").unwrap();
+        assert!(loaded.restore(&workspace).unwrap());
+        assert_eq!(load_result_schema(&workspace).unwrap(), loaded);
+
+        fs::write(&path, "{").unwrap();
+        assert!(load_result_schema(&workspace)
+            .unwrap_err()
+            .to_string()
+            .starts_with("Invalid result schema"));
+        let _ = fs::remove_dir_all(&workspace);
+    }
+
+    #[test]
+    fn follow_ups_become_linked_unstarted_tasks() {
+        let parent = json!({ "task_id": "T1", "title": "Parent", "model": "gpt-5.1-codex" });
+        let tasks = vec![
+            parent.clone(),
+            json!({ "task_id": "T1-FU1", "title": "Earlier follow-up" }),
+        ];
+        let result = json!({
+            "follow_ups": [
+                { "title": "Add docs", "definition_of_done": ["Docs exist"], "approach": "Write them" },
+                { "title": "earlier follow-up ", "definition_of_done": ["x"], "approach": "y" },
+                { "title": "No DoD", "definition_of_done": [" "], "approach": "y" },
+                { "title": "Add tests", "definition_of_done": ["Tests pass"], "approach": "Write them" }
+            ]
+        });
+        let follow_ups = follow_up_tasks(&result, &parent, &tasks);
+        let ids: Vec<&str> = follow_ups
+            .accepted
+            .iter()
+            .map(|task| task["task_id"].as_str().unwrap())
+            .collect();
+        assert_eq!(ids, vec!["T1-FU2", "T1-FU3"]);
+        assert_eq!(follow_ups.accepted[0]["parent_task_id"], "T1");
+        assert_eq!(follow_ups.accepted[0]["status"], "unstarted");
+        assert_eq!(follow_ups.accepted[0]["model"], "gpt-5.1-codex");
+        assert_eq!(
+            follow_ups.accepted[0]["recommended"],
+            json!({ "approach": "Write them" })
+        );
+        assert_eq!(follow_ups.rejected.len(), 2);
+        assert!(follow_ups.rejected[0].contains("duplicate title"));
+
+        assert_eq!(
+            follow_up_tasks(&json!({}), &parent, &tasks),
+            FollowUps::default()
+        );
+    }
+
+    fn valid_result(task_id: &str) -> Value {
+        json!({
+            "task_id": task_id,
+            "outcome": "completed",
+            "dod_met": true,
+            "summary": "ok",
+            "tests": { "ran": true, "commands": ["cargo test"], "passed": true },
+            "notes": "",
+            "blockers": [],
+            "dod_items": [],
+            "files_changed": [],
+            "follow_ups": []
+        })
+    }
+
+    #[test]
+    fn result_validation_reports_schema_and_task_id_problems() {
+        let schema = result_schema();
+        let raw = valid_result("T1").to_string();
+        assert_eq!(
+            validate_result(&raw, &schema, "T1").unwrap(),
+            valid_result("T1")
+        );
+
+        let mut v1_shape = valid_result("T1");
+        v1_shape.as_object_mut().unwrap().remove("follow_ups");
+        let errors = validate_result(&v1_shape.to_string(), &schema, "T1").unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("follow_ups"), "{:?}", errors);
+
+        let errors = validate_result(&raw, &schema, "T2").unwrap_err();
+        assert_eq!(
+            errors,
+            vec!["task_id \"T1\" does not match the selected task \"T2\"".to_string()]
+        );
+
+        let mut broken = valid_result("T1");
+        broken["dod_met"] = json!("yes");
+        broken.as_object_mut().unwrap().remove("summary");
+        let errors = validate_result(&broken.to_string(), &schema, "T1").unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert!(errors.iter().any(|error| error.starts_with("/dod_met: ")));
+
+        let errors = validate_result("All done!", &schema, "T1").unwrap_err();
+        assert!(errors[0].starts_with("not valid JSON"));
+    }
+
+    #[test]
+    fn dod_items_and_files_changed_tolerate_missing_fields() {
+        let result = json!({
+            "dod_items": [
+                { "item": "Tests pass", "status": "met", "evidence": "cargo test" },
+                { "item": "Docs" }
+            ],
+            "files_changed": ["src/lib.rs", " "]
+        });
+        assert_eq!(
+            dod_items(&result),
+            vec![DodItemStatus {
+                item: "Tests pass".to_string(),
+                status: "met".to_string()
+            }]
+        );
+        assert_eq!(files_changed(&result), vec!["src/lib.rs".to_string()]);
+        assert!(dod_items(&json!({})).is_empty());
+    }
+}
@@ -0,0 +1,380 @@
+use std::{collections::BTreeSet, error::Error, fs, path::Path};
+
+use serde_json::{json, Value};
+
+type DynError = Box<dyn Error + Send + Sync + 'static>;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TamperKind {
+    Modified,
+    Removed,
+    Added,
+    Unreadable,
+}
+
+impl TamperKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            TamperKind::Modified => "modified",
+            TamperKind::Removed => "removed",
+            TamperKind::Added => "added",
+            TamperKind::Unreadable => "unreadable",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TamperEvent {
+    pub task_id: String,
+    pub kind: TamperKind,
+    pub fields: Vec<String>,
+}
+
+impl TamperEvent {
+    pub fn describe(&self) -> String {
+        if self.fields.is_empty() {
+            format!("{}:{}", self.task_id, self.kind.label())
+        } else {
+            format!(
+                "{}:{}({})",
+                self.task_id,
+                self.kind.label(),
+                self.fields.join(",")
+            )
+        }
+    }
+}
+
+/// Byte-exact copy of the tasks file taken before the agent runs.
+pub struct TasksFileSnapshot {
+    raw: String,
+    root: Value,
+}
+
+impl TasksFileSnapshot {
+    pub fn capture(path: &Path) -> Result<Self, DynError> {
+        let raw = fs::read_to_string(path)
+            .map_err(|err| format!("Failed to read tasks file {}: {}", path.display(), err))?;
+        let root = serde_json::from_str(&raw)?;
+        Ok(Self { raw, root })
+    }
+
+    pub fn detect(&self, path: &Path) -> Vec<TamperEvent> {
+        let current = fs::read_to_string(path)
+            .ok()
+            .and_then(|raw| serde_json::from_str::<Value>(&raw).ok());
+        match current {
+            Some(current) => diff_tasks_roots(&self.root, &current),
+            None => vec![TamperEvent {
+                task_id: "*".to_string(),
+                kind: TamperKind::Unreadable,
+                fields: Vec::new(),
+            }],
+        }
+    }
+
+    /// Reverts the fields, entries, and additions named in `events` and
+    /// keeps every other part of the current file. An unreadable file or a
+    /// layout change is reverted to the snapshot as a whole.
+    pub fn restore(&self, path: &Path, events: &[TamperEvent]) -> Result<(), DynError> {
+        let restored = fs::read_to_string(path)
+            .ok()
+            .and_then(|raw| serde_json::from_str::<Value>(&raw).ok())
+            .and_then(|current| restore_events(&self.root, current, events));
+        let contents = match restored {
+            Some(root) => serde_json::to_string_pretty(&root)?,
+            None => self.raw.clone(),
+        };
+        fs::write(path, contents).map_err(|err| {
+            format!("Failed to restore tasks file {}: {}", path.display(), err).into()
+        })
+    }
+}
+
+/// Applies the snapshot's values for each event to `after`. `None` when the
+/// events cannot be reverted entry by entry.
+fn restore_events(before: &Value, mut after: Value, events: &[TamperEvent]) -> Option<Value> {
+    if events
+        .iter()
+        .any(|event| event.kind == TamperKind::Unreadable || event.task_id == "*")
+    {
+        return None;
+    }
+    let before_tasks = task_entries(before)?;
+    let after_tasks = task_entries_mut(&mut after)?;
+    let find = |tasks: &[Value], key: &str| {
+        tasks
+            .iter()
+            .enumerate()
+            .position(|(index, entry)| entry_key(entry, index) == key)
+    };
+
+    for event in events
+        .iter()
+        .filter(|event| event.kind == TamperKind::Modified)
+    {
+        let original = &before_tasks[find(before_tasks, &event.task_id)?];
+        let index = find(after_tasks, &event.task_id)?;
+        let (Some(original_fields), Some(current_fields)) =
+            (original.as_object(), after_tasks[index].as_object_mut())
+        else {
+            after_tasks[index] = original.clone();
+            continue;
+        };
+        for field in &event.fields {
+            match original_fields.get(field) {
+                Some(value) => {
+                    current_fields.insert(field.clone(), value.clone());
+                }
+                None => {
+                    current_fields.remove(field);
+                }
+            }
+        }
+    }
+
+    let added: BTreeSet<&str> = events
+        .iter()
+        .filter(|event| event.kind == TamperKind::Added)
+        .map(|event| event.task_id.as_str())
+        .collect();
+    let mut index = 0;
+    after_tasks.retain(|entry| {
+        let keep = !added.contains(entry_key(entry, index).as_str());
+        index += 1;
+        keep
+    });
+
+    for event in events
+        .iter()
+        .filter(|event| event.kind == TamperKind::Removed)
+    {
+        let position = find(before_tasks, &event.task_id)?;
+        let at = position.min(after_tasks.len());
+        after_tasks.insert(at, before_tasks[position].clone());
+    }
+    Some(after)
+}
+
+pub fn diff_tasks_roots(before: &Value, after: &Value) -> Vec<TamperEvent> {
+    let (Some(before_tasks), Some(after_tasks)) = (task_entries(before), task_entries(after))
+    else {
+        if before == after {
+            return Vec::new();
+        }
+        return vec![TamperEvent {
+            task_id: "*".to_string(),
+            kind: TamperKind::Unreadable,
+            fields: Vec::new(),
+        }];
+    };
+
+    let mut events = Vec::new();
+    for (index, entry) in before_tasks.iter().enumerate() {
+        let key = entry_key(entry, index);
+        let counterpart = after_tasks
+            .iter()
+            .enumerate()
+            .find(|(idx, candidate)| entry_key(candidate, *idx) == key)
+            .map(|(_, candidate)| candidate);
+        match counterpart {
+            None => events.push(TamperEvent {
+                task_id: key,
+                kind: TamperKind::Removed,
+                fields: Vec::new(),
+            }),
+            Some(candidate) if candidate != entry => events.push(TamperEvent {
+                task_id: key,
+                kind: TamperKind::Modified,
+                fields: changed_fields(entry, candidate),
+            }),
+            Some(_) => {}
+        }
+    }
+
+    for (index, entry) in after_tasks.iter().enumerate() {
+        let key = entry_key(entry, index);
+        let known = before_tasks
+            .iter()
+            .enumerate()
+            .any(|(idx, candidate)| entry_key(candidate, idx) == key);
+        if !known {
+            events.push(TamperEvent {
+                task_id: key,
+                kind: TamperKind::Added,
+                fields: Vec::new(),
+            });
+        }
+    }
+
+    if events.is_empty() && before != after {
+        events.push(TamperEvent {
+            task_id: "*".to_string(),
+            kind: TamperKind::Modified,
+            fields: vec!["layout".to_string()],
+        });
+    }
+    events
+}
+
+pub fn write_tamper_report(
+    path: &Path,
+    run_id: &str,
+    tasks_path: &Path,
+    events: &[TamperEvent],
+) -> Result<(), DynError> {
+    let entries: Vec<Value> = events
+        .iter()
+        .map(|event| {
+            json!({
+                "task_id": event.task_id,
+                "kind": event.kind.label(),
+                "fields": event.fields,
+            })
+        })
+        .collect();
+    let payload = json!({
+        "run_id": run_id,
+        "tasks_path": tasks_path.display().to_string(),
+        "action": "restored",
+        "events": entries,
+    });
+    fs::write(path, serde_json::to_string_pretty(&payload)?)?;
+    Ok(())
+}
+
+fn task_entries(root: &Value) -> Option<&Vec<Value>> {
+    match root {
+        Value::Array(items) => Some(items),
+        Value::Object(map) => map.get("tasks").and_then(Value::as_array),
+        _ => None,
+    }
+}
+
+fn task_entries_mut(root: &mut Value) -> Option<&mut Vec<Value>> {
+    match root {
+        Value::Array(items) => Some(items),
+        Value::Object(map) => map.get_mut("tasks").and_then(Value::as_array_mut),
+        _ => None,
+    }
+}
+
+fn entry_key(entry: &Value, index: usize) -> String {
+    match entry.get("task_id").and_then(Value::as_str) {
+        Some(task_id) if !task_id.is_empty() => task_id.to_string(),
+        _ => format!("#{}", index),
+    }
+}
+
+fn changed_fields(before: &Value, after: &Value) -> Vec<String> {
+    let (Some(before), Some(after)) = (before.as_object(), after.as_object()) else {
+        return vec!["entry".to_string()];
+    };
+    let keys: BTreeSet<&String> = before.keys().chain(after.keys()).collect();
+    keys.into_iter()
+        .filter(|key| before.get(*key) != after.get(*key))
+        .map(|key| key.to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn root(tasks: Value) -> Value {
+        json!({ "tasks": tasks })
+    }
+
+    #[test]
+    fn diff_reports_modified_fields() {
+        let before = root(json!([
+            { "task_id": "T1", "status": "started", "definition_of_done": ["a", "b"] }
+        ]));
+        let after = root(json!([
+            { "task_id": "T1", "status": "completed", "definition_of_done": ["a"] }
+        ]));
+
+        let events = diff_tasks_roots(&before, &after);
+        assert_eq!(
+            events,
+            vec![TamperEvent {
+                task_id: "T1".to_string(),
+                kind: TamperKind::Modified,
+                fields: vec!["definition_of_done".to_string(), "status".to_string()],
+            }]
+        );
+    }
+
+    #[test]
+    fn diff_reports_removed_and_added_tasks() {
+        let before = root(json!([{ "task_id": "T1" }, { "task_id": "T2" }]));
+        let after = root(json!([{ "task_id": "T1" }, { "task_id": "T3" }]));
+
+        let events = diff_tasks_roots(&before, &after);
+        let described: Vec<String> = events.iter().map(TamperEvent::describe).collect();
+        assert_eq!(described, vec!["T2:removed", "T3:added"]);
+    }
+
+    #[test]
+    fn diff_is_empty_for_identical_roots() {
+        let before = root(json!([{ "task_id": "T1", "status": "started" }]));
+        assert!(diff_tasks_roots(&before, &before.clone()).is_empty());
+    }
+
+    #[test]
+    fn restore_reverts_only_the_tampered_fields() {
+        let dir = std::env::temp_dir().join(format!("lever-tasks-guard-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("prd.json");
+        let before = root(json!([
+            { "task_id": "T1", "status": "started", "definition_of_done": ["a", "b"] },
+            { "task_id": "T2", "status": "unstarted" },
+            { "task_id": "T3", "status": "unstarted" }
+        ]));
+        fs::write(&path, serde_json::to_string(&before).unwrap()).unwrap();
+        let snapshot = TasksFileSnapshot::capture(&path).unwrap();
+
+        let tampered = root(json!([
+            { "task_id": "T1", "status": "completed", "notes": "done" },
+            { "task_id": "T3", "status": "unstarted" },
+            { "task_id": "T4", "status": "unstarted" }
+        ]));
+        fs::write(&path, serde_json::to_string(&tampered).unwrap()).unwrap();
+        let events = snapshot.detect(&path);
+        let described: Vec<String> = events.iter().map(TamperEvent::describe).collect();
+        assert_eq!(
+            described,
+            vec![
+                "T1:modified(definition_of_done,notes,status)",
+                "T2:removed",
+                "T4:added"
+            ]
+        );
+
+        // An edit outside the reported events, made after detection, is kept.
+        let mut current = tampered.clone();
+        current["tasks"][1]["observability"] = json!({ "last_note": "kept" });
+        fs::write(&path, serde_json::to_string(&current).unwrap()).unwrap();
+        snapshot.restore(&path, &events).unwrap();
+
+        let restored: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        let mut expected = before.clone();
+        expected["tasks"][2]["observability"] = json!({ "last_note": "kept" });
+        assert_eq!(restored, expected);
+
+        fs::write(&path, "not json").unwrap();
+        let events = snapshot.detect(&path);
+        snapshot.restore(&path, &events).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), snapshot.raw);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn diff_flags_unreadable_layout() {
+        let before = root(json!([{ "task_id": "T1" }]));
+        let after = json!({ "items": [] });
+        let events = diff_tasks_roots(&before, &after);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].kind, TamperKind::Unreadable);
+    }
+}
@@ -0,0 +1,94 @@
+use tiktoken_rs::o200k_base_singleton;
+
+/// Counts prompt tokens. Implementations must work offline.
+pub trait TokenEstimator: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn count(&self, text: &str) -> u64;
+}
+
+/// `o200k_base` BPE encoding; the vocabulary is compiled into the binary.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct BpeEstimator;
+
+impl TokenEstimator for BpeEstimator {
+    fn name(&self) -> &'static str {
+        "o200k_base"
+    }
+
+    fn count(&self, text: &str) -> u64 {
+        if text.is_empty() {
+            return 0;
+        }
+        o200k_base_singleton().encode_ordinary(text).len() as u64
+    }
+}
+
+/// Legacy heuristic (bytes / 4), kept for callers that cannot afford BPE.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ByteRatioEstimator;
+
+impl TokenEstimator for ByteRatioEstimator {
+    fn name(&self) -> &'static str {
+        "bytes/4"
+    }
+
+    fn count(&self, text: &str) -> u64 {
+        (text.len() as u64).div_ceil(4)
+    }
+}
+
+pub fn default_estimator() -> &'static dyn TokenEstimator {
+    static BPE: BpeEstimator = BpeEstimator;
+    &BPE
+}
+
+/// Returns the longest prefix of whole lines that fits in `max_tokens`.
+pub fn truncate_to_tokens(estimator: &dyn TokenEstimator, text: &str, max_tokens: u64) -> String {
+    if estimator.count(text) <= max_tokens {
+        return text.to_string();
+    }
+    let lines: Vec<&str> = text.split_inclusive('\n').collect();
+    let (mut low, mut high) = (0usize, lines.len());
+    while low < high {
+        let mid = (low + high).div_ceil(2);
+        if estimator.count(&lines[..mid].concat()) <= max_tokens {
+            low = mid;
+        } else {
+            high = mid - 1;
+        }
+    }
+    lines[..low].concat()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bpe_estimator_counts_known_text() {
+        let estimator = default_estimator();
+        assert_eq!(estimator.name(), "o200k_base");
+        assert_eq!(estimator.count(""), 0);
+        assert_eq!(estimator.count("hello world"), 2);
+        let code = "fn main() {\n    println!(\"hello\");\n}\n";
+        let bpe = estimator.count(code);
+        assert!(bpe > 0 && bpe < code.len() as u64);
+    }
+
+    #[test]
+    fn byte_ratio_estimator_rounds_up() {
+        assert_eq!(ByteRatioEstimator.count("abcde"), 2);
+        assert_eq!(ByteRatioEstimator.count(""), 0);
+    }
+
+    #[test]
+    fn truncate_keeps_whole_lines_within_budget() {
+        let text = "alpha beta\ngamma delta\nepsilon zeta\n";
+        let estimator = default_estimator();
+        let first_line = estimator.count("alpha beta\n");
+        let truncated = truncate_to_tokens(estimator, text, first_line);
+        assert_eq!(truncated, "alpha beta\n");
+        assert_eq!(truncate_to_tokens(estimator, text, 10_000), text);
+        assert_eq!(truncate_to_tokens(estimator, text, 0), "");
+    }
+}
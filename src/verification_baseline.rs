@@ -0,0 +1,209 @@
+use std::{
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+type DynError = Box<dyn Error + Send + Sync + 'static>;
+
+pub const BASELINE_CACHE_DIR: &str = ".ralph/cache/baselines";
+
+/// Whether one verification step (the suite or a definition-of-done
+/// `verify` command) passed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StepOutcome {
+    pub step: String,
+    pub passed: bool,
+}
+
+/// Verification results for a commit before the agent ran.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Baseline {
+    pub commit: String,
+    pub steps: Vec<StepOutcome>,
+}
+
+/// Cache key for a baseline: the commit SHA plus a hash of the verification
+/// setup (`setup`), since tasks on one commit can verify differently.
+pub fn baseline_cache_key(commit: &str, setup: &Value) -> Result<String, DynError> {
+    let digest = format!(
+        "{:x}",
+        Sha256::digest(serde_json::to_string(setup)?.as_bytes())
+    );
+    Ok(format!("{}-{}", commit, &digest[..16]))
+}
+
+pub fn load_cached_baseline(workspace: &Path, key: &str) -> Option<Baseline> {
+    let raw = fs::read_to_string(cache_path(workspace, key)).ok()?;
+    serde_json::from_str(&raw).ok()
+}
+
+pub fn store_baseline(workspace: &Path, key: &str, baseline: &Baseline) -> Result<(), DynError> {
+    let path = cache_path(workspace, key);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, serde_json::to_string_pretty(baseline)?).map_err(|err| {
+        format!(
+            "Failed to write verification baseline {}: {}",
+            path.display(),
+            err
+        )
+    })?;
+    Ok(())
+}
+
+fn cache_path(workspace: &Path, key: &str) -> PathBuf {
+    workspace
+        .join(BASELINE_CACHE_DIR)
+        .join(format!("{}.json", key))
+}
+
+/// One step's post-run result next to its baseline.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StepComparison {
+    pub step: String,
+    pub baseline: Option<bool>,
+    pub after: bool,
+    pub status: &'static str,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BaselineComparison {
+    pub steps: Vec<StepComparison>,
+}
+
+impl BaselineComparison {
+    /// Classifies each post-run step: `newly_failing` (passed or was absent
+    /// before), `still_failing`, `fixed`, or `passing`.
+    pub fn new(baseline: &Baseline, after: &[StepOutcome]) -> Self {
+        let steps = after
+            .iter()
+            .map(|outcome| {
+                let before = baseline
+                    .steps
+                    .iter()
+                    .find(|step| step.step == outcome.step)
+                    .map(|step| step.passed);
+                let status = match (before, outcome.passed) {
+                    (Some(false), false) => "still_failing",
+                    (_, false) => "newly_failing",
+                    (Some(false), true) => "fixed",
+                    (_, true) => "passing",
+                };
+                StepComparison {
+                    step: outcome.step.clone(),
+                    baseline: before,
+                    after: outcome.passed,
+                    status,
+                }
+            })
+            .collect();
+        Self { steps }
+    }
+
+    pub fn count(&self, status: &str) -> usize {
+        self.steps
+            .iter()
+            .filter(|step| step.status == status)
+            .count()
+    }
+
+    pub fn to_json(&self, baseline: &Baseline, cached: bool) -> Value {
+        json!({
+            "commit": baseline.commit,
+            "cached": cached,
+            "newly_failing": self.count("newly_failing"),
+            "still_failing": self.count("still_failing"),
+            "fixed": self.count("fixed"),
+            "steps": self
+                .steps
+                .iter()
+                .map(|step| json!({
+                    "step": step.step,
+                    "baseline": step.baseline,
+                    "after": step.after,
+                    "status": step.status,
+                }))
+                .collect::<Vec<_>>(),
+        })
+    }
+
+    /// Appends `baseline newly_failing=<n> still_failing=<n> fixed=<n>` to a
+    /// task note.
+    pub fn append_note(&self, note: &str) -> String {
+        format!(
+            "{}; baseline newly_failing={} still_failing={} fixed={}",
+            note.trim_end(),
+            self.count("newly_failing"),
+            self.count("still_failing"),
+            self.count("fixed")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outcome(step: &str, passed: bool) -> StepOutcome {
+        StepOutcome {
+            step: step.to_string(),
+            passed,
+        }
+    }
+
+    #[test]
+    fn comparison_classifies_each_step() {
+        let baseline = Baseline {
+            commit: "abc".to_string(),
+            steps: vec![
+                outcome("suite", true),
+                outcome("docs", false),
+                outcome("lint", false),
+            ],
+        };
+        let comparison = BaselineComparison::new(
+            &baseline,
+            &[
+                outcome("suite", false),
+                outcome("docs", false),
+                outcome("lint", true),
+                outcome("new", false),
+            ],
+        );
+        let statuses: Vec<&str> = comparison.steps.iter().map(|step| step.status).collect();
+        assert_eq!(
+            statuses,
+            vec!["newly_failing", "still_failing", "fixed", "newly_failing"]
+        );
+        assert_eq!(
+            comparison.append_note("Run r1 progress"),
+            "Run r1 progress; baseline newly_failing=2 still_failing=1 fixed=1"
+        );
+    }
+
+    #[test]
+    fn baselines_are_cached_per_commit_and_setup() {
+        let workspace = std::env::temp_dir().join(format!("lever-baseline-{}", std::process::id()));
+        let key = baseline_cache_key("abc", &json!({ "commands": ["make test"] })).unwrap();
+        assert_ne!(
+            key,
+            baseline_cache_key("abc", &json!({ "commands": ["make lint"] })).unwrap()
+        );
+        assert!(key.starts_with("abc-"));
+        assert_eq!(load_cached_baseline(&workspace, &key), None);
+
+        let baseline = Baseline {
+            commit: "abc".to_string(),
+            steps: vec![outcome("suite", false)],
+        };
+        store_baseline(&workspace, &key, &baseline).unwrap();
+        assert_eq!(load_cached_baseline(&workspace, &key), Some(baseline));
+        fs::remove_dir_all(&workspace).unwrap();
+    }
+}
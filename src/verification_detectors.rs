@@ -0,0 +1,358 @@
+use std::{error::Error, fs, path::Path, process::Command};
+
+use serde_json::Value;
+
+type DynError = Box<dyn Error + Send + Sync + 'static>;
+
+/// Commands chosen for a workspace without `verification.commands`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DetectedSuite {
+    pub detector: &'static str,
+    pub commands: Vec<Vec<String>>,
+}
+
+type DetectFn = fn(&Path) -> Result<Option<Vec<Vec<String>>>, DynError>;
+
+struct Detector {
+    name: &'static str,
+    detect: DetectFn,
+}
+
+/// Detectors in their default order: project CI entry points first, then
+/// ecosystem test runners.
+const DETECTORS: &[Detector] = &[
+    Detector {
+        name: "ci-script",
+        detect: detect_ci_script,
+    },
+    Detector {
+        name: "make-ci",
+        detect: detect_make_ci,
+    },
+    Detector {
+        name: "just-ci",
+        detect: detect_just_ci,
+    },
+    Detector {
+        name: "tests-run",
+        detect: detect_tests_run,
+    },
+    Detector {
+        name: "cargo",
+        detect: detect_cargo,
+    },
+    Detector {
+        name: "go",
+        detect: detect_go,
+    },
+    Detector {
+        name: "pnpm",
+        detect: detect_pnpm,
+    },
+    Detector {
+        name: "npm",
+        detect: detect_npm,
+    },
+    Detector {
+        name: "maven",
+        detect: detect_maven,
+    },
+    Detector {
+        name: "gradle",
+        detect: detect_gradle,
+    },
+    Detector {
+        name: "pytest",
+        detect: detect_pytest,
+    },
+];
+
+pub fn detector_names() -> impl Iterator<Item = &'static str> {
+    DETECTORS.iter().map(|detector| detector.name)
+}
+
+pub fn is_detector(name: &str) -> bool {
+    detector_names().any(|known| known == name)
+}
+
+/// Runs the detectors in `order` (or the default order), skipping
+/// `disabled`, and returns the first match.
+pub fn detect(
+    workspace: &Path,
+    order: Option<&[String]>,
+    disabled: &[String],
+) -> Result<Option<DetectedSuite>, DynError> {
+    let names: Vec<&str> = match order {
+        Some(order) => order.iter().map(String::as_str).collect(),
+        None => detector_names().collect(),
+    };
+    for name in names {
+        if disabled.iter().any(|off| off == name) {
+            continue;
+        }
+        let Some(detector) = DETECTORS.iter().find(|detector| detector.name == name) else {
+            continue;
+        };
+        if let Some(commands) = (detector.detect)(workspace)? {
+            return Ok(Some(DetectedSuite {
+                detector: detector.name,
+                commands,
+            }));
+        }
+    }
+    Ok(None)
+}
+
+fn command(parts: &[&str]) -> Vec<String> {
+    parts.iter().map(|part| part.to_string()).collect()
+}
+
+fn detect_ci_script(workspace: &Path) -> Result<Option<Vec<Vec<String>>>, DynError> {
+    Ok(
+        is_executable(&workspace.join("scripts/ci.sh"))
+            .then(|| vec![command(&["./scripts/ci.sh"])]),
+    )
+}
+
+fn detect_make_ci(workspace: &Path) -> Result<Option<Vec<Vec<String>>>, DynError> {
+    Ok(has_ci_recipe(&workspace.join("Makefile"))?.then(|| vec![command(&["make", "ci"])]))
+}
+
+fn detect_just_ci(workspace: &Path) -> Result<Option<Vec<Vec<String>>>, DynError> {
+    for name in ["justfile", "Justfile", ".justfile"] {
+        if has_ci_recipe(&workspace.join(name))? {
+            return Ok(Some(vec![command(&["just", "ci"])]));
+        }
+    }
+    Ok(None)
+}
+
+fn detect_tests_run(workspace: &Path) -> Result<Option<Vec<Vec<String>>>, DynError> {
+    Ok(is_executable(&workspace.join("tests/run.sh")).then(|| vec![command(&["./tests/run.sh"])]))
+}
+
+fn detect_cargo(workspace: &Path) -> Result<Option<Vec<Vec<String>>>, DynError> {
+    if !workspace.join("Cargo.toml").is_file() {
+        return Ok(None);
+    }
+    let mut commands = Vec::new();
+    if command_available("cargo", &["clippy", "--version"]) {
+        commands.push(command(&["cargo", "clippy", "--all-targets"]));
+    }
+    commands.push(command(&["cargo", "test"]));
+    Ok(Some(commands))
+}
+
+fn detect_go(workspace: &Path) -> Result<Option<Vec<Vec<String>>>, DynError> {
+    Ok(workspace
+        .join("go.mod")
+        .is_file()
+        .then(|| vec![command(&["go", "test", "./..."])]))
+}
+
+fn detect_pnpm(workspace: &Path) -> Result<Option<Vec<Vec<String>>>, DynError> {
+    if !workspace.join("pnpm-lock.yaml").is_file() || !has_npm_test_script(workspace)? {
+        return Ok(None);
+    }
+    Ok(Some(vec![command(&["pnpm", "test"])]))
+}
+
+fn detect_npm(workspace: &Path) -> Result<Option<Vec<Vec<String>>>, DynError> {
+    Ok(has_npm_test_script(workspace)?.then(|| vec![command(&["npm", "test"])]))
+}
+
+fn detect_maven(workspace: &Path) -> Result<Option<Vec<Vec<String>>>, DynError> {
+    if !workspace.join("pom.xml").is_file() {
+        return Ok(None);
+    }
+    let program = if is_executable(&workspace.join("mvnw")) {
+        "./mvnw"
+    } else {
+        "mvn"
+    };
+    Ok(Some(vec![command(&[program, "-B", "test"])]))
+}
+
+fn detect_gradle(workspace: &Path) -> Result<Option<Vec<Vec<String>>>, DynError> {
+    let markers = [
+        "build.gradle",
+        "build.gradle.kts",
+        "settings.gradle",
+        "settings.gradle.kts",
+    ];
+    if !markers
+        .iter()
+        .any(|marker| workspace.join(marker).is_file())
+    {
+        return Ok(None);
+    }
+    let program = if is_executable(&workspace.join("gradlew")) {
+        "./gradlew"
+    } else {
+        "gradle"
+    };
+    Ok(Some(vec![command(&[program, "test"])]))
+}
+
+fn detect_pytest(workspace: &Path) -> Result<Option<Vec<Vec<String>>>, DynError> {
+    if !command_available("pytest", &["--version"]) || !has_python_tests(workspace)? {
+        return Ok(None);
+    }
+    Ok(Some(vec![command(&["pytest", "-q"])]))
+}
+
+/// True for a `Makefile` or `justfile` with a `ci` recipe.
+fn has_ci_recipe(path: &Path) -> Result<bool, DynError> {
+    if !path.is_file() {
+        return Ok(false);
+    }
+    let content = fs::read_to_string(path)?;
+    Ok(content
+        .lines()
+        .any(|line| line.trim_start().starts_with("ci:")))
+}
+
+/// True when `package.json` has a `test` script other than the `npm init`
+/// placeholder.
+fn has_npm_test_script(workspace: &Path) -> Result<bool, DynError> {
+    let path = workspace.join("package.json");
+    if !path.is_file() {
+        return Ok(false);
+    }
+    let raw = fs::read_to_string(&path)?;
+    let manifest: Value =
+        serde_json::from_str(&raw).map_err(|err| format!("Invalid {}: {}", path.display(), err))?;
+    Ok(manifest
+        .get("scripts")
+        .and_then(|scripts| scripts.get("test"))
+        .and_then(Value::as_str)
+        .is_some_and(|script| !script.contains("no test specified")))
+}
+
+fn command_available(program: &str, args: &[&str]) -> bool {
+    Command::new(program)
+        .args(args)
+        .output()
+        .map(|out| out.status.success())
+        .unwrap_or(false)
+}
+
+fn has_python_tests(workspace: &Path) -> Result<bool, DynError> {
+    let root_markers = ["pytest.ini", "pyproject.toml", "setup.cfg", "tox.ini"];
+    if root_markers
+        .iter()
+        .any(|marker| workspace.join(marker).is_file())
+    {
+        return Ok(true);
+    }
+
+    let tests_dir = workspace.join("tests");
+    if !tests_dir.is_dir() {
+        return Ok(false);
+    }
+    dir_contains_py(&tests_dir)
+}
+
+fn dir_contains_py(path: &Path) -> Result<bool, DynError> {
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let path = entry.path();
+        if path.is_dir() {
+            if dir_contains_py(&path)? {
+                return Ok(true);
+            }
+        } else if path.extension().and_then(|ext| ext.to_str()) == Some("py") {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+fn is_executable(path: &Path) -> bool {
+    if !path.is_file() {
+        return false;
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if let Ok(metadata) = fs::metadata(path) {
+            return metadata.permissions().mode() & 0o111 != 0;
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn temp_workspace(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("lever-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn detects_ecosystems_by_marker_files() {
+        let dir = temp_workspace("detect-go");
+        assert_eq!(detect(&dir, None, &[]).unwrap(), None);
+
+        fs::write(dir.join("go.mod"), "module example.com/demo\n").unwrap();
+        let suite = detect(&dir, None, &[]).unwrap().unwrap();
+        assert_eq!(suite.detector, "go");
+        assert_eq!(suite.commands, vec![command(&["go", "test", "./..."])]);
+
+        fs::write(dir.join("justfile"), "ci:\n    just test\n").unwrap();
+        assert_eq!(
+            detect(&dir, None, &[]).unwrap().unwrap().detector,
+            "just-ci"
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn npm_detection_skips_the_init_placeholder_and_prefers_pnpm_lockfiles() {
+        let dir = temp_workspace("detect-npm");
+        fs::write(
+            dir.join("package.json"),
+            r#"{ "scripts": { "test": "echo \"Error: no test specified\" && exit 1" } }"#,
+        )
+        .unwrap();
+        assert_eq!(detect(&dir, None, &[]).unwrap(), None);
+
+        fs::write(
+            dir.join("package.json"),
+            r#"{ "scripts": { "test": "vitest" } }"#,
+        )
+        .unwrap();
+        assert_eq!(detect(&dir, None, &[]).unwrap().unwrap().detector, "npm");
+        fs::write(dir.join("pnpm-lock.yaml"), "lockfileVersion: '9.0'\n").unwrap();
+        let suite = detect(&dir, None, &[]).unwrap().unwrap();
+        assert_eq!(suite.detector, "pnpm");
+        assert_eq!(suite.commands, vec![command(&["pnpm", "test"])]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn order_and_opt_outs_are_respected() {
+        let dir = temp_workspace("detect-order");
+        fs::write(dir.join("go.mod"), "module example.com/demo\n").unwrap();
+        fs::write(dir.join("pom.xml"), "<project/>\n").unwrap();
+        assert_eq!(detect(&dir, None, &[]).unwrap().unwrap().detector, "go");
+
+        let disabled = vec!["go".to_string()];
+        let suite = detect(&dir, None, &disabled).unwrap().unwrap();
+        assert_eq!(suite.detector, "maven");
+        assert_eq!(suite.commands, vec![command(&["mvn", "-B", "test"])]);
+
+        let order = vec!["gradle".to_string(), "go".to_string()];
+        assert_eq!(
+            detect(&dir, Some(&order), &[]).unwrap().unwrap().detector,
+            "go"
+        );
+        assert_eq!(detect(&dir, Some(&order), &disabled).unwrap(), None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
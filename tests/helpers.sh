@@ -35,3 +35,45 @@ ensure_workspace_prompt() {
 Test prompt
 EOF
 }
+
+# Installs `assembly-pack-metadata` into a stub bin directory. Assembly stubs
+# call it after writing their pack so manifest.json lists sha256 hashes and
+# the summary JSON names the task, as Lever's pack validation requires.
+install_pack_metadata_stub() {
+  local dir="$1"
+  cat > "$dir/assembly-pack-metadata" <<'EOF2'
+#!/usr/bin/env python3
+import hashlib
+import json
+import os
+import sys
+
+out_dir, summary, task_id = sys.argv[1:4]
+manifest_path = os.path.join(out_dir, "manifest.json")
+if os.path.isfile(manifest_path):
+    files = []
+    for root, _, names in os.walk(out_dir):
+        for name in names:
+            path = os.path.join(root, name)
+            rel = os.path.relpath(path, out_dir)
+            if rel == "manifest.json":
+                continue
+            with open(path, "rb") as handle:
+                digest = hashlib.sha256(handle.read()).hexdigest()
+            files.append({"path": rel, "sha256": digest})
+    files.sort(key=lambda entry: entry["path"])
+    with open(manifest_path, "w", encoding="utf-8") as handle:
+        json.dump({"task_id": task_id, "files": files}, handle, indent=2)
+
+if summary:
+    context_path = os.path.join(out_dir, "context.md")
+    tokens = 0
+    if os.path.isfile(context_path):
+        with open(context_path, encoding="utf-8") as handle:
+            tokens = len(handle.read().split())
+    os.makedirs(os.path.dirname(summary), exist_ok=True)
+    with open(summary, "w", encoding="utf-8") as handle:
+        json.dump({"task_id": task_id, "token_count": tokens}, handle, indent=2)
+EOF2
+  chmod +x "$dir/assembly-pack-metadata"
+}
@@ -86,3 +86,87 @@ if [[ "$output" != *"missing required build flags"* ]]; then
   echo "Expected missing flag error, got: $output" >&2
   exit 1
 fi
+
+output="$("$validator_bin" --assembly "$stub_dir/assembly")"
+if [[ "$output" != *"version 2026-02-16, assembly 1.2.3, via help"* ]]; then
+  echo "Expected help without --include/--pin to negotiate 2026-02-16, got: $output" >&2
+  exit 1
+fi
+
+cat > "$stub_dir/assembly-prefix" <<'EOF2'
+#!/usr/bin/env bash
+set -euo pipefail
+
+if [[ "${1:-}" == "--version" ]]; then
+  printf '%s\n' "assembly 1.4.0"
+  exit 0
+fi
+
+if [[ "${1:-}" == "build" && "${2:-}" == "--help" ]]; then
+  cat <<'HELP'
+Options:
+  --repo <PATH>           Repository root
+  --task <PATH>           Task input file (supports @file)
+  --task-id <ID>          Task identifier
+  --out <DIR>             Output pack directory
+  --token-budget <TOKENS> Token budget for context
+  --exclude-runtime <GLOB> Runtime artifact exclusion glob (repeatable)
+  --summary-json <PATH>   Write machine-readable summary JSON
+HELP
+  exit 0
+fi
+
+exit 1
+EOF2
+chmod +x "$stub_dir/assembly-prefix"
+
+output="$("$validator_bin" --assembly "$stub_dir/assembly-prefix" 2>&1 || true)"
+if [[ "$output" != *"missing required build flags: --exclude."* ]]; then
+  echo "Expected --exclude-runtime not to satisfy --exclude, got: $output" >&2
+  exit 1
+fi
+
+cat > "$stub_dir/assembly-future" <<'EOF2'
+#!/usr/bin/env bash
+set -euo pipefail
+
+if [[ "${1:-}" == "--version" ]]; then
+  printf '%s\n' "assembly 3.1.0"
+  exit 0
+fi
+
+if [[ "${1:-}" == "capabilities" && -n "${CAPABILITIES:-}" ]]; then
+  printf '%s\n' "$CAPABILITIES"
+  exit 0
+fi
+
+exit 1
+EOF2
+chmod +x "$stub_dir/assembly-future"
+
+output="$("$validator_bin" --assembly "$stub_dir/assembly-future" 2>&1 || true)"
+if [[ "$output" != *"Unsupported Assembly version 3.1.0"* ]]; then
+  echo "Expected unsupported version error without capabilities, got: $output" >&2
+  exit 1
+fi
+
+output="$(CAPABILITIES='{"contract_versions": ["2026-02-16", "2027-01-01"], "build_flags": ["--repo", "--task", "--task-id", "--out", "--token-budget", "--exclude", "--exclude-runtime", "--summary-json"]}' \
+  "$validator_bin" --assembly "$stub_dir/assembly-future")"
+if [[ "$output" != *"version 2026-02-16, assembly 3.1.0, via capabilities"* ]]; then
+  echo "Expected capabilities to negotiate contract 2026-02-16, got: $output" >&2
+  exit 1
+fi
+
+output="$(CAPABILITIES='{"contract_versions": ["2026-02-16", "2026-10-18"], "build_flags": ["--repo", "--task", "--task-id", "--out", "--token-budget", "--exclude", "--exclude-runtime", "--summary-json", "--include", "--pin"]}' \
+  "$validator_bin" --assembly "$stub_dir/assembly-future")"
+if [[ "$output" != *"version 2026-10-18, assembly 3.1.0, via capabilities"* ]]; then
+  echo "Expected capabilities with --include/--pin to negotiate 2026-10-18, got: $output" >&2
+  exit 1
+fi
+
+output="$(CAPABILITIES='{"contract_versions": ["2027-01-01"], "build_flags": []}' \
+  "$validator_bin" --assembly "$stub_dir/assembly-future" 2>&1 || true)"
+if [[ "$output" != *"none supported by lever"* ]]; then
+  echo "Expected no common contract error, got: $output" >&2
+  exit 1
+fi
@@ -67,7 +67,10 @@ cat > "$out_path" <<'JSON'
   "summary": "ok",
   "tests": {"ran": false, "commands": [], "passed": true},
   "notes": "",
-  "blockers": []
+  "blockers": [],
+  "dod_items": [],
+  "files_changed": [],
+  "follow_ups": []
 }
 JSON
 EOF2
@@ -0,0 +1,161 @@
+#!/usr/bin/env bash
+set -euo pipefail
+
+TEST_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+# shellcheck source=helpers.sh
+source "$TEST_DIR/helpers.sh"
+
+require_cmd jq
+require_cmd git
+require_cmd cargo
+
+repo_root="$(cd "$TEST_DIR/.." && pwd)"
+repo_dir="$(make_temp_dir)"
+stub_bin="$(make_temp_dir)"
+trap 'rm -rf "$repo_dir" "$stub_bin"' EXIT
+
+cat > "$repo_dir/prd.json" <<'JSON'
+{
+  "tasks": [
+    {
+      "task_id": "T1",
+      "title": "Retry scheduler backoff",
+      "status": "unstarted",
+      "model": "gpt-5.1-codex-mini",
+      "definition_of_done": [
+        "Scheduler retries failed jobs with jitter"
+      ],
+      "recommended": {
+        "approach": "Extend the scheduler module"
+      }
+    }
+  ]
+}
+JSON
+
+ensure_workspace_prompt "$repo_dir"
+cat > "$repo_dir/README.md" <<'EOF2'
+Test repo
+EOF2
+mkdir -p "$repo_dir/src" "$repo_dir/build"
+cat > "$repo_dir/src/scheduler.rs" <<'EOF2'
+// Runs jobs and retries them with backoff.
+pub fn schedule() {}
+EOF2
+cat > "$repo_dir/src/unrelated.rs" <<'EOF2'
+pub fn parse() {}
+EOF2
+cat > "$repo_dir/build/scheduler-cache.rs" <<'EOF2'
+// generated scheduler retries backoff
+EOF2
+printf '%s\n' "build/" > "$repo_dir/.gitignore"
+
+cat > "$stub_bin/codex" <<'EOF2'
+#!/usr/bin/env bash
+set -euo pipefail
+
+if [[ "${1:-}" == "--version" ]]; then
+  printf '%s\n' "codex 0.0.0"
+  exit 0
+fi
+
+out_path=""
+while [[ $# -gt 0 ]]; do
+  case "$1" in
+    --output-last-message)
+      out_path="$2"
+      shift 2
+      ;;
+    *)
+      shift 1
+      ;;
+  esac
+done
+
+cat > "$out_path" <<'JSON'
+{
+  "task_id": "T1",
+  "outcome": "completed",
+  "dod_met": true,
+  "summary": "ok",
+  "tests": {"ran": false, "commands": [], "passed": true},
+  "notes": "",
+  "blockers": [],
+  "dod_items": [],
+  "files_changed": [],
+  "follow_ups": []
+}
+JSON
+EOF2
+chmod +x "$stub_bin/codex"
+
+init_git_repo "$repo_dir"
+
+(
+  cd "$repo_root"
+  cargo build --quiet
+)
+lever_bin="$repo_root/target/debug/lever"
+
+run_output=$(PATH="$stub_bin:$PATH" \
+  GIT_AUTHOR_NAME=test GIT_AUTHOR_EMAIL=test@example.com \
+  GIT_COMMITTER_NAME=test GIT_COMMITTER_EMAIL=test@example.com \
+  "$lever_bin" \
+  --workspace "$repo_dir" \
+  --tasks prd.json \
+  --task-id T1 \
+  --context-compile \
+  --context-failure-policy required \
+  --assembly-path lever-test-missing-assembly \
+  2>&1)
+
+if [[ "$run_output" != *"using the built-in context compiler"* ]]; then
+  echo "Expected fallback warning, got: $run_output" >&2
+  exit 1
+fi
+
+run_dir="$(find "$repo_dir/.ralph/runs/T1" -mindepth 1 -maxdepth 1 -type d | head -n 1)"
+pack_dir="$run_dir/pack"
+for file in manifest.json index.json context.md policy.md lint.json; do
+  if [[ ! -f "$pack_dir/$file" ]]; then
+    echo "Expected native pack file $file at $pack_dir" >&2
+    exit 1
+  fi
+done
+
+compile_report="$run_dir/context-compile.json"
+status="$(jq -r '.status' "$compile_report")"
+compiler="$(jq -r '.compiler' "$compile_report")"
+if [[ "$status" != "succeeded" || "$compiler" != "native" ]]; then
+  echo "Expected native compile to succeed, got status=$status compiler=$compiler" >&2
+  exit 1
+fi
+
+indexed="$(jq -r '[.files[].path] | join(",")' "$pack_dir/index.json")"
+if [[ ",$indexed," != *",src/scheduler.rs,"* ]]; then
+  echo "Expected src/scheduler.rs in the native index, got: $indexed" >&2
+  exit 1
+fi
+
+context="$(cat "$pack_dir/context.md")"
+if [[ "$context" != *"## src/scheduler.rs"* ]]; then
+  echo "Expected context.md to include the matching file" >&2
+  exit 1
+fi
+if [[ "$context" == *"build/scheduler-cache.rs"* || "$context" == *"src/unrelated.rs"* ]]; then
+  echo "Expected context.md to skip ignored and unrelated files" >&2
+  exit 1
+fi
+
+summary_budget="$(jq -r '.token_budget' "$run_dir/assembly-summary.json")"
+token_count="$(jq -r '.token_count' "$run_dir/assembly-summary.json")"
+if (( token_count > summary_budget )); then
+  echo "Expected token_count $token_count within budget $summary_budget" >&2
+  exit 1
+fi
+
+prompt="$(cat "$run_dir/prompt.md")"
+if [[ "$prompt" != *"Compiled context:"* || "$prompt" != *"pub fn schedule() {}"* ]]; then
+  echo "Expected prompt to include the natively compiled context" >&2
+  exit 1
+fi
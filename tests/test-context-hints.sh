@@ -0,0 +1,271 @@
+#!/usr/bin/env bash
+set -euo pipefail
+
+TEST_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+# shellcheck source=helpers.sh
+source "$TEST_DIR/helpers.sh"
+
+require_cmd jq
+require_cmd git
+require_cmd cargo
+require_cmd python3
+
+repo_root="$(cd "$TEST_DIR/.." && pwd)"
+repo_dir="$(make_temp_dir)"
+stub_bin="$(make_temp_dir)"
+trap 'rm -rf "$repo_dir" "$stub_bin"' EXIT
+
+cat > "$repo_dir/prd.json" <<'JSON'
+{
+  "tasks": [
+    {
+      "task_id": "T1",
+      "title": "Tune request throttling",
+      "status": "unstarted",
+      "model": "gpt-5.1-codex-mini",
+      "definition_of_done": [
+        "Throttling waits for the window to reset"
+      ],
+      "recommended": {
+        "approach": "Adjust the limiter"
+      },
+      "context": {
+        "include": ["vendor/limiter/**"],
+        "exclude": ["fixtures/**"],
+        "pin": ["src/rate_limit.rs"],
+        "token_budget": 1234
+      }
+    }
+  ]
+}
+JSON
+
+ensure_workspace_prompt "$repo_dir"
+cat > "$repo_dir/README.md" <<'EOF2'
+Test repo
+EOF2
+mkdir -p "$repo_dir/src" "$repo_dir/fixtures" "$repo_dir/vendor/limiter"
+cat > "$repo_dir/src/rate_limit.rs" <<'EOF2'
+pub fn sleep_until_reset() {}
+EOF2
+cat > "$repo_dir/fixtures/throttling.txt" <<'EOF2'
+throttling window reset limiter
+EOF2
+cat > "$repo_dir/vendor/limiter/bucket.rs" <<'EOF2'
+pub struct Bucket;
+EOF2
+
+cat > "$stub_bin/codex" <<'EOF2'
+#!/usr/bin/env bash
+set -euo pipefail
+
+if [[ "${1:-}" == "--version" ]]; then
+  printf '%s\n' "codex 0.0.0"
+  exit 0
+fi
+
+out_path=""
+while [[ $# -gt 0 ]]; do
+  case "$1" in
+    --output-last-message)
+      out_path="$2"
+      shift 2
+      ;;
+    *)
+      shift 1
+      ;;
+  esac
+done
+
+cat > "$out_path" <<'JSON'
+{
+  "task_id": "T1",
+  "outcome": "completed",
+  "dod_met": true,
+  "summary": "ok",
+  "tests": {"ran": false, "commands": [], "passed": true},
+  "notes": "",
+  "blockers": [],
+  "dod_items": [],
+  "files_changed": [],
+  "follow_ups": []
+}
+JSON
+EOF2
+chmod +x "$stub_bin/codex"
+
+cat > "$stub_bin/assembly" <<'EOF2'
+#!/usr/bin/env bash
+set -euo pipefail
+
+if [[ "${1:-}" == "--version" ]]; then
+  printf '%s\n' "assembly 1.2.3"
+  exit 0
+fi
+
+if [[ "${1:-}" == "build" && "${2:-}" == "--help" ]]; then
+  cat <<'HELP'
+Usage: assembly build [OPTIONS]
+
+Options:
+  --repo <PATH>           Repository root
+  --task <PATH>           Task input file (supports @file)
+  --task-id <ID>          Task identifier
+  --out <DIR>             Output pack directory
+  --token-budget <TOKENS> Token budget for context
+  --exclude <GLOB>        Additive exclude glob (repeatable)
+  --exclude-runtime <GLOB> Runtime artifact exclusion glob (repeatable)
+  --summary-json <PATH>   Write machine-readable summary JSON
+HELP
+  if [[ -z "${ASSEMBLY_WITHOUT_HINTS:-}" ]]; then
+    printf '%s\n' \
+      "  --include <GLOB>        Include glob (repeatable)" \
+      "  --pin <PATH>            Pinned file (repeatable)"
+  fi
+  exit 0
+fi
+
+printf '%s\n' "$@" > "$ASSEMBLY_ARGS_FILE"
+
+out_dir=""
+summary=""
+task_id=""
+while [[ $# -gt 0 ]]; do
+  case "$1" in
+    --out)
+      out_dir="$2"
+      shift 2
+      ;;
+    --summary-json)
+      summary="$2"
+      shift 2
+      ;;
+    --task-id)
+      task_id="$2"
+      shift 2
+      ;;
+    *)
+      shift 1
+      ;;
+  esac
+done
+
+mkdir -p "$out_dir"
+printf '%s\n' "{}" > "$out_dir/manifest.json"
+printf '%s\n' "{}" > "$out_dir/index.json"
+printf '%s\n' "Compiled context" > "$out_dir/context.md"
+printf '%s\n' "policy" > "$out_dir/policy.md"
+printf '%s\n' "{}" > "$out_dir/lint.json"
+
+assembly-pack-metadata "$out_dir" "$summary" "$task_id"
+EOF2
+chmod +x "$stub_bin/assembly"
+install_pack_metadata_stub "$stub_bin"
+
+init_git_repo "$repo_dir"
+
+(
+  cd "$repo_root"
+  cargo build --quiet
+)
+lever_bin="$repo_root/target/debug/lever"
+
+run_lever() {
+  PATH="$stub_bin:$PATH" \
+    ASSEMBLY_ARGS_FILE="$stub_bin/assembly-args" \
+    GIT_AUTHOR_NAME=test GIT_AUTHOR_EMAIL=test@example.com \
+    GIT_COMMITTER_NAME=test GIT_COMMITTER_EMAIL=test@example.com \
+    "$lever_bin" \
+    --workspace "$repo_dir" \
+    --tasks prd.json \
+    --task-id T1 \
+    --context-compile \
+    --context-failure-policy required \
+    "$@" \
+    >/dev/null 2>&1
+}
+
+latest_run_dir() {
+  ls -td "$repo_dir/.ralph/runs/T1"/* | head -n 1
+}
+
+reset_task() {
+  local tmp
+  tmp="$(mktemp)"
+  jq '.tasks[0].status = "unstarted" | del(.tasks[0].observability)' "$repo_dir/prd.json" > "$tmp"
+  mv "$tmp" "$repo_dir/prd.json"
+  git -C "$repo_dir" checkout -q main
+  git -C "$repo_dir" add prd.json
+  GIT_AUTHOR_NAME=test GIT_AUTHOR_EMAIL=test@example.com \
+    GIT_COMMITTER_NAME=test GIT_COMMITTER_EMAIL=test@example.com \
+    git -C "$repo_dir" commit -q -m "reset task" --allow-empty
+}
+
+run_lever
+
+args="$(tr '\n' ' ' < "$stub_bin/assembly-args")"
+for expected in \
+  "--token-budget 1234 " \
+  "--exclude fixtures/** " \
+  "--include vendor/limiter/** " \
+  "--pin src/rate_limit.rs "; do
+  if [[ "$args" != *"$expected"* ]]; then
+    echo "Expected assembly args to include '$expected', got: $args" >&2
+    exit 1
+  fi
+done
+
+run_dir="$(latest_run_dir)"
+task_context="$(jq -c '.context' "$run_dir/assembly-task.json")"
+expected_context='{"exclude":["fixtures/**"],"include":["vendor/limiter/**"],"pin":["src/rate_limit.rs"],"token_budget":1234}'
+if [[ "$task_context" != "$expected_context" ]]; then
+  echo "Expected assembly-task.json context $expected_context, got: $task_context" >&2
+  exit 1
+fi
+
+reset_task
+run_lever --assembly-path lever-test-missing-assembly
+
+run_dir="$(latest_run_dir)"
+if [[ "$(jq -r '.compiler' "$run_dir/context-compile.json")" != "native" ]]; then
+  echo "Expected the built-in compiler to build the second pack" >&2
+  exit 1
+fi
+
+first_file="$(jq -r '.files[0].path' "$run_dir/pack/index.json")"
+if [[ "$first_file" != "src/rate_limit.rs" ]]; then
+  echo "Expected pinned file first in the native index, got: $first_file" >&2
+  exit 1
+fi
+
+context="$(cat "$run_dir/pack/context.md")"
+if [[ "$context" != *"## vendor/limiter/bucket.rs"* ]]; then
+  echo "Expected included file in native context" >&2
+  exit 1
+fi
+if [[ "$context" == *"fixtures/throttling.txt"* ]]; then
+  echo "Expected task exclude glob to drop fixtures/throttling.txt" >&2
+  exit 1
+fi
+
+budget="$(jq -r '.token_budget' "$run_dir/assembly-summary.json")"
+if [[ "$budget" != "1234" ]]; then
+  echo "Expected native summary token_budget 1234, got: $budget" >&2
+  exit 1
+fi
+
+# An Assembly on the older contract, without --include/--pin, cannot build a
+# pack for a task with include or pin hints.
+reset_task
+rm -f "$stub_bin/assembly-args"
+ASSEMBLY_WITHOUT_HINTS=1 run_lever || true
+
+if [[ "$(head -n 1 "$stub_bin/assembly-args" 2>/dev/null)" == "build" ]]; then
+  echo "Expected Assembly build not to run without include/pin support" >&2
+  exit 1
+fi
+note="$(jq -r '.tasks[0].observability.last_note' "$repo_dir/prd.json")"
+if [[ "$note" != *"Assembly does not support include/pin (contract 2026-02-16)"* ]]; then
+  echo "Expected the unsupported include/pin error in the note, got: $note" >&2
+  exit 1
+fi
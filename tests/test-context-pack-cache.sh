@@ -0,0 +1,205 @@
+#!/usr/bin/env bash
+set -euo pipefail
+
+TEST_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+# shellcheck source=helpers.sh
+source "$TEST_DIR/helpers.sh"
+
+require_cmd git
+require_cmd cargo
+require_cmd python3
+require_cmd jq
+
+repo_root="$(cd "$TEST_DIR/.." && pwd)"
+repo_dir="$(make_temp_dir)"
+stub_bin="$(make_temp_dir)"
+trap 'rm -rf "$repo_dir" "$stub_bin"' EXIT
+
+cat > "$repo_dir/prd.json" <<'JSON'
+{
+  "tasks": [
+    {
+      "task_id": "T1",
+      "title": "Context pack cache",
+      "status": "unstarted",
+      "model": "gpt-5.1-codex-mini",
+      "definition_of_done": [
+        "Reuse the cached pack"
+      ],
+      "recommended": {
+        "approach": "Run the same task twice"
+      }
+    }
+  ]
+}
+JSON
+
+ensure_workspace_prompt "$repo_dir"
+cat > "$repo_dir/README.md" <<'EOF2'
+Test repo
+EOF2
+
+cat > "$stub_bin/codex" <<'EOF2'
+#!/usr/bin/env bash
+set -euo pipefail
+echo "Codex should not run when the prompt exceeds its budget" >&2
+exit 1
+EOF2
+chmod +x "$stub_bin/codex"
+
+cat > "$stub_bin/assembly" <<'EOF2'
+#!/usr/bin/env bash
+set -euo pipefail
+
+if [[ "${1:-}" == "--version" ]]; then
+  printf '%s\n' "assembly 1.2.3"
+  exit 0
+fi
+
+if [[ "${1:-}" == "build" && "${2:-}" == "--help" ]]; then
+  cat <<'HELP'
+Usage: assembly build [OPTIONS]
+
+Options:
+  --repo <PATH>           Repository root
+  --task <PATH>           Task input file (supports @file)
+  --task-id <ID>          Task identifier
+  --out <DIR>             Output pack directory
+  --token-budget <TOKENS> Token budget for context
+  --exclude <GLOB>        Additive exclude glob (repeatable)
+  --exclude-runtime <GLOB> Runtime artifact exclusion glob (repeatable)
+  --summary-json <PATH>   Write machine-readable summary JSON
+HELP
+  exit 0
+fi
+
+out_dir=""
+summary=""
+task_id=""
+while [[ $# -gt 0 ]]; do
+  case "$1" in
+    --out)
+      out_dir="$2"
+      shift 2
+      ;;
+    --summary-json)
+      summary="$2"
+      shift 2
+      ;;
+    --task-id)
+      task_id="$2"
+      shift 2
+      ;;
+    *)
+      shift 1
+      ;;
+  esac
+done
+
+if [[ -z "$out_dir" ]]; then
+  echo "Missing --out" >&2
+  exit 2
+fi
+
+count_file="$(dirname "$0")/assembly-builds"
+printf '%s\n' "build" >> "$count_file"
+
+mkdir -p "$out_dir"
+printf '%s\n' "{}" > "$out_dir/manifest.json"
+printf '%s\n' "{}" > "$out_dir/index.json"
+printf '%s\n' "Compiled context line 1" "Compiled context line 2" > "$out_dir/context.md"
+printf '%s\n' "{}" > "$out_dir/policy.md"
+printf '%s\n' "{}" > "$out_dir/lint.json"
+
+assembly-pack-metadata "$out_dir" "$summary" "$task_id"
+EOF2
+chmod +x "$stub_bin/assembly"
+install_pack_metadata_stub "$stub_bin"
+
+init_git_repo "$repo_dir"
+
+(
+  cd "$repo_root"
+  cargo build --quiet
+)
+lever_bin="$repo_root/target/debug/lever"
+
+# A one-token prompt budget stops each run after context compilation. Each
+# run blocks the task and commits it on ralph/T1, which must not change the
+# cache key.
+run_lever() {
+  set +e
+  PATH="$stub_bin:$PATH" \
+    GIT_AUTHOR_NAME=test GIT_AUTHOR_EMAIL=test@example.com \
+    GIT_COMMITTER_NAME=test GIT_COMMITTER_EMAIL=test@example.com \
+    "$lever_bin" \
+    --workspace "$repo_dir" \
+    --tasks prd.json \
+    --task-id T1 \
+    --context-compile \
+    --prompt-token-budget 1 \
+    "$@" \
+    >/dev/null 2>&1
+  set -e
+}
+
+latest_report() {
+  local run_dir
+  run_dir="$(find "$repo_dir/.ralph/runs/T1" -mindepth 1 -maxdepth 1 -type d | sort | tail -n 1)"
+  printf '%s\n' "$run_dir/context-compile.json"
+}
+
+assert_cache() {
+  local expected_hit="$1"
+  local expected_builds="$2"
+  local report
+  report="$(latest_report)"
+  if [[ "$(jq -r '.cache_hit' "$report")" != "$expected_hit" ]]; then
+    echo "Expected cache_hit=$expected_hit in $report" >&2
+    cat "$report" >&2
+    exit 1
+  fi
+  if [[ "$(jq -r '.status' "$report")" != "succeeded" ]]; then
+    echo "Expected context compile to succeed in $report" >&2
+    exit 1
+  fi
+  local builds
+  builds="$(wc -l < "$stub_bin/assembly-builds" | tr -d ' ')"
+  if [[ "$builds" != "$expected_builds" ]]; then
+    echo "Expected $expected_builds Assembly builds, got $builds" >&2
+    exit 1
+  fi
+}
+
+run_lever
+assert_cache false 1
+first_key="$(jq -r '.cache_key' "$(latest_report)")"
+
+sleep 1
+run_lever
+assert_cache true 1
+if [[ "$(jq -r '.cache_key' "$(latest_report)")" != "$first_key" ]]; then
+  echo "Expected the cache hit to reuse key $first_key" >&2
+  exit 1
+fi
+
+pack_dir="$(dirname "$(latest_report)")/pack"
+for file in manifest.json index.json context.md policy.md lint.json; do
+  if [[ ! -f "$pack_dir/$file" ]]; then
+    echo "Expected cached pack file $file at $pack_dir" >&2
+    exit 1
+  fi
+done
+if [[ ! -f "$(dirname "$(latest_report)")/assembly-summary.json" ]]; then
+  echo "Expected cached assembly-summary.json in the run directory" >&2
+  exit 1
+fi
+
+printf '%s\n' "scratch" > "$repo_dir/notes.txt"
+sleep 1
+run_lever --dirty allow
+assert_cache false 2
+
+sleep 1
+run_lever --dirty allow --context-token-budget 4000
+assert_cache false 3
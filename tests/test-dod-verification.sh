@@ -0,0 +1,163 @@
+#!/usr/bin/env bash
+set -euo pipefail
+
+TEST_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+# shellcheck source=helpers.sh
+source "$TEST_DIR/helpers.sh"
+
+require_cmd jq
+require_cmd git
+require_cmd cargo
+
+repo_root="$(cd "$TEST_DIR/.." && pwd)"
+repo_dir="$(make_temp_dir)"
+stub_bin="$(make_temp_dir)"
+trap 'rm -rf "$repo_dir" "$stub_bin"' EXIT
+
+cat > "$repo_dir/prd.json" <<'JSON'
+{
+  "tasks": [
+    {
+      "task_id": "T1",
+      "title": "Verifiable definition of done",
+      "status": "unstarted",
+      "model": "gpt-5.1-codex-mini",
+      "definition_of_done": [
+        "Docs updated",
+        { "item": "Marker file exists", "verify": "test -f marker.txt" },
+        { "item": "Shell works", "verify": "true" }
+      ],
+      "recommended": {
+        "approach": "Stub verification"
+      }
+    }
+  ]
+}
+JSON
+
+ensure_workspace_prompt "$repo_dir"
+
+cat > "$stub_bin/codex" <<'EOF2'
+#!/usr/bin/env bash
+set -euo pipefail
+
+if [[ "${1:-}" == "--version" ]]; then
+  printf '%s\n' "codex 0.0.0"
+  exit 0
+fi
+
+out_path=""
+while [[ $# -gt 0 ]]; do
+  case "$1" in
+    --output-last-message)
+      out_path="$2"
+      shift 2
+      ;;
+    *)
+      shift 1
+      ;;
+  esac
+done
+
+if [[ "${CREATE_MARKER:-0}" == "1" ]]; then
+  printf '%s\n' "done" > marker.txt
+fi
+
+cat > "$out_path" <<'JSON'
+{
+  "task_id": "T1",
+  "outcome": "completed",
+  "dod_met": true,
+  "summary": "ok",
+  "tests": {"ran": false, "commands": [], "passed": true},
+  "notes": "",
+  "blockers": [],
+  "dod_items": [],
+  "files_changed": [],
+  "follow_ups": []
+}
+JSON
+EOF2
+chmod +x "$stub_bin/codex"
+
+init_git_repo "$repo_dir"
+
+(
+  cd "$repo_root"
+  cargo build --quiet
+)
+lever_bin="$repo_root/target/debug/lever"
+
+run_task() {
+  set +e
+  PATH="$stub_bin:$PATH" \
+    CREATE_MARKER="$2" \
+    GIT_AUTHOR_NAME=test GIT_AUTHOR_EMAIL=test@example.com \
+    GIT_COMMITTER_NAME=test GIT_COMMITTER_EMAIL=test@example.com \
+    "$lever_bin" \
+    --workspace "$repo_dir" \
+    --tasks prd.json \
+    --task-id T1 \
+    >"$stub_bin/output-$1" 2>&1
+  run_status=$?
+  set -e
+}
+
+# The agent claims dod_met, but one verifiable item fails.
+run_task first 0
+if [[ $run_status -ne 12 ]]; then
+  echo "Expected progress exit 12 with a failing DoD check, got $run_status" >&2
+  cat "$stub_bin/output-first" >&2
+  exit 1
+fi
+run_dir="$(find "$repo_dir/.ralph/runs/T1" -mindepth 1 -maxdepth 1 -type d | head -n 1)"
+report="$run_dir/dod-verification.json"
+if [[ "$(jq -c '[.passed, .total]' "$report")" != "[1,2]" ]]; then
+  echo "Expected 1 of 2 DoD checks to pass" >&2
+  cat "$report" >&2
+  exit 1
+fi
+if [[ "$(jq -c '.items[0] | [.item, .command, .passed]' "$report")" != '["Marker file exists","test -f marker.txt",false]' ]]; then
+  echo "Unexpected first DoD check" >&2
+  cat "$report" >&2
+  exit 1
+fi
+dod_results="$(jq -c '.tasks[0].observability.dod_results' "$repo_dir/prd.json")"
+if [[ "$dod_results" != '[{"item":"Marker file exists","passed":false},{"item":"Shell works","passed":true}]' ]]; then
+  echo "Unexpected observability.dod_results: $dod_results" >&2
+  exit 1
+fi
+if [[ "$(jq -r '.tasks[0].status' "$repo_dir/prd.json")" != "started" ]]; then
+  echo "Expected T1 to stay started" >&2
+  exit 1
+fi
+if [[ "$(jq -r '.tasks[0].observability.last_note' "$repo_dir/prd.json")" != *"dod_checks=1/2"* ]]; then
+  echo "Expected DoD check counts in last_note" >&2
+  exit 1
+fi
+if ! grep -q "lever: definition of done: Marker file exists" "$run_dir/verify.log"; then
+  echo "Expected DoD check output in verify.log" >&2
+  exit 1
+fi
+if ! grep -q 'Marker file exists (verified by `test -f marker.txt`)' "$run_dir/prompt.md"; then
+  echo "Expected verify commands in the prompt" >&2
+  cat "$run_dir/prompt.md" >&2
+  exit 1
+fi
+
+# Once every verifiable item passes, the task completes.
+run_task second 1
+if [[ $run_status -ne 0 ]]; then
+  echo "Expected completion once DoD checks pass, got $run_status" >&2
+  cat "$stub_bin/output-second" >&2
+  exit 1
+fi
+git -C "$repo_dir" checkout -q main
+if [[ "$(jq -r '.tasks[0].status' "$repo_dir/prd.json")" != "completed" ]]; then
+  echo "Expected T1 completed" >&2
+  exit 1
+fi
+if [[ "$(jq -c '[.tasks[0].observability.dod_results[].passed]' "$repo_dir/prd.json")" != "[true,true]" ]]; then
+  echo "Expected all DoD results to pass" >&2
+  exit 1
+fi
@@ -0,0 +1,167 @@
+#!/usr/bin/env bash
+set -euo pipefail
+
+TEST_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+# shellcheck source=helpers.sh
+source "$TEST_DIR/helpers.sh"
+
+require_cmd jq
+require_cmd git
+require_cmd cargo
+
+repo_root="$(cd "$TEST_DIR/.." && pwd)"
+repo_dir="$(make_temp_dir)"
+stub_bin="$(make_temp_dir)"
+trap 'rm -rf "$repo_dir" "$stub_bin"' EXIT
+
+cat > "$repo_dir/prd.json" <<'JSON'
+{
+  "tasks": [
+    {
+      "task_id": "T1",
+      "title": "Invalid result handling",
+      "status": "unstarted",
+      "model": "gpt-5.1-codex-mini",
+      "definition_of_done": [
+        "Result is validated"
+      ],
+      "recommended": {
+        "approach": "Stub result"
+      }
+    }
+  ]
+}
+JSON
+
+ensure_workspace_prompt "$repo_dir"
+
+cat > "$stub_bin/codex" <<'EOF2'
+#!/usr/bin/env bash
+set -euo pipefail
+
+if [[ "${1:-}" == "--version" ]]; then
+  printf '%s\n' "codex 0.0.0"
+  exit 0
+fi
+
+out_path=""
+while [[ $# -gt 0 ]]; do
+  case "$1" in
+    --output-last-message)
+      out_path="$2"
+      shift 2
+      ;;
+    *)
+      shift 1
+      ;;
+  esac
+done
+
+case "$RESULT_MODE" in
+  text)
+    printf '%s\n' "All done! Everything passes." > "$out_path"
+    ;;
+  mismatch)
+    cat > "$out_path" <<'JSON'
+{
+  "task_id": "T9",
+  "outcome": "completed",
+  "dod_met": true,
+  "summary": "ok",
+  "tests": {"ran": false, "commands": [], "passed": true},
+  "notes": "",
+  "blockers": [],
+  "dod_items": [],
+  "files_changed": [],
+  "follow_ups": []
+}
+JSON
+    ;;
+  loosen)
+    printf '%s\n' '{}' > .ralph/task_result.schema.json
+    printf '%s\n' '{"task_id": "T1", "outcome": "completed"}' > "$out_path"
+    ;;
+esac
+EOF2
+chmod +x "$stub_bin/codex"
+
+init_git_repo "$repo_dir"
+
+(
+  cd "$repo_root"
+  cargo build --quiet
+)
+lever_bin="$repo_root/target/debug/lever"
+
+run_task() {
+  set +e
+  PATH="$stub_bin:$PATH" \
+    RESULT_MODE="$1" \
+    GIT_AUTHOR_NAME=test GIT_AUTHOR_EMAIL=test@example.com \
+    GIT_COMMITTER_NAME=test GIT_COMMITTER_EMAIL=test@example.com \
+    "$lever_bin" \
+    --workspace "$repo_dir" \
+    --tasks prd.json \
+    --task-id T1 \
+    >"$stub_bin/output-$1" 2>&1
+  run_status=$?
+  set -e
+  if [[ $run_status -ne 17 ]]; then
+    echo "Expected invalid result exit 17 for $1, got $run_status" >&2
+    cat "$stub_bin/output-$1" >&2
+    exit 1
+  fi
+}
+
+latest_run_dir() {
+  ls -td "$repo_dir"/.ralph/runs/T1/*/ | head -n 1
+}
+
+# A plain-text last message blocks the task instead of crashing the run.
+run_task text
+run_dir="$(latest_run_dir)"
+if [[ "$(cat "$run_dir/result.json")" != "All done! Everything passes." ]]; then
+  echo "Expected the raw result to be preserved" >&2
+  exit 1
+fi
+if [[ "$(jq -r '.errors[0]' "$run_dir/result-errors.json")" != "not valid JSON"* ]]; then
+  echo "Expected a JSON error in result-errors.json" >&2
+  cat "$run_dir/result-errors.json" >&2
+  exit 1
+fi
+if [[ "$(jq -r '.tasks[0].status' "$repo_dir/prd.json")" != "blocked" ]]; then
+  echo "Expected T1 blocked after an invalid result" >&2
+  exit 1
+fi
+note="$(jq -r '.tasks[0].observability.last_note' "$repo_dir/prd.json")"
+if [[ "$note" != "Codex produced an invalid result.json: not valid JSON"* ]]; then
+  echo "Unexpected last_note: $note" >&2
+  exit 1
+fi
+
+# A well-formed result for another task is rejected too.
+run_task mismatch
+run_dir="$(latest_run_dir)"
+if [[ "$(jq -r '.errors[0]' "$run_dir/result-errors.json")" != 'task_id "T9" does not match the selected task "T1"' ]]; then
+  echo "Expected a task_id mismatch error" >&2
+  cat "$run_dir/result-errors.json" >&2
+  exit 1
+fi
+if ! grep -q "Blocked: invalid result.json" "$stub_bin/output-mismatch"; then
+  echo "Expected an invalid result message" >&2
+  exit 1
+fi
+
+# Loosening the schema file during the run does not change how the result
+# is checked.
+run_task loosen
+run_dir="$(latest_run_dir)"
+if ! jq -e '.errors | any(contains("dod_met"))' "$run_dir/result-errors.json" >/dev/null; then
+  echo "Expected the result to be checked against the schema loaded before Codex ran" >&2
+  cat "$run_dir/result-errors.json" >&2
+  exit 1
+fi
+if [[ "$(jq -r '.title' "$repo_dir/.ralph/task_result.schema.json")" != "Lever task result v"* ]]; then
+  echo "Expected the schema file to be restored after the run" >&2
+  exit 1
+fi
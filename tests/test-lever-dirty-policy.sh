@@ -0,0 +1,202 @@
+#!/usr/bin/env bash
+set -euo pipefail
+
+TEST_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+# shellcheck source=helpers.sh
+source "$TEST_DIR/helpers.sh"
+
+require_cmd cargo
+require_cmd git
+require_cmd jq
+
+repo_root="$(cd "$TEST_DIR/.." && pwd)"
+(
+  cd "$repo_root"
+  cargo build --quiet
+)
+lever_bin="$repo_root/target/debug/lever"
+
+stub_dir="$(make_temp_dir)"
+workspaces=()
+cleanup() {
+  rm -rf "$stub_dir" "${workspaces[@]}"
+}
+trap cleanup EXIT
+
+cat > "$stub_dir/noop-agent" <<'EOF2'
+#!/usr/bin/env bash
+set -euo pipefail
+exit 0
+EOF2
+chmod +x "$stub_dir/noop-agent"
+
+cat > "$stub_dir/crash-agent" <<'EOF2'
+#!/usr/bin/env bash
+set -euo pipefail
+kill -KILL "$PPID"
+EOF2
+chmod +x "$stub_dir/crash-agent"
+
+export GIT_AUTHOR_NAME=test GIT_AUTHOR_EMAIL=test@example.com
+export GIT_COMMITTER_NAME=test GIT_COMMITTER_EMAIL=test@example.com
+
+new_workspace() {
+  local workspace
+  workspace="$(make_temp_dir)"
+  workspaces+=("$workspace")
+  cat > "$workspace/prd.json" <<'JSON'
+{
+  "tasks": [
+    {
+      "task_id": "T1",
+      "title": "Dirty policy",
+      "status": "unstarted",
+      "model": "gpt-5.1-codex-mini",
+      "definition_of_done": [
+        "Protect local changes"
+      ],
+      "recommended": {
+        "approach": "Exercise the git workspace guard"
+      }
+    }
+  ]
+}
+JSON
+  ensure_workspace_prompt "$workspace"
+  init_git_repo "$workspace"
+  printf '%s\n' "local edit" >> "$workspace/README.md"
+  printf '%s\n' "$workspace"
+}
+
+run_lever() {
+  local workspace="$1"
+  shift
+  "$lever_bin" --workspace "$workspace" --tasks prd.json --task-id T1 "$@"
+}
+
+# refuse: the run aborts before the command path executes.
+workspace="$(new_workspace)"
+set +e
+output="$(run_lever "$workspace" --dirty refuse --command-path "$stub_dir/noop-agent" 2>&1)"
+status=$?
+set -e
+if [[ $status -eq 0 ]]; then
+  echo "Expected --dirty refuse to fail on a dirty workspace" >&2
+  exit 1
+fi
+if [[ "$output" != *"uncommitted file(s)"* ]]; then
+  echo "Expected refusal message, got: $output" >&2
+  exit 1
+fi
+if [[ -n "$(git -C "$workspace" stash list)" ]]; then
+  echo "Expected --dirty refuse not to create a stash" >&2
+  exit 1
+fi
+
+# doctor: previews the stash without touching the workspace.
+output="$("$lever_bin" doctor --workspace "$workspace" --tasks prd.json --task-id T1)"
+for needle in "dirty policy=stash dirty_files=1" "would stash 1 file(s)" "would checkout main then ralph/T1 (new)" "preview only"; do
+  if [[ "$output" != *"$needle"* ]]; then
+    echo "Expected doctor output to include '$needle', got: $output" >&2
+    exit 1
+  fi
+done
+if [[ -z "$(git -C "$workspace" status --porcelain)" ]]; then
+  echo "Expected doctor to leave local changes in place" >&2
+  exit 1
+fi
+if git -C "$workspace" rev-parse --verify --quiet refs/heads/ralph/T1 >/dev/null; then
+  echo "Expected doctor not to create the task branch" >&2
+  exit 1
+fi
+
+# stash: the stash is recorded and marked restored after the run.
+run_lever "$workspace" --command-path "$stub_dir/noop-agent" >/dev/null 2>&1
+if ! grep -q "local edit" "$workspace/README.md"; then
+  echo "Expected stashed change to be restored" >&2
+  exit 1
+fi
+ledger_status="$(jq -r '.stashes[0].status' "$workspace/.ralph/stashes.json")"
+if [[ "$ledger_status" != "restored" ]]; then
+  echo "Expected ledger status restored, got: $ledger_status" >&2
+  exit 1
+fi
+if git -C "$workspace" status --porcelain | grep -q "stashes.json"; then
+  echo "Expected stash ledger to be excluded from git status" >&2
+  exit 1
+fi
+
+# crash: the ledger survives and lever stash restore recovers the change.
+workspace="$(new_workspace)"
+set +e
+run_lever "$workspace" --command-path "$stub_dir/crash-agent" >/dev/null 2>&1
+set -e
+output="$("$lever_bin" stash list --workspace "$workspace")"
+if [[ "$output" != *"status=pending"*"task=T1"* ]]; then
+  echo "Expected pending stash in lever stash list, got: $output" >&2
+  exit 1
+fi
+stash_id="$(jq -r '.stashes[0].commit' "$workspace/.ralph/stashes.json" | cut -c1-12)"
+git -C "$workspace" checkout --quiet main
+"$lever_bin" stash restore "$stash_id" --workspace "$workspace" >/dev/null
+if ! grep -q "local edit" "$workspace/README.md"; then
+  echo "Expected lever stash restore to re-apply the change" >&2
+  exit 1
+fi
+if [[ -n "$(git -C "$workspace" stash list)" ]]; then
+  echo "Expected lever stash restore to drop the git stash" >&2
+  exit 1
+fi
+ledger_status="$(jq -r '.stashes[0].status' "$workspace/.ralph/stashes.json")"
+if [[ "$ledger_status" != "restored" ]]; then
+  echo "Expected ledger status restored after lever stash restore, got: $ledger_status" >&2
+  exit 1
+fi
+
+# commit: refused on the base branch.
+workspace="$(new_workspace)"
+base_head="$(git -C "$workspace" rev-parse main)"
+set +e
+output="$(run_lever "$workspace" --dirty commit --command-path "$stub_dir/noop-agent" 2>&1)"
+status=$?
+set -e
+if [[ $status -eq 0 || "$output" != *"main is the base branch"* ]]; then
+  echo "Expected --dirty commit to refuse on main, got: $output" >&2
+  exit 1
+fi
+if [[ "$(git -C "$workspace" rev-parse main)" != "$base_head" ]]; then
+  echo "Expected --dirty commit not to commit on main" >&2
+  exit 1
+fi
+output="$("$lever_bin" doctor --workspace "$workspace" --tasks prd.json --task-id T1 --dirty commit)"
+if [[ "$output" != *"would refuse to commit: main is the base branch"* ]]; then
+  echo "Expected doctor to preview the refusal, got: $output" >&2
+  exit 1
+fi
+
+# commit: on a working branch local changes are committed, lever state is not.
+git -C "$workspace" checkout -q -b feature
+mkdir -p "$workspace/.ralph"
+printf '%s\n' "scratch" > "$workspace/.ralph/scratch.txt"
+run_lever "$workspace" --dirty commit --command-path "$stub_dir/noop-agent" >/dev/null 2>&1
+subject="$(git -C "$workspace" log -1 --format=%s feature)"
+if [[ "$subject" != "lever: snapshot local changes before run"* ]]; then
+  echo "Expected snapshot commit on feature, got: $subject" >&2
+  exit 1
+fi
+if git -C "$workspace" show --name-only --format= feature | grep -q '^\.ralph/'; then
+  echo "Expected snapshot commit to exclude .ralph/" >&2
+  exit 1
+fi
+
+# allow: changes stay in the working tree and nothing is stashed.
+workspace="$(new_workspace)"
+run_lever "$workspace" --dirty allow --command-path "$stub_dir/noop-agent" >/dev/null 2>&1
+if [[ -n "$(git -C "$workspace" stash list)" ]]; then
+  echo "Expected --dirty allow not to stash" >&2
+  exit 1
+fi
+if ! grep -q "local edit" "$workspace/README.md"; then
+  echo "Expected --dirty allow to keep local changes" >&2
+  exit 1
+fi
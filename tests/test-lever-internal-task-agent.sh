@@ -81,7 +81,10 @@ cat > "$out_path" <<'JSON'
   "summary": "lever delegation",
   "tests": {"ran": false, "commands": [], "passed": true},
   "notes": "",
-  "blockers": []
+  "blockers": [],
+  "dod_items": [],
+  "files_changed": [],
+  "follow_ups": []
 }
 JSON
 EOF
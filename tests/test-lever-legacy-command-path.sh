@@ -73,7 +73,10 @@ cat > "$out_path" <<'JSON_OUT_EOF'
   "summary": "legacy fallback",
   "tests": {"ran": false, "commands": [], "passed": true},
   "notes": "",
-  "blockers": []
+  "blockers": [],
+  "dod_items": [],
+  "files_changed": [],
+  "follow_ups": []
 }
 JSON_OUT_EOF
 CODEX_EOF
@@ -56,15 +56,19 @@ if [[ -z "$out_path" ]]; then
   exit 2
 fi
 
-cat > "$out_path" <<'JSON'
+task_id="$(jq -r '[.tasks[] | select(.status != "completed")][0].task_id' prd.json)"
+cat > "$out_path" <<JSON
 {
-  "task_id": "loop-task",
+  "task_id": "$task_id",
   "outcome": "completed",
   "dod_met": true,
   "summary": "ok",
   "tests": {"ran": false, "commands": [], "passed": true},
   "notes": "",
-  "blockers": []
+  "blockers": [],
+  "dod_items": [],
+  "files_changed": [],
+  "follow_ups": []
 }
 JSON
 EOF2
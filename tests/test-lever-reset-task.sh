@@ -82,7 +82,10 @@ cat > "$out_path" <<'JSON'
   "summary": "reset run ok",
   "tests": {"ran": false, "commands": [], "passed": true},
   "notes": "",
-  "blockers": []
+  "blockers": [],
+  "dod_items": [],
+  "files_changed": [],
+  "follow_ups": []
 }
 JSON
 EOF
@@ -0,0 +1,137 @@
+#!/usr/bin/env bash
+set -euo pipefail
+
+TEST_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+# shellcheck source=helpers.sh
+source "$TEST_DIR/helpers.sh"
+
+require_cmd jq
+require_cmd git
+require_cmd cargo
+
+repo_root="$(cd "$TEST_DIR/.." && pwd)"
+repo_dir="$(make_temp_dir)"
+stub_bin="$(make_temp_dir)"
+trap 'rm -rf "$repo_dir" "$stub_bin"' EXIT
+
+cat > "$repo_dir/prd.json" <<'JSON'
+{
+  "tasks": [
+    {
+      "task_id": "T1",
+      "title": "Capped task",
+      "status": "unstarted",
+      "model": "gpt-5.1-codex-mini",
+      "definition_of_done": [
+        "Never runs"
+      ],
+      "recommended": {
+        "approach": "Daily cap should stop the run"
+      }
+    }
+  ]
+}
+JSON
+
+cat > "$repo_dir/lever.json" <<'JSON'
+{
+  "rate_limits": {
+    "window_seconds": 120,
+    "models": {
+      "gpt-5.1-codex-mini": { "rpm": 50, "daily_tokens": 4000 }
+    }
+  }
+}
+JSON
+
+ensure_workspace_prompt "$repo_dir"
+
+cat > "$stub_bin/codex" <<'EOF2'
+#!/usr/bin/env bash
+set -euo pipefail
+if [[ "${1:-}" == "--version" ]]; then
+  exit 0
+fi
+touch "$(dirname "$0")/codex-invoked"
+exit 1
+EOF2
+chmod +x "$stub_bin/codex"
+
+init_git_repo "$repo_dir"
+
+now="$(date +%s)"
+mkdir -p "$repo_dir/.ralph"
+printf '{"ts":%s,"model":"gpt-5.1-codex-mini","tokens":3990}\n' "$now" > "$repo_dir/.ralph/usage.jsonl"
+printf 'not json\n' >> "$repo_dir/.ralph/usage.jsonl"
+printf '{"ts":%s,"model":"gpt-5.2-codex","tokens":10}\n' "$now" >> "$repo_dir/.ralph/usage.jsonl"
+
+(
+  cd "$repo_root"
+  cargo build --quiet
+)
+lever_bin="$repo_root/target/debug/lever"
+
+set +e
+PATH="$stub_bin:$PATH" \
+  GIT_AUTHOR_NAME=test GIT_AUTHOR_EMAIL=test@example.com \
+  GIT_COMMITTER_NAME=test GIT_COMMITTER_EMAIL=test@example.com \
+  "$lever_bin" \
+  --workspace "$repo_dir" \
+  --tasks prd.json \
+  --task-id T1 \
+  >/dev/null 2>&1
+exit_code=$?
+set -e
+
+if [[ "$exit_code" -ne 14 ]]; then
+  echo "Expected lever to exit 14 when the daily token cap is reached, got: $exit_code" >&2
+  exit 1
+fi
+
+if [[ -f "$stub_bin/codex-invoked" ]]; then
+  echo "Expected codex not to run once the daily cap is reached" >&2
+  exit 1
+fi
+
+status="$(jq -r '.tasks[0].status' "$repo_dir/prd.json")"
+if [[ "$status" != "unstarted" ]]; then
+  echo "Expected task status to stay unstarted, got: $status" >&2
+  exit 1
+fi
+
+note="$(jq -r '.tasks[0].observability.last_note // ""' "$repo_dir/prd.json")"
+if [[ "$note" != *"Daily token cap reached"* ]]; then
+  echo "Expected last_note to mention the daily token cap, got: $note" >&2
+  exit 1
+fi
+
+if ! grep -qx '/.ralph/usage.jsonl' "$repo_dir/.git/info/exclude"; then
+  echo "Expected usage store to be excluded from git" >&2
+  exit 1
+fi
+
+if [[ "$(wc -l < "$repo_dir/.ralph/usage.jsonl")" -ne 3 ]]; then
+  echo "Expected capped run not to record usage" >&2
+  exit 1
+fi
+
+usage_output="$("$lever_bin" --workspace "$repo_dir" usage --days 1)"
+today="$(date -u +%Y-%m-%d)"
+
+if ! grep -q "model=gpt-5.1-codex-mini window=120s requests=1/50 tokens=3990/200000" <<< "$usage_output"; then
+  echo "Expected window usage against configured limits, got:" >&2
+  echo "$usage_output" >&2
+  exit 1
+fi
+
+if ! grep -q "model=gpt-5.1-codex-mini day=$today requests=1 tokens=3990/4000" <<< "$usage_output"; then
+  echo "Expected daily usage against the daily cap, got:" >&2
+  echo "$usage_output" >&2
+  exit 1
+fi
+
+if ! grep -q "model=gpt-5.2-codex day=$today requests=1 tokens=10$" <<< "$usage_output"; then
+  echo "Expected uncapped model usage without a cap, got:" >&2
+  echo "$usage_output" >&2
+  exit 1
+fi
@@ -0,0 +1,273 @@
+#!/usr/bin/env bash
+set -euo pipefail
+
+TEST_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+# shellcheck source=helpers.sh
+source "$TEST_DIR/helpers.sh"
+
+require_cmd jq
+require_cmd git
+require_cmd cargo
+require_cmd python3
+
+repo_root="$(cd "$TEST_DIR/.." && pwd)"
+repo_dir="$(make_temp_dir)"
+stub_bin="$(make_temp_dir)"
+trap 'rm -rf "$repo_dir" "$stub_bin"' EXIT
+
+task_json() {
+  cat <<JSON
+    {
+      "task_id": "$1",
+      "title": "Lint threshold $1",
+      "status": "unstarted",
+      "model": "gpt-5.1-codex-mini",
+      "definition_of_done": [
+        "Respect lint threshold"
+      ],
+      "recommended": {
+        "approach": "Stub assembly"
+      }
+    }
+JSON
+}
+
+cat > "$repo_dir/prd.json" <<JSON
+{
+  "tasks": [
+$(task_json T1),
+$(task_json T2),
+$(task_json T3)
+  ]
+}
+JSON
+
+ensure_workspace_prompt "$repo_dir"
+cat > "$repo_dir/README.md" <<'EOF2'
+Test repo
+EOF2
+
+cat > "$stub_bin/codex" <<'EOF2'
+#!/usr/bin/env bash
+set -euo pipefail
+
+if [[ "${1:-}" == "--version" ]]; then
+  printf '%s\n' "codex 0.0.0"
+  exit 0
+fi
+
+printf '%s\n' "invoked" >> "$CODEX_MARKER"
+
+out_path=""
+while [[ $# -gt 0 ]]; do
+  case "$1" in
+    --output-last-message)
+      out_path="$2"
+      shift 2
+      ;;
+    *)
+      shift 1
+      ;;
+  esac
+done
+
+task_id="$(jq -r '.tasks[] | select(.status != "completed") | .task_id' prd.json | head -n 1)"
+cat > "$out_path" <<JSON
+{
+  "task_id": "$task_id",
+  "outcome": "completed",
+  "dod_met": true,
+  "summary": "ok",
+  "tests": {"ran": false, "commands": [], "passed": true},
+  "notes": "",
+  "blockers": [],
+  "dod_items": [],
+  "files_changed": [],
+  "follow_ups": []
+}
+JSON
+EOF2
+chmod +x "$stub_bin/codex"
+
+cat > "$stub_bin/assembly" <<'EOF2'
+#!/usr/bin/env bash
+set -euo pipefail
+
+if [[ "${1:-}" == "--version" ]]; then
+  printf '%s\n' "assembly 1.2.3"
+  exit 0
+fi
+
+if [[ "${1:-}" == "build" && "${2:-}" == "--help" ]]; then
+  cat <<'HELP'
+Usage: assembly build [OPTIONS]
+
+Options:
+  --repo <PATH>           Repository root
+  --task <PATH>           Task input file (supports @file)
+  --task-id <ID>          Task identifier
+  --out <DIR>             Output pack directory
+  --token-budget <TOKENS> Token budget for context
+  --exclude <GLOB>        Additive exclude glob (repeatable)
+  --exclude-runtime <GLOB> Runtime artifact exclusion glob (repeatable)
+  --summary-json <PATH>   Write machine-readable summary JSON
+HELP
+  exit 0
+fi
+
+out_dir=""
+summary=""
+task_id=""
+while [[ $# -gt 0 ]]; do
+  case "$1" in
+    --out)
+      out_dir="$2"
+      shift 2
+      ;;
+    --summary-json)
+      summary="$2"
+      shift 2
+      ;;
+    --task-id)
+      task_id="$2"
+      shift 2
+      ;;
+    *)
+      shift 1
+      ;;
+  esac
+done
+
+mkdir -p "$out_dir"
+printf '%s\n' "{}" > "$out_dir/manifest.json"
+printf '%s\n' "{}" > "$out_dir/index.json"
+printf '%s\n' "Compiled context" > "$out_dir/context.md"
+printf '%s\n' "policy" > "$out_dir/policy.md"
+if [[ "$LINT_MODE" == "malformed" ]]; then
+  printf '%s\n' '{"summary": "clean"}' > "$out_dir/lint.json"
+else
+  cat > "$out_dir/lint.json" <<'JSON'
+{
+  "issues": [
+    {"severity": "error", "message": "First", "path": "src/a.rs", "line": 1},
+    {"severity": "error", "message": "Second", "path": "src/b.rs", "line": 2},
+    {"severity": "warning", "message": "Third", "path": "src/b.rs", "line": 3}
+  ]
+}
+JSON
+fi
+
+assembly-pack-metadata "$out_dir" "$summary" "$task_id"
+EOF2
+chmod +x "$stub_bin/assembly"
+install_pack_metadata_stub "$stub_bin"
+
+init_git_repo "$repo_dir"
+
+(
+  cd "$repo_root"
+  cargo build --quiet
+)
+lever_bin="$repo_root/target/debug/lever"
+
+set_lint_config() {
+  printf '%s\n' "$1" > "$repo_dir/lever.json"
+  git -C "$repo_dir" add lever.json
+  GIT_AUTHOR_NAME=test GIT_AUTHOR_EMAIL=test@example.com \
+    GIT_COMMITTER_NAME=test GIT_COMMITTER_EMAIL=test@example.com \
+    git -C "$repo_dir" commit -q -m "lint config"
+}
+
+run_task() {
+  local task_id="$1"
+  local lint_mode="$2"
+  set +e
+  PATH="$stub_bin:$PATH" \
+    LINT_MODE="$lint_mode" \
+    CODEX_MARKER="$stub_bin/codex-$task_id" \
+    GIT_AUTHOR_NAME=test GIT_AUTHOR_EMAIL=test@example.com \
+    GIT_COMMITTER_NAME=test GIT_COMMITTER_EMAIL=test@example.com \
+    "$lever_bin" \
+    --workspace "$repo_dir" \
+    --tasks prd.json \
+    --task-id "$task_id" \
+    --context-compile \
+    >"$stub_bin/output-$task_id" 2>&1
+  run_status=$?
+  set -e
+}
+
+task_status() {
+  jq -r --arg id "$1" '.tasks[] | select(.task_id == $id) | .status' "$repo_dir/prd.json"
+}
+
+# A malformed lint report is a context-compile warning, not a failure.
+run_task T1 malformed
+if [[ $run_status -ne 0 ]]; then
+  echo "Expected malformed lint report to be tolerated, got $run_status" >&2
+  cat "$stub_bin/output-T1" >&2
+  exit 1
+fi
+run_dir="$(find "$repo_dir/.ralph/runs/T1" -mindepth 1 -maxdepth 1 -type d | head -n 1)"
+warnings="$(jq -r '.warnings | join(";")' "$run_dir/context-compile.json")"
+if [[ "$warnings" != "lint.json: no findings list"* ]]; then
+  echo "Expected lint warning in context-compile.json, got: $warnings" >&2
+  exit 1
+fi
+if ! grep -q "Lint report is malformed" "$stub_bin/output-T1"; then
+  echo "Expected malformed lint log line" >&2
+  exit 1
+fi
+
+# fail=verification lets Codex run and fails verification instead.
+set_lint_config '{"lint": {"max_errors": 1, "fail": "verification"}}'
+run_task T2 errors
+if [[ $run_status -ne 12 ]]; then
+  echo "Expected progress exit 12 after failed lint verification, got $run_status" >&2
+  cat "$stub_bin/output-T2" >&2
+  exit 1
+fi
+if [[ ! -f "$stub_bin/codex-T2" ]]; then
+  echo "Expected Codex to run with fail=verification" >&2
+  exit 1
+fi
+run_dir="$(find "$repo_dir/.ralph/runs/T2" -mindepth 1 -maxdepth 1 -type d | head -n 1)"
+if ! grep -q "lint errors 2 exceed lint.max_errors 1" "$run_dir/verify.log"; then
+  echo "Expected lint threshold failure in verify.log" >&2
+  exit 1
+fi
+if [[ "$(task_status T2)" != "started" ]]; then
+  echo "Expected T2 started, got $(task_status T2)" >&2
+  exit 1
+fi
+
+# Tasks run in order, so finish T2 on the base branch before the next scenario.
+git -C "$repo_dir" checkout -q main
+tmp="$(mktemp)"
+jq '(.tasks[] | select(.task_id == "T2") | .status) = "completed"' "$repo_dir/prd.json" > "$tmp"
+mv "$tmp" "$repo_dir/prd.json"
+git -C "$repo_dir" add prd.json
+GIT_AUTHOR_NAME=test GIT_AUTHOR_EMAIL=test@example.com \
+  GIT_COMMITTER_NAME=test GIT_COMMITTER_EMAIL=test@example.com \
+  git -C "$repo_dir" commit -q -m "complete T2"
+
+# fail=run blocks before Codex starts.
+set_lint_config '{"lint": {"max_errors": 1}}'
+run_task T3 errors
+if [[ $run_status -ne 16 ]]; then
+  echo "Expected lint threshold exit 16, got $run_status" >&2
+  cat "$stub_bin/output-T3" >&2
+  exit 1
+fi
+if [[ -f "$stub_bin/codex-T3" ]]; then
+  echo "Expected Codex not to run when lint errors exceed the threshold" >&2
+  exit 1
+fi
+if [[ "$(task_status T3)" != "blocked" ]]; then
+  echo "Expected T3 blocked, got $(task_status T3)" >&2
+  exit 1
+fi
+if ! grep -q "lint errors 2 exceed lint.max_errors 1" "$stub_bin/output-T3"; then
+  echo "Expected threshold message in output" >&2
+  exit 1
+fi
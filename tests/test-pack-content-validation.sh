@@ -0,0 +1,255 @@
+#!/usr/bin/env bash
+set -euo pipefail
+
+TEST_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+# shellcheck source=helpers.sh
+source "$TEST_DIR/helpers.sh"
+
+require_cmd git
+require_cmd cargo
+require_cmd python3
+require_cmd jq
+
+repo_root="$(cd "$TEST_DIR/.." && pwd)"
+repo_dir="$(make_temp_dir)"
+stub_bin="$(make_temp_dir)"
+trap 'rm -rf "$repo_dir" "$stub_bin"' EXIT
+
+cat > "$repo_dir/prd.json" <<'JSON'
+{
+  "tasks": [
+    {
+      "task_id": "T1",
+      "title": "Pack content validation",
+      "status": "unstarted",
+      "model": "gpt-5.1-codex-mini",
+      "definition_of_done": [
+        "Reject invalid pack contents"
+      ],
+      "recommended": {
+        "approach": "Corrupt the summary and manifest"
+      }
+    }
+  ]
+}
+JSON
+
+ensure_workspace_prompt "$repo_dir"
+cat > "$repo_dir/README.md" <<'EOF2'
+Test repo
+EOF2
+
+cat > "$stub_bin/codex" <<'EOF2'
+#!/usr/bin/env bash
+set -euo pipefail
+out_path=""
+while [[ $# -gt 0 ]]; do
+  case "$1" in
+    --output-last-message)
+      out_path="$2"
+      shift 2
+      ;;
+    *)
+      shift 1
+      ;;
+  esac
+done
+
+if [[ -z "$out_path" ]]; then
+  echo "Missing --output-last-message" >&2
+  exit 2
+fi
+
+if [[ -n "${CODEX_MARKER:-}" ]]; then
+  touch "$CODEX_MARKER"
+fi
+
+cat > "$out_path" <<'JSON'
+{
+  "task_id": "T1",
+  "outcome": "completed",
+  "dod_met": true,
+  "summary": "ok",
+  "tests": {"ran": false, "commands": [], "passed": true},
+  "notes": "",
+  "blockers": [],
+  "dod_items": [],
+  "files_changed": [],
+  "follow_ups": []
+}
+JSON
+EOF2
+chmod +x "$stub_bin/codex"
+
+cat > "$stub_bin/assembly" <<'EOF2'
+#!/usr/bin/env bash
+set -euo pipefail
+
+if [[ "${1:-}" == "--version" ]]; then
+  printf '%s\n' "assembly 1.2.3"
+  exit 0
+fi
+
+if [[ "${1:-}" == "build" && "${2:-}" == "--help" ]]; then
+  cat <<'HELP'
+Usage: assembly build [OPTIONS]
+
+Options:
+  --repo <PATH>           Repository root
+  --task <PATH>           Task input file (supports @file)
+  --task-id <ID>          Task identifier
+  --out <DIR>             Output pack directory
+  --token-budget <TOKENS> Token budget for context
+  --exclude <GLOB>        Additive exclude glob (repeatable)
+  --exclude-runtime <GLOB> Runtime artifact exclusion glob (repeatable)
+  --summary-json <PATH>   Write machine-readable summary JSON
+HELP
+  exit 0
+fi
+
+out_dir=""
+summary=""
+task_id=""
+while [[ $# -gt 0 ]]; do
+  case "$1" in
+    --out)
+      out_dir="$2"
+      shift 2
+      ;;
+    --summary-json)
+      summary="$2"
+      shift 2
+      ;;
+    --task-id)
+      task_id="$2"
+      shift 2
+      ;;
+    *)
+      shift 1
+      ;;
+  esac
+done
+
+if [[ -z "$out_dir" ]]; then
+  echo "Missing --out" >&2
+  exit 2
+fi
+
+mkdir -p "$out_dir"
+printf '%s\n' "{}" > "$out_dir/manifest.json"
+printf '%s\n' "{}" > "$out_dir/index.json"
+printf '%s\n' "Compiled context line 1" "Compiled context line 2" > "$out_dir/context.md"
+printf '%s\n' "{}" > "$out_dir/policy.md"
+printf '%s\n' "{}" > "$out_dir/lint.json"
+
+assembly-pack-metadata "$out_dir" "$summary" "$task_id"
+
+case "${CORRUPT:-}" in
+  summary)
+    printf '%s\n' '{"task_id": "OTHER", "token_count": 9000}' > "$summary"
+    ;;
+  manifest)
+    printf '%s\n' "Edited after hashing" >> "$out_dir/context.md"
+    ;;
+esac
+EOF2
+chmod +x "$stub_bin/assembly"
+install_pack_metadata_stub "$stub_bin"
+
+init_git_repo "$repo_dir"
+
+(
+  cd "$repo_root"
+  cargo build --quiet
+)
+lever_bin="$repo_root/target/debug/lever"
+
+latest_report() {
+  local run_dir
+  run_dir="$(ls -td "$repo_dir/.ralph/runs/T1"/* | head -n 1)"
+  printf '%s\n' "$run_dir/context-compile.json"
+}
+
+set +e
+required_output=$(PATH="$stub_bin:$PATH" \
+  CORRUPT=summary \
+  CODEX_MARKER="$stub_bin/codex-required.marker" \
+  GIT_AUTHOR_NAME=test GIT_AUTHOR_EMAIL=test@example.com \
+  GIT_COMMITTER_NAME=test GIT_COMMITTER_EMAIL=test@example.com \
+  "$lever_bin" \
+  --workspace "$repo_dir" \
+  --tasks prd.json \
+  --task-id T1 \
+  --context-compile \
+  --context-failure-policy required \
+  2>&1)
+required_status=$?
+set -e
+
+if [[ $required_status -ne 13 ]]; then
+  echo "Expected invalid pack contents to exit 13 with required policy, got $required_status" >&2
+  printf '%s\n' "$required_output" >&2
+  exit 1
+fi
+
+if [[ "$required_output" != *"Invalid pack contents"* ]]; then
+  echo "Expected invalid pack contents error, got: $required_output" >&2
+  exit 1
+fi
+
+if [[ -f "$stub_bin/codex-required.marker" ]]; then
+  echo "Expected required policy to stop before Codex" >&2
+  exit 1
+fi
+
+report="$(latest_report)"
+errors="$(jq -r '.pack_errors | join("|")' "$report")"
+expected="assembly-summary.json: task_id OTHER does not match task T1|assembly-summary.json: token_count 9000 exceeds token budget 8000"
+if [[ "$errors" != "$expected" ]]; then
+  echo "Unexpected pack_errors: $errors" >&2
+  exit 1
+fi
+if [[ "$(jq -r '.policy_outcome' "$report")" != "blocked" ]]; then
+  echo "Expected policy_outcome blocked in $report" >&2
+  exit 1
+fi
+
+set +e
+best_effort_output=$(PATH="$stub_bin:$PATH" \
+  CORRUPT=manifest \
+  CODEX_MARKER="$stub_bin/codex-best-effort.marker" \
+  GIT_AUTHOR_NAME=test GIT_AUTHOR_EMAIL=test@example.com \
+  GIT_COMMITTER_NAME=test GIT_COMMITTER_EMAIL=test@example.com \
+  "$lever_bin" \
+  --workspace "$repo_dir" \
+  --tasks prd.json \
+  --task-id T1 \
+  --reset-task \
+  --context-compile \
+  --context-failure-policy best-effort \
+  2>&1)
+best_effort_status=$?
+set -e
+
+if [[ $best_effort_status -ne 0 ]]; then
+  echo "Expected best-effort policy to continue past invalid pack contents" >&2
+  printf '%s\n' "$best_effort_output" >&2
+  exit 1
+fi
+
+if [[ ! -f "$stub_bin/codex-best-effort.marker" ]]; then
+  echo "Expected best-effort policy to run Codex" >&2
+  exit 1
+fi
+
+report="$(latest_report)"
+if ! jq -e '.pack_errors | any(startswith("manifest.json: sha256 mismatch for context.md"))' "$report" >/dev/null; then
+  echo "Expected a manifest hash mismatch in $report" >&2
+  cat "$report" >&2
+  exit 1
+fi
+
+if grep -q "Compiled context:" "$(dirname "$report")/prompt.md"; then
+  echo "Expected invalid pack contents to be left out of the prompt" >&2
+  exit 1
+fi
@@ -8,6 +8,7 @@ source "$TEST_DIR/helpers.sh"
 require_cmd jq
 require_cmd git
 require_cmd cargo
+require_cmd python3
 
 repo_root="$(cd "$TEST_DIR/.." && pwd)"
 repo_dir="$(make_temp_dir)"
@@ -78,7 +79,10 @@ cat > "$out_path" <<'JSON'
   "summary": "ok",
   "tests": {"ran": false, "commands": [], "passed": true},
   "notes": "",
-  "blockers": []
+  "blockers": [],
+  "dod_items": [],
+  "files_changed": [],
+  "follow_ups": []
 }
 JSON
 EOF2
@@ -112,6 +116,7 @@ fi
 
 out_dir=""
 summary=""
+task_id=""
 while [[ $# -gt 0 ]]; do
   case "$1" in
     --out)
@@ -122,6 +127,10 @@ while [[ $# -gt 0 ]]; do
       summary="$2"
       shift 2
       ;;
+    --task-id)
+      task_id="$2"
+      shift 2
+      ;;
     *)
       shift 1
       ;;
@@ -139,15 +148,17 @@ for file in "${required[@]}"; do
   if [[ -n "${MISSING_FILE:-}" && "$file" == "$MISSING_FILE" ]]; then
     continue
   fi
-  printf '%s\n' "data" > "$out_dir/$file"
+  if [[ "$file" == *.json ]]; then
+    printf '%s\n' "{}" > "$out_dir/$file"
+  else
+    printf '%s\n' "data" > "$out_dir/$file"
+  fi
 done
 
-if [[ -n "$summary" ]]; then
-  mkdir -p "$(dirname "$summary")"
-  printf '%s\n' "{}" > "$summary"
-fi
+assembly-pack-metadata "$out_dir" "$summary" "$task_id"
 EOF2
 chmod +x "$stub_bin/assembly"
+install_pack_metadata_stub "$stub_bin"
 
 init_git_repo "$repo_dir"
 
@@ -19,6 +19,7 @@ cat > "$tmp_dir/valid.json" <<'JSON'
       "status": "unstarted",
       "model": "gpt-5.1-codex-mini",
       "title": "Valid schema task",
+      "prompt": "refactor",
       "definition_of_done": [
         "A minimal valid task exists"
       ],
@@ -0,0 +1,196 @@
+#!/usr/bin/env bash
+set -euo pipefail
+
+TEST_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+# shellcheck source=helpers.sh
+source "$TEST_DIR/helpers.sh"
+
+require_cmd jq
+require_cmd git
+require_cmd cargo
+
+repo_root="$(cd "$TEST_DIR/.." && pwd)"
+repo_dir="$(make_temp_dir)"
+stub_bin="$(make_temp_dir)"
+trap 'rm -rf "$repo_dir" "$stub_bin"' EXIT
+
+cat > "$repo_dir/prd.json" <<'JSON'
+{
+  "tasks": [
+    {
+      "task_id": "T1",
+      "title": "Process timeouts",
+      "status": "unstarted",
+      "model": "gpt-5.1-codex-mini",
+      "definition_of_done": [
+        "Stop hung processes"
+      ],
+      "recommended": {
+        "approach": "Stub codex and assembly to hang"
+      },
+      "timeouts": {
+        "assembly_seconds": 1
+      }
+    }
+  ]
+}
+JSON
+
+ensure_workspace_prompt "$repo_dir"
+printf '%s\n' '{"timeouts": {"codex_seconds": 1, "kill_grace_seconds": 1}}' > "$repo_dir/lever.json"
+
+cat > "$stub_bin/codex" <<'EOF2'
+#!/usr/bin/env bash
+set -euo pipefail
+
+if [[ "${1:-}" == "--version" ]]; then
+  printf '%s\n' "codex 0.0.0"
+  exit 0
+fi
+
+printf '%s\n' "codex invoked" > "$CODEX_MARKER"
+# A grandchild that must go down with the process group.
+sleep 30 &
+printf '%s\n' "$!" > "$CODEX_CHILD_PID"
+sleep 30
+EOF2
+chmod +x "$stub_bin/codex"
+
+cat > "$stub_bin/assembly" <<'EOF2'
+#!/usr/bin/env bash
+set -euo pipefail
+
+if [[ "${1:-}" == "--version" ]]; then
+  printf '%s\n' "assembly 1.2.3"
+  exit 0
+fi
+
+if [[ "${1:-}" == "build" && "${2:-}" == "--help" ]]; then
+  cat <<'HELP'
+Usage: assembly build [OPTIONS]
+
+Options:
+  --repo <PATH>           Repository root
+  --task <PATH>           Task input file (supports @file)
+  --task-id <ID>          Task identifier
+  --out <DIR>             Output pack directory
+  --token-budget <TOKENS> Token budget for context
+  --exclude <GLOB>        Additive exclude glob (repeatable)
+  --exclude-runtime <GLOB> Runtime artifact exclusion glob (repeatable)
+  --summary-json <PATH>   Write machine-readable summary JSON
+HELP
+  exit 0
+fi
+
+trap '' TERM
+sleep 30
+EOF2
+chmod +x "$stub_bin/assembly"
+
+init_git_repo "$repo_dir"
+
+(
+  cd "$repo_root"
+  cargo build --quiet
+)
+lever_bin="$repo_root/target/debug/lever"
+
+run_lever() {
+  rm -f "$stub_bin/marker"
+  set +e
+  PATH="$stub_bin:$PATH" \
+    CODEX_MARKER="$stub_bin/marker" \
+    CODEX_CHILD_PID="$stub_bin/child.pid" \
+    GIT_AUTHOR_NAME=test GIT_AUTHOR_EMAIL=test@example.com \
+    GIT_COMMITTER_NAME=test GIT_COMMITTER_EMAIL=test@example.com \
+    "$lever_bin" \
+    --workspace "$repo_dir" \
+    --tasks prd.json \
+    --task-id T1 \
+    "$@" \
+    >"$stub_bin/output" 2>&1
+  run_status=$?
+  set -e
+}
+
+# Zombies count as gone: they are only waiting for init to reap them.
+pid_running() {
+  local state
+  state="$(ps -o stat= -p "$1" 2>/dev/null || true)"
+  [[ -n "$state" && "$state" != Z* ]]
+}
+
+observability() {
+  jq -r ".tasks[0].observability.$1" "$repo_dir/prd.json"
+}
+
+# Codex past `timeouts.codex_seconds` is stopped with its process group.
+started=$SECONDS
+run_lever
+if [[ $run_status -ne 18 ]]; then
+  echo "Expected timeout exit 18, got $run_status" >&2
+  cat "$stub_bin/output" >&2
+  exit 1
+fi
+if (( SECONDS - started >= 30 )); then
+  echo "Expected Codex to be stopped at its timeout" >&2
+  exit 1
+fi
+if pid_running "$(cat "$stub_bin/child.pid")"; then
+  echo "Expected the Codex process group to be terminated" >&2
+  exit 1
+fi
+if [[ "$(observability last_note)" != "Codex timed out after 1s for run "* ]]; then
+  echo "Expected a timeout note, got: $(observability last_note)" >&2
+  exit 1
+fi
+if [[ "$(jq -r '.tasks[0].status' "$repo_dir/prd.json")" != "started" ]]; then
+  echo "Expected the timed out task to stay started" >&2
+  exit 1
+fi
+if ! jq -e '.tasks[0].observability.elapsed_seconds.codex >= 1' "$repo_dir/prd.json" >/dev/null; then
+  echo "Expected the Codex elapsed time in observability" >&2
+  jq '.tasks[0].observability' "$repo_dir/prd.json" >&2
+  exit 1
+fi
+if ! grep -q "Process timed out.*process=Codex" "$stub_bin/output"; then
+  echo "Expected a timeout log line" >&2
+  exit 1
+fi
+
+# Assembly ignoring SIGTERM is killed after the grace period; with the
+# required policy the run ends as a timeout before Codex starts.
+run_lever --context-compile --context-failure-policy required
+if [[ $run_status -ne 18 ]]; then
+  echo "Expected Assembly timeout exit 18, got $run_status" >&2
+  cat "$stub_bin/output" >&2
+  exit 1
+fi
+if [[ -f "$stub_bin/marker" ]]; then
+  echo "Expected Codex not to run after an Assembly timeout" >&2
+  exit 1
+fi
+if [[ "$(observability last_note)" != "Assembly timed out after 1s for run "*"policy_outcome=timed_out"* ]]; then
+  echo "Expected an Assembly timeout note, got: $(observability last_note)" >&2
+  exit 1
+fi
+if [[ "$(jq -c '.tasks[0].observability.elapsed_seconds | keys' "$repo_dir/prd.json")" != '["assembly"]' ]]; then
+  echo "Expected only the Assembly elapsed time for this run" >&2
+  jq '.tasks[0].observability' "$repo_dir/prd.json" >&2
+  exit 1
+fi
+
+# In loop mode a timeout stops the loop.
+git -C "$repo_dir" checkout -q main
+run_lever --loop 2
+if [[ $run_status -ne 1 ]] || ! grep -q "Task T1 timed out" "$stub_bin/output"; then
+  echo "Expected the loop to stop on a timeout, got $run_status" >&2
+  cat "$stub_bin/output" >&2
+  exit 1
+fi
+if grep -q "lever: --loop limit reached" "$stub_bin/output"; then
+  echo "Expected the loop to stop before its limit" >&2
+  exit 1
+fi
+
+echo "ok"
@@ -0,0 +1,162 @@
+#!/usr/bin/env bash
+set -euo pipefail
+
+TEST_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+# shellcheck source=helpers.sh
+source "$TEST_DIR/helpers.sh"
+
+require_cmd jq
+require_cmd git
+require_cmd cargo
+
+repo_root="$(cd "$TEST_DIR/.." && pwd)"
+repo_dir="$(make_temp_dir)"
+stub_bin="$(make_temp_dir)"
+trap 'rm -rf "$repo_dir" "$stub_bin"' EXIT
+
+cat > "$repo_dir/prd.json" <<'JSON'
+{
+  "tasks": [
+    {
+      "task_id": "T1",
+      "title": "Checked claims",
+      "status": "unstarted",
+      "model": "gpt-5.1-codex-mini",
+      "definition_of_done": [
+        "Library builds"
+      ],
+      "recommended": {
+        "approach": "Stub claims"
+      }
+    }
+  ]
+}
+JSON
+
+ensure_workspace_prompt "$repo_dir"
+
+cat > "$stub_bin/codex" <<'EOF2'
+#!/usr/bin/env bash
+set -euo pipefail
+
+if [[ "${1:-}" == "--version" ]]; then
+  printf '%s\n' "codex 0.0.0"
+  exit 0
+fi
+
+out_path=""
+while [[ $# -gt 0 ]]; do
+  case "$1" in
+    --output-last-message)
+      out_path="$2"
+      shift 2
+      ;;
+    *)
+      shift 1
+      ;;
+  esac
+done
+
+if [[ "${HONEST:-0}" == "1" ]]; then
+  mkdir -p src
+  printf '%s\n' "pub fn lib() {}" > src/lib.rs
+  printf '%s\n' "built" > built.txt
+fi
+
+cat > "$out_path" <<'JSON'
+{
+  "task_id": "T1",
+  "outcome": "completed",
+  "dod_met": true,
+  "summary": "ok",
+  "tests": {"ran": true, "commands": ["test -f built.txt"], "passed": true},
+  "notes": "",
+  "blockers": [],
+  "dod_items": [],
+  "files_changed": ["src/lib.rs"],
+  "follow_ups": []
+}
+JSON
+EOF2
+chmod +x "$stub_bin/codex"
+
+init_git_repo "$repo_dir"
+
+(
+  cd "$repo_root"
+  cargo build --quiet
+)
+lever_bin="$repo_root/target/debug/lever"
+
+run_task() {
+  set +e
+  PATH="$stub_bin:$PATH" \
+    HONEST="$2" \
+    GIT_AUTHOR_NAME=test GIT_AUTHOR_EMAIL=test@example.com \
+    GIT_COMMITTER_NAME=test GIT_COMMITTER_EMAIL=test@example.com \
+    "$lever_bin" \
+    --workspace "$repo_dir" \
+    --tasks prd.json \
+    --task-id T1 \
+    >"$stub_bin/output-$1" 2>&1
+  run_status=$?
+  set -e
+}
+
+# The agent claims success without changing anything.
+run_task first 0
+if [[ $run_status -ne 12 ]]; then
+  echo "Expected progress exit 12 when a claimed test fails on re-run, got $run_status" >&2
+  cat "$stub_bin/output-first" >&2
+  exit 1
+fi
+run_dir="$(find "$repo_dir/.ralph/runs/T1" -mindepth 1 -maxdepth 1 -type d | head -n 1)"
+claims="$run_dir/claims.json"
+if [[ "$(jq -c '[.checks[] | select(.ok | not) | .kind]' "$claims")" != '["empty_diff","files_changed","tests_rerun"]' ]]; then
+  echo "Expected empty_diff, files_changed and tests_rerun discrepancies" >&2
+  cat "$claims" >&2
+  exit 1
+fi
+if [[ "$(jq -r '.checks[1].detail' "$claims")" != "reported but unchanged: src/lib.rs" ]]; then
+  echo "Unexpected files_changed detail" >&2
+  cat "$claims" >&2
+  exit 1
+fi
+if [[ "$(jq -r '.tasks[0].status' "$repo_dir/prd.json")" != "started" ]]; then
+  echo "Expected T1 to stay started" >&2
+  exit 1
+fi
+note="$(jq -r '.tasks[0].observability.last_note' "$repo_dir/prd.json")"
+if [[ "$note" != *"claim_discrepancies=empty_diff,files_changed,tests_rerun" ]]; then
+  echo "Expected claim discrepancies in last_note, got: $note" >&2
+  exit 1
+fi
+if [[ "$(jq -r '.tasks[0].observability.claim_discrepancies' "$repo_dir/prd.json")" != "3" ]]; then
+  echo "Expected observability.claim_discrepancies to be 3" >&2
+  exit 1
+fi
+if ! grep -q 'lever: \$ test -f built.txt' "$run_dir/verify.log"; then
+  echo "Expected the claimed test command in verify.log" >&2
+  exit 1
+fi
+if ! grep -q "Result claim does not hold" "$stub_bin/output-first"; then
+  echo "Expected claim discrepancy log lines" >&2
+  exit 1
+fi
+
+# Claims that match the run complete the task.
+run_task second 1
+if [[ $run_status -ne 0 ]]; then
+  echo "Expected completion once claims hold, got $run_status" >&2
+  cat "$stub_bin/output-second" >&2
+  exit 1
+fi
+git -C "$repo_dir" checkout -q main
+if [[ "$(jq -r '.tasks[0].status' "$repo_dir/prd.json")" != "completed" ]]; then
+  echo "Expected T1 completed" >&2
+  exit 1
+fi
+if [[ "$(jq -r '.tasks[0].observability.claim_discrepancies' "$repo_dir/prd.json")" != "3" ]]; then
+  echo "Expected the discrepancy tally to stay at 3" >&2
+  exit 1
+fi
@@ -0,0 +1,224 @@
+#!/usr/bin/env bash
+set -euo pipefail
+
+TEST_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+# shellcheck source=helpers.sh
+source "$TEST_DIR/helpers.sh"
+
+require_cmd jq
+require_cmd git
+require_cmd cargo
+
+repo_root="$(cd "$TEST_DIR/.." && pwd)"
+repo_dir="$(make_temp_dir)"
+stub_bin="$(make_temp_dir)"
+trap 'rm -rf "$repo_dir" "$stub_bin"' EXIT
+
+cat > "$repo_dir/prd.json" <<'JSON'
+{
+  "tasks": [
+    {
+      "task_id": "T1",
+      "title": "Ship the parser",
+      "status": "unstarted",
+      "model": "gpt-5.1-codex",
+      "definition_of_done": [
+        "Parser works"
+      ],
+      "recommended": {
+        "approach": "Stub result"
+      }
+    },
+    {
+      "task_id": "T2",
+      "title": "Write the changelog",
+      "status": "unstarted",
+      "model": "gpt-5.1-codex-mini",
+      "definition_of_done": [
+        "Changelog updated"
+      ],
+      "recommended": {
+        "approach": "Stub result"
+      }
+    }
+  ]
+}
+JSON
+
+ensure_workspace_prompt "$repo_dir"
+cat > "$repo_dir/prompts/autonomous-senior-engineer.prompt.md" <<'EOF2'
+# {{ title }}
+{{ previous_attempt }}
+EOF2
+
+cat > "$stub_bin/codex" <<'EOF2'
+#!/usr/bin/env bash
+set -euo pipefail
+
+if [[ "${1:-}" == "--version" ]]; then
+  printf '%s\n' "codex 0.0.0"
+  exit 0
+fi
+
+out_path=""
+schema_path=""
+while [[ $# -gt 0 ]]; do
+  case "$1" in
+    --output-last-message)
+      out_path="$2"
+      shift 2
+      ;;
+    --output-schema)
+      schema_path="$2"
+      shift 2
+      ;;
+    *)
+      shift 1
+      ;;
+  esac
+done
+cp "$schema_path" "$SCHEMA_COPY"
+
+cat > "$out_path" <<'JSON'
+{
+  "task_id": "T1",
+  "outcome": "started",
+  "dod_met": false,
+  "summary": "Parser half done",
+  "tests": {"ran": false, "commands": [], "passed": false},
+  "notes": "",
+  "blockers": [],
+  "dod_items": [
+    {"item": "Parser works", "status": "partial", "evidence": "handles numbers only"}
+  ],
+  "files_changed": ["src/parser.rs"],
+  "follow_ups": [
+    {"title": "Parse strings", "definition_of_done": ["Strings parse"], "approach": "Extend the lexer"},
+    {"title": "Write the changelog", "definition_of_done": ["Done"], "approach": "Duplicate"},
+    {"title": "Missing approach", "definition_of_done": ["Done"], "approach": ""}
+  ]
+}
+JSON
+EOF2
+chmod +x "$stub_bin/codex"
+
+# A schema from an older Lever is upgraded before Codex runs.
+mkdir -p "$repo_dir/.ralph"
+cat > "$repo_dir/.ralph/task_result.schema.json" <<'JSON'
+{
+  "$schema": "https://json-schema.org/draft/2020-12/schema",
+  "type": "object",
+  "additionalProperties": false,
+  "required": ["task_id", "outcome", "dod_met", "summary", "tests", "notes", "blockers"],
+  "properties": {
+    "task_id": { "type": "string" },
+    "outcome": { "type": "string", "enum": ["completed", "blocked", "started"] },
+    "dod_met": { "type": "boolean" },
+    "summary": { "type": "string" },
+    "tests": {
+      "type": "object",
+      "additionalProperties": false,
+      "required": ["ran", "commands", "passed"],
+      "properties": {
+        "ran": { "type": "boolean" },
+        "commands": { "type": "array", "items": { "type": "string" } },
+        "passed": { "type": "boolean" }
+      }
+    },
+    "notes": { "type": "string" },
+    "blockers": { "type": "array", "items": { "type": "string" } }
+  }
+}
+JSON
+
+init_git_repo "$repo_dir"
+
+(
+  cd "$repo_root"
+  cargo build --quiet
+)
+lever_bin="$repo_root/target/debug/lever"
+
+run_task() {
+  set +e
+  PATH="$stub_bin:$PATH" \
+    SCHEMA_COPY="$stub_bin/schema-$1.json" \
+    GIT_AUTHOR_NAME=test GIT_AUTHOR_EMAIL=test@example.com \
+    GIT_COMMITTER_NAME=test GIT_COMMITTER_EMAIL=test@example.com \
+    "$lever_bin" \
+    --workspace "$repo_dir" \
+    --tasks prd.json \
+    --task-id T1 \
+    >"$stub_bin/output-$1" 2>&1
+  run_status=$?
+  set -e
+  if [[ $run_status -ne 12 ]]; then
+    echo "Expected progress exit 12, got $run_status" >&2
+    cat "$stub_bin/output-$1" >&2
+    exit 1
+  fi
+}
+
+run_task first
+
+if ! grep -q "Upgraded result schema" "$stub_bin/output-first"; then
+  echo "Expected the stale schema to be upgraded" >&2
+  cat "$stub_bin/output-first" >&2
+  exit 1
+fi
+if [[ "$(jq -r '.title' "$stub_bin/schema-first.json")" != "Lever task result v2" ]]; then
+  echo "Expected Codex to receive the v2 schema" >&2
+  exit 1
+fi
+if [[ "$(jq -r '.required | index("follow_ups") != null' "$stub_bin/schema-first.json")" != "true" ]]; then
+  echo "Expected follow_ups in the schema" >&2
+  exit 1
+fi
+# `codex exec --output-schema` runs in strict mode, which requires every
+# property to be listed in `required`.
+if [[ "$(jq -c '(.properties | keys) - .required' "$stub_bin/schema-first.json")" != "[]" ]]; then
+  echo "Expected every schema property to be required" >&2
+  exit 1
+fi
+
+follow_ups="$(jq -c '[.tasks[] | select(.parent_task_id == "T1")]' "$repo_dir/prd.json")"
+if [[ "$(jq 'length' <<<"$follow_ups")" != "1" ]]; then
+  echo "Expected exactly one accepted follow-up, got: $follow_ups" >&2
+  exit 1
+fi
+expected='{"task_id":"T1-FU1","title":"Parse strings","status":"unstarted","model":"gpt-5.1-codex","definition_of_done":["Strings parse"],"recommended":{"approach":"Extend the lexer"},"parent_task_id":"T1"}'
+if [[ "$(jq -S -c '.[0]' <<<"$follow_ups")" != "$(jq -S -c . <<<"$expected")" ]]; then
+  echo "Unexpected follow-up task: $follow_ups" >&2
+  exit 1
+fi
+if [[ "$(grep -c "Rejected follow-up task" "$stub_bin/output-first")" != "2" ]]; then
+  echo "Expected two rejected follow-ups" >&2
+  cat "$stub_bin/output-first" >&2
+  exit 1
+fi
+if ! git -C "$repo_dir" show HEAD:prd.json | jq -e '.tasks[] | select(.task_id == "T1-FU1")' >/dev/null; then
+  echo "Expected the follow-up to be committed with the run" >&2
+  exit 1
+fi
+
+# The next run proposes the same follow-up again; it is not duplicated, and
+# the prompt reports the previous DoD status and changed files.
+run_task second
+if grep -q "Upgraded result schema" "$stub_bin/output-second"; then
+  echo "Expected the current schema to be left alone" >&2
+  exit 1
+fi
+if [[ "$(jq '[.tasks[] | select(.parent_task_id == "T1")] | length' "$repo_dir/prd.json")" != "1" ]]; then
+  echo "Expected the repeated follow-up to be rejected" >&2
+  exit 1
+fi
+prompt="$(ls -t "$repo_dir"/.ralph/runs/T1/*/prompt.md | head -n 1)"
+if [[ -z "$prompt" ]]; then
+  echo "Expected a prompt for the second run" >&2
+  exit 1
+fi
+if ! grep -q "\[partial\] Parser works" "$prompt" || ! grep -q "Files changed: src/parser.rs" "$prompt"; then
+  echo "Expected changed files in the previous attempt section" >&2
+  cat "$prompt" >&2
+  exit 1
+fi
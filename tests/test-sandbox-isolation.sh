@@ -0,0 +1,212 @@
+#!/usr/bin/env bash
+set -euo pipefail
+
+TEST_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+# shellcheck source=helpers.sh
+source "$TEST_DIR/helpers.sh"
+
+require_cmd jq
+require_cmd git
+require_cmd cargo
+require_cmd unshare
+require_cmd chroot
+
+# The bwrap stand-in below needs a private mount namespace.
+if ! unshare --mount --map-root-user true 2>/dev/null; then
+  echo "skipping: mount namespaces are not available" >&2
+  exit 0
+fi
+
+repo_root="$(cd "$TEST_DIR/.." && pwd)"
+repo_dir="$(make_temp_dir)"
+stub_bin="$(make_temp_dir)"
+# Outside /tmp, which the sandbox replaces anyway, and outside the workspace.
+mkdir -p "$repo_root/target"
+secret_dir="$(mktemp -d "$repo_root/target/sandbox-secret.XXXXXX")"
+trap 'rm -rf "$repo_dir" "$stub_bin" "$secret_dir"' EXIT
+printf '%s\n' "token" > "$secret_dir/credentials"
+secret="$secret_dir/credentials"
+hook_marker="$secret_dir/hook-ran"
+
+cat > "$repo_dir/prd.json" <<JSON
+{
+  "tasks": [
+    {
+      "task_id": "T1",
+      "title": "Sandboxed filesystem",
+      "status": "unstarted",
+      "model": "gpt-5.1-codex-mini",
+      "definition_of_done": [
+        "Only the workspace is visible"
+      ],
+      "recommended": {
+        "approach": "Probe the filesystem from verification"
+      },
+      "sandbox": {},
+      "verification": {
+        "commands": [
+          "test -f prd.json",
+          "touch built.txt",
+          "if cat $secret; then exit 1; fi",
+          "if test -e $secret_dir; then exit 1; fi",
+          "if touch /usr/lever-probe; then exit 1; fi",
+          "(echo '#!/bin/sh'; echo 'touch $hook_marker') > .git/hooks/pre-commit 2>/dev/null && chmod +x .git/hooks/pre-commit || true"
+        ]
+      }
+    }
+  ]
+}
+JSON
+
+ensure_workspace_prompt "$repo_dir"
+
+cat > "$stub_bin/codex" <<'EOF2'
+#!/usr/bin/env bash
+set -euo pipefail
+
+if [[ "${1:-}" == "--version" ]]; then
+  printf '%s\n' "codex 0.0.0"
+  exit 0
+fi
+
+out_path=""
+while [[ $# -gt 0 ]]; do
+  case "$1" in
+    --output-last-message)
+      out_path="$2"
+      shift 2
+      ;;
+    *)
+      shift 1
+      ;;
+  esac
+done
+
+outcome="completed"
+if [[ -e "$SECRET_PATH" ]]; then
+  outcome="blocked"
+fi
+cat > "$out_path" <<JSON
+{
+  "task_id": "T1",
+  "outcome": "$outcome",
+  "dod_met": true,
+  "summary": "ok",
+  "tests": {"ran": false, "commands": [], "passed": true},
+  "notes": "",
+  "blockers": [],
+  "dod_items": [],
+  "files_changed": [],
+  "follow_ups": []
+}
+JSON
+EOF2
+chmod +x "$stub_bin/codex"
+
+# A minimal bubblewrap: builds an empty root from the bind arguments in a
+# private mount namespace and runs the command chrooted into it.
+cat > "$stub_bin/bwrap" <<'EOF2'
+#!/usr/bin/env bash
+set -euo pipefail
+
+if [[ "${1:-}" == "--version" ]]; then
+  printf '%s\n' "bubblewrap 0.0.0"
+  exit 0
+fi
+if [[ -z "${BWRAP_STAND_IN:-}" ]]; then
+  exec env BWRAP_STAND_IN=1 unshare --mount --map-root-user "$0" "$@"
+fi
+
+root="$(mktemp -d)"
+mount -t tmpfs tmpfs "$root"
+chdir="/"
+while [[ $# -gt 0 ]]; do
+  case "$1" in
+    --die-with-parent|--unshare-all|--share-net)
+      shift 1
+      ;;
+    --ro-bind|--bind)
+      if [[ -d "$2" ]]; then
+        mkdir -p "$root$3"
+      else
+        mkdir -p "$(dirname "$root$3")"
+        touch "$root$3"
+      fi
+      mount --rbind "$2" "$root$3"
+      if [[ "$1" == "--ro-bind" ]]; then
+        mount -o remount,bind,ro "$root$3"
+      fi
+      shift 3
+      ;;
+    --symlink)
+      ln -s "$2" "$root$3"
+      shift 3
+      ;;
+    --dev|--proc)
+      mkdir -p "$root$2"
+      mount --rbind "$2" "$root$2"
+      shift 2
+      ;;
+    --tmpfs)
+      mkdir -p "$root$2"
+      mount -t tmpfs tmpfs "$root$2"
+      shift 2
+      ;;
+    --chdir)
+      chdir="$2"
+      shift 2
+      ;;
+    --)
+      shift 1
+      break
+      ;;
+    *)
+      echo "bwrap stand-in: unsupported argument $1" >&2
+      exit 2
+      ;;
+  esac
+done
+exec chroot "$root" /bin/sh -c 'cd "$1" && shift && exec "$@"' sh "$chdir" "$@"
+EOF2
+chmod +x "$stub_bin/bwrap"
+
+init_git_repo "$repo_dir"
+
+(
+  cd "$repo_root"
+  cargo build --quiet
+)
+lever_bin="$repo_root/target/debug/lever"
+
+set +e
+PATH="$stub_bin:$PATH" \
+  SECRET_PATH="$secret" \
+  CODEX_HOME="$stub_bin/no-codex-home" \
+  GIT_AUTHOR_NAME=test GIT_AUTHOR_EMAIL=test@example.com \
+  GIT_COMMITTER_NAME=test GIT_COMMITTER_EMAIL=test@example.com \
+  "$lever_bin" \
+  --workspace "$repo_dir" \
+  --tasks prd.json \
+  --task-id T1 \
+  >"$stub_bin/output" 2>&1
+run_status=$?
+set -e
+
+if [[ $run_status -ne 0 ]]; then
+  echo "Expected T1 to complete with only the workspace visible, got $run_status" >&2
+  cat "$stub_bin/output" >&2
+  find "$repo_dir/.ralph/runs/T1" -name verify.log -exec cat {} + >&2 || true
+  exit 1
+fi
+
+if [[ ! -f "$repo_dir/built.txt" ]]; then
+  echo "Expected verification to write to the workspace" >&2
+  exit 1
+fi
+
+# Lever commits on the host after verification, so a hook written from
+# inside the sandbox would run outside it.
+if [[ -e "$repo_dir/.git/hooks/pre-commit" || -e "$hook_marker" ]]; then
+  echo "Expected .git to be read-only inside the sandbox" >&2
+  exit 1
+fi
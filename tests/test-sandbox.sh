@@ -0,0 +1,202 @@
+#!/usr/bin/env bash
+set -euo pipefail
+
+TEST_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+# shellcheck source=helpers.sh
+source "$TEST_DIR/helpers.sh"
+
+require_cmd jq
+require_cmd git
+require_cmd cargo
+
+repo_root="$(cd "$TEST_DIR/.." && pwd)"
+repo_dir="$(make_temp_dir)"
+stub_bin="$(make_temp_dir)"
+trap 'rm -rf "$repo_dir" "$stub_bin"' EXIT
+
+cat > "$repo_dir/prd.json" <<'JSON'
+{
+  "tasks": [
+    {
+      "task_id": "T1",
+      "title": "Sandboxed task",
+      "status": "unstarted",
+      "model": "gpt-5.1-codex-mini",
+      "definition_of_done": [
+        "Suite passes"
+      ],
+      "recommended": {
+        "approach": "Stub verification"
+      },
+      "sandbox": {
+        "cpu_seconds": 60,
+        "memory_mb": 4096
+      },
+      "verification": {
+        "commands": [
+          "ulimit -t | grep -qx 60"
+        ]
+      }
+    },
+    {
+      "task_id": "T2",
+      "title": "Sandboxed task with a wall-clock limit",
+      "status": "unstarted",
+      "model": "gpt-5.1-codex-mini",
+      "definition_of_done": [
+        "Suite passes"
+      ],
+      "recommended": {
+        "approach": "Stub verification"
+      },
+      "sandbox": {},
+      "timeouts": {
+        "verification_seconds": 5
+      },
+      "verification": {
+        "commands": [
+          "sleep 30"
+        ]
+      }
+    }
+  ]
+}
+JSON
+
+ensure_workspace_prompt "$repo_dir"
+
+cat > "$stub_bin/codex" <<'EOF2'
+#!/usr/bin/env bash
+set -euo pipefail
+
+if [[ "${1:-}" == "--version" ]]; then
+  printf '%s\n' "codex 0.0.0"
+  exit 0
+fi
+
+out_path=""
+while [[ $# -gt 0 ]]; do
+  case "$1" in
+    --output-last-message)
+      out_path="$2"
+      shift 2
+      ;;
+    *)
+      shift 1
+      ;;
+  esac
+done
+
+cat > "$out_path" <<JSON
+{
+  "task_id": "$STUB_TASK_ID",
+  "outcome": "completed",
+  "dod_met": true,
+  "summary": "ok",
+  "tests": {"ran": false, "commands": [], "passed": true},
+  "notes": "",
+  "blockers": [],
+  "dod_items": [],
+  "files_changed": [],
+  "follow_ups": []
+}
+JSON
+EOF2
+chmod +x "$stub_bin/codex"
+
+# Records each invocation and runs the command after `--` unconfined.
+cat > "$stub_bin/bwrap" <<'EOF2'
+#!/usr/bin/env bash
+set -euo pipefail
+
+if [[ "${1:-}" == "--version" ]]; then
+  printf '%s\n' "bubblewrap 0.0.0"
+  exit 0
+fi
+
+printf '%s\n' "$*" >> "$BWRAP_LOG"
+while [[ $# -gt 0 && "$1" != "--" ]]; do
+  shift 1
+done
+shift 1
+exec "$@"
+EOF2
+chmod +x "$stub_bin/bwrap"
+
+init_git_repo "$repo_dir"
+
+(
+  cd "$repo_root"
+  cargo build --quiet
+)
+lever_bin="$repo_root/target/debug/lever"
+
+run_task() {
+  set +e
+  PATH="$stub_bin:$PATH" \
+    STUB_TASK_ID="$1" \
+    BWRAP_LOG="$stub_bin/bwrap-$1.log" \
+    GIT_AUTHOR_NAME=test GIT_AUTHOR_EMAIL=test@example.com \
+    GIT_COMMITTER_NAME=test GIT_COMMITTER_EMAIL=test@example.com \
+    "$lever_bin" \
+    --workspace "$repo_dir" \
+    --tasks prd.json \
+    --task-id "$1" \
+    >"$stub_bin/output-$1" 2>&1
+  run_status=$?
+  set -e
+}
+
+# Codex and verification both run under bwrap with the task's limits.
+run_task T1
+if [[ $run_status -ne 0 ]]; then
+  echo "Expected T1 to complete in the sandbox, got $run_status" >&2
+  cat "$stub_bin/output-T1" >&2
+  exit 1
+fi
+if ! grep -q -- "--share-net .* codex exec" "$stub_bin/bwrap-T1.log"; then
+  echo "Expected Codex to run under bwrap with network" >&2
+  cat "$stub_bin/bwrap-T1.log" >&2
+  exit 1
+fi
+verify_line="$(grep -- "-- bash -c ulimit -t 60 && ulimit -v 4194304 && exec" "$stub_bin/bwrap-T1.log" | grep -v "codex exec")"
+if [[ -z "$verify_line" || "$verify_line" == *"--share-net"* ]]; then
+  echo "Expected verification under bwrap without network" >&2
+  cat "$stub_bin/bwrap-T1.log" >&2
+  exit 1
+fi
+if [[ "$verify_line" != *"--bind $repo_dir $repo_dir"* ]]; then
+  echo "Expected the workspace to be writable in the sandbox" >&2
+  exit 1
+fi
+run_dir="$(find "$repo_dir/.ralph/runs/T1" -mindepth 1 -maxdepth 1 -type d | head -n 1)"
+if [[ "$(jq -c '[.source, .network, .cpu_seconds, .memory_mb]' "$run_dir/sandbox.json")" != '["task",false,60,4096]' ]]; then
+  echo "Expected the sandbox settings in sandbox.json" >&2
+  cat "$run_dir/sandbox.json" >&2
+  exit 1
+fi
+
+# The task's verification timeout stops a sandboxed command that runs too
+# long.
+started=$SECONDS
+run_task T2
+if [[ $run_status -ne 12 ]]; then
+  echo "Expected T2 progress exit 12 after the timeout, got $run_status" >&2
+  cat "$stub_bin/output-T2" >&2
+  exit 1
+fi
+if (( SECONDS - started >= 30 )); then
+  echo "Expected the verification timeout to stop the sandboxed command" >&2
+  exit 1
+fi
+
+# Without bwrap a sandboxed task does not run.
+rm "$stub_bin/bwrap"
+run_task T2
+if [[ $run_status -eq 0 ]] || ! grep -q "Missing dependency: bwrap" "$stub_bin/output-T2"; then
+  echo "Expected a missing bwrap error" >&2
+  cat "$stub_bin/output-T2" >&2
+  exit 1
+fi
+
+echo "ok"
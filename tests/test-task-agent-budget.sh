@@ -0,0 +1,196 @@
+#!/usr/bin/env bash
+set -euo pipefail
+
+TEST_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+# shellcheck source=helpers.sh
+source "$TEST_DIR/helpers.sh"
+
+require_cmd jq
+require_cmd git
+require_cmd cargo
+
+repo_root="$(cd "$TEST_DIR/.." && pwd)"
+repo_dir="$(make_temp_dir)"
+stub_bin="$(make_temp_dir)"
+trap 'rm -rf "$repo_dir" "$stub_bin"' EXIT
+
+cat > "$repo_dir/prd.json" <<'JSON'
+{
+  "tasks": [
+    {
+      "task_id": "T1",
+      "title": "Budgeted task",
+      "status": "unstarted",
+      "model": "gpt-5.1-codex-mini",
+      "definition_of_done": [
+        "Stay within budget"
+      ],
+      "recommended": {
+        "approach": "Spend tokens once"
+      },
+      "budget": {
+        "max_tokens": 4050
+      }
+    }
+  ]
+}
+JSON
+
+cat > "$repo_dir/lever.json" <<'JSON'
+{
+  "pricing": {
+    "gpt-5.1-codex-mini": { "input_per_million": 1.0, "output_per_million": 4.0 }
+  }
+}
+JSON
+
+ensure_workspace_prompt "$repo_dir"
+
+cat > "$stub_bin/codex" <<EOF2
+#!/usr/bin/env bash
+set -euo pipefail
+if [[ "\${1:-}" == "--version" ]]; then
+  echo "codex stub"
+  exit 0
+fi
+echo run >> "$stub_bin/invocations"
+out_path=""
+while [[ \$# -gt 0 ]]; do
+  case "\$1" in
+    --output-last-message)
+      out_path="\$2"
+      shift 2
+      ;;
+    *)
+      shift 1
+      ;;
+  esac
+done
+
+echo '{"type":"turn.completed","usage":{"input_tokens":3000,"output_tokens":1000}}'
+
+cat > "\$out_path" <<'JSON'
+{
+  "task_id": "T1",
+  "outcome": "started",
+  "dod_met": false,
+  "summary": "partial",
+  "tests": {"ran": false, "commands": [], "passed": false},
+  "notes": "",
+  "blockers": [],
+  "dod_items": [],
+  "files_changed": [],
+  "follow_ups": []
+}
+JSON
+EOF2
+chmod +x "$stub_bin/codex"
+
+init_git_repo "$repo_dir"
+
+(
+  cd "$repo_root"
+  cargo build --quiet
+)
+lever_bin="$repo_root/target/debug/lever"
+
+run_lever() {
+  set +e
+  PATH="$stub_bin:$PATH" \
+    GIT_AUTHOR_NAME=test GIT_AUTHOR_EMAIL=test@example.com \
+    GIT_COMMITTER_NAME=test GIT_COMMITTER_EMAIL=test@example.com \
+    "$lever_bin" \
+    --workspace "$repo_dir" \
+    --tasks prd.json \
+    --task-id T1 \
+    "$@" \
+    >/dev/null 2>&1
+  exit_code=$?
+  set -e
+}
+
+run_lever
+if [[ "$exit_code" -ne 12 ]]; then
+  echo "Expected first run to record progress (12), got: $exit_code" >&2
+  exit 1
+fi
+
+total_tokens="$(jq -r '.tasks[0].observability.total_tokens' "$repo_dir/prd.json")"
+if [[ "$total_tokens" != "4000" ]]; then
+  echo "Expected observability.total_tokens=4000, got: $total_tokens" >&2
+  exit 1
+fi
+
+total_cost="$(jq -r '.tasks[0].observability.total_cost_usd' "$repo_dir/prd.json")"
+if [[ "$total_cost" != "0.007" ]]; then
+  echo "Expected observability.total_cost_usd=0.007, got: $total_cost" >&2
+  exit 1
+fi
+
+ledger="$repo_dir/.ralph/usage.jsonl"
+ledger_entry="$(jq -c 'select(.task_id == "T1")' "$ledger")"
+if [[ "$(jq -r '.input_tokens, .output_tokens, .tokens' <<< "$ledger_entry" | paste -sd,)" != "3000,1000,4000" ]]; then
+  echo "Expected ledger entry with token split, got: $ledger_entry" >&2
+  exit 1
+fi
+if [[ "$(jq -r '.session_id // ""' <<< "$ledger_entry")" == "" ]]; then
+  echo "Expected ledger entry to carry a session_id, got: $ledger_entry" >&2
+  exit 1
+fi
+
+run_lever
+if [[ "$exit_code" -ne 15 ]]; then
+  echo "Expected task budget to stop the second run (15), got: $exit_code" >&2
+  exit 1
+fi
+
+if [[ "$(wc -l < "$stub_bin/invocations")" -ne 1 ]]; then
+  echo "Expected codex to run only once" >&2
+  exit 1
+fi
+
+status="$(jq -r '.tasks[0].status' "$repo_dir/prd.json")"
+if [[ "$status" != "blocked" ]]; then
+  echo "Expected task over budget to be blocked, got: $status" >&2
+  exit 1
+fi
+
+note="$(jq -r '.tasks[0].observability.last_note // ""' "$repo_dir/prd.json")"
+if [[ "$note" != *"task token budget exceeded"* ]]; then
+  echo "Expected last_note to explain the task budget, got: $note" >&2
+  exit 1
+fi
+
+tmp="$(mktemp)"
+jq '.tasks[0].status = "started" | del(.tasks[0].budget)' "$repo_dir/prd.json" > "$tmp"
+mv "$tmp" "$repo_dir/prd.json"
+(
+  cd "$repo_dir"
+  git add prd.json
+  GIT_AUTHOR_NAME=test GIT_AUTHOR_EMAIL=test@example.com \
+    GIT_COMMITTER_NAME=test GIT_COMMITTER_EMAIL=test@example.com \
+    git commit -m "raise budget" >/dev/null
+)
+
+run_lever --max-tokens 50
+if [[ "$exit_code" -ne 15 ]]; then
+  echo "Expected --max-tokens to stop the run (15), got: $exit_code" >&2
+  exit 1
+fi
+
+status="$(jq -r '.tasks[0].status' "$repo_dir/prd.json")"
+if [[ "$status" != "started" ]]; then
+  echo "Expected --max-tokens to leave the status unchanged, got: $status" >&2
+  exit 1
+fi
+
+note="$(jq -r '.tasks[0].observability.last_note // ""' "$repo_dir/prd.json")"
+if [[ "$note" != *"loop token budget exceeded"* ]]; then
+  echo "Expected last_note to explain the loop budget, got: $note" >&2
+  exit 1
+fi
+
+if [[ "$(wc -l < "$stub_bin/invocations")" -ne 1 ]]; then
+  echo "Expected codex not to run past the --max-tokens cap" >&2
+  exit 1
+fi
@@ -0,0 +1,97 @@
+#!/usr/bin/env bash
+set -euo pipefail
+
+TEST_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+# shellcheck source=helpers.sh
+source "$TEST_DIR/helpers.sh"
+
+require_cmd jq
+require_cmd git
+require_cmd cargo
+
+repo_root="$(cd "$TEST_DIR/.." && pwd)"
+repo_dir="$(make_temp_dir)"
+stub_bin="$(make_temp_dir)"
+trap 'rm -rf "$repo_dir" "$stub_bin"' EXIT
+
+cat > "$repo_dir/prd.json" <<'JSON'
+{
+  "tasks": [
+    {
+      "task_id": "T1",
+      "title": "Prompt over budget",
+      "status": "unstarted",
+      "model": "gpt-5.1-codex-mini",
+      "definition_of_done": [
+        "Never reaches Codex"
+      ],
+      "recommended": {
+        "approach": "Required sections alone exceed the budget"
+      }
+    }
+  ]
+}
+JSON
+
+ensure_workspace_prompt "$repo_dir"
+
+cat > "$stub_bin/codex" <<EOF2
+#!/usr/bin/env bash
+set -euo pipefail
+if [[ "\${1:-}" == "--version" ]]; then
+  echo "codex stub"
+  exit 0
+fi
+echo run >> "$stub_bin/invocations"
+exit 1
+EOF2
+chmod +x "$stub_bin/codex"
+
+init_git_repo "$repo_dir"
+
+(
+  cd "$repo_root"
+  cargo build --quiet
+)
+lever_bin="$repo_root/target/debug/lever"
+
+set +e
+PATH="$stub_bin:$PATH" \
+  GIT_AUTHOR_NAME=test GIT_AUTHOR_EMAIL=test@example.com \
+  GIT_COMMITTER_NAME=test GIT_COMMITTER_EMAIL=test@example.com \
+  "$lever_bin" \
+  --workspace "$repo_dir" \
+  --tasks prd.json \
+  --task-id T1 \
+  --prompt-token-budget 5 \
+  >/dev/null 2>&1
+exit_code=$?
+set -e
+
+if [[ "$exit_code" -ne 19 ]]; then
+  echo "Expected lever to exit 19 when required prompt sections exceed the budget, got: $exit_code" >&2
+  exit 1
+fi
+
+if [[ -f "$stub_bin/invocations" ]]; then
+  echo "Expected codex not to run when the prompt exceeds its budget" >&2
+  exit 1
+fi
+
+status="$(jq -r '.tasks[0].status' "$repo_dir/prd.json")"
+if [[ "$status" != "blocked" ]]; then
+  echo "Expected task status blocked, got: $status" >&2
+  exit 1
+fi
+
+note="$(jq -r '.tasks[0].observability.last_note // ""' "$repo_dir/prd.json")"
+if [[ "$note" != *"prompt exceeds token budget"*"prompt-budget.json"* ]]; then
+  echo "Expected last_note to point at prompt-budget.json, got: $note" >&2
+  exit 1
+fi
+
+run_dir="$(find "$repo_dir/.ralph/runs/T1" -mindepth 1 -maxdepth 1 -type d | head -n 1)"
+if [[ "$(jq -r '.fits' "$run_dir/prompt-budget.json")" != "false" ]]; then
+  echo "Expected prompt budget report to record a prompt that does not fit" >&2
+  exit 1
+fi
@@ -0,0 +1,246 @@
+#!/usr/bin/env bash
+set -euo pipefail
+
+TEST_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+# shellcheck source=helpers.sh
+source "$TEST_DIR/helpers.sh"
+
+require_cmd git
+require_cmd cargo
+require_cmd python3
+require_cmd jq
+
+repo_root="$(cd "$TEST_DIR/.." && pwd)"
+repo_dir="$(make_temp_dir)"
+stub_bin="$(make_temp_dir)"
+trap 'rm -rf "$repo_dir" "$stub_bin"' EXIT
+
+cat > "$repo_dir/prd.json" <<'JSON'
+{
+  "tasks": [
+    {
+      "task_id": "T1",
+      "title": "Prompt budget priorities",
+      "status": "unstarted",
+      "model": "gpt-5.1-codex-mini",
+      "definition_of_done": [
+        "Append compiled context"
+      ],
+      "recommended": {
+        "approach": "Use compiled context"
+      }
+    }
+  ]
+}
+JSON
+
+ensure_workspace_prompt "$repo_dir"
+
+cat > "$repo_dir/README.md" <<'EOF2'
+Test repo
+EOF2
+
+cat > "$stub_bin/codex" <<'EOF2'
+#!/usr/bin/env bash
+set -euo pipefail
+out_path=""
+while [[ $# -gt 0 ]]; do
+  case "$1" in
+    --output-last-message)
+      out_path="$2"
+      shift 2
+      ;;
+    *)
+      shift 1
+      ;;
+  esac
+done
+
+if [[ -z "$out_path" ]]; then
+  echo "Missing --output-last-message" >&2
+  exit 2
+fi
+
+cat > "$out_path" <<'JSON'
+{
+  "task_id": "T1",
+  "outcome": "completed",
+  "dod_met": true,
+  "summary": "ok",
+  "tests": {"ran": false, "commands": [], "passed": true},
+  "notes": "",
+  "blockers": [],
+  "dod_items": [],
+  "files_changed": [],
+  "follow_ups": []
+}
+JSON
+EOF2
+chmod +x "$stub_bin/codex"
+
+cat > "$stub_bin/assembly" <<'EOF2'
+#!/usr/bin/env bash
+set -euo pipefail
+
+if [[ "${1:-}" == "--version" ]]; then
+  printf '%s\n' "assembly 1.2.3"
+  exit 0
+fi
+
+if [[ "${1:-}" == "build" && "${2:-}" == "--help" ]]; then
+  cat <<'HELP'
+Usage: assembly build [OPTIONS]
+
+Options:
+  --repo <PATH>           Repository root
+  --task <PATH>           Task input file (supports @file)
+  --task-id <ID>          Task identifier
+  --out <DIR>             Output pack directory
+  --token-budget <TOKENS> Token budget for context
+  --exclude <GLOB>        Additive exclude glob (repeatable)
+  --exclude-runtime <GLOB> Runtime artifact exclusion glob (repeatable)
+  --summary-json <PATH>   Write machine-readable summary JSON
+HELP
+  exit 0
+fi
+
+out_dir=""
+summary=""
+task_id=""
+while [[ $# -gt 0 ]]; do
+  case "$1" in
+    --out)
+      out_dir="$2"
+      shift 2
+      ;;
+    --summary-json)
+      summary="$2"
+      shift 2
+      ;;
+    --task-id)
+      task_id="$2"
+      shift 2
+      ;;
+    *)
+      shift 1
+      ;;
+  esac
+done
+
+if [[ -z "$out_dir" ]]; then
+  echo "Missing --out" >&2
+  exit 2
+fi
+
+mkdir -p "$out_dir"
+printf '%s\n' "{}" > "$out_dir/manifest.json"
+cat > "$out_dir/index.json" <<'JSON'
+{
+  "files": [
+    { "path": "src/module_a.rs", "score": 0.9 },
+    { "path": "src/module_b.rs", "score": 0.1 },
+    { "path": "src/module_c.rs", "score": 0.8 },
+    { "path": "src/module_d.rs", "score": 0.2 },
+    { "path": "src/module_e.rs", "score": 0.7 },
+    { "path": "src/module_f.rs", "score": 0.3 }
+  ]
+}
+JSON
+{
+  printf '# Context pack\n'
+  for name in a b c d e f; do
+    printf '## src/module_%s.rs\n```rust\n' "$name"
+    for idx in $(seq 1 15); do
+      printf 'let module_%s_value_%s = compute_%s(%s);\n' "$name" "$idx" "$name" "$idx"
+    done
+    printf '```\n'
+  done
+} > "$out_dir/context.md"
+printf '%s\n' "{}" > "$out_dir/policy.md"
+printf '%s\n' "{}" > "$out_dir/lint.json"
+
+assembly-pack-metadata "$out_dir" "$summary" "$task_id"
+EOF2
+chmod +x "$stub_bin/assembly"
+install_pack_metadata_stub "$stub_bin"
+
+cat > "$repo_dir/lever.json" <<'JSON'
+{
+  "prompt_budget": {
+    "max_tokens": 800
+  }
+}
+JSON
+
+init_git_repo "$repo_dir"
+
+(
+  cd "$repo_root"
+  cargo build --quiet
+)
+lever_bin="$repo_root/target/debug/lever"
+
+PATH="$stub_bin:$PATH" \
+  GIT_AUTHOR_NAME=test GIT_AUTHOR_EMAIL=test@example.com \
+  GIT_COMMITTER_NAME=test GIT_COMMITTER_EMAIL=test@example.com \
+  "$lever_bin" \
+  --workspace "$repo_dir" \
+  --tasks prd.json \
+  --task-id T1 \
+  --context-compile \
+  >/dev/null 2>&1
+
+run_dir="$(find "$repo_dir/.ralph/runs/T1" -mindepth 1 -maxdepth 1 -type d | head -n 1)"
+if [[ -z "$run_dir" ]]; then
+  echo "Expected run directory to exist" >&2
+  exit 1
+fi
+
+prompt_file="$run_dir/prompt.md"
+report="$run_dir/prompt-budget.json"
+if [[ ! -f "$report" ]]; then
+  echo "Expected prompt budget report at $report" >&2
+  exit 1
+fi
+
+if [[ "$(jq -r '.budget' "$report")" != "800" || "$(jq -r '.fits' "$report")" != "true" ]]; then
+  echo "Expected lever.json prompt budget to be applied and met" >&2
+  exit 1
+fi
+
+if (( $(jq -r '.total_tokens' "$report") > 800 )); then
+  echo "Expected prompt to fit the 800 token budget" >&2
+  exit 1
+fi
+
+cuts="$(jq -r '.cuts | map(.section + ":" + .action + ":" + (.path // "")) | join(",")' "$report")"
+expected="task_json:elide_duplicates:,compiled_context:drop_file:src/module_b.rs,compiled_context:drop_file:src/module_d.rs,compiled_context:drop_file:src/module_f.rs"
+if [[ "$cuts" != "$expected" ]]; then
+  echo "Unexpected prompt budget cuts: $cuts" >&2
+  exit 1
+fi
+
+priorities="$(jq -r '.sections | map(.name + "=" + (.priority | tostring)) | join(",")' "$report")"
+if [[ "$priorities" != "base_prompt=null,task=null,task_json=10,compiled_context=20" ]]; then
+  echo "Unexpected section priorities: $priorities" >&2
+  exit 1
+fi
+
+for name in a c e; do
+  if ! grep -q "module_${name}_value_15" "$prompt_file"; then
+    echo "Expected high-priority file src/module_${name}.rs to be kept" >&2
+    exit 1
+  fi
+done
+
+for name in b d f; do
+  if grep -q "module_${name}_value_1 " "$prompt_file"; then
+    echo "Expected low-priority file src/module_${name}.rs to be dropped" >&2
+    exit 1
+  fi
+done
+
+if ! grep -q "dropped 3 low-priority file(s) to fit the prompt token budget" "$prompt_file"; then
+  echo "Expected dropped files note in prompt" >&2
+  exit 1
+fi
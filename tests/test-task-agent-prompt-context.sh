@@ -7,7 +7,8 @@ source "$TEST_DIR/helpers.sh"
 
 require_cmd git
 require_cmd cargo
-require_cmd python
+require_cmd python3
+require_cmd jq
 
 repo_root="$(cd "$TEST_DIR/.." && pwd)"
 repo_dir="$(make_temp_dir)"
@@ -68,7 +69,10 @@ cat > "$out_path" <<'JSON'
   "summary": "ok",
   "tests": {"ran": false, "commands": [], "passed": true},
   "notes": "",
-  "blockers": []
+  "blockers": [],
+  "dod_items": [],
+  "files_changed": [],
+  "follow_ups": []
 }
 JSON
 EOF2
@@ -102,6 +106,7 @@ fi
 
 out_dir=""
 summary=""
+task_id=""
 while [[ $# -gt 0 ]]; do
   case "$1" in
     --out)
@@ -112,6 +117,10 @@ while [[ $# -gt 0 ]]; do
       summary="$2"
       shift 2
       ;;
+    --task-id)
+      task_id="$2"
+      shift 2
+      ;;
     *)
       shift 1
       ;;
@@ -130,12 +139,10 @@ printf '%s\n' "Compiled context line 1" "Compiled context line 2" > "$out_dir/co
 printf '%s\n' "{}" > "$out_dir/policy.md"
 printf '%s\n' "{}" > "$out_dir/lint.json"
 
-if [[ -n "$summary" ]]; then
-  mkdir -p "$(dirname "$summary")"
-  printf '%s\n' "{}" > "$summary"
-fi
+assembly-pack-metadata "$out_dir" "$summary" "$task_id"
 EOF2
 chmod +x "$stub_bin/assembly"
+install_pack_metadata_stub "$stub_bin"
 
 init_git_repo "$repo_dir"
 commit_sha="$(git -C "$repo_dir" rev-parse --short=12 HEAD)"
@@ -196,3 +203,25 @@ if ! diff -u "$expected_file" "$prompt_file"; then
   echo "Expected prompt to include compiled context appended to base prompt" >&2
   exit 1
 fi
+
+report="$run_dir/prompt-tokens.json"
+sections="$(jq -r '.sections | map(.name + "=" + .status) | join(",")' "$report")"
+if [[ "$sections" != "base_prompt=included,task=included,task_json=included,compiled_context=included" ]]; then
+  echo "Expected prompt token report to list included sections, got: $sections" >&2
+  exit 1
+fi
+
+if [[ "$(jq -r '.budget' "$report")" != "null" ]]; then
+  echo "Expected no prompt budget by default" >&2
+  exit 1
+fi
+
+if [[ "$(jq -r '.template' "$report")" != "default" ]]; then
+  echo "Expected default prompt template" >&2
+  exit 1
+fi
+
+if [[ -e "$run_dir/prompt-budget.json" ]]; then
+  echo "Expected no prompt budget report without a budget" >&2
+  exit 1
+fi
@@ -7,7 +7,7 @@ source "$TEST_DIR/helpers.sh"
 
 require_cmd git
 require_cmd cargo
-require_cmd python
+require_cmd python3
 
 repo_root="$(cd "$TEST_DIR/.." && pwd)"
 repo_dir="$(make_temp_dir)"
@@ -68,7 +68,10 @@ cat > "$out_path" <<'JSON'
   "summary": "ok",
   "tests": {"ran": false, "commands": [], "passed": true},
   "notes": "",
-  "blockers": []
+  "blockers": [],
+  "dod_items": [],
+  "files_changed": [],
+  "follow_ups": []
 }
 JSON
 EOF2
@@ -102,6 +105,7 @@ fi
 
 out_dir=""
 summary=""
+task_id=""
 while [[ $# -gt 0 ]]; do
   case "$1" in
     --out)
@@ -112,6 +116,10 @@ while [[ $# -gt 0 ]]; do
       summary="$2"
       shift 2
       ;;
+    --task-id)
+      task_id="$2"
+      shift 2
+      ;;
     *)
       shift 1
       ;;
@@ -136,12 +144,10 @@ cat > "$out_dir/lint.json" <<'JSON'
 }
 JSON
 
-if [[ -n "$summary" ]]; then
-  mkdir -p "$(dirname "$summary")"
-  printf '%s\n' "{}" > "$summary"
-fi
+assembly-pack-metadata "$out_dir" "$summary" "$task_id"
 EOF2
 chmod +x "$stub_bin/assembly"
+install_pack_metadata_stub "$stub_bin"
 
 init_git_repo "$repo_dir"
 commit_sha="$(git -C "$repo_dir" rev-parse --short=12 HEAD)"
@@ -7,7 +7,7 @@ source "$TEST_DIR/helpers.sh"
 
 require_cmd git
 require_cmd cargo
-require_cmd python
+require_cmd python3
 
 repo_root="$(cd "$TEST_DIR/.." && pwd)"
 repo_dir="$(make_temp_dir)"
@@ -68,7 +68,10 @@ cat > "$out_path" <<'JSON'
   "summary": "ok",
   "tests": {"ran": false, "commands": [], "passed": true},
   "notes": "",
-  "blockers": []
+  "blockers": [],
+  "dod_items": [],
+  "files_changed": [],
+  "follow_ups": []
 }
 JSON
 EOF2
@@ -102,6 +105,7 @@ fi
 
 out_dir=""
 summary=""
+task_id=""
 while [[ $# -gt 0 ]]; do
   case "$1" in
     --out)
@@ -112,6 +116,10 @@ while [[ $# -gt 0 ]]; do
       summary="$2"
       shift 2
       ;;
+    --task-id)
+      task_id="$2"
+      shift 2
+      ;;
     *)
       shift 1
       ;;
@@ -125,7 +133,7 @@ fi
 
 mkdir -p "$out_dir"
 printf '%s\n' "{}" > "$out_dir/manifest.json"
-printf '%s\n' "{}" > "$out_dir/index.json"
+printf '%s\n' '{"files": [{"path": "src/lib.rs"}]}' > "$out_dir/index.json"
 printf '%s\n' "Compiled context line 1" "Compiled context line 2" > "$out_dir/context.md"
 printf '%s\n' "{}" > "$out_dir/policy.md"
 cat > "$out_dir/lint.json" <<'JSON'
@@ -138,12 +146,10 @@ cat > "$out_dir/lint.json" <<'JSON'
 }
 JSON
 
-if [[ -n "$summary" ]]; then
-  mkdir -p "$(dirname "$summary")"
-  printf '%s\n' "{}" > "$summary"
-fi
+assembly-pack-metadata "$out_dir" "$summary" "$task_id"
 EOF2
 chmod +x "$stub_bin/assembly"
+install_pack_metadata_stub "$stub_bin"
 
 init_git_repo "$repo_dir"
 commit_sha="$(git -C "$repo_dir" rev-parse --short=12 HEAD)"
@@ -198,9 +204,11 @@ Task JSON (authoritative):
 Lint summary:
 Totals: error=1 warning=1 note=1 info=0 other=0
 Findings (showing 3 of 3):
-- [error] src/main.rs:10 R100 - Bad thing
-- [warning] src/lib.rs:5 W200 - Needs attention
-- [note] src/lib.rs:8 - Optional improvement
+src/lib.rs (in context):
+- [warning] line 5 W200 - Needs attention
+- [note] line 8 - Optional improvement
+src/main.rs:
+- [error] line 10 R100 - Bad thing
 
 Compiled context:
 Provenance: manifest=$manifest_rel commit=$commit_sha
@@ -0,0 +1,210 @@
+#!/usr/bin/env bash
+set -euo pipefail
+
+TEST_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+# shellcheck source=helpers.sh
+source "$TEST_DIR/helpers.sh"
+
+require_cmd git
+require_cmd cargo
+require_cmd python
+require_cmd jq
+
+repo_root="$(cd "$TEST_DIR/.." && pwd)"
+repo_dir="$(make_temp_dir)"
+stub_bin="$(make_temp_dir)"
+trap 'rm -rf "$repo_dir" "$stub_bin"' EXIT
+
+cat > "$repo_dir/prd.json" <<'JSON'
+{
+  "tasks": [
+    {
+      "task_id": "T1",
+      "title": "Refactor task",
+      "status": "unstarted",
+      "model": "gpt-5.1-codex-mini",
+      "prompt": "refactor",
+      "definition_of_done": [
+        "Use the refactor profile"
+      ],
+      "recommended": {
+        "approach": "Select the task profile"
+      }
+    },
+    {
+      "task_id": "T2",
+      "title": "Model default task",
+      "status": "unstarted",
+      "model": "gpt-5.1-codex",
+      "definition_of_done": [
+        "Use the model profile"
+      ],
+      "recommended": {
+        "approach": "Select the model profile"
+      }
+    },
+    {
+      "task_id": "T3",
+      "title": "Default prompt task",
+      "status": "unstarted",
+      "model": "gpt-5.1-codex-mini",
+      "definition_of_done": [
+        "Use the default prompt"
+      ],
+      "recommended": {
+        "approach": "Fall back to --prompt"
+      }
+    },
+    {
+      "task_id": "T4",
+      "title": "Missing profile task",
+      "status": "unstarted",
+      "model": "gpt-5.1-codex-mini",
+      "prompt": "missing",
+      "definition_of_done": [
+        "Fail on an unknown profile"
+      ],
+      "recommended": {
+        "approach": "Reference a profile without a file"
+      }
+    }
+  ]
+}
+JSON
+
+ensure_workspace_prompt "$repo_dir"
+cat > "$repo_dir/prompts/refactor.prompt.md" <<'EOF2'
+Refactor profile: keep behavior identical.
+EOF2
+cat > "$repo_dir/prompts/deep.prompt.md" <<'EOF2'
+Deep profile: reason step by step.
+EOF2
+
+cat > "$repo_dir/lever.json" <<'JSON'
+{
+  "prompt_profiles": {
+    "models": {
+      "gpt-5.1-codex": "deep"
+    }
+  }
+}
+JSON
+
+cat > "$stub_bin/codex" <<'EOF2'
+#!/usr/bin/env bash
+set -euo pipefail
+out_path=""
+while [[ $# -gt 0 ]]; do
+  case "$1" in
+    --output-last-message)
+      out_path="$2"
+      shift 2
+      ;;
+    *)
+      shift 1
+      ;;
+  esac
+done
+
+if [[ -z "$out_path" ]]; then
+  echo "Missing --output-last-message" >&2
+  exit 2
+fi
+
+task_id="$(python - <<'PY'
+import json
+with open("prd.json", encoding="utf-8") as handle:
+    tasks = json.load(handle)["tasks"]
+print(next(task["task_id"] for task in tasks if task["status"] != "completed"))
+PY
+)"
+
+cat > "$out_path" <<JSON
+{
+  "task_id": "$task_id",
+  "outcome": "completed",
+  "dod_met": true,
+  "summary": "ok",
+  "tests": {"ran": false, "commands": [], "passed": true},
+  "notes": "",
+  "blockers": [],
+  "dod_items": [],
+  "files_changed": [],
+  "follow_ups": []
+}
+JSON
+EOF2
+chmod +x "$stub_bin/codex"
+
+init_git_repo "$repo_dir"
+
+(
+  cd "$repo_root"
+  cargo build --quiet
+)
+lever_bin="$repo_root/target/debug/lever"
+
+run_lever() {
+  local task_id="$1"
+  PATH="$stub_bin:$PATH" \
+    GIT_AUTHOR_NAME=test GIT_AUTHOR_EMAIL=test@example.com \
+    GIT_COMMITTER_NAME=test GIT_COMMITTER_EMAIL=test@example.com \
+    "$lever_bin" \
+    --workspace "$repo_dir" \
+    --tasks prd.json \
+    --task-id "$task_id"
+}
+
+assert_profile() {
+  local task_id="$1"
+  local expected_profile="$2"
+  local expected_source="$3"
+  local expected_path="$4"
+  local expected_first_line="$5"
+
+  local run_dir
+  run_dir="$(find "$repo_dir/.ralph/runs/$task_id" -mindepth 1 -maxdepth 1 -type d | head -n 1)"
+  local record="$run_dir/prompt-profile.json"
+  if [[ ! -f "$record" ]]; then
+    echo "Expected prompt profile record at $record" >&2
+    exit 1
+  fi
+
+  local actual
+  actual="$(jq -r '[.profile, .source, .path] | join(",")' "$record")"
+  if [[ "$actual" != "$expected_profile,$expected_source,$expected_path" ]]; then
+    echo "Unexpected prompt profile for $task_id: $actual" >&2
+    exit 1
+  fi
+
+  if [[ "$(head -n 1 "$run_dir/prompt.md")" != "$expected_first_line" ]]; then
+    echo "Expected $task_id prompt to start with: $expected_first_line" >&2
+    exit 1
+  fi
+}
+
+run_lever T1 >/dev/null 2>&1
+assert_profile T1 refactor task prompts/refactor.prompt.md "Refactor profile: keep behavior identical."
+
+run_lever T2 >/dev/null 2>&1
+assert_profile T2 deep model prompts/deep.prompt.md "Deep profile: reason step by step."
+
+run_lever T3 >/dev/null 2>&1
+assert_profile T3 default default prompts/autonomous-senior-engineer.prompt.md "Test prompt"
+
+set +e
+run_lever T4 >"$stub_bin/missing.out" 2>&1
+status=$?
+set -e
+
+if [[ $status -ne 2 ]]; then
+  echo "Expected missing prompt profile to exit 2, got $status" >&2
+  cat "$stub_bin/missing.out" >&2
+  exit 1
+fi
+
+if ! grep -q "Prompt profile not found for task T4: missing (selected by task; expected prompts/missing.prompt.md)" "$stub_bin/missing.out"; then
+  echo "Expected missing prompt profile error" >&2
+  cat "$stub_bin/missing.out" >&2
+  exit 1
+fi
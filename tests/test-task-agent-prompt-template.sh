@@ -0,0 +1,200 @@
+#!/usr/bin/env bash
+set -euo pipefail
+
+TEST_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+# shellcheck source=helpers.sh
+source "$TEST_DIR/helpers.sh"
+
+require_cmd git
+require_cmd cargo
+require_cmd python
+require_cmd jq
+
+repo_root="$(cd "$TEST_DIR/.." && pwd)"
+repo_dir="$(make_temp_dir)"
+stub_bin="$(make_temp_dir)"
+trap 'rm -rf "$repo_dir" "$stub_bin"' EXIT
+
+cat > "$repo_dir/prd.json" <<'JSON'
+{
+  "tasks": [
+    {
+      "task_id": "T1",
+      "title": "Templated prompt",
+      "status": "started",
+      "model": "gpt-5.1-codex-mini",
+      "definition_of_done": [
+        "Render the custom template",
+        "Include the previous attempt"
+      ],
+      "recommended": {
+        "approach": "Use placeholders"
+      },
+      "observability": {
+        "run_attempts": 1,
+        "last_note": "Verification failed",
+        "last_update_utc": "2026-01-01T00:00:00Z",
+        "last_run_id": "run-prev"
+      }
+    },
+    {
+      "task_id": "T2",
+      "title": "Broken template",
+      "status": "unstarted",
+      "model": "gpt-5.1-codex-mini",
+      "definition_of_done": [
+        "Fail before Codex starts"
+      ],
+      "recommended": {
+        "approach": "Reference an unknown variable"
+      }
+    }
+  ]
+}
+JSON
+
+mkdir -p "$repo_dir/prompts"
+cat > "$repo_dir/prompts/autonomous-senior-engineer.prompt.md" <<'EOF2'
+# {{ title }} ({{ task_id }})
+Branch: {{ repo.branch }}
+
+Done when:
+{% for item in definition_of_done %}
+* {{ item }}
+{% endfor %}
+{{ previous_attempt }}
+Approach: {{ approach }}
+EOF2
+
+mkdir -p "$repo_dir/.ralph/runs/T1/run-prev"
+cat > "$repo_dir/.ralph/runs/T1/run-prev/result.json" <<'JSON'
+{"task_id": "T1", "outcome": "blocked", "dod_met": false, "summary": "Tests failed", "blockers": ["flaky fixture"]}
+JSON
+
+cat > "$stub_bin/codex" <<'EOF2'
+#!/usr/bin/env bash
+set -euo pipefail
+out_path=""
+while [[ $# -gt 0 ]]; do
+  case "$1" in
+    --output-last-message)
+      out_path="$2"
+      shift 2
+      ;;
+    *)
+      shift 1
+      ;;
+  esac
+done
+
+if [[ -z "$out_path" ]]; then
+  echo "Missing --output-last-message" >&2
+  exit 2
+fi
+
+cat > "$out_path" <<'JSON'
+{
+  "task_id": "T1",
+  "outcome": "completed",
+  "dod_met": true,
+  "summary": "ok",
+  "tests": {"ran": false, "commands": [], "passed": true},
+  "notes": "",
+  "blockers": [],
+  "dod_items": [],
+  "files_changed": [],
+  "follow_ups": []
+}
+JSON
+EOF2
+chmod +x "$stub_bin/codex"
+
+init_git_repo "$repo_dir"
+
+(
+  cd "$repo_root"
+  cargo build --quiet
+)
+lever_bin="$repo_root/target/debug/lever"
+
+PATH="$stub_bin:$PATH" \
+  GIT_AUTHOR_NAME=test GIT_AUTHOR_EMAIL=test@example.com \
+  GIT_COMMITTER_NAME=test GIT_COMMITTER_EMAIL=test@example.com \
+  "$lever_bin" \
+  --workspace "$repo_dir" \
+  --tasks prd.json \
+  --task-id T1 \
+  >/dev/null
+
+run_dir="$(find "$repo_dir/.ralph/runs/T1" -mindepth 1 -maxdepth 1 -type d ! -name run-prev | head -n 1)"
+if [[ -z "$run_dir" ]]; then
+  echo "Expected run directory to exist" >&2
+  exit 1
+fi
+
+prompt_file="$run_dir/prompt.md"
+expected_file="$repo_dir/expected-prompt.txt"
+cat > "$expected_file" <<'EOF'
+# Templated prompt (T1)
+Branch: ralph/T1
+
+Done when:
+* Render the custom template
+* Include the previous attempt
+
+Previous attempt:
+Run: run-prev (status: started)
+Note: Verification failed
+Outcome: blocked
+Summary: Tests failed
+Blockers:
+  - flaky fixture
+
+Approach: Use placeholders
+EOF
+
+if ! diff -u "$expected_file" "$prompt_file"; then
+  echo "Expected prompt to follow the custom template" >&2
+  exit 1
+fi
+
+report="$run_dir/prompt-tokens.json"
+sections="$(jq -r '.sections | map(.name) | join(",")' "$report")"
+if [[ "$sections" != "template,previous_attempt" ]]; then
+  echo "Unexpected prompt sections for custom template: $sections" >&2
+  exit 1
+fi
+
+if [[ "$(jq -r '.template' "$report")" != "custom" ]]; then
+  echo "Expected prompt token report to record a custom template" >&2
+  exit 1
+fi
+
+cat > "$repo_dir/prompts/autonomous-senior-engineer.prompt.md" <<'EOF2'
+{{ title }} {{ unknown_field }}
+EOF2
+git -C "$repo_dir" add -A
+git -C "$repo_dir" -c user.name=test -c user.email=test@example.com commit -q -m "broken template"
+
+set +e
+PATH="$stub_bin:$PATH" \
+  GIT_AUTHOR_NAME=test GIT_AUTHOR_EMAIL=test@example.com \
+  GIT_COMMITTER_NAME=test GIT_COMMITTER_EMAIL=test@example.com \
+  "$lever_bin" \
+  --workspace "$repo_dir" \
+  --tasks prd.json \
+  --task-id T2 \
+  >"$stub_bin/broken.out" 2>&1
+status=$?
+set -e
+
+if [[ $status -eq 0 ]]; then
+  echo "Expected unknown template variable to fail the run" >&2
+  exit 1
+fi
+
+if ! grep -q "Invalid prompt template" "$stub_bin/broken.out"; then
+  echo "Expected invalid template error, got:" >&2
+  cat "$stub_bin/broken.out" >&2
+  exit 1
+fi
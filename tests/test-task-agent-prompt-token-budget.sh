@@ -0,0 +1,236 @@
+#!/usr/bin/env bash
+set -euo pipefail
+
+TEST_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+# shellcheck source=helpers.sh
+source "$TEST_DIR/helpers.sh"
+
+require_cmd git
+require_cmd cargo
+require_cmd python3
+require_cmd jq
+
+repo_root="$(cd "$TEST_DIR/.." && pwd)"
+repo_dir="$(make_temp_dir)"
+stub_bin="$(make_temp_dir)"
+trap 'rm -rf "$repo_dir" "$stub_bin"' EXIT
+
+cat > "$repo_dir/prd.json" <<'JSON'
+{
+  "tasks": [
+    {
+      "task_id": "T1",
+      "title": "Prompt token budget",
+      "status": "unstarted",
+      "model": "gpt-5.1-codex-mini",
+      "definition_of_done": [
+        "Append compiled context"
+      ],
+      "recommended": {
+        "approach": "Use compiled context"
+      }
+    }
+  ]
+}
+JSON
+
+ensure_workspace_prompt "$repo_dir"
+
+cat > "$repo_dir/README.md" <<'EOF2'
+Test repo
+EOF2
+
+cat > "$stub_bin/codex" <<'EOF2'
+#!/usr/bin/env bash
+set -euo pipefail
+out_path=""
+while [[ $# -gt 0 ]]; do
+  case "$1" in
+    --output-last-message)
+      out_path="$2"
+      shift 2
+      ;;
+    *)
+      shift 1
+      ;;
+  esac
+done
+
+if [[ -z "$out_path" ]]; then
+  echo "Missing --output-last-message" >&2
+  exit 2
+fi
+
+cat > "$out_path" <<'JSON'
+{
+  "task_id": "T1",
+  "outcome": "completed",
+  "dod_met": true,
+  "summary": "ok",
+  "tests": {"ran": false, "commands": [], "passed": true},
+  "notes": "",
+  "blockers": [],
+  "dod_items": [],
+  "files_changed": [],
+  "follow_ups": []
+}
+JSON
+EOF2
+chmod +x "$stub_bin/codex"
+
+cat > "$stub_bin/assembly" <<'EOF2'
+#!/usr/bin/env bash
+set -euo pipefail
+
+if [[ "${1:-}" == "--version" ]]; then
+  printf '%s\n' "assembly 1.2.3"
+  exit 0
+fi
+
+if [[ "${1:-}" == "build" && "${2:-}" == "--help" ]]; then
+  cat <<'HELP'
+Usage: assembly build [OPTIONS]
+
+Options:
+  --repo <PATH>           Repository root
+  --task <PATH>           Task input file (supports @file)
+  --task-id <ID>          Task identifier
+  --out <DIR>             Output pack directory
+  --token-budget <TOKENS> Token budget for context
+  --exclude <GLOB>        Additive exclude glob (repeatable)
+  --exclude-runtime <GLOB> Runtime artifact exclusion glob (repeatable)
+  --summary-json <PATH>   Write machine-readable summary JSON
+HELP
+  exit 0
+fi
+
+out_dir=""
+summary=""
+task_id=""
+while [[ $# -gt 0 ]]; do
+  case "$1" in
+    --out)
+      out_dir="$2"
+      shift 2
+      ;;
+    --summary-json)
+      summary="$2"
+      shift 2
+      ;;
+    --task-id)
+      task_id="$2"
+      shift 2
+      ;;
+    *)
+      shift 1
+      ;;
+  esac
+done
+
+if [[ -z "$out_dir" ]]; then
+  echo "Missing --out" >&2
+  exit 2
+fi
+
+mkdir -p "$out_dir"
+printf '%s\n' "{}" > "$out_dir/manifest.json"
+printf '%s\n' "{}" > "$out_dir/index.json"
+for idx in $(seq 1 400); do
+  printf 'Compiled context line %s describes src/module_%s.rs\n' "$idx" "$idx"
+done > "$out_dir/context.md"
+printf '%s\n' "{}" > "$out_dir/policy.md"
+printf '%s\n' "{}" > "$out_dir/lint.json"
+
+assembly-pack-metadata "$out_dir" "$summary" "$task_id"
+EOF2
+chmod +x "$stub_bin/assembly"
+install_pack_metadata_stub "$stub_bin"
+
+init_git_repo "$repo_dir"
+commit_sha="$(git -C "$repo_dir" rev-parse --short=12 HEAD)"
+
+(
+  cd "$repo_root"
+  cargo build --quiet
+)
+lever_bin="$repo_root/target/debug/lever"
+
+PATH="$stub_bin:$PATH" \
+  GIT_AUTHOR_NAME=test GIT_AUTHOR_EMAIL=test@example.com \
+  GIT_COMMITTER_NAME=test GIT_COMMITTER_EMAIL=test@example.com \
+  "$lever_bin" \
+  --workspace "$repo_dir" \
+  --tasks prd.json \
+  --task-id T1 \
+  --context-compile \
+  --prompt-token-budget 400 \
+  >/dev/null 2>&1
+
+run_dir="$(find "$repo_dir/.ralph/runs/T1" -mindepth 1 -maxdepth 1 -type d | head -n 1)"
+if [[ -z "$run_dir" ]]; then
+  echo "Expected run directory to exist" >&2
+  exit 1
+fi
+
+prompt_file="$run_dir/prompt.md"
+report="$run_dir/prompt-tokens.json"
+if [[ ! -f "$report" ]]; then
+  echo "Expected prompt token report at $report" >&2
+  exit 1
+fi
+
+if [[ "$(jq -r '.estimator' "$report")" != "o200k_base" ]]; then
+  echo "Expected BPE estimator in report, got: $(jq -r '.estimator' "$report")" >&2
+  exit 1
+fi
+
+total="$(jq -r '.total_tokens' "$report")"
+if (( total > 400 )); then
+  echo "Expected prompt to fit the 400 token budget, got: $total" >&2
+  exit 1
+fi
+
+sections="$(jq -r '.sections | map(.name + "=" + .status) | join(",")' "$report")"
+if [[ "$sections" != "base_prompt=included,task=included,task_json=elided,compiled_context=truncated" ]]; then
+  echo "Unexpected section statuses: $sections" >&2
+  exit 1
+fi
+
+original="$(jq -r '.sections[] | select(.name == "compiled_context") | .original_tokens' "$report")"
+kept="$(jq -r '.sections[] | select(.name == "compiled_context") | .tokens' "$report")"
+if (( original <= kept )); then
+  echo "Expected compiled context to shrink, original=$original kept=$kept" >&2
+  exit 1
+fi
+
+if ! grep -q "Compiled context line 1 describes" "$prompt_file"; then
+  echo "Expected leading compiled context lines to be kept" >&2
+  exit 1
+fi
+
+if grep -q "Compiled context line 400 describes" "$prompt_file"; then
+  echo "Expected trailing compiled context lines to be cut" >&2
+  exit 1
+fi
+
+if ! grep -q "compiled context truncated to fit the prompt token budget" "$prompt_file"; then
+  echo "Expected truncation marker in prompt" >&2
+  exit 1
+fi
+
+budget_report="$run_dir/prompt-budget.json"
+if [[ "$(jq -r '.fits' "$budget_report")" != "true" ]]; then
+  echo "Expected prompt budget report to record a fitting prompt" >&2
+  exit 1
+fi
+
+cuts="$(jq -r '.cuts | map(.section + ":" + .action) | join(",")' "$budget_report")"
+if [[ "$cuts" != "task_json:elide_duplicates,compiled_context:truncate" ]]; then
+  echo "Unexpected prompt budget cuts: $cuts" >&2
+  exit 1
+fi
+
+if grep -q '"title":"Prompt token budget"' "$prompt_file"; then
+  echo "Expected duplicated task JSON fields to be elided" >&2
+  exit 1
+fi
@@ -68,7 +68,10 @@ cat > "$out_path" <<'JSON'
   "summary": "blocked by model",
   "tests": {"ran": true, "commands": ["pnpm test missing"], "passed": false},
   "notes": "model marked blocked",
-  "blockers": ["model blocker"]
+  "blockers": ["model blocker"],
+  "dod_items": [],
+  "files_changed": [],
+  "follow_ups": []
 }
 JSON
 EOF2
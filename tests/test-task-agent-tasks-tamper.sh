@@ -0,0 +1,161 @@
+#!/usr/bin/env bash
+set -euo pipefail
+
+TEST_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+# shellcheck source=helpers.sh
+source "$TEST_DIR/helpers.sh"
+
+require_cmd jq
+require_cmd git
+require_cmd cargo
+
+repo_root="$(cd "$TEST_DIR/.." && pwd)"
+repo_dir="$(make_temp_dir)"
+stub_bin="$(make_temp_dir)"
+trap 'rm -rf "$repo_dir" "$stub_bin"' EXIT
+
+cat > "$repo_dir/prd.json" <<'JSON'
+{
+  "tasks": [
+    {
+      "task_id": "T1",
+      "title": "Guard tasks file",
+      "status": "unstarted",
+      "model": "gpt-5.1-codex-mini",
+      "definition_of_done": [
+        "First criterion",
+        "Second criterion"
+      ],
+      "recommended": {
+        "approach": "Agent must not edit its own task entry"
+      }
+    },
+    {
+      "task_id": "T2",
+      "title": "Later task",
+      "status": "unstarted",
+      "model": "gpt-5.1-codex-mini",
+      "definition_of_done": [
+        "Untouched"
+      ],
+      "recommended": {
+        "approach": "Stay untouched"
+      }
+    }
+  ]
+}
+JSON
+
+ensure_workspace_prompt "$repo_dir"
+
+cat > "$stub_bin/codex" <<'EOF2'
+#!/usr/bin/env bash
+set -euo pipefail
+out_path=""
+while [[ $# -gt 0 ]]; do
+  case "$1" in
+    --output-last-message)
+      out_path="$2"
+      shift 2
+      ;;
+    *)
+      shift 1
+      ;;
+  esac
+done
+
+if [[ -z "$out_path" ]]; then
+  echo "Missing --output-last-message" >&2
+  exit 2
+fi
+
+tmp="$(mktemp)"
+jq '.tasks[0].status = "completed"
+  | .tasks[0].definition_of_done = ["First criterion"]
+  | del(.tasks[1])' prd.json > "$tmp"
+mv "$tmp" prd.json
+
+cat > "$out_path" <<'JSON'
+{
+  "task_id": "T1",
+  "outcome": "started",
+  "dod_met": false,
+  "summary": "tampered",
+  "tests": {"ran": false, "commands": [], "passed": false},
+  "notes": "",
+  "blockers": [],
+  "dod_items": [],
+  "files_changed": [],
+  "follow_ups": []
+}
+JSON
+EOF2
+chmod +x "$stub_bin/codex"
+
+init_git_repo "$repo_dir"
+
+(
+  cd "$repo_root"
+  cargo build --quiet
+)
+lever_bin="$repo_root/target/debug/lever"
+
+set +e
+PATH="$stub_bin:$PATH" \
+  GIT_AUTHOR_NAME=test GIT_AUTHOR_EMAIL=test@example.com \
+  GIT_COMMITTER_NAME=test GIT_COMMITTER_EMAIL=test@example.com \
+  "$lever_bin" \
+  --workspace "$repo_dir" \
+  --tasks prd.json \
+  --task-id T1 \
+  >/dev/null 2>&1
+exit_code=$?
+set -e
+
+if [[ "$exit_code" -ne 12 ]]; then
+  echo "Expected lever to exit 12 for progress state, got: $exit_code" >&2
+  exit 1
+fi
+
+status="$(jq -r '.tasks[0].status' "$repo_dir/prd.json")"
+if [[ "$status" != "started" ]]; then
+  echo "Expected agent status edit to be reverted (started), got: $status" >&2
+  exit 1
+fi
+
+dod_count="$(jq -r '.tasks[0].definition_of_done | length' "$repo_dir/prd.json")"
+if [[ "$dod_count" != "2" ]]; then
+  echo "Expected definition_of_done to be restored to 2 items, got: $dod_count" >&2
+  exit 1
+fi
+
+task_count="$(jq -r '.tasks | length' "$repo_dir/prd.json")"
+if [[ "$task_count" != "2" ]]; then
+  echo "Expected removed task to be restored, got $task_count tasks" >&2
+  exit 1
+fi
+
+run_dir="$(ls -td "$repo_dir/.ralph/runs/T1"/* | head -n1)"
+report="$run_dir/tasks-tamper.json"
+if [[ ! -f "$report" ]]; then
+  echo "Expected tamper report at $report" >&2
+  exit 1
+fi
+
+fields="$(jq -r '.events[] | select(.task_id == "T1") | .fields | join(",")' "$report")"
+if [[ "$fields" != "definition_of_done,status" ]]; then
+  echo "Expected tamper report to list modified fields, got: $fields" >&2
+  exit 1
+fi
+
+removed="$(jq -r '.events[] | select(.task_id == "T2") | .kind' "$report")"
+if [[ "$removed" != "removed" ]]; then
+  echo "Expected tamper report to record removed task T2, got: $removed" >&2
+  exit 1
+fi
+
+note="$(jq -r '.tasks[0].observability.last_note // ""' "$repo_dir/prd.json")"
+if [[ "$note" != *"tasks_tamper_reverted="* ]]; then
+  echo "Expected last_note to mention tasks_tamper_reverted, got: $note" >&2
+  exit 1
+fi
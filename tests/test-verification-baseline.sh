@@ -0,0 +1,214 @@
+#!/usr/bin/env bash
+set -euo pipefail
+
+TEST_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+# shellcheck source=helpers.sh
+source "$TEST_DIR/helpers.sh"
+
+require_cmd jq
+require_cmd git
+require_cmd cargo
+
+repo_root="$(cd "$TEST_DIR/.." && pwd)"
+repo_dir="$(make_temp_dir)"
+stub_bin="$(make_temp_dir)"
+trap 'rm -rf "$repo_dir" "$stub_bin"' EXIT
+
+task_json() {
+  cat <<JSON
+    {
+      "task_id": "$1",
+      "title": "Baseline verification $1",
+      "status": "unstarted",
+      "model": "gpt-5.1-codex-mini",
+      "definition_of_done": [
+        "Suite passes"
+      ],
+      "recommended": {
+        "approach": "Stub verification"
+      },
+      "verification": {
+        "commands": [
+          "$2"
+        ]
+      }
+    }
+JSON
+}
+
+cat > "$repo_dir/prd.json" <<JSON
+{
+  "tasks": [
+$(task_json T1 "test -f fixed.txt"),
+$(task_json T2 "test ! -f broken.txt"),
+$(task_json T3 "test -f missing.txt")
+  ]
+}
+JSON
+
+ensure_workspace_prompt "$repo_dir"
+printf '%s\n' '{"verification": {"baseline": true}}' > "$repo_dir/lever.json"
+
+cat > "$stub_bin/codex" <<'EOF2'
+#!/usr/bin/env bash
+set -euo pipefail
+
+if [[ "${1:-}" == "--version" ]]; then
+  printf '%s\n' "codex 0.0.0"
+  exit 0
+fi
+
+out_path=""
+while [[ $# -gt 0 ]]; do
+  case "$1" in
+    --output-last-message)
+      out_path="$2"
+      shift 2
+      ;;
+    *)
+      shift 1
+      ;;
+  esac
+done
+
+case "$STUB_TASK_ID" in
+  T1) printf 'fixed\n' > fixed.txt ;;
+  T2) printf 'broken\n' > broken.txt ;;
+esac
+
+cat > "$out_path" <<JSON
+{
+  "task_id": "$STUB_TASK_ID",
+  "outcome": "completed",
+  "dod_met": true,
+  "summary": "ok",
+  "tests": {"ran": false, "commands": [], "passed": true},
+  "notes": "",
+  "blockers": [],
+  "dod_items": [],
+  "files_changed": [],
+  "follow_ups": []
+}
+JSON
+EOF2
+chmod +x "$stub_bin/codex"
+
+init_git_repo "$repo_dir"
+
+(
+  cd "$repo_root"
+  cargo build --quiet
+)
+lever_bin="$repo_root/target/debug/lever"
+
+run_task() {
+  set +e
+  PATH="$stub_bin:$PATH" \
+    STUB_TASK_ID="$1" \
+    GIT_AUTHOR_NAME=test GIT_AUTHOR_EMAIL=test@example.com \
+    GIT_COMMITTER_NAME=test GIT_COMMITTER_EMAIL=test@example.com \
+    "$lever_bin" \
+    --workspace "$repo_dir" \
+    --tasks prd.json \
+    --task-id "$1" \
+    >"$stub_bin/output-$1" 2>&1
+  run_status=$?
+  set -e
+}
+
+task_note() {
+  jq -r --arg id "$1" '.tasks[] | select(.task_id == $id) | .observability.last_note' "$repo_dir/prd.json"
+}
+
+latest_run_dir() {
+  find "$repo_dir/.ralph/runs/$1" -mindepth 1 -maxdepth 1 -type d | sort | tail -n 1
+}
+
+# A step that failed before the run and passes after it is reported as fixed.
+run_task T1
+if [[ $run_status -ne 0 ]]; then
+  echo "Expected T1 to complete, got $run_status" >&2
+  cat "$stub_bin/output-T1" >&2
+  exit 1
+fi
+if [[ "$(task_note T1)" != *"; baseline newly_failing=0 still_failing=0 fixed=1" ]]; then
+  echo "Expected a fixed step in the T1 note, got: $(task_note T1)" >&2
+  exit 1
+fi
+run_dir="$(latest_run_dir T1)"
+if [[ "$(jq -c '.baseline | [.cached, .fixed, .steps[0].status]' "$run_dir/verification.json")" != '[false,1,"fixed"]' ]]; then
+  echo "Expected the baseline comparison in verification.json" >&2
+  cat "$run_dir/verification.json" >&2
+  exit 1
+fi
+if [[ ! -f "$run_dir/baseline/verify.log" ]]; then
+  echo "Expected the baseline verify.log" >&2
+  exit 1
+fi
+if [[ -n "$(git -C "$repo_dir" ls-files .ralph/cache)" ]]; then
+  echo "Expected the baseline cache to stay out of git" >&2
+  exit 1
+fi
+
+# A step that passed before the run and fails after it is newly failing.
+run_task T2
+if [[ $run_status -ne 12 ]]; then
+  echo "Expected T2 progress exit 12, got $run_status" >&2
+  cat "$stub_bin/output-T2" >&2
+  exit 1
+fi
+if [[ "$(task_note T2)" != *"; baseline newly_failing=1 still_failing=0 fixed=0" ]]; then
+  echo "Expected a newly failing step in the T2 note, got: $(task_note T2)" >&2
+  exit 1
+fi
+
+git -C "$repo_dir" checkout -q main
+jq '(.tasks[] | select(.task_id == "T2") | .status) = "completed"' "$repo_dir/prd.json" >"$stub_bin/prd.json"
+mv "$stub_bin/prd.json" "$repo_dir/prd.json"
+git -C "$repo_dir" add prd.json
+GIT_AUTHOR_NAME=test GIT_AUTHOR_EMAIL=test@example.com \
+  GIT_COMMITTER_NAME=test GIT_COMMITTER_EMAIL=test@example.com \
+  git -C "$repo_dir" commit -q -m "complete T2"
+
+# A step that fails before and after the run is still failing.
+t3_start="$(git -C "$repo_dir" rev-parse HEAD)"
+run_task T3
+if [[ $run_status -ne 12 ]]; then
+  echo "Expected T3 progress exit 12, got $run_status" >&2
+  cat "$stub_bin/output-T3" >&2
+  exit 1
+fi
+if [[ "$(task_note T3)" != *"; baseline newly_failing=0 still_failing=1 fixed=0" ]]; then
+  echo "Expected a still failing step in the T3 note, got: $(task_note T3)" >&2
+  exit 1
+fi
+
+# A second run on ralph/T3, which now carries the first run's progress
+# commit, takes its baseline where the branch forked from main and reuses
+# the cached one.
+if [[ "$(git -C "$repo_dir" rev-parse HEAD)" == "$t3_start" ]]; then
+  echo "Expected the first T3 run to commit progress on its branch" >&2
+  exit 1
+fi
+run_task T3
+if ! grep -q "Verification baseline.*cached=true" "$stub_bin/output-T3"; then
+  echo "Expected a cached baseline on the same commit" >&2
+  cat "$stub_bin/output-T3" >&2
+  exit 1
+fi
+run_dir="$(latest_run_dir T3)"
+if [[ "$(jq -r '.baseline.cached' "$run_dir/verification.json")" != "true" ]]; then
+  echo "Expected verification.json to record the cached baseline" >&2
+  exit 1
+fi
+if [[ "$(jq -r '.baseline.commit' "$run_dir/verification.json")" != "$t3_start" ]]; then
+  echo "Expected the baseline at the merge-base with main" >&2
+  cat "$run_dir/verification.json" >&2
+  exit 1
+fi
+if [[ -d "$run_dir/baseline" ]]; then
+  echo "Expected no baseline re-run for a cached baseline" >&2
+  exit 1
+fi
+
+echo "ok"
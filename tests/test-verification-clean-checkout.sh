@@ -0,0 +1,170 @@
+#!/usr/bin/env bash
+set -euo pipefail
+
+TEST_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+# shellcheck source=helpers.sh
+source "$TEST_DIR/helpers.sh"
+
+require_cmd jq
+require_cmd git
+require_cmd cargo
+
+repo_root="$(cd "$TEST_DIR/.." && pwd)"
+repo_dir="$(make_temp_dir)"
+stub_bin="$(make_temp_dir)"
+trap 'rm -rf "$repo_dir" "$stub_bin"' EXIT
+
+task_json() {
+  cat <<JSON
+    {
+      "task_id": "$1",
+      "title": "Clean checkout $1",
+      "status": "unstarted",
+      "model": "gpt-5.1-codex-mini",
+      "definition_of_done": [
+        "Artifact built"
+      ],
+      "recommended": {
+        "approach": "Stub verification"
+      },
+      "verification": {
+        "commands": [
+          "echo \"verify_root=\$PWD\"",
+          "test -f src/$1.rs",
+          "test -f build/artifact"
+        ]
+      }
+    }
+JSON
+}
+
+cat > "$repo_dir/prd.json" <<JSON
+{
+  "tasks": [
+$(task_json T1),
+$(task_json T2)
+  ]
+}
+JSON
+
+ensure_workspace_prompt "$repo_dir"
+printf '%s\n' "build/" > "$repo_dir/.gitignore"
+
+cat > "$stub_bin/codex" <<'EOF2'
+#!/usr/bin/env bash
+set -euo pipefail
+
+if [[ "${1:-}" == "--version" ]]; then
+  printf '%s\n' "codex 0.0.0"
+  exit 0
+fi
+
+out_path=""
+while [[ $# -gt 0 ]]; do
+  case "$1" in
+    --output-last-message)
+      out_path="$2"
+      shift 2
+      ;;
+    *)
+      shift 1
+      ;;
+  esac
+done
+
+# The source change is committed by lever; the ignored build product is not.
+task_id="$(jq -r '[.tasks[] | select(.status != "completed")][0].task_id' prd.json)"
+mkdir -p src build
+printf '%s\n' "pub fn run() {}" > "src/$task_id.rs"
+printf '%s\n' "built" > build/artifact
+
+cat > "$out_path" <<JSON
+{
+  "task_id": "$task_id",
+  "outcome": "completed",
+  "dod_met": true,
+  "summary": "ok",
+  "tests": {"ran": false, "commands": [], "passed": true},
+  "notes": "",
+  "blockers": [],
+  "dod_items": [],
+  "files_changed": ["src/$task_id.rs"],
+  "follow_ups": []
+}
+JSON
+EOF2
+chmod +x "$stub_bin/codex"
+
+init_git_repo "$repo_dir"
+
+(
+  cd "$repo_root"
+  cargo build --quiet
+)
+lever_bin="$repo_root/target/debug/lever"
+
+run_task() {
+  set +e
+  PATH="$stub_bin:$PATH" \
+    GIT_AUTHOR_NAME=test GIT_AUTHOR_EMAIL=test@example.com \
+    GIT_COMMITTER_NAME=test GIT_COMMITTER_EMAIL=test@example.com \
+    "$lever_bin" \
+    --workspace "$repo_dir" \
+    --tasks prd.json \
+    --task-id "$1" \
+    >"$stub_bin/output-$1" 2>&1
+  run_status=$?
+  set -e
+}
+
+# By default verification sees the ignored build product in the workspace.
+run_task T1
+if [[ $run_status -ne 0 ]]; then
+  echo "Expected T1 to complete when verifying the workspace, got $run_status" >&2
+  cat "$stub_bin/output-T1" >&2
+  exit 1
+fi
+run_dir="$(find "$repo_dir/.ralph/runs/T1" -mindepth 1 -maxdepth 1 -type d | head -n 1)"
+if ! grep -qx "verify_root=$repo_dir" "$run_dir/verify.log"; then
+  echo "Expected T1 verification to run in the workspace" >&2
+  cat "$run_dir/verify.log" >&2
+  exit 1
+fi
+
+printf '%s\n' '{"verification": {"clean_checkout": true}}' > "$repo_dir/lever.json"
+git -C "$repo_dir" add lever.json
+GIT_AUTHOR_NAME=test GIT_AUTHOR_EMAIL=test@example.com \
+  GIT_COMMITTER_NAME=test GIT_COMMITTER_EMAIL=test@example.com \
+  git -C "$repo_dir" commit -q -m "verify clean checkouts"
+
+# A clean checkout only has what was committed, so the artifact is missing.
+run_task T2
+if [[ $run_status -ne 12 ]]; then
+  echo "Expected progress exit 12 in a clean checkout, got $run_status" >&2
+  cat "$stub_bin/output-T2" >&2
+  exit 1
+fi
+run_dir="$(find "$repo_dir/.ralph/runs/T2" -mindepth 1 -maxdepth 1 -type d | head -n 1)"
+verify_root="$(sed -n 's/^verify_root=//p' "$run_dir/verify.log")"
+if [[ "$verify_root" != *"/lever-verify-T2-"* ]]; then
+  echo "Expected T2 verification in a clean checkout, got: $verify_root" >&2
+  cat "$run_dir/verify.log" >&2
+  exit 1
+fi
+if [[ -e "$verify_root" ]]; then
+  echo "Expected the clean checkout to be removed" >&2
+  exit 1
+fi
+if [[ "$(git -C "$repo_dir" worktree list | wc -l | tr -d ' ')" != "1" ]]; then
+  echo "Expected the verification worktree to be unregistered" >&2
+  git -C "$repo_dir" worktree list >&2
+  exit 1
+fi
+if ! git -C "$repo_dir" cat-file -e HEAD:src/T2.rs; then
+  echo "Expected the agent's change to be committed before verification" >&2
+  exit 1
+fi
+if [[ "$(jq -r '.tasks[1].status' "$repo_dir/prd.json")" != "started" ]]; then
+  echo "Expected T2 to stay started" >&2
+  exit 1
+fi
@@ -0,0 +1,184 @@
+#!/usr/bin/env bash
+set -euo pipefail
+
+TEST_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+# shellcheck source=helpers.sh
+source "$TEST_DIR/helpers.sh"
+
+require_cmd jq
+require_cmd git
+require_cmd cargo
+
+repo_root="$(cd "$TEST_DIR/.." && pwd)"
+repo_dir="$(make_temp_dir)"
+stub_bin="$(make_temp_dir)"
+trap 'rm -rf "$repo_dir" "$stub_bin"' EXIT
+
+task_json() {
+  cat <<JSON
+    {
+      "task_id": "$1",
+      "title": "Detected verification $1",
+      "status": "unstarted",
+      "model": "gpt-5.1-codex-mini",
+      "definition_of_done": [
+        "Suite passes"
+      ],
+      "recommended": {
+        "approach": "Stub verification"
+      }
+    }
+JSON
+}
+
+cat > "$repo_dir/prd.json" <<JSON
+{
+  "tasks": [
+$(task_json T1),
+$(task_json T2)
+  ]
+}
+JSON
+
+ensure_workspace_prompt "$repo_dir"
+printf '%s\n' "module example.com/demo" > "$repo_dir/go.mod"
+cat > "$repo_dir/package.json" <<'JSON'
+{ "name": "demo", "scripts": { "test": "node test.js" } }
+JSON
+
+cat > "$stub_bin/codex" <<'EOF2'
+#!/usr/bin/env bash
+set -euo pipefail
+
+if [[ "${1:-}" == "--version" ]]; then
+  printf '%s\n' "codex 0.0.0"
+  exit 0
+fi
+
+out_path=""
+while [[ $# -gt 0 ]]; do
+  case "$1" in
+    --output-last-message)
+      out_path="$2"
+      shift 2
+      ;;
+    *)
+      shift 1
+      ;;
+  esac
+done
+
+task_id="$(jq -r '[.tasks[] | select(.status != "completed")][0].task_id' prd.json)"
+cat > "$out_path" <<JSON
+{
+  "task_id": "$task_id",
+  "outcome": "completed",
+  "dod_met": true,
+  "summary": "ok",
+  "tests": {"ran": false, "commands": [], "passed": true},
+  "notes": "",
+  "blockers": [],
+  "dod_items": [],
+  "files_changed": [],
+  "follow_ups": []
+}
+JSON
+EOF2
+chmod +x "$stub_bin/codex"
+
+for tool in go npm; do
+  cat > "$stub_bin/$tool" <<EOF2
+#!/usr/bin/env bash
+printf '%s\n' "$tool \$*" >> "\$TOOL_LOG"
+[[ "$tool" == "go" ]]
+EOF2
+  chmod +x "$stub_bin/$tool"
+done
+
+init_git_repo "$repo_dir"
+
+(
+  cd "$repo_root"
+  cargo build --quiet
+)
+lever_bin="$repo_root/target/debug/lever"
+
+run_task() {
+  set +e
+  PATH="$stub_bin:$PATH" \
+    TOOL_LOG="$stub_bin/tools-$1" \
+    GIT_AUTHOR_NAME=test GIT_AUTHOR_EMAIL=test@example.com \
+    GIT_COMMITTER_NAME=test GIT_COMMITTER_EMAIL=test@example.com \
+    "$lever_bin" \
+    --workspace "$repo_dir" \
+    --tasks prd.json \
+    --task-id "$1" \
+    >"$stub_bin/output-$1" 2>&1
+  run_status=$?
+  set -e
+}
+
+# go.mod wins over package.json in the default order.
+run_task T1
+if [[ $run_status -ne 0 ]]; then
+  echo "Expected T1 to complete with the go detector, got $run_status" >&2
+  cat "$stub_bin/output-T1" >&2
+  exit 1
+fi
+run_dir="$(find "$repo_dir/.ralph/runs/T1" -mindepth 1 -maxdepth 1 -type d | head -n 1)"
+report="$(jq -c '[.source, .detector, .commands]' "$run_dir/verification.json")"
+if [[ "$report" != '["detector","go",["go test ./..."]]' ]]; then
+  echo "Unexpected verification.json: $report" >&2
+  exit 1
+fi
+if [[ "$(cat "$stub_bin/tools-T1")" != "go test ./..." ]]; then
+  echo "Expected only go test to run" >&2
+  cat "$stub_bin/tools-T1" >&2
+  exit 1
+fi
+if ! grep -q "lever: verification detector: go" "$run_dir/verify.log"; then
+  echo "Expected the detector in verify.log" >&2
+  exit 1
+fi
+if ! grep -q "detector=go" "$stub_bin/output-T1"; then
+  echo "Expected the detector in the verification log line" >&2
+  exit 1
+fi
+
+printf '%s\n' '{"verification": {"disabled_detectors": ["go"]}}' > "$repo_dir/lever.json"
+git -C "$repo_dir" add lever.json
+GIT_AUTHOR_NAME=test GIT_AUTHOR_EMAIL=test@example.com \
+  GIT_COMMITTER_NAME=test GIT_COMMITTER_EMAIL=test@example.com \
+  git -C "$repo_dir" commit -q -m "opt out of go"
+
+# Opting out of go falls through to npm, whose tests fail.
+run_task T2
+if [[ $run_status -ne 12 ]]; then
+  echo "Expected progress exit 12 from failing npm tests, got $run_status" >&2
+  cat "$stub_bin/output-T2" >&2
+  exit 1
+fi
+run_dir="$(find "$repo_dir/.ralph/runs/T2" -mindepth 1 -maxdepth 1 -type d | head -n 1)"
+report="$(jq -c '[.ok, .detector, .commands]' "$run_dir/verification.json")"
+if [[ "$report" != '[false,"npm",["npm test"]]' ]]; then
+  echo "Unexpected verification.json: $report" >&2
+  exit 1
+fi
+if [[ "$(cat "$stub_bin/tools-T2")" != "npm test" ]]; then
+  echo "Expected only npm test to run" >&2
+  cat "$stub_bin/tools-T2" >&2
+  exit 1
+fi
+
+# Unknown detector names are rejected up front.
+printf '%s\n' '{"verification": {"detectors": ["bazel"]}}' > "$repo_dir/lever.json"
+set +e
+PATH="$stub_bin:$PATH" "$lever_bin" --workspace "$repo_dir" --tasks prd.json --task-id T2 \
+  >"$stub_bin/output-invalid" 2>&1
+run_status=$?
+set -e
+if [[ $run_status -eq 0 ]] || ! grep -q 'verification.detectors has unknown detector' "$stub_bin/output-invalid"; then
+  echo "Expected an unknown detector error" >&2
+  cat "$stub_bin/output-invalid" >&2
+  exit 1
+fi
@@ -0,0 +1,176 @@
+#!/usr/bin/env bash
+set -euo pipefail
+
+TEST_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+# shellcheck source=helpers.sh
+source "$TEST_DIR/helpers.sh"
+
+require_cmd jq
+require_cmd git
+require_cmd cargo
+
+repo_root="$(cd "$TEST_DIR/.." && pwd)"
+repo_dir="$(make_temp_dir)"
+stub_bin="$(make_temp_dir)"
+trap 'rm -rf "$repo_dir" "$stub_bin"' EXIT
+
+task_json() {
+  cat <<JSON
+    {
+      "task_id": "$1",
+      "title": "Flaky verification $1",
+      "status": "unstarted",
+      "model": "gpt-5.1-codex-mini",
+      "definition_of_done": [
+        "Suite passes"
+      ],
+      "recommended": {
+        "approach": "Stub verification"
+      },
+      "verification": {
+        "commands": [
+          "n=\"\$(cat \"\$FLAKE_COUNTER\" 2>/dev/null || echo 0)\"; echo \$((n + 1)) > \"\$FLAKE_COUNTER\"; [[ \$((n % 2)) -eq 1 ]]"
+        ]
+      }
+    }
+JSON
+}
+
+cat > "$repo_dir/prd.json" <<JSON
+{
+  "tasks": [
+$(task_json T1),
+$(task_json T2),
+$(task_json T3)
+  ]
+}
+JSON
+
+ensure_workspace_prompt "$repo_dir"
+printf '%s\n' '{"verification": {"retries": 1}}' > "$repo_dir/lever.json"
+
+cat > "$stub_bin/codex" <<'EOF2'
+#!/usr/bin/env bash
+set -euo pipefail
+
+if [[ "${1:-}" == "--version" ]]; then
+  printf '%s\n' "codex 0.0.0"
+  exit 0
+fi
+
+out_path=""
+while [[ $# -gt 0 ]]; do
+  case "$1" in
+    --output-last-message)
+      out_path="$2"
+      shift 2
+      ;;
+    *)
+      shift 1
+      ;;
+  esac
+done
+
+task_id="$(jq -r '[.tasks[] | select(.status != "completed")][0].task_id' prd.json)"
+cat > "$out_path" <<JSON
+{
+  "task_id": "$task_id",
+  "outcome": "completed",
+  "dod_met": true,
+  "summary": "ok",
+  "tests": {"ran": false, "commands": [], "passed": true},
+  "notes": "",
+  "blockers": [],
+  "dod_items": [],
+  "files_changed": [],
+  "follow_ups": []
+}
+JSON
+EOF2
+chmod +x "$stub_bin/codex"
+
+init_git_repo "$repo_dir"
+
+(
+  cd "$repo_root"
+  cargo build --quiet
+)
+lever_bin="$repo_root/target/debug/lever"
+
+run_task() {
+  rm -f "$stub_bin/counter"
+  set +e
+  PATH="$stub_bin:$PATH" \
+    FLAKE_COUNTER="$stub_bin/counter" \
+    GIT_AUTHOR_NAME=test GIT_AUTHOR_EMAIL=test@example.com \
+    GIT_COMMITTER_NAME=test GIT_COMMITTER_EMAIL=test@example.com \
+    "$lever_bin" \
+    --workspace "$repo_dir" \
+    --tasks prd.json \
+    --task-id "$1" \
+    >"$stub_bin/output-$1" 2>&1
+  run_status=$?
+  set -e
+}
+
+# The first attempt fails and the retry passes, so the run completes.
+run_task T1
+if [[ $run_status -ne 0 ]]; then
+  echo "Expected T1 to complete after a retry, got $run_status" >&2
+  cat "$stub_bin/output-T1" >&2
+  exit 1
+fi
+run_dir="$(find "$repo_dir/.ralph/runs/T1" -mindepth 1 -maxdepth 1 -type d | head -n 1)"
+if [[ "$(jq -c '[.retries, [.flaky[].attempts]]' "$run_dir/verification.json")" != "[1,[2]]" ]]; then
+  echo "Expected a flaky step in verification.json" >&2
+  cat "$run_dir/verification.json" >&2
+  exit 1
+fi
+if ! grep -q "lever: flaky: passed on attempt 2" "$run_dir/verify.log"; then
+  echo "Expected the retry in verify.log" >&2
+  exit 1
+fi
+if [[ "$(jq -r '.commands[0].flakes' "$repo_dir/.ralph/flaky.json")" != "1" ]]; then
+  echo "Expected one flake in the ledger" >&2
+  exit 1
+fi
+if [[ -n "$(git -C "$repo_dir" ls-files .ralph/flaky.json)" ]]; then
+  echo "Expected the flaky ledger to stay out of git" >&2
+  exit 1
+fi
+
+# A second flake of the same command is surfaced as repeated.
+run_task T2
+if [[ $run_status -ne 0 ]]; then
+  echo "Expected T2 to complete after a retry, got $run_status" >&2
+  cat "$stub_bin/output-T2" >&2
+  exit 1
+fi
+if ! grep -q "Flaky verification command" "$stub_bin/output-T2"; then
+  echo "Expected a flaky warning" >&2
+  exit 1
+fi
+"$lever_bin" --workspace "$repo_dir" --tasks prd.json status >"$stub_bin/status" 2>&1
+if ! grep -q "lever status: tasks=.* total=3 completed=2 unstarted=1" "$stub_bin/status"; then
+  echo "Expected task counts in lever status" >&2
+  cat "$stub_bin/status" >&2
+  exit 1
+fi
+if ! grep -q "lever status: repeated flakes=2 .* last_task=T2 command=" "$stub_bin/status"; then
+  echo "Expected the repeated flake in lever status" >&2
+  cat "$stub_bin/status" >&2
+  exit 1
+fi
+
+# Without retries the same failure is a verification failure.
+printf '%s\n' '{}' > "$repo_dir/lever.json"
+git -C "$repo_dir" add lever.json
+GIT_AUTHOR_NAME=test GIT_AUTHOR_EMAIL=test@example.com \
+  GIT_COMMITTER_NAME=test GIT_COMMITTER_EMAIL=test@example.com \
+  git -C "$repo_dir" commit -q -m "no retries"
+run_task T3
+if [[ $run_status -ne 12 ]]; then
+  echo "Expected progress exit 12 without retries, got $run_status" >&2
+  cat "$stub_bin/output-T3" >&2
+  exit 1
+fi
@@ -0,0 +1,178 @@
+#!/usr/bin/env bash
+set -euo pipefail
+
+TEST_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+# shellcheck source=helpers.sh
+source "$TEST_DIR/helpers.sh"
+
+require_cmd jq
+require_cmd git
+require_cmd cargo
+
+repo_root="$(cd "$TEST_DIR/.." && pwd)"
+repo_dir="$(make_temp_dir)"
+stub_bin="$(make_temp_dir)"
+trap 'rm -rf "$repo_dir" "$stub_bin"' EXIT
+
+cat > "$repo_dir/prd.json" <<'JSON'
+{
+  "tasks": [
+    {
+      "task_id": "T1",
+      "title": "SARIF verification",
+      "status": "unstarted",
+      "model": "gpt-5.1-codex-mini",
+      "definition_of_done": [
+        "Clippy is clean"
+      ],
+      "recommended": {
+        "approach": "Stub verification"
+      },
+      "verification": {
+        "commands": [
+          "cp sarif/clippy.sarif \"$LEVER_SARIF_DIR/clippy.sarif\"",
+          "printf 'not sarif' > \"$LEVER_SARIF_DIR/broken.sarif\"",
+          "exit 1"
+        ]
+      }
+    }
+  ]
+}
+JSON
+
+ensure_workspace_prompt "$repo_dir"
+mkdir -p "$repo_dir/sarif"
+cat > "$repo_dir/sarif/clippy.sarif" <<JSON
+{
+  "version": "2.1.0",
+  "runs": [
+    {
+      "tool": { "driver": { "name": "clippy" } },
+      "results": [
+        {
+          "level": "error",
+          "ruleId": "clippy::unwrap_used",
+          "message": { "text": "used unwrap on a Result" },
+          "locations": [
+            {
+              "physicalLocation": {
+                "artifactLocation": { "uri": "file://$repo_dir/src/lib.rs" },
+                "region": { "startLine": 12, "startColumn": 5 }
+              }
+            }
+          ]
+        }
+      ]
+    }
+  ]
+}
+JSON
+
+cat > "$stub_bin/codex" <<'EOF2'
+#!/usr/bin/env bash
+set -euo pipefail
+
+if [[ "${1:-}" == "--version" ]]; then
+  printf '%s\n' "codex 0.0.0"
+  exit 0
+fi
+
+out_path=""
+while [[ $# -gt 0 ]]; do
+  case "$1" in
+    --output-last-message)
+      out_path="$2"
+      shift 2
+      ;;
+    *)
+      shift 1
+      ;;
+  esac
+done
+
+cat > "$out_path" <<'JSON'
+{
+  "task_id": "T1",
+  "outcome": "completed",
+  "dod_met": true,
+  "summary": "ok",
+  "tests": {"ran": false, "commands": [], "passed": true},
+  "notes": "",
+  "blockers": [],
+  "dod_items": [],
+  "files_changed": [],
+  "follow_ups": []
+}
+JSON
+EOF2
+chmod +x "$stub_bin/codex"
+
+init_git_repo "$repo_dir"
+
+(
+  cd "$repo_root"
+  cargo build --quiet
+)
+lever_bin="$repo_root/target/debug/lever"
+
+run_task() {
+  set +e
+  PATH="$stub_bin:$PATH" \
+    GIT_AUTHOR_NAME=test GIT_AUTHOR_EMAIL=test@example.com \
+    GIT_COMMITTER_NAME=test GIT_COMMITTER_EMAIL=test@example.com \
+    "$lever_bin" \
+    --workspace "$repo_dir" \
+    --tasks prd.json \
+    --task-id T1 \
+    >"$stub_bin/output-$1" 2>&1
+  run_status=$?
+  set -e
+  if [[ $run_status -ne 12 ]]; then
+    echo "Expected progress exit 12 after failed verification, got $run_status" >&2
+    cat "$stub_bin/output-$1" >&2
+    exit 1
+  fi
+}
+
+run_task first
+run_dir="$(find "$repo_dir/.ralph/runs/T1" -mindepth 1 -maxdepth 1 -type d | head -n 1)"
+findings="$run_dir/findings.sarif"
+if [[ ! -f "$findings" ]]; then
+  echo "Expected findings.sarif in $run_dir" >&2
+  exit 1
+fi
+if [[ "$(jq -r '.version' "$findings")" != "2.1.0" ]]; then
+  echo "Expected SARIF 2.1.0 log" >&2
+  exit 1
+fi
+if [[ "$(jq -r '[.runs[].tool.driver.name] | join(",")' "$findings")" != "clippy" ]]; then
+  echo "Expected only the clippy run in findings.sarif" >&2
+  cat "$findings" >&2
+  exit 1
+fi
+if ! grep -q "Ignoring invalid SARIF file" "$stub_bin/output-first"; then
+  echo "Expected a warning for the invalid SARIF file" >&2
+  exit 1
+fi
+
+run_task second
+prompt="$(find "$repo_dir/.ralph/runs/T1" -name prompt.md -newer "$findings" | head -n 1)"
+if [[ -z "$prompt" ]]; then
+  echo "Expected a prompt for the second run" >&2
+  exit 1
+fi
+if ! grep -q "Verification findings from run" "$prompt" || ! grep -q "Totals: error=1 " "$prompt"; then
+  echo "Expected verification findings totals in the repair prompt" >&2
+  cat "$prompt" >&2
+  exit 1
+fi
+if [[ "$(jq -r '.sections | map(.name) | index("verification_findings") != null' "$(dirname "$prompt")/prompt-tokens.json")" != "true" ]]; then
+  echo "Expected verification_findings in prompt-tokens.json" >&2
+  exit 1
+fi
+if ! grep -q "src/lib.rs:" "$prompt" \
+  || ! grep -q "\[error\] line 12:5 clippy::unwrap_used - used unwrap on a Result" "$prompt"; then
+  echo "Expected grouped SARIF finding in the repair prompt" >&2
+  cat "$prompt" >&2
+  exit 1
+fi
@@ -0,0 +1,205 @@
+#!/usr/bin/env bash
+set -euo pipefail
+
+TEST_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+# shellcheck source=helpers.sh
+source "$TEST_DIR/helpers.sh"
+
+require_cmd jq
+require_cmd git
+require_cmd cargo
+
+repo_root="$(cd "$TEST_DIR/.." && pwd)"
+repo_dir="$(make_temp_dir)"
+stub_bin="$(make_temp_dir)"
+trap 'rm -rf "$repo_dir" "$stub_bin"' EXIT
+
+# Each case replaces the tasks file on main with a single task.
+write_task() {
+  local timeouts="$1"
+  local commands="$2"
+  git -C "$repo_dir" checkout -q main 2>/dev/null || true
+  cat > "$repo_dir/prd.json" <<JSON
+{
+  "tasks": [
+    {
+      "task_id": "T1",
+      "title": "Verification timeouts",
+      "status": "unstarted",
+      "model": "gpt-5.1-codex-mini",
+      "definition_of_done": [
+        "Hung verification is stopped"
+      ],
+      "recommended": {
+        "approach": "Stub verification to hang"
+      },
+      $timeouts
+      "verification": {
+        "commands": $commands
+      }
+    }
+  ]
+}
+JSON
+  if git -C "$repo_dir" rev-parse -q --verify main >/dev/null 2>&1; then
+    git -C "$repo_dir" branch -q -D ralph/T1 2>/dev/null || true
+    git -C "$repo_dir" add prd.json
+    git -C "$repo_dir" -c user.name=test -c user.email=test@example.com commit -q -m "tasks"
+  fi
+}
+
+# The verification script hangs with a grandchild.
+write_task '"timeouts": { "verification_seconds": 5 },' \
+  "[\"sleep 60 & echo \$! > $stub_bin/child.pid; sleep 60\"]"
+
+ensure_workspace_prompt "$repo_dir"
+printf '%s\n' '{"timeouts": {"verification_seconds": 6, "kill_grace_seconds": 1}}' > "$repo_dir/lever.json"
+
+cat > "$stub_bin/codex" <<'EOF2'
+#!/usr/bin/env bash
+set -euo pipefail
+
+if [[ "${1:-}" == "--version" ]]; then
+  printf '%s\n' "codex 0.0.0"
+  exit 0
+fi
+
+printf '%s\n' "codex invoked" > "$CODEX_MARKER"
+out_path=""
+while [[ $# -gt 0 ]]; do
+  case "$1" in
+    --output-last-message)
+      out_path="$2"
+      shift 2
+      ;;
+    *)
+      shift 1
+      ;;
+  esac
+done
+
+printf '%s\n' "change" > change.txt
+cat > "$out_path" <<JSON
+{
+  "task_id": "T1",
+  "outcome": "completed",
+  "dod_met": true,
+  "summary": "ok",
+  "tests": {"ran": true, "commands": ["sleep 60"], "passed": true},
+  "notes": "",
+  "blockers": [],
+  "dod_items": [],
+  "files_changed": [],
+  "follow_ups": []
+}
+JSON
+EOF2
+chmod +x "$stub_bin/codex"
+
+init_git_repo "$repo_dir"
+
+(
+  cd "$repo_root"
+  cargo build --quiet
+)
+lever_bin="$repo_root/target/debug/lever"
+
+run_lever() {
+  rm -f "$stub_bin/marker"
+  started=$SECONDS
+  set +e
+  PATH="$stub_bin:$PATH" \
+    CODEX_MARKER="$stub_bin/marker" \
+    GIT_AUTHOR_NAME=test GIT_AUTHOR_EMAIL=test@example.com \
+    GIT_COMMITTER_NAME=test GIT_COMMITTER_EMAIL=test@example.com \
+    "$lever_bin" \
+    --workspace "$repo_dir" \
+    --tasks prd.json \
+    --task-id T1 \
+    >"$stub_bin/output" 2>&1
+  run_status=$?
+  set -e
+  elapsed=$((SECONDS - started))
+}
+
+# Zombies count as gone: they are only waiting for init to reap them.
+pid_running() {
+  local state
+  state="$(ps -o stat= -p "$1" 2>/dev/null || true)"
+  [[ -n "$state" && "$state" != Z* ]]
+}
+
+latest_run_dir() {
+  ls -td "$repo_dir"/.ralph/runs/T1/*/ | head -n 1
+}
+
+# A verification script past the task's limit fails with its process group
+# stopped.
+run_lever
+if [[ $run_status -ne 12 ]]; then
+  echo "Expected a verification timeout to leave T1 in progress (12), got $run_status" >&2
+  cat "$stub_bin/output" >&2
+  exit 1
+fi
+if (( elapsed >= 60 )); then
+  echo "Expected verification to be stopped at its timeout" >&2
+  exit 1
+fi
+if pid_running "$(cat "$stub_bin/child.pid")"; then
+  echo "Expected the verification process group to be terminated" >&2
+  exit 1
+fi
+run_dir="$(latest_run_dir)"
+if ! grep -q "lever: timed out after 5s" "$run_dir/verify.log"; then
+  echo "Expected the timeout in verify.log" >&2
+  cat "$run_dir/verify.log" >&2
+  exit 1
+fi
+if [[ "$(jq -r '.timeout_seconds' "$run_dir/verification.json")" != "5" ]]; then
+  echo "Expected the task limit in verification.json" >&2
+  exit 1
+fi
+
+# Verification passes, but the test command Codex claims hangs on re-run; it
+# gets the lever.json limit.
+write_task "" '["true"]'
+run_lever
+if [[ $run_status -ne 12 ]]; then
+  echo "Expected a hanging claimed test to leave T1 in progress (12), got $run_status" >&2
+  cat "$stub_bin/output" >&2
+  exit 1
+fi
+if (( elapsed >= 60 )); then
+  echo "Expected the claimed test re-run to be stopped at its timeout" >&2
+  exit 1
+fi
+run_dir="$(latest_run_dir)"
+if ! grep -q "lever: timed out after 6s" "$run_dir/verify.log"; then
+  echo "Expected the re-run timeout in verify.log" >&2
+  cat "$run_dir/verify.log" >&2
+  exit 1
+fi
+if ! jq -e '.checks | any(.kind == "tests_rerun" and (.ok | not))' "$run_dir/claims.json" >/dev/null; then
+  echo "Expected a failed tests_rerun claim" >&2
+  cat "$run_dir/claims.json" >&2
+  exit 1
+fi
+
+# A task limit below 1 is rejected before Codex runs.
+write_task '"timeouts": { "verification_seconds": 0 },' '["true"]'
+run_lever
+if [[ $run_status -eq 0 ]]; then
+  echo "Expected invalid timeouts to fail the run" >&2
+  exit 1
+fi
+if ! grep -q "timeouts.verification_seconds must be >= 1" "$stub_bin/output"; then
+  echo "Expected the invalid limit in the error" >&2
+  cat "$stub_bin/output" >&2
+  exit 1
+fi
+if [[ -f "$stub_bin/marker" ]]; then
+  echo "Expected Codex not to run with invalid timeouts" >&2
+  exit 1
+fi
+
+echo "ok"